@@ -14,6 +14,7 @@ use crate::prodbench::ProdbenchInputs;
 
 mod hash_fns;
 mod merkleproofs;
+mod poseidon;
 mod prodbench;
 mod window_post;
 mod window_post_fake;
@@ -146,6 +147,17 @@ fn main() -> Result<()> {
     let hash_cmd =
         Command::new("hash-constraints").about("Benchmark hash function inside of a circuit");
 
+    let poseidon_cmd = Command::new("poseidon")
+        .about("Benchmark Poseidon hashing throughput")
+        .arg(
+            Arg::new("iterations")
+                .long("iterations")
+                .default_value("100000")
+                .required(false)
+                .help("How many hashes to compute per arity (default is 100000)")
+                .takes_value(true),
+        );
+
     let prodbench_cmd = Command::new("prodbench")
         .about("Benchmark prodbench")
         .arg(
@@ -213,6 +225,7 @@ fn main() -> Result<()> {
         .subcommand(window_post_fake_cmd)
         .subcommand(winning_post_cmd)
         .subcommand(hash_cmd)
+        .subcommand(poseidon_cmd)
         .subcommand(prodbench_cmd)
         .subcommand(merkleproof_cmd)
         .get_matches();
@@ -288,6 +301,10 @@ fn main() -> Result<()> {
         Some(("hash-constraints", _m)) => {
             hash_fns::run()?;
         }
+        Some(("poseidon", m)) => {
+            let iterations = m.value_of_t::<usize>("iterations")?;
+            poseidon::run(iterations)?;
+        }
         Some(("merkleproofs", m)) => {
             let size = Byte::from_str(m.value_of_t::<String>("size")?)?.get_bytes() as usize;
 
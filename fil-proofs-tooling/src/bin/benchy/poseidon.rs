@@ -0,0 +1,78 @@
+use anyhow::{bail, Result};
+use blstrs::Scalar as Fr;
+use ff::Field;
+use fil_proofs_tooling::{metadata::Metadata, with_timings, PhaseTiming, PhaseTimings};
+use filecoin_hashers::poseidon_types::PoseidonArity;
+use generic_array::typenum::{Unsigned, U11, U2, U4, U8};
+use neptune::poseidon::Poseidon;
+use serde::Serialize;
+
+/// The arities used by the Poseidon hashers in the trees and column commitments.
+const ARITIES: [usize; 4] = [2, 4, 8, 11];
+
+fn hash_chain<A: PoseidonArity>(iterations: usize) -> Result<Fr> {
+    let rng = &mut rand::thread_rng();
+    let mut preimage: Vec<Fr> = (0..A::to_usize()).map(|_| Fr::random(&mut *rng)).collect();
+
+    let mut poseidon = Poseidon::new(A::PARAMETERS());
+    for _ in 0..iterations {
+        poseidon.reset();
+        for elt in &preimage {
+            poseidon.input(*elt).expect("input failure");
+        }
+        // Feed each digest into the next preimage, so no hash can be skipped.
+        preimage[0] = poseidon.hash();
+    }
+
+    Ok(preimage[0])
+}
+
+/// Hashes `iterations` random preimages of the given `arity` and records the total duration
+/// under `poseidon-<arity>`.
+pub fn bench_poseidon(arity: usize, iterations: usize) -> Result<PhaseTimings> {
+    let mut timings = PhaseTimings::new();
+    let phase = format!("poseidon-{}", arity);
+
+    with_timings(&mut timings, &phase, || match arity {
+        2 => hash_chain::<U2>(iterations),
+        4 => hash_chain::<U4>(iterations),
+        8 => hash_chain::<U8>(iterations),
+        11 => hash_chain::<U11>(iterations),
+        _ => bail!("unsupported poseidon arity: {}", arity),
+    })?;
+
+    Ok(timings)
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct Report {
+    arity: usize,
+    iterations: usize,
+    timing: PhaseTiming,
+    hashes_per_sec: f64,
+}
+
+pub fn run(iterations: usize) -> Result<()> {
+    let mut reports = Vec::with_capacity(ARITIES.len());
+    for arity in ARITIES.iter().copied() {
+        let timings = bench_poseidon(arity, iterations)?;
+        let timing = *timings
+            .get(&format!("poseidon-{}", arity))
+            .expect("missing poseidon timing");
+        let wall_time_secs = timing.wall_time_ms.max(1) as f64 / 1000.0;
+
+        reports.push(Report {
+            arity,
+            iterations,
+            timing,
+            hashes_per_sec: iterations as f64 / wall_time_secs,
+        });
+    }
+
+    // print reports
+    let wrapped = Metadata::wrap(reports)?;
+    serde_json::to_writer(std::io::stdout(), &wrapped)?;
+
+    Ok(())
+}
@@ -4,7 +4,16 @@
 
 pub mod measure;
 pub mod metadata;
+pub mod proof_cost;
+pub mod seal_stats;
 pub mod shared;
-pub use measure::{measure, FuncMeasurement};
+pub mod tree_build;
+pub use measure::{
+    measure, measure_peak_rss, with_timings, FuncMeasurement, PeakRssMeasurement, PhaseTiming,
+    PhaseTimings,
+};
 pub use metadata::Metadata;
+pub use proof_cost::{proof_cost_report, ProofCostReport};
+pub use seal_stats::{seal_with_stats, JsonlSealStatsSink, SealStats, SealStatsSink};
 pub use shared::{create_replica, create_replicas};
+pub use tree_build::{bench_tree_build, TreeBuildReport};
@@ -1,7 +1,13 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
 use std::time::{Duration, Instant};
 
-use anyhow::Result;
+use anyhow::{anyhow, Context, Result};
 use cpu_time::ProcessTime;
+use serde::Serialize;
 
 pub struct FuncMeasurement<T> {
     pub cpu_time: Duration,
@@ -24,3 +30,231 @@ where
         return_value: x,
     })
 }
+
+pub struct PeakRssMeasurement<T> {
+    pub peak_rss_bytes: u64,
+    pub return_value: T,
+}
+
+/// Runs `f` (e.g. `seal_pre_commit_phase2`) while a background thread samples the resident set
+/// size of the process every `interval`, and returns the highest sample.
+///
+/// The RSS is also sampled right before and after `f`. Peaks lasting less than `interval` may be
+/// missed, so the result is a lower bound of the actual peak, which gets tighter with shorter
+/// intervals. Only supported on Linux, where the RSS is read from `/proc/self/status`.
+pub fn measure_peak_rss<T, F>(interval: Duration, f: F) -> Result<PeakRssMeasurement<T>>
+where
+    F: FnOnce() -> Result<T>,
+{
+    let peak = Arc::new(AtomicU64::new(current_rss_bytes()?));
+    let (stop_tx, stop_rx) = mpsc::channel::<()>();
+
+    let sampler = {
+        let peak = Arc::clone(&peak);
+        thread::spawn(move || loop {
+            if let Ok(rss) = current_rss_bytes() {
+                peak.fetch_max(rss, Ordering::SeqCst);
+            }
+            // Stops as soon as the sender is dropped.
+            if stop_rx.recv_timeout(interval) != Err(mpsc::RecvTimeoutError::Timeout) {
+                break;
+            }
+        })
+    };
+
+    let result = f();
+    drop(stop_tx);
+    sampler
+        .join()
+        .map_err(|_| anyhow!("rss sampler thread panicked"))?;
+    let return_value = result?;
+    peak.fetch_max(current_rss_bytes()?, Ordering::SeqCst);
+
+    Ok(PeakRssMeasurement {
+        peak_rss_bytes: peak.load(Ordering::SeqCst),
+        return_value,
+    })
+}
+
+fn current_rss_bytes() -> Result<u64> {
+    let status =
+        fs::read_to_string("/proc/self/status").context("failed to read /proc/self/status")?;
+
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))
+        .and_then(|value| value.trim().strip_suffix("kB"))
+        .and_then(|kib| kib.trim().parse::<u64>().ok())
+        .map(|kib| kib * 1024)
+        .ok_or_else(|| anyhow!("failed to parse VmRSS from /proc/self/status"))
+}
+
+/// The accumulated duration of a single phase.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct PhaseTiming {
+    pub cpu_time_ms: u64,
+    pub wall_time_ms: u64,
+}
+
+/// Durations per phase, keyed by phase name, suitable for `Metadata<PhaseTimings>` reports.
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct PhaseTimings(BTreeMap<String, PhaseTiming>);
+
+impl PhaseTimings {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Adds the given durations to the phase, so repeated phases accumulate.
+    pub fn record<S: Into<String>>(&mut self, phase: S, cpu_time: Duration, wall_time: Duration) {
+        let timing = self.0.entry(phase.into()).or_default();
+        timing.cpu_time_ms += cpu_time.as_millis() as u64;
+        timing.wall_time_ms += wall_time.as_millis() as u64;
+    }
+
+    pub fn get(&self, phase: &str) -> Option<&PhaseTiming> {
+        self.0.get(phase)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &PhaseTiming)> {
+        self.0.iter()
+    }
+}
+
+/// Runs `f` (typically a seal or post entry point), recording its total duration under `phase`
+/// and the duration of each internal operation it reports through `measure_op` (when the
+/// `measurements` feature is enabled) under the operation's name.
+///
+/// Operation measurements are collected from a process wide channel, so any operations run
+/// concurrently by other threads are attributed to this call as well. Measurements which were
+/// pending before `f` started are put back into the channel, for whoever reads them later.
+pub fn with_timings<T, F>(timings: &mut PhaseTimings, phase: &str, f: F) -> Result<T>
+where
+    F: FnOnce() -> Result<T>,
+{
+    #[cfg(feature = "measurements")]
+    let pending = take_op_measurements();
+
+    let measurement = measure(f);
+
+    #[cfg(feature = "measurements")]
+    {
+        let produced = take_op_measurements();
+        restore_op_measurements(pending);
+        if measurement.is_ok() {
+            record_op_measurements(timings, produced);
+        }
+    }
+
+    let measurement = measurement?;
+    timings.record(phase, measurement.cpu_time, measurement.wall_time);
+
+    Ok(measurement.return_value)
+}
+
+#[cfg(feature = "measurements")]
+fn take_op_measurements() -> Vec<storage_proofs_core::measurements::OpMeasurement> {
+    use storage_proofs_core::measurements::OP_MEASUREMENTS;
+
+    let measurements = OP_MEASUREMENTS
+        .1
+        .lock()
+        .expect("failed to acquire lock on rx side of perf channel");
+
+    measurements.try_iter().collect()
+}
+
+#[cfg(feature = "measurements")]
+fn restore_op_measurements(measurements: Vec<storage_proofs_core::measurements::OpMeasurement>) {
+    use storage_proofs_core::measurements::OP_MEASUREMENTS;
+
+    let opt_tx = OP_MEASUREMENTS
+        .0
+        .lock()
+        .expect("failed to acquire lock on tx side of perf channel");
+
+    // Without a sender the measurements could not be read anymore anyway.
+    if let Some(tx) = opt_tx.as_ref() {
+        for m in measurements {
+            tx.send(m).expect("failed to send to perf channel");
+        }
+    }
+}
+
+#[cfg(feature = "measurements")]
+fn record_op_measurements(
+    timings: &mut PhaseTimings,
+    measurements: Vec<storage_proofs_core::measurements::OpMeasurement>,
+) {
+    for m in measurements {
+        let op = serde_json::to_value(&m.op)
+            .ok()
+            .and_then(|op| op.as_str().map(str::to_string))
+            .unwrap_or_else(|| format!("{:?}", m.op));
+        timings.record(op, m.cpu_time, m.wall_time);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_with_timings() {
+        let mut timings = PhaseTimings::new();
+
+        let value = with_timings(&mut timings, "phase", || {
+            std::thread::sleep(Duration::from_millis(5));
+            Ok(42)
+        })
+        .expect("with_timings failed");
+        assert_eq!(value, 42);
+        with_timings(&mut timings, "phase", || Ok(())).expect("with_timings failed");
+
+        let timing = timings.get("phase").expect("missing phase timing");
+        assert!(timing.wall_time_ms >= 5);
+    }
+
+    #[cfg(feature = "measurements")]
+    #[test]
+    fn test_with_timings_keeps_pending_measurements() {
+        use storage_proofs_core::measurements::{measure_op, Operation};
+
+        // Other tests measure operations concurrently, so only operations unused by them are
+        // checked here.
+        measure_op(Operation::PostPartialTicketHash, || ());
+
+        let mut timings = PhaseTimings::new();
+        with_timings(&mut timings, "phase", || {
+            measure_op(Operation::PostFinalizeTicket, || ());
+            Ok(())
+        })
+        .expect("with_timings failed");
+        assert!(timings.get("post-finalize-ticket").is_some());
+        assert!(timings.get("post-partial-ticket-hash").is_none());
+
+        // The measurement from before is still pending.
+        let mut later = PhaseTimings::new();
+        with_timings(&mut later, "later", || Ok(())).expect("with_timings failed");
+        record_op_measurements(&mut later, take_op_measurements());
+        assert!(later.get("post-partial-ticket-hash").is_some());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_measure_peak_rss() {
+        const ALLOCATION_BYTES: usize = 64 << 20;
+
+        let measurement = measure_peak_rss(Duration::from_millis(1), || {
+            // Touch every page so the allocation is resident, then give the sampler time to see it.
+            let data = vec![1u8; ALLOCATION_BYTES];
+            std::thread::sleep(Duration::from_millis(50));
+            Ok(data.iter().map(|x| *x as usize).sum::<usize>())
+        })
+        .expect("measure_peak_rss failed");
+
+        assert_eq!(measurement.return_value, ALLOCATION_BYTES);
+        assert!(measurement.peak_rss_bytes >= ALLOCATION_BYTES as u64);
+    }
+}
@@ -56,6 +56,9 @@ pub struct SystemMetadata {
     processor_cores_logical: u64,
     processor_cores_physical: u64,
     memory_total_bytes: u64,
+    gpu_proving_enabled: bool,
+    /// The GPU backend used for proving, see `filecoin_proofs::gpu_backend_name`.
+    gpu_backend: Option<&'static str>,
 }
 
 impl SystemMetadata {
@@ -97,7 +100,11 @@ impl SystemMetadata {
                         .unwrap_or_default(),
                 )
             }
-            #[cfg(not(target_arch = "x86_64"))]
+            #[cfg(target_arch = "aarch64")]
+            {
+                aarch64_processor_info()
+            }
+            #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
             {
                 ("unknown".into(), 0, 0, "unknown".into())
             }
@@ -115,10 +122,67 @@ impl SystemMetadata {
             processor_cores_logical: cpu_logical,
             processor_cores_physical: cpu_physical.unwrap_or_default(),
             memory_total_bytes: memory.total().get::<heim::units::information::byte>(),
+            gpu_proving_enabled: filecoin_proofs::gpu_proving_enabled(),
+            gpu_backend: filecoin_proofs::gpu_backend_name(),
         })
     }
 }
 
+/// Reads the processor name and features from `/proc/cpuinfo` and the frequencies (in MHz, like
+/// cpuid reports them on x86_64) from the cpufreq sysfs entries of the first cpu.
+#[cfg(target_arch = "aarch64")]
+fn aarch64_processor_info() -> (String, u16, u16, String) {
+    use std::convert::TryFrom;
+
+    let (processor, features) = std::fs::read_to_string("/proc/cpuinfo")
+        .map(|cpuinfo| parse_cpuinfo(&cpuinfo))
+        .unwrap_or_else(|_| ("unknown".into(), "unknown".into()));
+
+    let cpufreq_mhz = |name: &str| -> Option<u16> {
+        let path = format!("/sys/devices/system/cpu/cpu0/cpufreq/{}", name);
+        let khz = std::fs::read_to_string(path)
+            .ok()?
+            .trim()
+            .parse::<u64>()
+            .ok()?;
+        u16::try_from(khz / 1000).ok()
+    };
+
+    (
+        processor,
+        cpufreq_mhz("base_frequency").unwrap_or_default(),
+        cpufreq_mhz("cpuinfo_max_freq").unwrap_or_default(),
+        features,
+    )
+}
+
+/// Returns the processor name and the (HWCAP) features listed in an ARM `/proc/cpuinfo`. Most
+/// ARM kernels don't report a model name, in which case the implementer and part ids are used.
+#[cfg(any(target_arch = "aarch64", test))]
+fn parse_cpuinfo(cpuinfo: &str) -> (String, String) {
+    let field = |key: &str| {
+        cpuinfo.lines().find_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            if name.trim() == key {
+                Some(value.trim().to_string())
+            } else {
+                None
+            }
+        })
+    };
+
+    let processor = field("model name")
+        .or_else(|| {
+            let implementer = field("CPU implementer")?;
+            let part = field("CPU part")?;
+            Some(format!("implementer {} part {}", implementer, part))
+        })
+        .unwrap_or_else(|| "unknown".into());
+    let features = field("Features").unwrap_or_else(|| "unknown".into());
+
+    (processor, features)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -129,5 +193,30 @@ mod tests {
         println!("{:#?}", m);
 
         assert!(m.system.memory_total_bytes > 0);
+        assert_eq!(m.system.gpu_proving_enabled, m.system.gpu_backend.is_some());
+    }
+
+    #[test]
+    fn test_parse_cpuinfo() {
+        let cpuinfo = "processor\t: 0\n\
+                       BogoMIPS\t: 50.00\n\
+                       Features\t: fp asimd evtstrm aes pmull sha1 sha2 crc32\n\
+                       CPU implementer\t: 0x41\n\
+                       CPU part\t: 0xd0c\n";
+        assert_eq!(
+            parse_cpuinfo(cpuinfo),
+            (
+                "implementer 0x41 part 0xd0c".to_string(),
+                "fp asimd evtstrm aes pmull sha1 sha2 crc32".to_string(),
+            )
+        );
+
+        let cpuinfo = format!("model name\t: Neoverse-N1\n{}", cpuinfo);
+        assert_eq!(parse_cpuinfo(&cpuinfo).0, "Neoverse-N1");
+
+        assert_eq!(
+            parse_cpuinfo(""),
+            ("unknown".to_string(), "unknown".to_string())
+        );
     }
 }
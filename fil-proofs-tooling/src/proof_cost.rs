@@ -0,0 +1,208 @@
+use anyhow::{anyhow, ensure, Result};
+use filecoin_proofs::{
+    aggregation_padded_count, get_num_partition_for_fallback_post, PoRepConfig, PoStConfig,
+    PoStType, SectorSize, SectorUpdateConfig, POREP_PARTITIONS, SINGLE_PARTITION_PROOF_LEN,
+    WINDOW_POST_CHALLENGE_COUNT, WINDOW_POST_SECTOR_COUNT, WINNING_POST_CHALLENGE_COUNT,
+    WINNING_POST_SECTOR_COUNT,
+};
+use serde::Serialize;
+use storage_proofs_core::{api_version::ApiVersion, parameter_cache::SRS_MAX_PROOFS_TO_AGGREGATE};
+
+/// The numbers of seal proofs for which an aggregate cost is reported.
+pub const AGGREGATE_REPORT_SEAL_COUNTS: [usize; 5] = [1, 16, 256, 819, 4096];
+
+/// Rough cost of a single Miller loop (including its share of the final exponentiation) on a
+/// modern CPU, used to turn pairing counts into estimated verification times.
+pub const ESTIMATED_PAIRING_TIME_MICROS: u64 = 500;
+
+// Native (compressed) serialization sizes of the BLS12-381 group elements.
+const G1_LEN: usize = 48;
+const G2_LEN: usize = 96;
+const GT_LEN: usize = 288;
+
+// The fixed part of a SnarkPack aggregate proof: the commitments `com_ab` and `com_c` (two GT
+// elements each), `ip_ab` (GT) and `agg_c` (G1), then the GIPA proof's `nproofs` (u32),
+// `final_a` (G1), `final_b` (G2), `final_c` (G1), `final_vkey` (two G2) and `final_wkey`
+// (two G1), and lastly the KZG openings of `vkey` (two G2) and `wkey` (two G1).
+const AGGREGATE_PROOF_FIXED_LEN: usize = 2 * 2 * GT_LEN
+    + GT_LEN
+    + G1_LEN
+    + std::mem::size_of::<u32>()
+    + G1_LEN
+    + G2_LEN
+    + G1_LEN
+    + 2 * G2_LEN
+    + 2 * G1_LEN
+    + 2 * G2_LEN
+    + 2 * G1_LEN;
+
+// Each GIPA round adds the pairs of commitments `comms_ab` and `comms_c` (two pairs of two GT
+// elements each), `z_ab` (two GT) and `z_c` (two G1).
+const AGGREGATE_PROOF_ROUND_LEN: usize = 2 * 2 * 2 * GT_LEN + 2 * GT_LEN + 2 * G1_LEN;
+
+// The SnarkPack verifier runs a constant number of pairing checks (the KZG openings for both
+// commitment keys, the final GIPA checks and the aggregated groth16 equation).
+const AGGREGATE_VERIFY_PAIRINGS: usize = 16;
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ProofCost {
+    pub proof_type: String,
+    /// Number of groth16 proofs (partitions) contained in the proof.
+    pub snark_proofs: usize,
+    /// Size in bytes of the serialized proof.
+    pub proof_bytes: usize,
+    /// Estimated number of pairings performed by the verifier.
+    pub estimated_verify_pairings: usize,
+    /// Estimated verification time in microseconds.
+    pub estimated_verify_time_micros: u64,
+}
+
+impl ProofCost {
+    fn new(proof_type: String, snark_proofs: usize, proof_bytes: usize, pairings: usize) -> Self {
+        ProofCost {
+            proof_type,
+            snark_proofs,
+            proof_bytes,
+            estimated_verify_pairings: pairings,
+            estimated_verify_time_micros: pairings as u64 * ESTIMATED_PAIRING_TIME_MICROS,
+        }
+    }
+
+    /// The cost of a proof made up of `partitions` concatenated groth16 proofs, which are batch
+    /// verified: one pairing per proof plus two shared ones for the verifying key terms.
+    fn groth16(proof_type: &str, partitions: usize) -> Self {
+        ProofCost::new(
+            proof_type.to_string(),
+            partitions,
+            partitions * SINGLE_PARTITION_PROOF_LEN,
+            partitions + 2,
+        )
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ProofCostReport {
+    pub sector_size: u64,
+    pub costs: Vec<ProofCost>,
+}
+
+/// Returns the size of a natively serialized aggregate proof over `num_proofs` groth16 proofs.
+pub fn aggregate_proof_len(num_proofs: usize) -> usize {
+    let target_len = aggregation_padded_count(num_proofs);
+    let rounds = target_len.trailing_zeros() as usize;
+
+    AGGREGATE_PROOF_FIXED_LEN + rounds * AGGREGATE_PROOF_ROUND_LEN
+}
+
+/// Lists, per proof type, the serialized proof size and an estimated verification cost for the
+/// given sector size. All partition counts are derived from the current proof configuration.
+pub fn proof_cost_report(sector_size: SectorSize) -> Result<ProofCostReport> {
+    let size = u64::from(sector_size);
+
+    ensure!(
+        POREP_PARTITIONS
+            .read()
+            .expect("POREP_PARTITIONS poisoned")
+            .contains_key(&size),
+        "unsupported sector size {}",
+        size
+    );
+    let window_post_sector_count = *WINDOW_POST_SECTOR_COUNT
+        .read()
+        .expect("WINDOW_POST_SECTOR_COUNT poisoned")
+        .get(&size)
+        .ok_or_else(|| anyhow!("unsupported sector size {}", size))?;
+
+    let porep_config = PoRepConfig::new_groth16(size, [0u8; 32], ApiVersion::V1_2_0);
+    let seal_partitions = usize::from(porep_config.partitions);
+
+    let winning_post_config = PoStConfig {
+        sector_size,
+        challenge_count: WINNING_POST_CHALLENGE_COUNT,
+        sector_count: WINNING_POST_SECTOR_COUNT,
+        typ: PoStType::Winning,
+        priority: false,
+        api_version: ApiVersion::V1_2_0,
+    };
+    let winning_post_partitions =
+        get_num_partition_for_fallback_post(&winning_post_config, WINNING_POST_SECTOR_COUNT);
+
+    let window_post_config = PoStConfig {
+        sector_size,
+        challenge_count: WINDOW_POST_CHALLENGE_COUNT,
+        sector_count: window_post_sector_count,
+        typ: PoStType::Window,
+        priority: false,
+        api_version: ApiVersion::V1_2_0,
+    };
+    let window_post_partitions =
+        get_num_partition_for_fallback_post(&window_post_config, window_post_sector_count);
+
+    let update_partitions =
+        usize::from(SectorUpdateConfig::from_porep_config(&porep_config).update_partitions);
+
+    let mut costs = vec![
+        ProofCost::groth16("seal", seal_partitions),
+        ProofCost::groth16("winning-post", winning_post_partitions),
+        ProofCost::groth16("window-post", window_post_partitions),
+        ProofCost::groth16("sector-update", update_partitions),
+    ];
+
+    for num_seals in AGGREGATE_REPORT_SEAL_COUNTS.iter().copied() {
+        let snark_proofs = num_seals * seal_partitions;
+        if aggregation_padded_count(snark_proofs) > SRS_MAX_PROOFS_TO_AGGREGATE {
+            continue;
+        }
+        costs.push(ProofCost::new(
+            format!("aggregate-of-{}", num_seals),
+            snark_proofs,
+            aggregate_proof_len(snark_proofs),
+            AGGREGATE_VERIFY_PAIRINGS,
+        ));
+    }
+
+    Ok(ProofCostReport {
+        sector_size: size,
+        costs,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use filecoin_proofs::{SECTOR_SIZE_2_KIB, SECTOR_SIZE_32_GIB};
+
+    #[test]
+    fn test_aggregate_proof_len() {
+        // Matches the aggregate proof fixture over 257 (padded to 512) proofs.
+        assert_eq!(aggregate_proof_len(257), 29_044);
+        assert_eq!(aggregate_proof_len(512), 29_044);
+        assert_eq!(aggregate_proof_len(1), aggregate_proof_len(2));
+
+        assert_eq!(AGGREGATE_PROOF_FIXED_LEN, 2260);
+        assert_eq!(AGGREGATE_PROOF_ROUND_LEN, 2976);
+    }
+
+    #[test]
+    fn test_proof_cost_report() {
+        let report =
+            proof_cost_report(SectorSize(SECTOR_SIZE_32_GIB)).expect("failed to build report");
+        let seal = &report.costs[0];
+        assert_eq!(seal.proof_type, "seal");
+        assert_eq!(seal.proof_bytes, 10 * SINGLE_PARTITION_PROOF_LEN);
+
+        let window_post = &report.costs[2];
+        assert_eq!(window_post.proof_type, "window-post");
+        assert_eq!(window_post.proof_bytes, SINGLE_PARTITION_PROOF_LEN);
+
+        assert!(report
+            .costs
+            .iter()
+            .any(|cost| cost.proof_type == "aggregate-of-819"));
+
+        assert!(proof_cost_report(SectorSize(SECTOR_SIZE_2_KIB + 1)).is_err());
+    }
+}
@@ -0,0 +1,286 @@
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use filecoin_proofs::{
+    seal_commit_phase1, seal_commit_phase2, seal_pre_commit_phase1, seal_pre_commit_phase2,
+    validate_cache_for_commit, Commitment, MerkleTreeTrait, PieceInfo, PoRepConfig, ProverId,
+    SealCommitOutput, Ticket,
+};
+use serde::Serialize;
+use storage_proofs_core::sector::SectorId;
+
+use crate::measure::{with_timings, PhaseTimings};
+
+/// The record `seal_with_stats` emits once it has sealed a sector.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SealStats {
+    pub sector_id: u64,
+    pub sector_size: u64,
+    pub api_version: String,
+    /// The durations of the seal phases, see `seal_with_stats`.
+    pub phases: PhaseTimings,
+    /// The hex encoded replica commitment.
+    pub comm_r: String,
+}
+
+impl SealStats {
+    pub fn new(
+        porep_config: &PoRepConfig,
+        sector_id: SectorId,
+        phases: PhaseTimings,
+        comm_r: Commitment,
+    ) -> Self {
+        SealStats {
+            sector_id: u64::from(sector_id),
+            sector_size: u64::from(porep_config.sector_size),
+            api_version: porep_config.api_version.to_string(),
+            phases,
+            comm_r: comm_r.iter().map(|b| format!("{:02x}", b)).collect(),
+        }
+    }
+}
+
+/// Receives a `SealStats` record for every sector sealed with `seal_with_stats`. The seal phases
+/// of filecoin-proofs do not emit records themselves, sectors sealed by calling them directly are
+/// not recorded.
+pub trait SealStatsSink {
+    fn record(&mut self, stats: &SealStats) -> Result<()>;
+}
+
+impl SealStatsSink for Vec<SealStats> {
+    fn record(&mut self, stats: &SealStats) -> Result<()> {
+        self.push(stats.clone());
+        Ok(())
+    }
+}
+
+/// Appends every record as a single JSON line to a file.
+pub struct JsonlSealStatsSink {
+    path: PathBuf,
+    writer: BufWriter<File>,
+}
+
+impl JsonlSealStatsSink {
+    /// Opens `path` for appending, creating it if it does not exist yet.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("could not open seal stats file {:?}", path))?;
+
+        Ok(JsonlSealStatsSink {
+            path,
+            writer: BufWriter::new(file),
+        })
+    }
+}
+
+impl SealStatsSink for JsonlSealStatsSink {
+    fn record(&mut self, stats: &SealStats) -> Result<()> {
+        serde_json::to_writer(&mut self.writer, stats)?;
+        self.writer.write_all(b"\n")?;
+        // Flush every record, so the file stays a complete audit trail if the process dies.
+        self.writer
+            .flush()
+            .with_context(|| format!("could not write to seal stats file {:?}", self.path))
+    }
+}
+
+/// A tooling wrapper which runs all seal phases of filecoin-proofs for a single sector, timing
+/// each of them with `with_timings`, and emits the resulting `SealStats` to `sink` once the
+/// sector is sealed.
+#[allow(clippy::too_many_arguments)]
+pub fn seal_with_stats<Tree: 'static + MerkleTreeTrait, S: SealStatsSink>(
+    porep_config: &PoRepConfig,
+    cache_path: &Path,
+    staged_path: &Path,
+    sealed_path: &Path,
+    prover_id: ProverId,
+    sector_id: SectorId,
+    ticket: Ticket,
+    seed: Ticket,
+    piece_infos: &[PieceInfo],
+    sink: &mut S,
+) -> Result<SealCommitOutput> {
+    let mut phases = PhaseTimings::new();
+
+    let phase1_output = with_timings(&mut phases, "seal-pre-commit-phase1", || {
+        seal_pre_commit_phase1::<_, _, _, Tree>(
+            porep_config,
+            cache_path,
+            staged_path,
+            sealed_path,
+            prover_id,
+            sector_id,
+            ticket,
+            piece_infos,
+        )
+    })?;
+    let pre_commit_output = with_timings(&mut phases, "seal-pre-commit-phase2", || {
+        seal_pre_commit_phase2(porep_config, phase1_output, cache_path, sealed_path)
+    })?;
+    let comm_r = pre_commit_output.comm_r;
+
+    validate_cache_for_commit::<_, _, Tree>(cache_path, sealed_path)?;
+
+    let commit_phase1_output = with_timings(&mut phases, "seal-commit-phase1", || {
+        seal_commit_phase1::<_, Tree>(
+            porep_config,
+            cache_path,
+            sealed_path,
+            prover_id,
+            sector_id,
+            ticket,
+            seed,
+            pre_commit_output,
+            piece_infos,
+        )
+    })?;
+    let commit_output = with_timings(&mut phases, "seal-commit-phase2", || {
+        seal_commit_phase2(porep_config, commit_phase1_output, prover_id, sector_id)
+    })?;
+
+    sink.record(&SealStats::new(porep_config, sector_id, phases, comm_r))?;
+
+    Ok(commit_output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+    use std::time::Duration;
+
+    use filecoin_proofs::{
+        add_piece, compute_comm_d, read_cached_comm_r, verify_seal, PaddedBytesAmount,
+        SectorShape2KiB, UnpaddedBytesAmount, SECTOR_SIZE_2_KIB,
+    };
+    use rand::random;
+    use storage_proofs_core::api_version::ApiVersion;
+    use tempfile::{tempdir, NamedTempFile};
+
+    use crate::shared::{create_piece, PROVER_ID};
+
+    #[test]
+    fn test_seal_with_stats_2kib() {
+        let porep_config =
+            PoRepConfig::new_groth16(SECTOR_SIZE_2_KIB, [128; 32], ApiVersion::V1_1_0);
+        let piece_size = UnpaddedBytesAmount::from(PaddedBytesAmount(SECTOR_SIZE_2_KIB));
+
+        let cache_dir = tempdir().expect("failed to create cache dir");
+        let mut staged_file = NamedTempFile::new().expect("failed to create staged file");
+        let sealed_file = NamedTempFile::new().expect("failed to create sealed file");
+        let mut piece_file = create_piece(piece_size, false);
+        let (piece_info, _) = add_piece(&mut piece_file, &mut staged_file, piece_size, &[])
+            .expect("failed to add piece");
+        let piece_infos = vec![piece_info];
+
+        let prover_id = PROVER_ID;
+        let sector_id = SectorId::from(random::<u64>());
+        let ticket = random();
+        let seed = random();
+
+        let mut stats: Vec<SealStats> = Vec::new();
+        let commit_output = seal_with_stats::<SectorShape2KiB, _>(
+            &porep_config,
+            cache_dir.path(),
+            staged_file.path(),
+            sealed_file.path(),
+            prover_id,
+            sector_id,
+            ticket,
+            seed,
+            &piece_infos,
+            &mut stats,
+        )
+        .expect("failed to seal");
+
+        assert_eq!(stats.len(), 1);
+        let stats = &stats[0];
+        assert_eq!(stats.sector_id, u64::from(sector_id));
+        for phase in [
+            "seal-pre-commit-phase1",
+            "seal-pre-commit-phase2",
+            "seal-commit-phase1",
+            "seal-commit-phase2",
+        ] {
+            assert!(stats.phases.get(phase).is_some(), "missing phase {}", phase);
+        }
+
+        // The reported comm_r is the one of the sealed replica, which the returned proof is for.
+        let comm_r = read_cached_comm_r::<SectorShape2KiB>(
+            &porep_config,
+            sealed_file.path(),
+            cache_dir.path(),
+        )
+        .expect("failed to read the cached comm_r");
+        assert_eq!(
+            stats.comm_r,
+            comm_r
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect::<String>()
+        );
+        let comm_d = compute_comm_d(porep_config.sector_size, &piece_infos)
+            .expect("failed to compute comm_d");
+        assert!(verify_seal::<SectorShape2KiB>(
+            &porep_config,
+            comm_r,
+            comm_d,
+            prover_id,
+            sector_id,
+            ticket,
+            seed,
+            &commit_output.proof,
+        )
+        .expect("failed to verify seal"));
+    }
+
+    #[test]
+    fn test_jsonl_seal_stats_sink() {
+        let porep_config = PoRepConfig::new_groth16(
+            filecoin_proofs::SECTOR_SIZE_2_KIB,
+            [0; 32],
+            ApiVersion::V1_2_0,
+        );
+        let mut phases = PhaseTimings::new();
+        phases.record(
+            "seal-pre-commit-phase1",
+            Duration::from_millis(3),
+            Duration::from_millis(5),
+        );
+
+        let dir = tempdir().expect("failed to create temp dir");
+        let path = dir.path().join("seal-stats.jsonl");
+        for i in 0..2 {
+            // Reopen the sink each time, to ensure records are appended.
+            let mut sink = JsonlSealStatsSink::open(&path).expect("failed to open sink");
+            let stats = SealStats::new(&porep_config, SectorId::from(i), phases.clone(), [1; 32]);
+            sink.record(&stats).expect("failed to record stats");
+        }
+
+        let contents = fs::read_to_string(&path).expect("failed to read stats");
+        let records = contents
+            .lines()
+            .map(|line| serde_json::from_str::<serde_json::Value>(line).expect("invalid json"))
+            .collect::<Vec<_>>();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1]["sector-id"], 1);
+        assert_eq!(
+            records[1]["sector-size"],
+            filecoin_proofs::SECTOR_SIZE_2_KIB
+        );
+        assert_eq!(records[1]["api-version"], "1.2.0");
+        assert_eq!(records[1]["comm-r"], "01".repeat(32));
+        assert_eq!(
+            records[1]["phases"]["seal-pre-commit-phase1"]["wall-time-ms"],
+            5
+        );
+    }
+}
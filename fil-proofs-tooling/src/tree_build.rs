@@ -0,0 +1,192 @@
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{ensure, Context, Result};
+use filecoin_hashers::{Domain, Hasher};
+use filecoin_proofs::{MerkleTreeTrait, SectorSize};
+use generic_array::typenum::{Unsigned, U0};
+use memmap2::MmapOptions;
+use merkletree::{merkle::get_merkle_tree_len, store::StoreConfig};
+use rand::RngCore;
+use serde::Serialize;
+use storage_proofs_core::{
+    cache_key::CacheKey,
+    merkle::{
+        create_disk_tree, create_lc_tree, get_base_tree_count, split_config,
+        split_config_and_replica, DiskTree, LCTree,
+    },
+    util::{default_rows_to_discard, NODE_SIZE},
+};
+use tempfile::tempdir;
+
+use crate::measure::{measure, PhaseTimings};
+use crate::metadata::Metadata;
+
+const TREE_C_PHASE: &str = "generate-tree-c";
+const TREE_R_LAST_PHASE: &str = "generate-tree-r-last";
+
+/// The durations and throughput of building the trees of a sector, see `bench_tree_build`.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct TreeBuildReport {
+    pub sector_size: u64,
+    /// The durations of building tree-c and tree-r-last.
+    pub phases: PhaseTimings,
+    pub tree_c_mib_per_sec: f64,
+    pub tree_r_last_mib_per_sec: f64,
+    /// The wall time of building both trees.
+    pub total_wall_time_ms: u64,
+}
+
+/// Builds tree-c and tree-r-last of a `Tree` shaped sector of `sector_size` over random nodes
+/// and reports how long each of them took, all intermediate files are written to a temporary
+/// directory.
+///
+/// Unlike `seal_pre_commit_phase2`, the trees are built directly over the random nodes, so the
+/// results reflect the merkle tree building alone, without the column hashing of tree-c and the
+/// encoding of tree-r-last.
+pub fn bench_tree_build<Tree: 'static + MerkleTreeTrait, R: RngCore>(
+    sector_size: SectorSize,
+    rng: &mut R,
+) -> Result<Metadata<TreeBuildReport>> {
+    Metadata::wrap(measure_tree_build::<Tree, _>(sector_size, rng)?)
+}
+
+fn measure_tree_build<Tree: 'static + MerkleTreeTrait, R: RngCore>(
+    sector_size: SectorSize,
+    rng: &mut R,
+) -> Result<TreeBuildReport> {
+    let sector_bytes = u64::from(sector_size) as usize;
+    let nodes_count = sector_bytes / NODE_SIZE;
+    let tree_count = get_base_tree_count::<Tree>();
+    ensure!(
+        nodes_count % tree_count == 0,
+        "sector size {} does not fit the tree shape",
+        sector_bytes
+    );
+    let base_tree_leafs = nodes_count / tree_count;
+    let base_tree_len = get_merkle_tree_len(base_tree_leafs, Tree::Arity::to_usize())?;
+    let base_tree_bytes = sector_bytes / tree_count;
+
+    // Stream the random nodes to the file through a buffer, so that the sector never needs to
+    // fit into memory, and build the trees from a mapping of the file, as sealing does.
+    let cache_dir = tempdir().context("failed to create temp dir")?;
+    let replica_path = cache_dir.path().join("replica");
+    {
+        let file = File::create(&replica_path)
+            .with_context(|| format!("could not create replica_path={:?}", replica_path))?;
+        let mut writer = BufWriter::new(file);
+        for _ in 0..nodes_count {
+            let node = <Tree::Hasher as Hasher>::Domain::random(rng).into_bytes();
+            writer
+                .write_all(&node)
+                .with_context(|| format!("could not write replica_path={:?}", replica_path))?;
+        }
+        writer
+            .flush()
+            .with_context(|| format!("could not write replica_path={:?}", replica_path))?;
+    }
+    let replica_file = File::open(&replica_path)
+        .with_context(|| format!("could not open replica_path={:?}", replica_path))?;
+    let data = unsafe {
+        MmapOptions::new()
+            .map(&replica_file)
+            .with_context(|| format!("could not mmap replica_path={:?}", replica_path))?
+    };
+
+    let mut phases = PhaseTimings::new();
+    let tree_c_time = {
+        let measurement = measure(|| {
+            let config = new_config::<Tree>(cache_dir.path(), CacheKey::CommCTree, nodes_count);
+            let configs = split_config(config, tree_count)?;
+            for (config, slice) in configs.iter().zip(data.chunks(base_tree_bytes)) {
+                DiskTree::<Tree::Hasher, Tree::Arity, U0, U0>::from_byte_slice_with_config(
+                    slice,
+                    config.clone(),
+                )?;
+            }
+            create_disk_tree::<Tree>(base_tree_len, &configs)
+        })?;
+        phases.record(TREE_C_PHASE, measurement.cpu_time, measurement.wall_time);
+        measurement.wall_time
+    };
+
+    let tree_r_last_time = {
+        let measurement = measure(|| {
+            let config = new_config::<Tree>(cache_dir.path(), CacheKey::CommRLastTree, nodes_count);
+            let (configs, replica_config) = split_config_and_replica(
+                config,
+                replica_path.clone(),
+                base_tree_leafs,
+                tree_count,
+            )?;
+            for (config, slice) in configs.iter().zip(data.chunks(base_tree_bytes)) {
+                LCTree::<Tree::Hasher, Tree::Arity, U0, U0>::from_byte_slice_with_config(
+                    slice,
+                    config.clone(),
+                )?;
+            }
+            create_lc_tree::<Tree>(base_tree_len, &configs, &replica_config)
+        })?;
+        phases.record(
+            TREE_R_LAST_PHASE,
+            measurement.cpu_time,
+            measurement.wall_time,
+        );
+        measurement.wall_time
+    };
+
+    Ok(TreeBuildReport {
+        sector_size: u64::from(sector_size),
+        phases,
+        tree_c_mib_per_sec: mib_per_sec(sector_bytes, tree_c_time),
+        tree_r_last_mib_per_sec: mib_per_sec(sector_bytes, tree_r_last_time),
+        total_wall_time_ms: (tree_c_time + tree_r_last_time).as_millis() as u64,
+    })
+}
+
+// Mirrors the tree configs of sealing, which discards rows based on the nodes of the whole sector.
+fn new_config<Tree: MerkleTreeTrait>(
+    cache_path: &Path,
+    key: CacheKey,
+    nodes_count: usize,
+) -> StoreConfig {
+    StoreConfig::new(
+        cache_path,
+        key.to_string(),
+        default_rows_to_discard(nodes_count, Tree::Arity::to_usize()),
+    )
+}
+
+fn mib_per_sec(bytes: usize, duration: Duration) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0) / duration.as_secs_f64()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use filecoin_proofs::{SectorShape2KiB, SECTOR_SIZE_2_KIB};
+    use rand::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    #[test]
+    fn test_measure_tree_build() {
+        let mut rng = XorShiftRng::from_seed([
+            0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06,
+            0xbc, 0xe5,
+        ]);
+
+        let report =
+            measure_tree_build::<SectorShape2KiB, _>(SectorSize(SECTOR_SIZE_2_KIB), &mut rng)
+                .expect("failed to build trees");
+
+        assert_eq!(report.sector_size, SECTOR_SIZE_2_KIB);
+        assert!(report.phases.get(TREE_C_PHASE).is_some());
+        assert!(report.phases.get(TREE_R_LAST_PHASE).is_some());
+        assert!(report.tree_c_mib_per_sec > 0.0);
+        assert!(report.tree_r_last_mib_per_sec > 0.0);
+    }
+}
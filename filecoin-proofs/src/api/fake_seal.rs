@@ -1,17 +1,23 @@
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::Write;
 use std::path::Path;
 
 use anyhow::{Context, Result};
-use bincode::serialize;
+use bincode::{deserialize, serialize};
 use filecoin_hashers::{Domain, Hasher};
+use log::info;
+use merkletree::store::{DiskStore, Store, StoreConfig};
 use rand::{thread_rng, Rng};
-use storage_proofs_core::{cache_key::CacheKey, merkle::MerkleTreeTrait};
-use storage_proofs_porep::stacked::StackedDrg;
+use storage_proofs_core::{
+    cache_key::CacheKey, merkle::MerkleTreeTrait, util::default_rows_to_discard,
+};
+use storage_proofs_porep::stacked::{PersistentAux, StackedDrg};
 
 use crate::{
-    constants::DefaultPieceHasher,
-    types::{Commitment, PoRepConfig},
+    api::{as_safe_commitment, comm_r_from_parts, get_base_tree_leafs, get_base_tree_size},
+    constants::{DefaultBinaryTree, DefaultPieceDomain, DefaultPieceHasher},
+    pieces::comm_d_for_empty_sector,
+    types::{Commitment, PoRepConfig, BINARY_ARITY},
 };
 
 pub fn fauxrep<R: AsRef<Path>, S: AsRef<Path>, Tree: 'static + MerkleTreeTrait>(
@@ -23,6 +29,13 @@ pub fn fauxrep<R: AsRef<Path>, S: AsRef<Path>, Tree: 'static + MerkleTreeTrait>(
     fauxrep_aux::<_, R, S, Tree>(&mut rng, porep_config, cache_path, out_path)
 }
 
+/// Creates a fake committed-capacity replica at `out_path` together with its cache at
+/// `cache_path` and returns its `comm_r`.
+///
+/// The `comm_r` is `H(comm_c || comm_r_last)`, where `comm_c` is sampled from `rng` and
+/// `comm_r_last` is the root of the tree over the all-zero replica, so a seeded `rng` yields the
+/// same `comm_r` on every call. A `comm_r` chosen up front cannot be produced, as that would
+/// require a preimage of the hash.
 pub fn fauxrep_aux<R: Rng, S: AsRef<Path>, T: AsRef<Path>, Tree: 'static + MerkleTreeTrait>(
     mut rng: &mut R,
     porep_config: &PoRepConfig,
@@ -81,3 +94,62 @@ pub fn fauxrep2<R: AsRef<Path>, S: AsRef<Path>, Tree: 'static + MerkleTreeTrait>
     commitment[..].copy_from_slice(&comm_r.into_bytes()[..]);
     Ok(commitment)
 }
+
+/// Checks that the sector whose cache is at `cache_path` is a committed-capacity sector, i.e.
+/// that `comm_r` matches the commitments persisted in the cache and that the `comm_d` of the
+/// data tree in the cache equals the `comm_d` of an empty sector.
+///
+/// The data tree is removed by `clear_cache`, so this must be called before the cache is
+/// cleared.
+///
+/// A `comm_r` alone cannot be checked for being a committed-capacity sector: it is
+/// `H(comm_c || comm_r_last)` and does not contain `comm_d`, so the cache is required.
+///
+/// # Arguments
+///
+/// * `porep_config` - porep configuration containing the sector size.
+/// * `comm_r` - the replica commitment of the sector.
+/// * `cache_path` - path to the sector's cache directory.
+pub fn verify_cc_sector<R: AsRef<Path>, Tree: 'static + MerkleTreeTrait>(
+    porep_config: &PoRepConfig,
+    comm_r: Commitment,
+    cache_path: R,
+) -> Result<bool> {
+    info!("verify_cc_sector:start");
+
+    let comm_r_safe = as_safe_commitment::<<Tree::Hasher as Hasher>::Domain, _>(&comm_r, "comm_r")?;
+
+    let p_aux: PersistentAux<<Tree::Hasher as Hasher>::Domain> = {
+        let p_aux_path = cache_path.as_ref().join(CacheKey::PAux.to_string());
+        let p_aux_bytes = fs::read(&p_aux_path)
+            .with_context(|| format!("could not read file p_aux={:?}", p_aux_path))?;
+
+        deserialize(&p_aux_bytes)
+    }?;
+
+    let expected_comm_r = comm_r_from_parts::<Tree>(&p_aux.comm_c, &p_aux.comm_r_last);
+    if expected_comm_r != comm_r_safe {
+        return Ok(false);
+    }
+
+    let base_tree_size = get_base_tree_size::<DefaultBinaryTree>(porep_config.sector_size)?;
+    let base_tree_leafs = get_base_tree_leafs::<DefaultBinaryTree>(base_tree_size)?;
+    let mut config = StoreConfig::new(
+        cache_path.as_ref(),
+        CacheKey::CommDTree.to_string(),
+        default_rows_to_discard(base_tree_leafs, BINARY_ARITY),
+    );
+    config.size = Some(base_tree_size);
+
+    let tree_d_store: DiskStore<DefaultPieceDomain> =
+        DiskStore::new_from_disk(base_tree_size, BINARY_ARITY, &config)
+            .with_context(|| format!("could not open tree_d in {:?}", cache_path.as_ref()))?;
+    let comm_d_root = tree_d_store.read_at(base_tree_size - 1)?;
+
+    let mut comm_d = [0u8; 32];
+    comm_d.copy_from_slice(&comm_d_root.into_bytes()[..]);
+
+    info!("verify_cc_sector:finish");
+
+    Ok(comm_d == comm_d_for_empty_sector(porep_config.sector_size))
+}
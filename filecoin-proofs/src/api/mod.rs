@@ -1,21 +1,26 @@
 use std::fs::{self, File, OpenOptions};
-use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
 
 use anyhow::{ensure, Context, Result};
-use bincode::deserialize;
-use filecoin_hashers::Hasher;
+use bincode::{deserialize, serialized_size};
+use blake2b_simd::Params as Blake2bParams;
+use filecoin_hashers::{Domain, Hasher};
 use fr32::{write_unpadded, Fr32Reader};
 use log::{info, trace};
 use memmap2::MmapOptions;
-use merkletree::store::{DiskStore, LevelCacheStore, StoreConfig};
+use merkletree::{
+    hash::Algorithm,
+    merkle::get_merkle_tree_cache_size,
+    store::{DiskStore, LevelCacheStore, StoreConfig},
+};
 use storage_proofs_core::{
     cache_key::CacheKey,
     measurements::{measure_op, Operation},
     merkle::get_base_tree_count,
     pieces::generate_piece_commitment_bytes_from_source,
     sector::SectorId,
-    util::default_rows_to_discard,
+    util::{default_rows_to_discard, NODE_SIZE},
 };
 use storage_proofs_porep::{
     stacked::{generate_replica_id, PersistentAux, StackedDrg, TemporaryAux},
@@ -31,10 +36,11 @@ use crate::{
         MINIMUM_RESERVED_BYTES_FOR_PIECE_IN_FULLY_ALIGNED_SECTOR as MINIMUM_PIECE_SIZE,
     },
     parameters::public_params,
-    pieces::{get_piece_alignment, sum_piece_bytes_with_alignment},
+    pieces::{get_piece_alignment, piece_hash, sum_piece_bytes_with_alignment, PieceSource},
     types::{
-        Commitment, MerkleTreeTrait, PaddedBytesAmount, PieceInfo, PoRepConfig, ProverId,
-        SealPreCommitPhase1Output, Ticket, UnpaddedByteIndex, UnpaddedBytesAmount,
+        CacheDefect, CacheFileSpec, Commitment, Fr32Options, MerkleTreeTrait, PaddedBytesAmount,
+        PieceCommitmentCache, PieceInfo, PoRepConfig, ProverId, SealPreCommitPhase1Output,
+        SectorSize, Ticket, UnpaddedByteIndex, UnpaddedBytesAmount, UnsealedRangeProof,
     },
 };
 
@@ -318,6 +324,148 @@ where
     Ok(amount)
 }
 
+/// Generates a proof that the unsealed bytes in the range starting at `offset` are part of the
+/// sector data committed to by comm_d, see `verify_unseal_range`. The proof is read from tree-d
+/// in `cache_path`, so it must be generated before the cache is cleared.
+///
+/// The range must cover a subtree of tree-d, as pieces do: `num_bytes` must be a valid piece
+/// size and `offset` a multiple of it.
+///
+/// # Arguments
+///
+/// * `cache_path` - path to the cache directory of the sector.
+/// * `sector_size` - the size of the sector.
+/// * `offset` - the byte index in the unsealed sector of the first byte of the range.
+/// * `num_bytes` - the number of bytes in the range.
+pub fn generate_unsealed_range_proof(
+    cache_path: &Path,
+    sector_size: SectorSize,
+    offset: UnpaddedByteIndex,
+    num_bytes: UnpaddedBytesAmount,
+) -> Result<UnsealedRangeProof> {
+    info!("generate_unsealed_range_proof:start");
+
+    let (level, mut index) = unsealed_range_position(offset, num_bytes)?;
+    let leaves = u64::from(sector_size) as usize / NODE_SIZE;
+    ensure!(
+        index < leaves >> level,
+        "range at offset {:?} is outside of the sector",
+        offset
+    );
+
+    let path = StoreConfig::data_path(cache_path, &CacheKey::CommDTree.to_string());
+    let file = File::open(&path).with_context(|| format!("could not open {:?}", path))?;
+    let data = unsafe { MmapOptions::new().map(&file) }
+        .with_context(|| format!("could not mmap {:?}", path))?;
+    ensure!(
+        data.len() == (2 * leaves - 1) * NODE_SIZE,
+        "unexpected tree-d length {}",
+        data.len()
+    );
+
+    // The rows of tree-d are stored one after the other, starting with the leaves.
+    let mut row_start = (0..level).map(|l| leaves >> l).sum::<usize>();
+    let mut row_len = leaves >> level;
+    let mut siblings = Vec::new();
+    while row_len > 1 {
+        let sibling = (row_start + (index ^ 1)) * NODE_SIZE;
+        let mut node = [0u8; NODE_SIZE];
+        node.copy_from_slice(&data[sibling..sibling + NODE_SIZE]);
+        siblings.push(node);
+
+        row_start += row_len;
+        row_len /= 2;
+        index /= 2;
+    }
+
+    info!("generate_unsealed_range_proof:finish");
+
+    Ok(UnsealedRangeProof { siblings })
+}
+
+/// Verifies that `unsealed_bytes`, e.g. as returned by an untrusted unseal server, are the
+/// sector data in the range starting at `offset`, using only comm_d and a proof generated by
+/// `generate_unsealed_range_proof`.
+///
+/// # Arguments
+///
+/// * `unsealed_bytes` - the unsealed, un-bit-padded bytes of the range.
+/// * `sector_size` - the size of the sector.
+/// * `offset` - the byte index in the unsealed sector of the first byte of the range.
+/// * `num_bytes` - the number of bytes in the range.
+/// * `comm_d` - the commitment to the sector's data.
+/// * `proof` - the proof of the range.
+pub fn verify_unseal_range(
+    unsealed_bytes: &[u8],
+    sector_size: SectorSize,
+    offset: UnpaddedByteIndex,
+    num_bytes: UnpaddedBytesAmount,
+    comm_d: Commitment,
+    proof: &UnsealedRangeProof,
+) -> Result<bool> {
+    info!("verify_unseal_range:start");
+
+    let (level, mut index) = unsealed_range_position(offset, num_bytes)?;
+    let leaves = u64::from(sector_size) as usize / NODE_SIZE;
+    ensure!(
+        index < leaves >> level,
+        "range at offset {:?} is outside of the sector",
+        offset
+    );
+    // The path must lead from the range's subtree exactly to the root of tree-d, otherwise a
+    // shorter or longer path could authenticate a range of a different position or tree.
+    let expected_siblings = leaves.trailing_zeros() as usize - level;
+    ensure!(
+        proof.siblings.len() == expected_siblings,
+        "expected {} siblings in the range proof, got {}",
+        expected_siblings,
+        proof.siblings.len()
+    );
+    ensure!(
+        unsealed_bytes.len() as u64 == u64::from(num_bytes),
+        "expected {:?} unsealed bytes, got {}",
+        num_bytes,
+        unsealed_bytes.len()
+    );
+
+    let range_commitment = generate_piece_commitment(unsealed_bytes, num_bytes)?.commitment;
+    let mut node = DefaultPieceDomain::try_from_bytes(&range_commitment)?;
+    for sibling in &proof.siblings {
+        node = if index % 2 == 0 {
+            piece_hash(AsRef::<[u8]>::as_ref(&node), sibling)
+        } else {
+            piece_hash(sibling, AsRef::<[u8]>::as_ref(&node))
+        };
+        index /= 2;
+    }
+
+    let result = AsRef::<[u8]>::as_ref(&node) == &comm_d[..];
+
+    info!("verify_unseal_range:finish");
+
+    Ok(result)
+}
+
+// Returns the level in tree-d of the subtree covering the range and its index within that level.
+fn unsealed_range_position(
+    offset: UnpaddedByteIndex,
+    num_bytes: UnpaddedBytesAmount,
+) -> Result<(usize, usize)> {
+    ensure_piece_size(num_bytes)?;
+    ensure!(
+        offset.0 % u64::from(num_bytes) == 0,
+        "offset {:?} is not aligned to the range size {:?}",
+        offset,
+        num_bytes
+    );
+
+    let padded_bytes = u64::from(PaddedBytesAmount::from(num_bytes));
+    let level = (padded_bytes / NODE_SIZE as u64).trailing_zeros() as usize;
+    let index = (offset.0 / u64::from(num_bytes)) as usize;
+
+    Ok((level, index))
+}
+
 /// Generates a piece commitment for the provided byte source. Returns an error
 /// if the byte source produced more than `piece_size` bytes.
 ///
@@ -351,6 +499,45 @@ pub fn generate_piece_commitment<T: Read>(
     result
 }
 
+/// Like `generate_piece_commitment`, but first looks up the commitment in `cache` by a hash of
+/// the piece bytes, and adds newly computed commitments to it. Hashing the piece is much
+/// cheaper than computing its commitment, which pays off when the same pieces are seen
+/// repeatedly. A cached commitment is only used if it was computed for the same `piece_size`.
+///
+/// # Arguments
+///
+/// * `source` - a readable and seekable source of unprocessed piece bytes, positioned at the
+///   start of the piece.
+/// * `piece_size` - the number of unpadded user-bytes which can be read from source.
+/// * `cache` - the cache of previously computed piece commitments.
+pub fn generate_piece_commitment_cached<T: Read + Seek>(
+    mut source: T,
+    piece_size: UnpaddedBytesAmount,
+    cache: &PieceCommitmentCache,
+) -> Result<PieceInfo> {
+    trace!("generate_piece_commitment_cached:start");
+
+    ensure_piece_size(piece_size)?;
+
+    let start = source.stream_position()?;
+    let mut hasher = Blake2bParams::new().hash_length(32).to_state();
+    io::copy(&mut (&mut source).take(u64::from(piece_size)), &mut hasher)?;
+    let mut content_hash = [0u8; 32];
+    content_hash.copy_from_slice(hasher.finalize().as_bytes());
+
+    if let Some(piece_info) = cache.get(&content_hash, piece_size) {
+        trace!("generate_piece_commitment_cached:finish (cached)");
+        return Ok(piece_info);
+    }
+
+    source.seek(SeekFrom::Start(start))?;
+    let piece_info = generate_piece_commitment(source, piece_size)?;
+    cache.insert(content_hash, piece_info.clone());
+
+    trace!("generate_piece_commitment_cached:finish");
+    Ok(piece_info)
+}
+
 /// Computes a NUL-byte prefix and/or suffix for `source` using the provided
 /// `piece_lengths` and `piece_size` (such that the `source`, after
 /// preprocessing, will occupy a subtree of a merkle tree built using the bytes
@@ -358,6 +545,10 @@ pub fn generate_piece_commitment<T: Read>(
 /// and writes the result to `target`. Returns a tuple containing the number of
 /// bytes written to `target` (`source` plus alignment) and the commitment.
 ///
+/// The commitment is computed from the padded bytes as they are written, so the
+/// piece is only processed once, and it equals the commitment returned by
+/// `generate_piece_commitment` for the same piece.
+///
 /// WARNING: Depending on the ordering and size of the pieces in
 /// `piece_lengths`, this function could write a prefix of NUL bytes which
 /// wastes ($SIZESECTORSIZE/2)-$MINIMUM_PIECE_SIZE space. This function will be
@@ -376,6 +567,84 @@ pub fn add_piece<R, W>(
     piece_size: UnpaddedBytesAmount,
     piece_lengths: &[UnpaddedBytesAmount],
 ) -> Result<(PieceInfo, UnpaddedBytesAmount)>
+where
+    R: Read,
+    W: Write,
+{
+    add_piece_with_options(
+        source,
+        target,
+        piece_size,
+        piece_lengths,
+        &Fr32Options::default(),
+    )
+}
+
+/// Like `add_piece`, but reads and writes the piece bytes in chunks of
+/// `options.chunk_bytes`, e.g. to use larger chunks on fast storage. The output
+/// and the commitment do not depend on the chunk size.
+///
+/// # Arguments
+///
+/// * `source` - a readable source of unprocessed piece bytes.
+/// * `target` - a writer where we will write the processed piece bytes.
+/// * `piece_size` - the number of unpadded user-bytes which can be read from source before EOF.
+/// * `piece_lengths` - the number of bytes for each previous piece in the sector.
+/// * `options` - the Fr32 padding options.
+pub fn add_piece_with_options<R, W>(
+    source: R,
+    target: W,
+    piece_size: UnpaddedBytesAmount,
+    piece_lengths: &[UnpaddedBytesAmount],
+    options: &Fr32Options,
+) -> Result<(PieceInfo, UnpaddedBytesAmount)>
+where
+    R: Read,
+    W: Write,
+{
+    add_piece_inner(source, target, piece_size, piece_lengths, options, None)
+}
+
+/// Like `add_piece`, but reads the piece from a `PieceSource`. If the source knows the
+/// commitment of the piece, it is used instead of computing it, see `PieceSource` for the
+/// implications.
+///
+/// # Arguments
+///
+/// * `source` - the source of the unprocessed piece bytes.
+/// * `target` - a writer where we will write the processed piece bytes.
+/// * `piece_size` - the number of unpadded user-bytes which can be read from source before EOF.
+/// * `piece_lengths` - the number of bytes for each previous piece in the sector.
+pub fn add_piece_from_source<S, W>(
+    source: S,
+    target: W,
+    piece_size: UnpaddedBytesAmount,
+    piece_lengths: &[UnpaddedBytesAmount],
+) -> Result<(PieceInfo, UnpaddedBytesAmount)>
+where
+    S: PieceSource,
+    W: Write,
+{
+    let known_comm_p = source.known_comm_p();
+    add_piece_inner(
+        source.into_reader(),
+        target,
+        piece_size,
+        piece_lengths,
+        &Fr32Options::default(),
+        known_comm_p,
+    )
+}
+
+// Writes the piece and computes its commitment, unless `known_comm_p` is given.
+fn add_piece_inner<R, W>(
+    source: R,
+    target: W,
+    piece_size: UnpaddedBytesAmount,
+    piece_lengths: &[UnpaddedBytesAmount],
+    options: &Fr32Options,
+    known_comm_p: Option<Commitment>,
+) -> Result<(PieceInfo, UnpaddedBytesAmount)>
 where
     R: Read,
     W: Write,
@@ -384,9 +653,10 @@ where
 
     let result = measure_op(Operation::AddPiece, || {
         ensure_piece_size(piece_size)?;
+        ensure!(options.chunk_bytes > 0, "Fr32 chunk size must not be zero");
 
-        let source = BufReader::new(source);
-        let mut target = BufWriter::new(target);
+        let source = BufReader::with_capacity(options.chunk_bytes, source);
+        let mut target = BufWriter::with_capacity(options.chunk_bytes, target);
 
         let written_bytes = sum_piece_bytes_with_alignment(piece_lengths);
         let piece_alignment = get_piece_alignment(written_bytes, piece_size);
@@ -397,9 +667,18 @@ where
             target.write_all(&[0u8][..])?;
         }
 
-        let mut commitment_reader = CommitmentReader::new(fr32_reader);
-        let n = io::copy(&mut commitment_reader, &mut target)
-            .context("failed to write and preprocess bytes")?;
+        let (n, commitment_reader) = match known_comm_p {
+            Some(_) => {
+                let mut fr32_reader = fr32_reader;
+                (io::copy(&mut fr32_reader, &mut target), None)
+            }
+            None => {
+                let mut commitment_reader = CommitmentReader::new(fr32_reader);
+                let n = io::copy(&mut commitment_reader, &mut target);
+                (n, Some(commitment_reader))
+            }
+        };
+        let n = n.context("failed to write and preprocess bytes")?;
 
         ensure!(n != 0, "add_piece: read 0 bytes before EOF from source");
         let n = PaddedBytesAmount(n);
@@ -412,9 +691,16 @@ where
             target.write_all(&[0u8][..])?;
         }
 
-        let commitment = commitment_reader.finish()?;
-        let mut comm = [0u8; 32];
-        comm.copy_from_slice(commitment.as_ref());
+        let comm = match commitment_reader {
+            Some(commitment_reader) => {
+                let commitment = commitment_reader.finish()?;
+                let mut comm = [0u8; 32];
+                comm.copy_from_slice(commitment.as_ref());
+                comm
+            }
+            // The known commitment is trusted, see `PieceSource`.
+            None => known_comm_p.context("missing piece commitment")?,
+        };
 
         let written = piece_alignment.left_bytes + piece_alignment.right_bytes + piece_size;
 
@@ -676,61 +962,432 @@ where
 {
     info!("validate_cache_for_commit:start");
 
-    // Verify that the replica exists and is not empty.
+    let (defects, _) = commit_cache_defects::<Tree>(cache_path.as_ref(), replica_path.as_ref());
     ensure!(
-        replica_path.as_ref().exists(),
-        "Missing replica: {}",
-        replica_path.as_ref().to_path_buf().display()
+        defects.is_empty(),
+        "invalid cache for commit: {}",
+        defects
+            .iter()
+            .map(|defect| format!("{}: {}", defect.path.display(), defect.description))
+            .collect::<Vec<_>>()
+            .join("; ")
     );
 
-    let metadata = File::open(&replica_path)?.metadata()?;
-    ensure!(
-        metadata.len() > 0,
-        "Replica {} exists, but is empty!",
-        replica_path.as_ref().to_path_buf().display()
-    );
+    info!("validate_cache_for_commit:finish");
 
-    let cache = &cache_path.as_ref();
+    Ok(())
+}
 
-    // Make sure p_aux exists and is valid.
-    let p_aux_path = cache.join(CacheKey::PAux.to_string());
-    let p_aux_bytes = fs::read(&p_aux_path)
-        .with_context(|| format!("could not read file p_aux={:?}", p_aux_path))?;
+/// Like `validate_cache_for_commit`, but instead of failing on the first problem all checks are
+/// run, and every defect found is returned. An empty list means the cache is valid.
+///
+/// In addition, every node of tree-d is recomputed from its children, so that corrupted nodes
+/// are reported with their byte offset. This reads the whole tree and is meant for diagnosing
+/// failing sectors.
+///
+/// # Arguments
+///
+/// * `cache_path` - path to the cache directory of the sector.
+/// * `replica_path` - path to the replica of the sector.
+pub fn validate_cache_for_commit_verbose<R, T, Tree: MerkleTreeTrait>(
+    cache_path: R,
+    replica_path: T,
+) -> Result<Vec<CacheDefect>>
+where
+    R: AsRef<Path>,
+    T: AsRef<Path>,
+{
+    info!("validate_cache_for_commit_verbose:start");
+
+    let replica = replica_path.as_ref();
+    let (mut defects, t_aux) = commit_cache_defects::<Tree>(cache_path.as_ref(), replica);
+
+    // The stores cannot be located without t_aux, and tree-d can only be traversed if it passed
+    // the structural checks.
+    if let Some(t_aux) = t_aux {
+        let tree_d_path =
+            StoreConfig::data_path(&t_aux.tree_d_config.path, &t_aux.tree_d_config.id);
+        let tree_d_valid = defects.iter().all(|defect| defect.path != tree_d_path);
+        if tree_d_valid && tree_d_path.exists() {
+            match tree_d_node_defects(&tree_d_path) {
+                Ok((leaves, tree_d_defects)) => {
+                    defects.extend(tree_d_defects);
+
+                    if let Ok(metadata) = fs::metadata(replica) {
+                        let expected_len = (leaves * NODE_SIZE) as u64;
+                        if metadata.len() != expected_len {
+                            defects.push(cache_defect(
+                                replica,
+                                None,
+                                format!(
+                                    "replica has length {}, expected {}",
+                                    metadata.len(),
+                                    expected_len
+                                ),
+                            ));
+                        }
+                    }
+                }
+                Err(err) => defects.push(cache_defect(&tree_d_path, None, format!("{:#}", err))),
+            }
+        }
+    }
 
-    let _: PersistentAux<<Tree::Hasher as Hasher>::Domain> = deserialize(&p_aux_bytes)?;
-    drop(p_aux_bytes);
+    info!("validate_cache_for_commit_verbose:finish");
 
-    // Make sure t_aux exists and is valid.
-    let t_aux = {
-        let t_aux_path = cache.join(CacheKey::TAux.to_string());
-        let t_aux_bytes = fs::read(&t_aux_path)
-            .with_context(|| format!("could not read file t_aux={:?}", t_aux_path))?;
+    Ok(defects)
+}
+
+// Runs the checks of `validate_cache_for_commit` and returns every defect found, together with
+// the t_aux of the cache (switched to `cache`) if it could be read.
+fn commit_cache_defects<Tree: MerkleTreeTrait>(
+    cache: &Path,
+    replica: &Path,
+) -> (
+    Vec<CacheDefect>,
+    Option<TemporaryAux<Tree, DefaultPieceHasher>>,
+) {
+    let mut defects = Vec::new();
+
+    // Verify that the replica exists and is not empty.
+    match fs::metadata(replica) {
+        Ok(metadata) => {
+            if metadata.len() == 0 {
+                defects.push(cache_defect(replica, None, "replica is empty".to_string()));
+            }
+        }
+        Err(err) => {
+            defects.push(cache_defect(
+                replica,
+                None,
+                format!("missing replica: {}", err),
+            ));
+        }
+    }
 
-        let mut res: TemporaryAux<Tree, DefaultPieceHasher> = deserialize(&t_aux_bytes)?;
+    // Make sure p_aux exists and is valid.
+    let p_aux_path = cache.join(CacheKey::PAux.to_string());
+    let p_aux = fs::read(&p_aux_path)
+        .context("could not read file")
+        .and_then(|bytes| {
+            deserialize::<PersistentAux<<Tree::Hasher as Hasher>::Domain>>(&bytes)
+                .map_err(Into::into)
+        });
+    if let Err(err) = p_aux {
+        defects.push(cache_defect(
+            &p_aux_path,
+            None,
+            format!("invalid p_aux: {:#}", err),
+        ));
+    }
 
-        // Switch t_aux to the passed in cache_path
-        res.set_cache_path(&cache_path);
-        res
+    // Make sure t_aux exists and is valid.
+    let t_aux_path = cache.join(CacheKey::TAux.to_string());
+    let t_aux = fs::read(&t_aux_path)
+        .context("could not read file")
+        .and_then(|bytes| {
+            deserialize::<TemporaryAux<Tree, DefaultPieceHasher>>(&bytes).map_err(Into::into)
+        });
+    let mut t_aux: TemporaryAux<Tree, DefaultPieceHasher> = match t_aux {
+        Ok(t_aux) => t_aux,
+        Err(err) => {
+            defects.push(cache_defect(
+                &t_aux_path,
+                None,
+                format!("invalid t_aux: {:#}", err),
+            ));
+            return (defects, None);
+        }
     };
+    // Switch t_aux to the passed in cache_path
+    t_aux.set_cache_path(cache);
 
     // Verify all stores/labels within the Labels object.
-    let cache = cache_path.as_ref().to_path_buf();
-    t_aux.labels.verify_stores(verify_store, &cache)?;
+    let base_tree_count = get_base_tree_count::<Tree>();
+    for label in &t_aux.labels.labels {
+        let mut label = label.clone();
+        label.path = cache.to_path_buf();
+        defects.extend(store_defect(
+            &label,
+            verify_store(
+                &label,
+                <DefaultBinaryTree as MerkleTreeTrait>::Arity::to_usize(),
+                base_tree_count,
+            ),
+        ));
+    }
 
     // Verify each tree disk store.
-    verify_store(
+    defects.extend(store_defect(
         &t_aux.tree_d_config,
-        <DefaultBinaryTree as MerkleTreeTrait>::Arity::to_usize(),
-        get_base_tree_count::<Tree>(),
-    )?;
-    verify_store(
+        verify_store(
+            &t_aux.tree_d_config,
+            <DefaultBinaryTree as MerkleTreeTrait>::Arity::to_usize(),
+            base_tree_count,
+        ),
+    ));
+    defects.extend(store_defect(
         &t_aux.tree_c_config,
-        <DefaultOctTree as MerkleTreeTrait>::Arity::to_usize(),
-        get_base_tree_count::<Tree>(),
+        verify_store(
+            &t_aux.tree_c_config,
+            <DefaultOctTree as MerkleTreeTrait>::Arity::to_usize(),
+            base_tree_count,
+        ),
+    ));
+    defects.extend(store_defect(
+        &t_aux.tree_r_last_config,
+        verify_level_cache_store::<DefaultOctTree>(&t_aux.tree_r_last_config),
+    ));
+
+    (defects, Some(t_aux))
+}
+
+fn cache_defect(path: &Path, offset: Option<u64>, description: String) -> CacheDefect {
+    CacheDefect {
+        path: path.to_path_buf(),
+        offset,
+        description,
+    }
+}
+
+fn store_defect(config: &StoreConfig, result: Result<()>) -> Option<CacheDefect> {
+    result.err().map(|err| {
+        cache_defect(
+            &StoreConfig::data_path(&config.path, &config.id),
+            None,
+            format!("{:#}", err),
+        )
+    })
+}
+
+// Recomputes every inner node of the tree-d stored at `path` from its children, and returns
+// the number of leaves and a defect for each node which does not match.
+fn tree_d_node_defects(path: &Path) -> Result<(usize, Vec<CacheDefect>)> {
+    let file = File::open(path).with_context(|| format!("could not open {:?}", path))?;
+    let data = unsafe { MmapOptions::new().map(&file) }
+        .with_context(|| format!("could not mmap {:?}", path))?;
+
+    // A binary tree over n leaves has 2n - 1 nodes.
+    let nodes = data.len() / NODE_SIZE;
+    let leaves = (nodes + 1) / 2;
+    if data.len() % NODE_SIZE != 0 || !leaves.is_power_of_two() || nodes != 2 * leaves - 1 {
+        let defect = cache_defect(
+            path,
+            None,
+            format!("unexpected tree-d length {}", data.len()),
+        );
+        return Ok((leaves, vec![defect]));
+    }
+
+    let node = |index: usize| &data[index * NODE_SIZE..(index + 1) * NODE_SIZE];
+    let mut defects = Vec::new();
+    let mut row_start = 0;
+    let mut row_len = leaves;
+    while row_len > 1 {
+        let parents_start = row_start + row_len;
+        for i in 0..row_len / 2 {
+            let left = row_start + 2 * i;
+            let parent = parents_start + i;
+            if piece_hash(node(left), node(left + 1)).as_ref() != node(parent) {
+                defects.push(cache_defect(
+                    path,
+                    Some((parent * NODE_SIZE) as u64),
+                    format!(
+                        "node does not match the hash of its children at offsets {} and {}",
+                        left * NODE_SIZE,
+                        (left + 1) * NODE_SIZE
+                    ),
+                ));
+            }
+        }
+        row_start = parents_start;
+        row_len /= 2;
+    }
+
+    Ok((leaves, defects))
+}
+
+/// Computes comm_r_last, the root of tree-r-last, from the roots cached in the tree-r-last files
+/// in `cache_path`, rather than from the p_aux stored alongside them.
+///
+/// The root of each base tree is the last node of its file, and the roots of the base trees are
+/// combined according to the sub and top tree arities of `Tree`. The base trees themselves are
+/// not rebuilt from the replica.
+///
+/// # Arguments
+///
+/// * `cache_path` - path to the cache directory of the sector.
+pub fn compute_tree_r_last_root<Tree: 'static + MerkleTreeTrait>(
+    cache_path: &Path,
+) -> Result<Commitment> {
+    info!("compute_tree_r_last_root:start");
+
+    let root = compute_cached_tree_root::<Tree>(cache_path, CacheKey::CommRLastTree)?;
+    let mut comm_r_last = [0; 32];
+    root.write_bytes(&mut comm_r_last)?;
+
+    info!("compute_tree_r_last_root:finish");
+
+    Ok(comm_r_last)
+}
+
+/// Computes comm_r = H(comm_c || comm_r_last) of a sealed sector from the roots cached in its
+/// tree-c and tree-r-last files, rather than from the p_aux stored alongside them.
+///
+/// Only the last node of each base tree file is read. Neither tree is rebuilt, so this does not
+/// check that the trees match the replica or the layers they were built from.
+///
+/// Tree-c is removed by `clear_cache`, so this only works on caches which have not been cleared.
+///
+/// # Arguments
+///
+/// * `porep_config` - porep configuration containing the sector size.
+/// * `replica_path` - path to the sealed replica, which must be exactly one sector long.
+/// * `cache_path` - path to the cache directory of the sector.
+pub fn read_cached_comm_r<Tree: 'static + MerkleTreeTrait>(
+    porep_config: &PoRepConfig,
+    replica_path: &Path,
+    cache_path: &Path,
+) -> Result<Commitment> {
+    info!("read_cached_comm_r:start");
+
+    let replica_len = fs::metadata(replica_path)
+        .with_context(|| format!("could not read metadata of replica={:?}", replica_path))?
+        .len();
+    ensure!(
+        replica_len == u64::from(porep_config.sector_size),
+        "invalid replica length {} of replica={:?}, expected the sector size {}",
+        replica_len,
+        replica_path,
+        u64::from(porep_config.sector_size)
+    );
+
+    let comm_c = compute_cached_tree_root::<Tree>(cache_path, CacheKey::CommCTree)
+        .context("failed to compute comm_c, was the cache cleared?")?;
+    let comm_r_last = compute_cached_tree_root::<Tree>(cache_path, CacheKey::CommRLastTree)
+        .context("failed to compute comm_r_last")?;
+
+    let mut comm_r = [0; 32];
+    comm_r_from_parts::<Tree>(&comm_c, &comm_r_last).write_bytes(&mut comm_r)?;
+
+    info!("read_cached_comm_r:finish");
+
+    Ok(comm_r)
+}
+
+/// Computes the root of a tree with the shape of `Tree` from its base tree files in
+/// `cache_path`. The root of each base tree is the last node of its file, and the roots of the
+/// base trees are combined according to the sub and top tree arities of `Tree`.
+fn compute_cached_tree_root<Tree: 'static + MerkleTreeTrait>(
+    cache_path: &Path,
+    cache_key: CacheKey,
+) -> Result<<Tree::Hasher as Hasher>::Domain> {
+    let tree_count = get_base_tree_count::<Tree>();
+    let id = cache_key.to_string();
+    let ids: Vec<String> = if tree_count == 1 {
+        vec![id]
+    } else {
+        (0..tree_count).map(|i| format!("{}-{}", id, i)).collect()
+    };
+
+    let mut roots = ids
+        .iter()
+        .map(|id| {
+            let path = StoreConfig::data_path(cache_path, id);
+            let mut file =
+                File::open(&path).with_context(|| format!("could not open {:?}", path))?;
+            let len = file.metadata()?.len();
+            ensure!(
+                len >= NODE_SIZE as u64,
+                "{} file {:?} is empty",
+                cache_key,
+                path
+            );
+
+            let mut root = [0u8; NODE_SIZE];
+            file.seek(SeekFrom::Start(len - NODE_SIZE as u64))?;
+            file.read_exact(&mut root)?;
+            <Tree::Hasher as Hasher>::Domain::try_from_bytes(&root)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    // Combine the base tree roots into the sub tree roots, then those into the top tree root.
+    for arity in [
+        Tree::SubTreeArity::to_usize(),
+        Tree::TopTreeArity::to_usize(),
+    ] {
+        if arity == 0 {
+            continue;
+        }
+        roots = roots
+            .chunks(arity)
+            .map(|nodes| <Tree::Hasher as Hasher>::Function::default().multi_node(nodes, 0))
+            .collect();
+    }
+    ensure!(roots.len() == 1, "invalid {} shape", cache_key);
+
+    Ok(roots[0])
+}
+
+/// Returns the files which are expected in the cache directory of a sealed sector once its
+/// temporary data has been cleared (see `clear_cache`), ordered by their relative path. The
+/// manifest is derived from the sector size and tree shape only, so it can be used to validate a
+/// cache directory (or a backup of it) without reading it.
+///
+/// # Arguments
+///
+/// * `sector_size` - the size of the sealed sector.
+pub fn cache_file_manifest<Tree: 'static + MerkleTreeTrait>(
+    sector_size: SectorSize,
+) -> Result<Vec<CacheFileSpec>> {
+    info!("cache_file_manifest:start");
+
+    let p_aux = PersistentAux::<<Tree::Hasher as Hasher>::Domain> {
+        comm_c: Default::default(),
+        comm_r_last: Default::default(),
+    };
+    let mut manifest = vec![
+        CacheFileSpec {
+            name: CacheKey::PAux.to_string(),
+            relative_path: PathBuf::from(CacheKey::PAux.to_string()),
+            size: Some(serialized_size(&p_aux)?),
+        },
+        CacheFileSpec {
+            name: CacheKey::TAux.to_string(),
+            relative_path: PathBuf::from(CacheKey::TAux.to_string()),
+            size: None,
+        },
+    ];
+
+    let arity = Tree::Arity::to_usize();
+    let base_tree_count = get_base_tree_count::<Tree>();
+    let base_tree_size = get_base_tree_size::<Tree>(sector_size)?;
+    let base_tree_leafs = get_base_tree_leafs::<Tree>(base_tree_size)?;
+    // Sealing picks rows_to_discard based on the node count of the whole sector.
+    let nodes_count = u64::from(sector_size) as usize / NODE_SIZE;
+    let cache_size = get_merkle_tree_cache_size(
+        base_tree_leafs,
+        arity,
+        default_rows_to_discard(nodes_count, arity),
     )?;
-    verify_level_cache_store::<DefaultOctTree>(&t_aux.tree_r_last_config)?;
+    let tree_r_last_len = (cache_size * NODE_SIZE) as u64;
+
+    for i in 0..base_tree_count {
+        let id = if base_tree_count == 1 {
+            CacheKey::CommRLastTree.to_string()
+        } else {
+            format!("{}-{}", CacheKey::CommRLastTree, i)
+        };
+        manifest.push(CacheFileSpec {
+            relative_path: StoreConfig::data_path(Path::new(""), &id),
+            name: id,
+            size: Some(tree_r_last_len),
+        });
+    }
 
-    info!("validate_cache_for_commit:finish");
+    manifest.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
 
-    Ok(())
+    info!("cache_file_manifest:finish");
+
+    Ok(manifest)
 }
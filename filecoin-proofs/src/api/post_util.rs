@@ -1,31 +1,50 @@
 use std::collections::BTreeMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use anyhow::{anyhow, ensure, Context, Result};
 use bincode::deserialize;
 use filecoin_hashers::{sha256::Sha256Hasher, Hasher};
+use generic_array::typenum::Unsigned;
 use log::{debug, info};
+use rand::Rng;
+use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
 use storage_proofs_core::{
-    cache_key::CacheKey, merkle::MerkleTreeTrait, proof::ProofScheme, sector::SectorId,
+    cache_key::CacheKey,
+    merkle::{MerkleProofTrait, MerkleTreeTrait},
+    proof::ProofScheme,
+    sector::SectorId,
+    util::{default_rows_to_discard, NODE_SIZE},
 };
 use storage_proofs_post::fallback::{
     self, generate_leaf_challenge, get_challenge_index, FallbackPoSt, SectorProof,
 };
 
 use crate::{
-    api::as_safe_commitment,
+    api::{as_safe_commitment, comm_r_from_parts, get_base_tree_size, remove_seal_job_outputs},
     constants::DefaultPieceHasher,
     types::{
-        ChallengeSeed, FallbackPoStSectorProof, PoStConfig, PrivateReplicaInfo, ProverId,
-        TemporaryAux, VanillaProof,
+        ChallengeSeed, ClearCacheReport, Commitment, FallbackPoStSectorProof, LeafInclusionProof,
+        MappedReplica, PoStConfig, PrivateReplicaInfo, ProverId, TemporaryAux, VanillaProof,
     },
     PartitionSnarkProof, PoStType, SnarkProof, SINGLE_PARTITION_PROOF_LEN,
+    WINNING_POST_SECTOR_COUNT,
 };
 
-// Ensure that any associated cached data persisted is discarded.
+// Ensure that any associated cached data persisted is discarded, including the phase outputs
+// persisted by `execute_seal_job`.
 pub fn clear_cache<Tree: MerkleTreeTrait>(cache_dir: &Path) -> Result<()> {
-    info!("clear_cache:start");
+    clear_cache_reporting::<Tree>(cache_dir).map(|_| ())
+}
+
+/// Like `clear_cache`, but reports which files were deleted from `cache_dir` and how many
+/// bytes were freed.
+///
+/// # Arguments
+///
+/// * `cache_dir` - the cache directory of the sealed (or updated) sector.
+pub fn clear_cache_reporting<Tree: MerkleTreeTrait>(cache_dir: &Path) -> Result<ClearCacheReport> {
+    info!("clear_cache_reporting:start");
 
     let mut t_aux: TemporaryAux<Tree, Sha256Hasher> = {
         let f_aux_path = cache_dir.to_path_buf().join(CacheKey::TAux.to_string());
@@ -35,12 +54,38 @@ pub fn clear_cache<Tree: MerkleTreeTrait>(cache_dir: &Path) -> Result<()> {
         deserialize(&aux_bytes)
     }?;
 
+    let files_before = cache_dir_files(cache_dir)?;
+
     t_aux.set_cache_path(cache_dir);
-    let result = TemporaryAux::<Tree, DefaultPieceHasher>::clear_temp(t_aux);
+    TemporaryAux::<Tree, DefaultPieceHasher>::clear_temp(t_aux)?;
+    remove_seal_job_outputs(cache_dir)?;
+
+    let files_after = cache_dir_files(cache_dir)?;
+    let mut report = ClearCacheReport::default();
+    for (path, len) in files_before {
+        if !files_after.contains_key(&path) {
+            report.freed_bytes += len;
+            report.deleted_files.push(path);
+        }
+    }
 
-    info!("clear_cache:finish");
+    info!("clear_cache_reporting:finish");
 
-    result
+    Ok(report)
+}
+
+// Lists the files directly within `dir`, together with their sizes.
+fn cache_dir_files(dir: &Path) -> Result<BTreeMap<PathBuf, u64>> {
+    let mut files = BTreeMap::new();
+    for entry in fs::read_dir(dir).with_context(|| format!("could not read dir={:?}", dir))? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if metadata.is_file() {
+            files.insert(entry.path(), metadata.len());
+        }
+    }
+
+    Ok(files)
 }
 
 // Ensure that any associated cached data persisted is discarded.
@@ -58,6 +103,114 @@ pub fn clear_caches<Tree: MerkleTreeTrait>(
     Ok(())
 }
 
+/// Checks that `post_config` can be used to prove (or verify) a proof-of-spacetime over
+/// `num_replicas` sectors, so that a misconfigured call fails before any proving work.
+///
+/// A Winning PoSt is generated over exactly `WINNING_POST_SECTOR_COUNT` sectors. A Window PoSt
+/// needs at least one sector; the replicas are split into partitions of `sector_count` sectors
+/// each.
+///
+/// # Arguments
+///
+/// * `post_config` - post config to validate.
+/// * `num_replicas` - the number of replicas the proof is generated (or verified) for.
+pub fn validate_post_config(post_config: &PoStConfig, num_replicas: usize) -> Result<()> {
+    ensure!(
+        post_config.challenge_count > 0,
+        "invalid post config: challenge count must be greater than zero"
+    );
+    ensure!(
+        post_config.sector_count > 0,
+        "invalid post config: sector count must be greater than zero"
+    );
+
+    match post_config.typ {
+        PoStType::Winning => {
+            ensure!(
+                post_config.sector_count == WINNING_POST_SECTOR_COUNT,
+                "invalid post config: winning post sector count must be {}, got {}",
+                WINNING_POST_SECTOR_COUNT,
+                post_config.sector_count
+            );
+            ensure!(
+                num_replicas == post_config.sector_count,
+                "invalid amount of replicas: expected {}, got {}",
+                post_config.sector_count,
+                num_replicas
+            );
+        }
+        PoStType::Window => {
+            ensure!(num_replicas > 0, "invalid amount of replicas: got none");
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks that the replica at `replica_path` is exactly one sector of `post_config` long, so
+/// that a truncated replica is caught with a clear error before proving, instead of as a
+/// faulty sector while generating the inclusion proofs. Updated (snap-deal) replicas have the
+/// same length as sealed ones.
+///
+/// # Arguments
+///
+/// * `post_config` - post config that contains the sector size.
+/// * `replica_path` - path to the sealed or updated replica.
+pub fn validate_replica_length<Tree: MerkleTreeTrait>(
+    post_config: &PoStConfig,
+    replica_path: &Path,
+) -> Result<()> {
+    let sector_size = u64::from(post_config.sector_size);
+    // The replica must fill the base trees of the sector shape completely.
+    get_base_tree_size::<Tree>(post_config.sector_size)
+        .with_context(|| format!("invalid sector size {} for the tree shape", sector_size))?;
+
+    let replica_len = fs::metadata(replica_path)
+        .with_context(|| format!("could not read metadata of replica={:?}", replica_path))?
+        .len();
+    ensure!(
+        replica_len == sector_size,
+        "invalid replica length {} of replica={:?}, expected the sector size {}",
+        replica_len,
+        replica_path,
+        sector_size
+    );
+
+    Ok(())
+}
+
+/// Checks that every sector proven in both a Winning PoSt and a Window PoSt was proven against
+/// the same comm_r. Sectors which are part of only one of the proofs are fine, as long as they
+/// are listed with a single comm_r.
+///
+/// Returns an error listing all sectors with differing commitments.
+///
+/// # Arguments
+///
+/// * `winning_replicas` - the sector ids and commitments of the Winning PoSt.
+/// * `window_replicas` - the sector ids and commitments of the Window PoSt.
+pub fn assert_comm_r_consistency(
+    winning_replicas: &[(SectorId, Commitment)],
+    window_replicas: &[(SectorId, Commitment)],
+) -> Result<()> {
+    let mut comm_rs: BTreeMap<SectorId, &Commitment> = BTreeMap::new();
+    let mut inconsistent = Vec::new();
+    for (sector_id, comm_r) in winning_replicas.iter().chain(window_replicas.iter()) {
+        let known = comm_rs.entry(*sector_id).or_insert(comm_r);
+        if *known != comm_r && !inconsistent.contains(sector_id) {
+            inconsistent.push(*sector_id);
+        }
+    }
+
+    ensure!(
+        inconsistent.is_empty(),
+        "comm_r differs between winning and window post for sectors {:?}",
+        inconsistent
+    );
+
+    Ok(())
+}
+
 /// Generates the challenges per SectorId required for either a Window
 /// proof-of-spacetime or a Winning proof-of-spacetime.
 pub fn generate_fallback_sector_challenges<Tree: 'static + MerkleTreeTrait>(
@@ -130,6 +283,34 @@ pub fn generate_fallback_sector_challenges<Tree: 'static + MerkleTreeTrait>(
     Ok(sector_challenges)
 }
 
+/// Like `generate_fallback_sector_challenges`, but generates the challenges for many
+/// `(randomness, prover_id, sectors)` entries in parallel, e.g. for all proofs of a tipset.
+///
+/// The results are in the order of `entries`, and each is identical to calling
+/// `generate_fallback_sector_challenges` with that entry.
+pub fn generate_fallback_sector_challenges_batch<Tree: 'static + MerkleTreeTrait>(
+    post_config: &PoStConfig,
+    entries: &[(ChallengeSeed, ProverId, Vec<SectorId>)],
+) -> Result<Vec<BTreeMap<SectorId, Vec<u64>>>> {
+    info!("generate_fallback_sector_challenges_batch:start");
+
+    let challenges = entries
+        .par_iter()
+        .map(|(randomness, prover_id, sectors)| {
+            generate_fallback_sector_challenges::<Tree>(
+                post_config,
+                randomness,
+                sectors,
+                *prover_id,
+            )
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    info!("generate_fallback_sector_challenges_batch:finish");
+
+    Ok(challenges)
+}
+
 /// Generates a single vanilla proof required for either Window proof-of-spacetime
 /// or Winning proof-of-spacetime.
 pub fn generate_single_vanilla_proof<Tree: 'static + MerkleTreeTrait>(
@@ -140,27 +321,60 @@ pub fn generate_single_vanilla_proof<Tree: 'static + MerkleTreeTrait>(
 ) -> Result<FallbackPoStSectorProof<Tree>> {
     info!("generate_single_vanilla_proof:start: {:?}", sector_id);
 
-    let tree = &replica
-        .merkle_tree(post_config.sector_size)
+    let mapped_replica = replica
+        .mapped_replica(post_config.sector_size)
         .with_context(|| {
             format!(
-                "generate_single_vanilla_proof: merkle_tree failed: {:?}",
+                "generate_single_vanilla_proof: opening replica failed: {:?}",
                 sector_id
             )
         })?;
-    let comm_r = replica.safe_comm_r().with_context(|| {
-        format!(
-            "generate_single_vanilla_poof: safe_comm_r failed: {:?}",
-            sector_id
-        )
-    })?;
-    let comm_c = replica.safe_comm_c();
-    let comm_r_last = replica.safe_comm_r_last();
+    let proof = single_vanilla_proof(sector_id, &mapped_replica, challenges)?;
+
+    info!("generate_single_vanilla_proof:finish: {:?}", sector_id);
 
+    Ok(proof)
+}
+
+/// Generates a single vanilla proof required for either Window proof-of-spacetime
+/// or Winning proof-of-spacetime, using an already opened replica (see
+/// [`PrivateReplicaInfo::with_mmap`]) which can be reused across multiple calls.
+pub fn generate_single_vanilla_proof_with_mapped_replica<Tree: 'static + MerkleTreeTrait>(
+    post_config: &PoStConfig,
+    sector_id: SectorId,
+    mapped_replica: &MappedReplica<Tree>,
+    challenges: &[u64],
+) -> Result<FallbackPoStSectorProof<Tree>> {
+    info!(
+        "generate_single_vanilla_proof_with_mapped_replica:start: {:?}",
+        sector_id
+    );
+    ensure!(
+        mapped_replica.sector_size() == post_config.sector_size,
+        "mapped replica sector size {:?} does not match post config sector size {:?}",
+        mapped_replica.sector_size(),
+        post_config.sector_size,
+    );
+
+    let proof = single_vanilla_proof(sector_id, mapped_replica, challenges)?;
+
+    info!(
+        "generate_single_vanilla_proof_with_mapped_replica:finish: {:?}",
+        sector_id
+    );
+
+    Ok(proof)
+}
+
+fn single_vanilla_proof<Tree: 'static + MerkleTreeTrait>(
+    sector_id: SectorId,
+    mapped_replica: &MappedReplica<Tree>,
+    challenges: &[u64],
+) -> Result<FallbackPoStSectorProof<Tree>> {
     let priv_sectors = vec![fallback::PrivateSector {
-        tree,
-        comm_c,
-        comm_r_last,
+        tree: mapped_replica.tree(),
+        comm_c: mapped_replica.safe_comm_c(),
+        comm_r_last: mapped_replica.safe_comm_r_last(),
     }];
 
     let priv_inputs = fallback::PrivateInputs::<Tree> {
@@ -175,15 +389,185 @@ pub fn generate_single_vanilla_proof<Tree: 'static + MerkleTreeTrait>(
             )
         })?;
 
-    info!("generate_single_vanilla_proof:finish: {:?}", sector_id);
-
     Ok(FallbackPoStSectorProof {
         sector_id,
-        comm_r,
+        comm_r: mapped_replica.safe_comm_r(),
         vanilla_proof,
     })
 }
 
+/// Verifies a single sector's vanilla proof, as generated by `generate_single_vanilla_proof`,
+/// against the sector's `comm_r` and the challenges it was generated for. Only the merkle
+/// inclusion proofs are checked, no SNARK is involved.
+///
+/// This allows rejecting a malformed vanilla proof (e.g. submitted by a worker) before it is
+/// used to generate a proof-of-spacetime.
+///
+/// # Arguments
+///
+/// * `post_config` - post config that contains the sector size.
+/// * `sector_id` - the sector id of the proven sector.
+/// * `comm_r` - the replica commitment of the proven sector.
+/// * `challenges` - the challenged leaves the proof was generated for.
+/// * `proof` - the vanilla proof to verify.
+pub fn verify_single_vanilla_proof<Tree: 'static + MerkleTreeTrait>(
+    post_config: &PoStConfig,
+    sector_id: SectorId,
+    comm_r: &Commitment,
+    challenges: &[u64],
+    proof: &FallbackPoStSectorProof<Tree>,
+) -> Result<bool> {
+    info!("verify_single_vanilla_proof:start: {:?}", sector_id);
+
+    let comm_r_safe: <Tree::Hasher as Hasher>::Domain = as_safe_commitment(comm_r, "comm_r")?;
+    ensure!(
+        proof.vanilla_proof.sectors.len() == 1,
+        "expected a single sector proof, got {}",
+        proof.vanilla_proof.sectors.len()
+    );
+
+    let sector_proof = &proof.vanilla_proof.sectors[0];
+    let inclusion_proofs = &sector_proof.inclusion_proofs;
+    let leaves = u64::from(post_config.sector_size) as usize / NODE_SIZE;
+
+    let is_valid = proof.sector_id == sector_id
+        && proof.comm_r == comm_r_safe
+        && comm_r_from_parts::<Tree>(&sector_proof.comm_c, &sector_proof.comm_r_last)
+            == comm_r_safe
+        && inclusion_proofs.len() == challenges.len()
+        && inclusion_proofs
+            .iter()
+            .zip(challenges.iter())
+            .all(|(inclusion_proof, challenge)| {
+                inclusion_proof.root() == sector_proof.comm_r_last
+                    && inclusion_proof.expected_len(leaves) == inclusion_proof.path().len()
+                    && inclusion_proof.validate(*challenge as usize)
+            });
+
+    info!("verify_single_vanilla_proof:finish: {:?}", sector_id);
+
+    Ok(is_valid)
+}
+
+/// Probabilistically checks a replica against its `comm_r`, by verifying the merkle inclusion
+/// proofs of `num_samples` uniformly random leaves of tree-r-last. This is much cheaper than a
+/// proof-of-spacetime and meant as a periodic health check: it is not a proof that the whole
+/// replica is intact, but corrupting a fraction `f` of the leaves escapes detection with a
+/// probability of only `(1 - f)^num_samples`.
+///
+/// Returns `Ok(false)` if comm_r does not match the cached comm_c and comm_r_last, or if any
+/// sampled leaf does not prove against comm_r_last.
+///
+/// # Arguments
+///
+/// * `post_config` - post config that contains the sector size.
+/// * `replica_path` - path to the sealed (or updated) replica.
+/// * `cache_path` - path to the cache directory of the replica.
+/// * `comm_r` - the replica commitment to check against.
+/// * `num_samples` - the number of leaves to sample, must not be 0.
+/// * `rng` - source of randomness for the sampled leaves.
+pub fn sample_verify_replica<Tree: 'static + MerkleTreeTrait, R: Rng>(
+    post_config: &PoStConfig,
+    replica_path: &Path,
+    cache_path: &Path,
+    comm_r: Commitment,
+    num_samples: usize,
+    rng: &mut R,
+) -> Result<bool> {
+    info!("sample_verify_replica:start");
+    ensure!(num_samples > 0, "at least one leaf must be sampled");
+
+    let replica = PrivateReplicaInfo::<Tree>::new(
+        replica_path.to_path_buf(),
+        comm_r,
+        cache_path.to_path_buf(),
+    )?;
+    let mapped_replica = replica.mapped_replica(post_config.sector_size)?;
+
+    let comm_r_last = mapped_replica.safe_comm_r_last();
+    let is_valid = mapped_replica.safe_comm_r()
+        == comm_r_from_parts::<Tree>(&mapped_replica.safe_comm_c(), &comm_r_last)
+        && {
+            let tree = mapped_replica.tree();
+            let leaves = tree.leafs();
+            let rows_to_discard = default_rows_to_discard(leaves, Tree::Arity::to_usize());
+            (0..num_samples).all(|_| {
+                let challenge = rng.gen_range(0..leaves);
+                match tree.gen_cached_proof(challenge, Some(rows_to_discard)) {
+                    Ok(proof) => proof.validate(challenge) && proof.root() == comm_r_last,
+                    Err(_) => false,
+                }
+            })
+        };
+
+    info!("sample_verify_replica:finish");
+
+    Ok(is_valid)
+}
+
+/// Generates the merkle inclusion proof of the single tree-r-last leaf `leaf_index`, i.e. the
+/// proof the vanilla PoSt proofs contain for each of their challenges. This allows for cheap
+/// spot challenges of a replica outside of a PoSt, see `verify_leaf_inclusion`.
+///
+/// # Arguments
+///
+/// * `post_config` - post config that contains the sector size.
+/// * `replica_path` - path to the sealed (or updated) replica.
+/// * `cache_path` - path to the cache directory of the replica.
+/// * `leaf_index` - the index of the leaf to prove.
+pub fn prove_leaf_inclusion<Tree: 'static + MerkleTreeTrait>(
+    post_config: &PoStConfig,
+    replica_path: &Path,
+    cache_path: &Path,
+    leaf_index: u64,
+) -> Result<LeafInclusionProof<Tree>> {
+    info!("prove_leaf_inclusion:start");
+
+    let replica = PrivateReplicaInfo::<Tree>::from_cache(
+        replica_path.to_path_buf(),
+        cache_path.to_path_buf(),
+    )?;
+    let tree = replica.merkle_tree(post_config.sector_size)?;
+    let leaves = tree.leafs();
+    ensure!(
+        leaf_index < leaves as u64,
+        "leaf index {} out of range, the tree has {} leaves",
+        leaf_index,
+        leaves
+    );
+
+    let rows_to_discard = default_rows_to_discard(leaves, Tree::Arity::to_usize());
+    let proof = tree.gen_cached_proof(leaf_index as usize, Some(rows_to_discard))?;
+    ensure!(
+        proof.validate(leaf_index as usize) && proof.root() == replica.safe_comm_r_last(),
+        "generated inclusion proof for leaf {} is invalid",
+        leaf_index
+    );
+
+    info!("prove_leaf_inclusion:finish");
+
+    Ok(proof)
+}
+
+/// Verifies that `proof`, as generated by `prove_leaf_inclusion`, proves the inclusion of leaf
+/// `leaf_index` in the tree-r-last with root `comm_r_last`. The proven leaf is `proof.leaf()`.
+///
+/// # Arguments
+///
+/// * `comm_r_last` - the root of tree-r-last of the replica.
+/// * `leaf_index` - the index of the challenged leaf.
+/// * `proof` - the inclusion proof to verify.
+pub fn verify_leaf_inclusion<Tree: 'static + MerkleTreeTrait>(
+    comm_r_last: Commitment,
+    leaf_index: u64,
+    proof: &LeafInclusionProof<Tree>,
+) -> Result<bool> {
+    let comm_r_last_safe: <Tree::Hasher as Hasher>::Domain =
+        as_safe_commitment(&comm_r_last, "comm_r_last")?;
+
+    Ok(proof.validate(leaf_index as usize) && proof.root() == comm_r_last_safe)
+}
+
 // Partition a flat vector of vanilla sector proofs.  The post_config
 // (PoSt) type is required in order to determine the proper shape of
 // the returned partitioned proofs.
@@ -404,3 +788,28 @@ pub fn get_num_partition_for_fallback_post(config: &PoStConfig, num_sectors: usi
         PoStType::Winning => 1,
     }
 }
+
+/// Returns the partition a Window PoSt proves the sector at `sector_index` in, and the index of
+/// the sector within that partition, where `sector_index` is the position of the sector in the
+/// (sector id ordered) replicas of the proof. Partitions hold `sector_count` sectors each, only
+/// the last one is padded, with copies of its last sector, so that a prover distributing the
+/// partitions assigns every sector exactly as `generate_window_post` does.
+///
+/// # Arguments
+///
+/// * `post_config` - post config that contains the number of sectors per partition.
+/// * `sector_index` - the position of the sector within all proven sectors.
+pub fn sector_partition_location(
+    post_config: &PoStConfig,
+    sector_index: usize,
+) -> Result<(usize, usize)> {
+    ensure!(
+        post_config.sector_count > 0,
+        "post config must allow at least one sector per partition"
+    );
+
+    Ok((
+        sector_index / post_config.sector_count,
+        sector_index % post_config.sector_count,
+    ))
+}
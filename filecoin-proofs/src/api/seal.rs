@@ -1,16 +1,21 @@
+use std::collections::{BTreeSet, HashMap};
 use std::fs::{self, metadata, File, OpenOptions};
 use std::io::Write;
+use std::marker::PhantomData;
+use std::ops::Range;
 use std::path::{Path, PathBuf};
 
-use anyhow::{ensure, Context, Result};
+use anyhow::{anyhow, ensure, Context, Result};
 use bellperson::groth16;
 use bincode::{deserialize, serialize};
 use blstrs::{Bls12, Scalar as Fr};
 use filecoin_hashers::{Domain, Hasher};
-use log::{info, trace};
+use log::{debug, info, trace};
 use memmap2::MmapOptions;
 use merkletree::store::{DiskStore, Store, StoreConfig};
-use rayon::prelude::*;
+use rand::rngs::OsRng;
+use rayon::{prelude::*, ThreadPool};
+use serde::{de::DeserializeOwned, Serialize};
 use sha2::{Digest, Sha256};
 use storage_proofs_core::{
     cache_key::CacheKey,
@@ -26,13 +31,16 @@ use storage_proofs_core::{
     Data,
 };
 use storage_proofs_porep::stacked::{
-    self, generate_replica_id, ChallengeRequirements, StackedCompound, StackedDrg, Tau,
-    TemporaryAux, TemporaryAuxCache,
+    self, generate_replica_id, ChallengeRequirements, PersistentAux, StackedCompound, StackedDrg,
+    Tau, TemporaryAux, TemporaryAuxCache,
 };
 
 use crate::POREP_MINIMUM_CHALLENGES;
 use crate::{
-    api::{as_safe_commitment, commitment_from_fr, get_base_tree_leafs, get_base_tree_size},
+    api::{
+        as_safe_commitment, comm_r_from_parts, fr_to_commitment, get_base_tree_leafs,
+        get_base_tree_size, read_cached_comm_r, validate_cache_for_commit,
+    },
     caches::{
         get_stacked_params, get_stacked_srs_key, get_stacked_srs_verifier_key,
         get_stacked_verifying_key,
@@ -41,11 +49,12 @@ use crate::{
         DefaultBinaryTree, DefaultPieceDomain, DefaultPieceHasher, SINGLE_PARTITION_PROOF_LEN,
     },
     parameters::setup_params,
-    pieces::{self, verify_pieces},
+    pieces::{self, validate_piece_infos, verify_pieces},
     types::{
-        AggregateSnarkProof, Commitment, PieceInfo, PoRepConfig, ProverId, SealCommitOutput,
-        SealCommitPhase1Output, SealPreCommitOutput, SealPreCommitPhase1Output, SectorSize, Ticket,
-        BINARY_ARITY,
+        AggregateSnarkProof, ChainSealSubmission, Commitment, PartitionInputs, PieceInfo,
+        PoRepConfig, PreCommitJob, ProverId, SealCommitOutput, SealCommitPartial,
+        SealCommitPhase1Output, SealJob, SealJobOutput, SealJobPhase, SealPreCommitOutput,
+        SealPreCommitPhase1Output, SealProof, SectorSize, Ticket, VerifyCost, BINARY_ARITY,
     },
 };
 
@@ -65,8 +74,172 @@ where
     S: AsRef<Path>,
     T: AsRef<Path>,
 {
+    #[cfg(feature = "tracing")]
+    let _span = tracing::info_span!(
+        "seal_pre_commit_phase1",
+        sector_id = u64::from(sector_id),
+        sector_size = u64::from(porep_config.sector_size),
+    )
+    .entered();
     info!("seal_pre_commit_phase1:start: {:?}", sector_id);
 
+    let compound_public_params = pre_commit_public_params::<Tree>(porep_config)?;
+    let out = seal_pre_commit_phase1_inner::<_, _, _, Tree>(
+        porep_config,
+        &compound_public_params,
+        cache_path,
+        in_path,
+        out_path,
+        prover_id,
+        sector_id,
+        ticket,
+        piece_infos,
+    )?;
+
+    info!("seal_pre_commit_phase1:finish: {:?}", sector_id);
+    Ok(out)
+}
+
+/// Runs `seal_pre_commit_phase1` for a batch of sectors sharing the same porep config, in
+/// parallel.
+///
+/// The public parameters and the parents cache (which only depends on the porep config) are
+/// set up once and shared across the batch. The outputs are in the order of `jobs` and are
+/// identical to sealing each sector independently.
+///
+/// # Arguments
+///
+/// * `porep_config` - the porep config shared by all sectors of the batch.
+/// * `jobs` - the per-sector inputs, see `seal_pre_commit_phase1`.
+pub fn seal_pre_commit_phase1_batch<Tree: 'static + MerkleTreeTrait>(
+    porep_config: &PoRepConfig,
+    jobs: &[PreCommitJob],
+) -> Result<Vec<SealPreCommitPhase1Output<Tree>>> {
+    info!("seal_pre_commit_phase1_batch:start: {} jobs", jobs.len());
+
+    let compound_public_params = pre_commit_public_params::<Tree>(porep_config)?;
+
+    // Make sure the parents cache exists before the sectors are labeled concurrently.
+    compound_public_params.vanilla_params.graph.parent_cache()?;
+
+    let outputs = jobs
+        .par_iter()
+        .map(|job| {
+            seal_pre_commit_phase1_inner::<_, _, _, Tree>(
+                porep_config,
+                &compound_public_params,
+                &job.cache_path,
+                &job.in_path,
+                &job.out_path,
+                job.prover_id,
+                job.sector_id,
+                job.ticket,
+                &job.piece_infos,
+            )
+            .with_context(|| format!("seal_pre_commit_phase1 failed: {:?}", job.sector_id))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    info!("seal_pre_commit_phase1_batch:finish");
+    Ok(outputs)
+}
+
+/// Returns the key of the SDR parents cache used when sealing with `porep_config`, without
+/// generating the cache. The cache is stored as `<key>.cache` in the parent cache directory and
+/// listed under this key in the parent cache manifest.
+///
+/// The key depends on the sector size and the porep_id, but not on the api version, even though
+/// the parents differ between api versions. Sectors sealed with different api versions must
+/// therefore use different porep_ids, as the registered seal proofs do.
+///
+/// # Arguments
+///
+/// * `porep_config` - porep configuration containing the sector size, porep_id and api version.
+pub fn porep_parents_cache_key<Tree: 'static + MerkleTreeTrait>(
+    porep_config: &PoRepConfig,
+) -> Result<String> {
+    let compound_public_params = pre_commit_public_params::<Tree>(porep_config)?;
+    let path = compound_public_params
+        .vanilla_params
+        .graph
+        .parent_cache_path();
+
+    path.file_stem()
+        .and_then(|stem| stem.to_str())
+        .map(str::to_string)
+        .ok_or_else(|| anyhow!("invalid parent cache path {:?}", path))
+}
+
+/// Returns the parents of `node_index` in the SDR graph used when sealing with `porep_config`:
+/// first the base (DRG) parents, which are labeled in the same layer, then the expander
+/// parents, which are labeled in the previous layer. Nodes of the first layer only use the base
+/// parents.
+///
+/// # Arguments
+///
+/// * `porep_config` - porep configuration containing the sector size, porep_id and api version.
+/// * `node_index` - the index of the node, which must be smaller than the number of nodes.
+pub fn parents_of<Tree: 'static + MerkleTreeTrait>(
+    porep_config: &PoRepConfig,
+    node_index: u64,
+) -> Result<Vec<u64>> {
+    let compound_public_params = pre_commit_public_params::<Tree>(porep_config)?;
+    let graph = &compound_public_params.vanilla_params.graph;
+    ensure!(
+        node_index < graph.size() as u64,
+        "node index {} is out of range for {} nodes",
+        node_index,
+        graph.size()
+    );
+
+    let mut parents = vec![0u32; graph.degree()];
+    graph.parents(node_index as usize, &mut parents)?;
+
+    Ok(parents.into_iter().map(u64::from).collect())
+}
+
+fn pre_commit_public_params<'a, Tree: 'static + MerkleTreeTrait>(
+    porep_config: &PoRepConfig,
+) -> Result<compound_proof::PublicParams<'a, StackedDrg<'a, Tree, DefaultPieceHasher>>> {
+    let compound_setup_params = compound_proof::SetupParams {
+        vanilla_params: setup_params(
+            porep_config.padded_bytes_amount(),
+            usize::from(porep_config.partitions),
+            porep_config.porep_id,
+            porep_config.api_version,
+        )?,
+        partitions: Some(usize::from(porep_config.partitions)),
+        priority: false,
+    };
+
+    <StackedCompound<Tree, DefaultPieceHasher> as CompoundProof<
+        StackedDrg<'_, Tree, DefaultPieceHasher>,
+        _,
+    >>::setup(&compound_setup_params)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn seal_pre_commit_phase1_inner<R, S, T, Tree: 'static + MerkleTreeTrait>(
+    porep_config: &PoRepConfig,
+    compound_public_params: &compound_proof::PublicParams<
+        '_,
+        StackedDrg<'_, Tree, DefaultPieceHasher>,
+    >,
+    cache_path: R,
+    in_path: S,
+    out_path: T,
+    prover_id: ProverId,
+    sector_id: SectorId,
+    ticket: Ticket,
+    piece_infos: &[PieceInfo],
+) -> Result<SealPreCommitPhase1Output<Tree>>
+where
+    R: AsRef<Path>,
+    S: AsRef<Path>,
+    T: AsRef<Path>,
+{
+    validate_piece_infos(porep_config.sector_size, piece_infos)?;
+
     let in_path_is_dev_zero = in_path.as_ref() == Path::new("/dev/zero");
     if in_path_is_dev_zero {
         trace!("using unreplicated data file /dev/zero");
@@ -125,22 +298,6 @@ where
             .with_context(|| format!("could not mmap out_path={:?}", out_path.as_ref().display()))?
     };
 
-    let compound_setup_params = compound_proof::SetupParams {
-        vanilla_params: setup_params(
-            porep_config.padded_bytes_amount(),
-            usize::from(porep_config.partitions),
-            porep_config.porep_id,
-            porep_config.api_version,
-        )?,
-        partitions: Some(usize::from(porep_config.partitions)),
-        priority: false,
-    };
-
-    let compound_public_params = <StackedCompound<Tree, DefaultPieceHasher> as CompoundProof<
-        StackedDrg<'_, Tree, DefaultPieceHasher>,
-        _,
-    >>::setup(&compound_setup_params)?;
-
     trace!("building merkle tree for the original data");
     let (config, comm_d) = measure_op(Operation::CommD, || -> Result<_> {
         let base_tree_size = get_base_tree_size::<DefaultBinaryTree>(porep_config.sector_size)?;
@@ -172,7 +329,7 @@ where
 
         config.size = Some(data_tree.len());
         let comm_d_root: Fr = data_tree.root().into();
-        let comm_d = commitment_from_fr(comm_d_root);
+        let comm_d = fr_to_commitment(&comm_d_root);
 
         drop(data_tree);
 
@@ -206,7 +363,6 @@ where
         comm_d,
     };
 
-    info!("seal_pre_commit_phase1:finish: {:?}", sector_id);
     Ok(out)
 }
 
@@ -221,6 +377,12 @@ where
     R: AsRef<Path>,
     S: AsRef<Path>,
 {
+    #[cfg(feature = "tracing")]
+    let _span = tracing::info_span!(
+        "seal_pre_commit_phase2",
+        sector_size = u64::from(porep_config.sector_size),
+    )
+    .entered();
     info!("seal_pre_commit_phase2:start");
 
     // Sanity check all input path types.
@@ -240,6 +402,18 @@ where
         ..
     } = phase1_output;
 
+    // The replica is encoded in place, so a completed phase2 must never be repeated.
+    if let Some(out) = completed_pre_commit_phase2::<Tree>(
+        porep_config,
+        cache_path.as_ref(),
+        replica_path.as_ref(),
+        comm_d,
+    )? {
+        info!("seal_pre_commit_phase2: already completed, reusing persisted output");
+        info!("seal_pre_commit_phase2:finish");
+        return Ok(out);
+    }
+
     labels.update_root(cache_path.as_ref());
     config.path = cache_path.as_ref().into();
 
@@ -309,24 +483,18 @@ where
         replica_path.as_ref().to_path_buf(),
     )?;
 
-    let comm_r = commitment_from_fr(tau.comm_r.into());
-
-    // Persist p_aux and t_aux here
-    let p_aux_path = cache_path.as_ref().join(CacheKey::PAux.to_string());
-    let mut f_p_aux = File::create(&p_aux_path)
-        .with_context(|| format!("could not create file p_aux={:?}", p_aux_path))?;
-    let p_aux_bytes = serialize(&p_aux)?;
-    f_p_aux
-        .write_all(&p_aux_bytes)
-        .with_context(|| format!("could not write to file p_aux={:?}", p_aux_path))?;
-
-    let t_aux_path = cache_path.as_ref().join(CacheKey::TAux.to_string());
-    let mut f_t_aux = File::create(&t_aux_path)
-        .with_context(|| format!("could not create file t_aux={:?}", t_aux_path))?;
-    let t_aux_bytes = serialize(&t_aux)?;
-    f_t_aux
-        .write_all(&t_aux_bytes)
-        .with_context(|| format!("could not write to file t_aux={:?}", t_aux_path))?;
+    let comm_r = fr_to_commitment(&tau.comm_r.into());
+
+    // Persist p_aux and t_aux here. Both are written atomically, so their presence marks phase2
+    // as completed.
+    persist_aux(
+        &cache_path.as_ref().join(CacheKey::TAux.to_string()),
+        &serialize(&t_aux)?,
+    )?;
+    persist_aux(
+        &cache_path.as_ref().join(CacheKey::PAux.to_string()),
+        &serialize(&p_aux)?,
+    )?;
 
     let out = SealPreCommitOutput { comm_r, comm_d };
 
@@ -334,6 +502,117 @@ where
     Ok(out)
 }
 
+/// Like `seal_pre_commit_phase2`, but runs its rayon work, e.g. the merkle tree construction of
+/// `merkletree`, on the given rayon `pool` instead of the global one. Work on dedicated thread
+/// pools does not honour `pool`, in particular the column hashing and the GPU batching of tree
+/// building. The output does not depend on the pool.
+pub fn seal_pre_commit_phase2_in_pool<R, S, Tree: 'static + MerkleTreeTrait>(
+    pool: &ThreadPool,
+    porep_config: &PoRepConfig,
+    phase1_output: SealPreCommitPhase1Output<Tree>,
+    cache_path: S,
+    replica_path: R,
+) -> Result<SealPreCommitOutput>
+where
+    R: AsRef<Path> + Send,
+    S: AsRef<Path> + Send,
+{
+    pool.install(|| {
+        seal_pre_commit_phase2::<R, S, Tree>(porep_config, phase1_output, cache_path, replica_path)
+    })
+}
+
+// Writes `bytes` to a temporary file next to `path` and moves it into place, so that `path`
+// either doesn't exist or is complete.
+fn persist_aux(path: &Path, bytes: &[u8]) -> Result<()> {
+    let tmp_path = path.with_extension("tmp");
+    let mut f_aux =
+        File::create(&tmp_path).with_context(|| format!("could not create file {:?}", tmp_path))?;
+    f_aux
+        .write_all(bytes)
+        .with_context(|| format!("could not write to file {:?}", tmp_path))?;
+    f_aux
+        .sync_all()
+        .with_context(|| format!("could not sync file {:?}", tmp_path))?;
+    fs::rename(&tmp_path, path)
+        .with_context(|| format!("could not rename {:?} to {:?}", tmp_path, path))?;
+
+    Ok(())
+}
+
+// Returns the output of an earlier, completed phase2 run over the same cache, if any. The trees
+// of runs which were interrupted before persisting p_aux and t_aux are reused (if their roots
+// match the recorded ones) or rebuilt by `replicate_phase2` instead.
+fn completed_pre_commit_phase2<Tree: 'static + MerkleTreeTrait>(
+    porep_config: &PoRepConfig,
+    cache_path: &Path,
+    replica_path: &Path,
+    comm_d: Commitment,
+) -> Result<Option<SealPreCommitOutput>> {
+    let p_aux_path = cache_path.join(CacheKey::PAux.to_string());
+    let t_aux_path = cache_path.join(CacheKey::TAux.to_string());
+
+    if !p_aux_path.exists() || !t_aux_path.exists() {
+        return Ok(None);
+    }
+
+    // Makes sure that all trees are in place and have the expected sizes.
+    validate_cache_for_commit::<_, _, Tree>(cache_path, replica_path)?;
+
+    // The persisted output must belong to the given phase1 output as well as to the trees.
+    let tree_d_root = {
+        let base_tree_size = get_base_tree_size::<DefaultBinaryTree>(porep_config.sector_size)?;
+        let base_tree_leafs = get_base_tree_leafs::<DefaultBinaryTree>(base_tree_size)?;
+        let mut config = StoreConfig::new(
+            cache_path,
+            CacheKey::CommDTree.to_string(),
+            default_rows_to_discard(base_tree_leafs, BINARY_ARITY),
+        );
+        config.size = Some(base_tree_size);
+
+        let store: DiskStore<DefaultPieceDomain> =
+            DiskStore::new_from_disk(base_tree_size, BINARY_ARITY, &config)
+                .with_context(|| format!("could not open tree_d in {:?}", cache_path))?;
+        store.read_at(base_tree_size - 1)?
+    };
+    ensure!(
+        tree_d_root.into_bytes() == comm_d,
+        "tree_d in {:?} does not match comm_d of the phase1 output",
+        cache_path
+    );
+
+    let p_aux: PersistentAux<<Tree::Hasher as Hasher>::Domain> = {
+        let p_aux_bytes = fs::read(&p_aux_path)
+            .with_context(|| format!("could not read file p_aux={:?}", p_aux_path))?;
+
+        deserialize(&p_aux_bytes)
+    }?;
+    // Both roots must match the ones recorded by phase2 once each tree was complete, as well as
+    // the roots stored in the tree files themselves.
+    for (key, root) in [
+        (CacheKey::CommCTree, p_aux.comm_c),
+        (CacheKey::CommRLastTree, p_aux.comm_r_last),
+    ] {
+        let config = StoreConfig::new(cache_path, key.to_string(), 0);
+        ensure!(
+            stacked::persisted_tree_root::<<Tree::Hasher as Hasher>::Domain>(&config)?
+                == Some(root),
+            "p_aux={:?} does not match the recorded root of {}",
+            p_aux_path,
+            key
+        );
+    }
+    let comm_r =
+        fr_to_commitment(&comm_r_from_parts::<Tree>(&p_aux.comm_c, &p_aux.comm_r_last).into());
+    ensure!(
+        read_cached_comm_r::<Tree>(porep_config, replica_path, cache_path)? == comm_r,
+        "p_aux={:?} does not match the roots cached in the trees",
+        p_aux_path
+    );
+
+    Ok(Some(SealPreCommitOutput { comm_r, comm_d }))
+}
+
 #[allow(clippy::too_many_arguments)]
 pub fn seal_commit_phase1<T: AsRef<Path>, Tree: 'static + MerkleTreeTrait>(
     porep_config: &PoRepConfig,
@@ -346,6 +625,13 @@ pub fn seal_commit_phase1<T: AsRef<Path>, Tree: 'static + MerkleTreeTrait>(
     pre_commit: SealPreCommitOutput,
     piece_infos: &[PieceInfo],
 ) -> Result<SealCommitPhase1Output<Tree>> {
+    #[cfg(feature = "tracing")]
+    let _span = tracing::info_span!(
+        "seal_commit_phase1",
+        sector_id = u64::from(sector_id),
+        sector_size = u64::from(porep_config.sector_size),
+    )
+    .entered();
     info!("seal_commit_phase1:start: {:?}", sector_id);
 
     // Sanity check all input path types.
@@ -462,77 +748,99 @@ pub fn seal_commit_phase1<T: AsRef<Path>, Tree: 'static + MerkleTreeTrait>(
     Ok(out)
 }
 
-#[allow(clippy::too_many_arguments)]
-pub fn seal_commit_phase2<Tree: 'static + MerkleTreeTrait>(
+/// Returns the node indices challenged by `seal_commit_phase1`, one list per partition.
+///
+/// The challenges are derived from the replica-id and the seed. As the replica-id is not
+/// recoverable from `comm_r`, the inputs it is generated from have to be passed instead.
+///
+/// # Arguments
+///
+/// * `porep_config` - this sector's porep config that contains the number of bytes in the sector.
+/// * `comm_d` - a commitment to a sector's data.
+/// * `prover_id` - the prover_id used to seal this sector.
+/// * `sector_id` - the sector_id of this sector.
+/// * `ticket` - the ticket used to generate this sector's replica-id.
+/// * `seed` - the seed used to derive the porep challenges.
+pub fn seal_commit_challenges<Tree: 'static + MerkleTreeTrait>(
     porep_config: &PoRepConfig,
-    phase1_output: SealCommitPhase1Output<Tree>,
+    comm_d: Commitment,
     prover_id: ProverId,
     sector_id: SectorId,
-) -> Result<SealCommitOutput> {
-    info!("seal_commit_phase2:start: {:?}", sector_id);
+    ticket: Ticket,
+    seed: Ticket,
+) -> Result<Vec<Vec<u64>>> {
+    ensure!(comm_d != [0; 32], "Invalid all zero commitment (comm_d)");
 
-    let SealCommitPhase1Output {
-        vanilla_proofs,
+    let replica_id = generate_replica_id::<Tree::Hasher, _>(
+        &prover_id,
+        sector_id.into(),
+        &ticket,
         comm_d,
-        comm_r,
-        replica_id,
-        seed,
-        ticket,
-    } = phase1_output;
-
-    ensure!(comm_d != [0; 32], "Invalid all zero commitment (comm_d)");
-    ensure!(comm_r != [0; 32], "Invalid all zero commitment (comm_r)");
+        &porep_config.porep_id,
+    );
 
-    let comm_r_safe = as_safe_commitment(&comm_r, "comm_r")?;
-    let comm_d_safe = DefaultPieceDomain::try_from_bytes(&comm_d)?;
+    let partitions = usize::from(porep_config.partitions);
+    let vanilla_params = setup_params(
+        porep_config.padded_bytes_amount(),
+        partitions,
+        porep_config.porep_id,
+        porep_config.api_version,
+    )?;
+    let public_params = StackedDrg::<Tree, DefaultPieceHasher>::setup(&vanilla_params)?;
 
-    let public_inputs = stacked::PublicInputs {
+    let public_inputs: stacked::PublicInputs<_, DefaultPieceDomain> = stacked::PublicInputs {
         replica_id,
-        tau: Some(stacked::Tau {
-            comm_d: comm_d_safe,
-            comm_r: comm_r_safe,
-        }),
+        tau: None,
         k: None,
         seed,
     };
 
-    let groth_params = get_stacked_params::<Tree>(porep_config)?;
+    let challenges = (0..partitions)
+        .map(|k| {
+            public_inputs
+                .challenges(
+                    &public_params.layer_challenges,
+                    public_params.graph.size(),
+                    Some(k),
+                )
+                .into_iter()
+                .map(|challenge| challenge as u64)
+                .collect()
+        })
+        .collect();
 
-    trace!(
-        "got groth params ({}) while sealing",
-        u64::from(porep_config.padded_bytes_amount())
-    );
+    Ok(challenges)
+}
 
-    let compound_setup_params = compound_proof::SetupParams {
-        vanilla_params: setup_params(
-            porep_config.padded_bytes_amount(),
-            usize::from(porep_config.partitions),
-            porep_config.porep_id,
-            porep_config.api_version,
-        )?,
-        partitions: Some(usize::from(porep_config.partitions)),
-        priority: false,
-    };
+#[allow(clippy::too_many_arguments)]
+pub fn seal_commit_phase2<Tree: 'static + MerkleTreeTrait>(
+    porep_config: &PoRepConfig,
+    phase1_output: SealCommitPhase1Output<Tree>,
+    prover_id: ProverId,
+    sector_id: SectorId,
+) -> Result<SealCommitOutput> {
+    #[cfg(feature = "tracing")]
+    let _span = tracing::info_span!(
+        "seal_commit_phase2",
+        sector_id = u64::from(sector_id),
+        sector_size = u64::from(porep_config.sector_size),
+    )
+    .entered();
+    info!("seal_commit_phase2:start: {:?}", sector_id);
 
-    let compound_public_params = <StackedCompound<Tree, DefaultPieceHasher> as CompoundProof<
-        StackedDrg<'_, Tree, DefaultPieceHasher>,
-        _,
-    >>::setup(&compound_setup_params)?;
+    let comm_r = phase1_output.comm_r;
+    let comm_d = phase1_output.comm_d;
+    let seed = phase1_output.seed;
+    let ticket = phase1_output.ticket;
 
-    trace!("snark_proof:start");
-    let groth_proofs = StackedCompound::<Tree, DefaultPieceHasher>::circuit_proofs(
-        &public_inputs,
-        vanilla_proofs,
-        &compound_public_params.vanilla_params,
-        &groth_params,
-        compound_public_params.priority,
-    )?;
-    trace!("snark_proof:finish");
+    let partitions = usize::from(porep_config.partitions);
+    let groth_proofs =
+        seal_commit_circuit_proofs::<Tree>(porep_config, phase1_output, 0..partitions)?;
 
+    let groth_params = get_stacked_params::<Tree>(porep_config)?;
     let proof = MultiProof::new(groth_proofs, &groth_params.pvk);
 
-    let mut buf =
-        Vec::with_capacity(SINGLE_PARTITION_PROOF_LEN * usize::from(porep_config.partitions));
+    let mut buf = Vec::with_capacity(SINGLE_PARTITION_PROOF_LEN * partitions);
 
     proof.write(&mut buf)?;
 
@@ -556,35 +864,204 @@ pub fn seal_commit_phase2<Tree: 'static + MerkleTreeTrait>(
     Ok(out)
 }
 
-/// Given the specified arguments, this method returns the inputs that were used to
-/// generate the seal proof.  This can be useful for proof aggregation, as verification
-/// requires these inputs.
+/// Like `seal_commit_phase2`, but only generates the groth16 proofs for the given range of
+/// partitions, so that the SNARK proving of a single sector can be spread across machines. The
+/// partial outputs are assembled into the final proof with `combine_seal_commit_partials`.
 ///
-/// This method allows them to be retrieved when needed, rather than storing them for
-/// some amount of time.
+/// The partial proofs are not verified, verify the combined proof with `verify_seal` instead.
 ///
 /// # Arguments
 ///
 /// * `porep_config` - this sector's porep config that contains the number of bytes in the sector.
-/// * `comm_r` - a commitment to a sector's replica.
-/// * `comm_d` - a commitment to a sector's data.
-/// * `prover_id` - the prover_id used to seal this sector.
-/// * `sector_id` - the sector_id of this sector.
-/// * `ticket` - the ticket used to generate this sector's replica-id.
-/// * `seed` - the seed used to derive the porep challenges.
-pub fn get_seal_inputs<Tree: 'static + MerkleTreeTrait>(
+/// * `phase1_output` - the output of `seal_commit_phase1`, with the vanilla proofs of all
+/// partitions.
+/// * `prover_id` - the prover-id that sealed this sector.
+/// * `sector_id` - the sector-id of this sector.
+/// * `partitions` - the partitions to prove.
+pub fn seal_commit_phase2_range<Tree: 'static + MerkleTreeTrait>(
     porep_config: &PoRepConfig,
-    comm_r: Commitment,
-    comm_d: Commitment,
-    prover_id: ProverId,
+    phase1_output: SealCommitPhase1Output<Tree>,
+    _prover_id: ProverId,
     sector_id: SectorId,
-    ticket: Ticket,
-    seed: Ticket,
-) -> Result<Vec<Vec<Fr>>> {
-    trace!("get_seal_inputs:start");
+    partitions: Range<usize>,
+) -> Result<SealCommitPartial> {
+    info!(
+        "seal_commit_phase2_range:start: {:?} {:?}",
+        sector_id, partitions
+    );
 
-    ensure!(comm_d != [0; 32], "Invalid all zero commitment (comm_d)");
-    ensure!(comm_r != [0; 32], "Invalid all zero commitment (comm_r)");
+    ensure!(!partitions.is_empty(), "empty partition range");
+    ensure!(
+        partitions.end <= usize::from(porep_config.partitions),
+        "partition range {:?} exceeds the {} partitions of the sector",
+        partitions,
+        usize::from(porep_config.partitions)
+    );
+
+    let groth_proofs =
+        seal_commit_circuit_proofs::<Tree>(porep_config, phase1_output, partitions.clone())?;
+
+    let mut proof = Vec::with_capacity(SINGLE_PARTITION_PROOF_LEN * groth_proofs.len());
+    for groth_proof in &groth_proofs {
+        groth_proof.write(&mut proof)?;
+    }
+
+    info!(
+        "seal_commit_phase2_range:finish: {:?} {:?}",
+        sector_id, partitions
+    );
+
+    Ok(SealCommitPartial { partitions, proof })
+}
+
+/// Assembles the outputs of `seal_commit_phase2_range` into the seal proof of the sector. The
+/// partial outputs may be given in any order, but must cover every partition exactly once.
+///
+/// # Arguments
+///
+/// * `porep_config` - this sector's porep config that contains the number of bytes in the sector.
+/// * `partials` - the partial outputs, covering all partitions of the sector.
+pub fn combine_seal_commit_partials(
+    porep_config: &PoRepConfig,
+    mut partials: Vec<SealCommitPartial>,
+) -> Result<SealCommitOutput> {
+    let partitions = usize::from(porep_config.partitions);
+    partials.sort_by_key(|partial| partial.partitions.start);
+
+    let mut proof = Vec::with_capacity(SINGLE_PARTITION_PROOF_LEN * partitions);
+    for partial in &partials {
+        ensure!(
+            partial.partitions.start == proof.len() / SINGLE_PARTITION_PROOF_LEN,
+            "partition range {:?} does not continue the previous ranges",
+            partial.partitions
+        );
+        ensure!(
+            partial.proof.len() == partial.partitions.len() * SINGLE_PARTITION_PROOF_LEN,
+            "invalid proof length {} for partition range {:?}",
+            partial.proof.len(),
+            partial.partitions
+        );
+        proof.extend_from_slice(&partial.proof);
+    }
+    ensure!(
+        proof.len() == partitions * SINGLE_PARTITION_PROOF_LEN,
+        "partial outputs cover {} of {} partitions",
+        proof.len() / SINGLE_PARTITION_PROOF_LEN,
+        partitions
+    );
+
+    Ok(SealCommitOutput { proof })
+}
+
+// Generates the groth16 proofs for the given partitions of a seal commit.
+fn seal_commit_circuit_proofs<Tree: 'static + MerkleTreeTrait>(
+    porep_config: &PoRepConfig,
+    phase1_output: SealCommitPhase1Output<Tree>,
+    partitions: Range<usize>,
+) -> Result<Vec<groth16::Proof<Bls12>>> {
+    let SealCommitPhase1Output {
+        vanilla_proofs,
+        comm_d,
+        comm_r,
+        replica_id,
+        seed,
+        ticket: _,
+    } = phase1_output;
+
+    ensure!(comm_d != [0; 32], "Invalid all zero commitment (comm_d)");
+    ensure!(comm_r != [0; 32], "Invalid all zero commitment (comm_r)");
+    ensure!(
+        partitions.end <= vanilla_proofs.len(),
+        "missing vanilla proofs for partition range {:?}",
+        partitions
+    );
+
+    let comm_r_safe = as_safe_commitment(&comm_r, "comm_r")?;
+    let comm_d_safe = DefaultPieceDomain::try_from_bytes(&comm_d)?;
+
+    let public_inputs = stacked::PublicInputs {
+        replica_id,
+        tau: Some(stacked::Tau {
+            comm_d: comm_d_safe,
+            comm_r: comm_r_safe,
+        }),
+        k: None,
+        seed,
+    };
+
+    let groth_params = get_stacked_params::<Tree>(porep_config)?;
+
+    trace!(
+        "got groth params ({}) while sealing",
+        u64::from(porep_config.padded_bytes_amount())
+    );
+
+    let compound_setup_params = compound_proof::SetupParams {
+        vanilla_params: setup_params(
+            porep_config.padded_bytes_amount(),
+            usize::from(porep_config.partitions),
+            porep_config.porep_id,
+            porep_config.api_version,
+        )?,
+        partitions: Some(usize::from(porep_config.partitions)),
+        priority: false,
+    };
+
+    let compound_public_params = <StackedCompound<Tree, DefaultPieceHasher> as CompoundProof<
+        StackedDrg<'_, Tree, DefaultPieceHasher>,
+        _,
+    >>::setup(&compound_setup_params)?;
+
+    let first_partition = partitions.start;
+    let vanilla_proofs = vanilla_proofs
+        .into_iter()
+        .skip(first_partition)
+        .take(partitions.len())
+        .collect();
+
+    trace!("snark_proof:start");
+    let groth_proofs = StackedCompound::<Tree, DefaultPieceHasher>::circuit_proofs_from_partition(
+        &public_inputs,
+        first_partition,
+        vanilla_proofs,
+        &compound_public_params.vanilla_params,
+        &groth_params,
+        compound_public_params.priority,
+    )?;
+    trace!("snark_proof:finish");
+
+    Ok(groth_proofs)
+}
+
+/// Given the specified arguments, this method returns the inputs that were used to
+/// generate the seal proof.  This can be useful for proof aggregation, as verification
+/// requires these inputs.
+///
+/// This method allows them to be retrieved when needed, rather than storing them for
+/// some amount of time.
+///
+/// # Arguments
+///
+/// * `porep_config` - this sector's porep config that contains the number of bytes in the sector.
+/// * `comm_r` - a commitment to a sector's replica.
+/// * `comm_d` - a commitment to a sector's data.
+/// * `prover_id` - the prover_id used to seal this sector.
+/// * `sector_id` - the sector_id of this sector.
+/// * `ticket` - the ticket used to generate this sector's replica-id.
+/// * `seed` - the seed used to derive the porep challenges.
+pub fn get_seal_inputs<Tree: 'static + MerkleTreeTrait>(
+    porep_config: &PoRepConfig,
+    comm_r: Commitment,
+    comm_d: Commitment,
+    prover_id: ProverId,
+    sector_id: SectorId,
+    ticket: Ticket,
+    seed: Ticket,
+) -> Result<Vec<Vec<Fr>>> {
+    trace!("get_seal_inputs:start");
+
+    ensure!(comm_d != [0; 32], "Invalid all zero commitment (comm_d)");
+    ensure!(comm_r != [0; 32], "Invalid all zero commitment (comm_r)");
 
     let replica_id = generate_replica_id::<Tree::Hasher, _>(
         &prover_id,
@@ -645,6 +1122,84 @@ pub fn get_seal_inputs<Tree: 'static + MerkleTreeTrait>(
     Ok(inputs)
 }
 
+/// Like `get_seal_inputs`, but first recomputes `comm_d` from the sector's pieces and
+/// ensures that it matches the passed `comm_d`. This catches inputs built for the wrong
+/// data here, rather than when the aggregate proof later fails to verify.
+///
+/// # Arguments
+///
+/// * `porep_config` - this sector's porep config that contains the number of bytes in the sector.
+/// * `comm_r` - a commitment to a sector's replica.
+/// * `comm_d` - a commitment to a sector's data.
+/// * `piece_infos` - the piece info (commitment and byte length) for each piece in this sector.
+/// * `prover_id` - the prover_id used to seal this sector.
+/// * `sector_id` - the sector_id of this sector.
+/// * `ticket` - the ticket used to generate this sector's replica-id.
+/// * `seed` - the seed used to derive the porep challenges.
+#[allow(clippy::too_many_arguments)]
+pub fn get_seal_inputs_checked<Tree: 'static + MerkleTreeTrait>(
+    porep_config: &PoRepConfig,
+    comm_r: Commitment,
+    comm_d: Commitment,
+    piece_infos: &[PieceInfo],
+    prover_id: ProverId,
+    sector_id: SectorId,
+    ticket: Ticket,
+    seed: Ticket,
+) -> Result<Vec<Vec<Fr>>> {
+    let computed_comm_d = compute_comm_d(porep_config.sector_size, piece_infos)?;
+    ensure!(
+        computed_comm_d == comm_d,
+        "comm_d does not match the supplied pieces"
+    );
+
+    get_seal_inputs::<Tree>(
+        porep_config,
+        comm_r,
+        comm_d,
+        prover_id,
+        sector_id,
+        ticket,
+        seed,
+    )
+}
+
+/// Like `get_seal_inputs`, but splits the inputs of each partition into the values they
+/// encode (replica_id, comm_d, comm_r and the node indices of each challenge). Useful to compare
+/// the inputs of a prover and a verifier, which are otherwise flat lists of field elements.
+///
+/// # Arguments
+///
+/// * `porep_config` - this sector's porep config that contains the number of bytes in the sector.
+/// * `comm_r` - a commitment to a sector's replica.
+/// * `comm_d` - a commitment to a sector's data.
+/// * `prover_id` - the prover_id used to seal this sector.
+/// * `sector_id` - the sector_id of this sector.
+/// * `ticket` - the ticket used to generate this sector's replica-id.
+/// * `seed` - the seed used to derive the porep challenges.
+pub fn get_seal_inputs_labeled<Tree: 'static + MerkleTreeTrait>(
+    porep_config: &PoRepConfig,
+    comm_r: Commitment,
+    comm_d: Commitment,
+    prover_id: ProverId,
+    sector_id: SectorId,
+    ticket: Ticket,
+    seed: Ticket,
+) -> Result<Vec<PartitionInputs>> {
+    get_seal_inputs::<Tree>(
+        porep_config,
+        comm_r,
+        comm_d,
+        prover_id,
+        sector_id,
+        ticket,
+        seed,
+    )?
+    .iter()
+    .map(|inputs| PartitionInputs::from_inputs(inputs))
+    .collect()
+}
+
 /// Given a value, get one suitable for aggregation.
 fn get_aggregate_target_len(len: usize) -> usize {
     if len == 1 {
@@ -654,6 +1209,52 @@ fn get_aggregate_target_len(len: usize) -> usize {
     }
 }
 
+/// Returns the number of groth16 proofs that `aggregate_seal_commit_proofs` will aggregate
+/// when given `num_proofs` proofs, after padding the count up to a power of two (with a
+/// minimum of two).
+///
+/// Note that each seal commit output contains one groth16 proof per partition, so for a
+/// list of commit outputs `num_proofs` is `commit_outputs.len() * porep_config.partitions`.
+/// The `comm_rs` and `seeds` passed for aggregation are never padded by the caller.
+///
+/// # Arguments
+///
+/// * `num_proofs` - the number of groth16 proofs to be aggregated.
+pub fn aggregation_padded_count(num_proofs: usize) -> usize {
+    get_aggregate_target_len(num_proofs)
+}
+
+/// Returns true if aggregating `num_proofs` groth16 proofs requires padding, i.e. if
+/// `aggregation_padded_count(num_proofs)` differs from `num_proofs`.
+///
+/// # Arguments
+///
+/// * `num_proofs` - the number of groth16 proofs to be aggregated.
+pub fn aggregation_is_padded(num_proofs: usize) -> bool {
+    aggregation_padded_count(num_proofs) != num_proofs
+}
+
+/// Returns the number of groth16 proofs aggregated into `proof`, including any padding.
+pub fn aggregate_proof_count(proof: &groth16::aggregate::AggregateProof<Bls12>) -> usize {
+    proof.tmipp.gipa.nproofs as usize
+}
+
+/// Cheaply checks that `proof` aggregates as many groth16 proofs as aggregating
+/// `expected_count` proofs would produce, i.e. `aggregation_padded_count(expected_count)`.
+/// This does not verify the proof and is only meant as a pre-check before
+/// `verify_aggregate_seal_commit_proofs`.
+///
+/// # Arguments
+///
+/// * `proof` - the aggregate proof to check.
+/// * `expected_count` - the number of groth16 proofs expected before padding.
+pub fn aggregate_proof_matches_expected(
+    proof: &groth16::aggregate::AggregateProof<Bls12>,
+    expected_count: usize,
+) -> bool {
+    expected_count != 0 && aggregate_proof_count(proof) == aggregation_padded_count(expected_count)
+}
+
 /// Given a list of proofs and a target_len, make sure that the proofs list is padded to the target_len size.
 fn pad_proofs_to_target(proofs: &mut Vec<groth16::Proof<Bls12>>, target_len: usize) -> Result<()> {
     trace!(
@@ -697,29 +1298,40 @@ fn pad_inputs_to_target(
     num_inputs_per_proof: usize,
     target_len: usize,
 ) -> Result<Vec<Vec<Fr>>> {
+    let mut new_inputs = commit_inputs.to_owned();
+    pad_inputs_to_target_in_place(&mut new_inputs, num_inputs_per_proof, target_len)?;
+
+    Ok(new_inputs)
+}
+
+/// Like `pad_inputs_to_target`, but pads `commit_inputs` itself instead of a copy.
+fn pad_inputs_to_target_in_place(
+    commit_inputs: &mut Vec<Vec<Fr>>,
+    num_inputs_per_proof: usize,
+    target_len: usize,
+) -> Result<()> {
     ensure!(
         !commit_inputs.is_empty(),
         "cannot aggregate with empty public inputs"
     );
 
-    let mut num_inputs = commit_inputs.len();
-    let mut new_inputs = commit_inputs.to_owned();
+    let num_inputs = commit_inputs.len();
 
     if target_len != num_inputs {
         ensure!(
             target_len > num_inputs,
             "target len must be greater than actual num inputs"
         );
-        let duplicate_inputs = &commit_inputs[(num_inputs - num_inputs_per_proof)..num_inputs];
+        let duplicate_start = num_inputs - num_inputs_per_proof;
 
         trace!("padding inputs from {} to {}", num_inputs, target_len);
-        while target_len != num_inputs {
-            new_inputs.extend_from_slice(duplicate_inputs);
-            num_inputs += num_inputs_per_proof;
+        commit_inputs.reserve(target_len - num_inputs);
+        while target_len != commit_inputs.len() {
+            commit_inputs.extend_from_within(duplicate_start..num_inputs);
         }
     }
 
-    Ok(new_inputs)
+    Ok(())
 }
 
 /// Given a porep_config and a list of seal commit outputs, this method aggregates
@@ -807,6 +1419,76 @@ pub fn aggregate_seal_commit_proofs<Tree: 'static + MerkleTreeTrait>(
     Ok(aggregate_proof_bytes)
 }
 
+// The operation counts below follow the SnarkPack verifier of bellperson 0.24, see
+// `verify_aggregate_proof` in `src/groth16/aggregate/verify.rs` and the functions it calls. The
+// random linear combination `PairingChecks` merges the pairing checks with is not counted.
+
+// `gipa_verify_tipp_mipp`, per round: the left and right TIPP commitments T_ab, U_ab and Z_ab and
+// MIPP commitments T_c and U_c (all GT) are raised to the round challenge and its inverse, and
+// the left and right MIPP commitments Z_c (G1) are multiplied with them.
+const GIPA_ROUND_GT_EXPONENTIATIONS: usize = 5 * 2;
+const GIPA_ROUND_G1_SCALAR_MULTIPLICATIONS: usize = 2;
+
+// `verify_kzg_v` and `verify_kzg_w`: two KZG openings of each of the final commitment keys v
+// (G2) and w (G1). Every opening shifts the commitment and the key by a scalar multiplication in
+// G1 and one in G2, and checks a product of two pairings.
+const KZG_OPENINGS: usize = 2 * 2;
+const KZG_OPENING_MILLER_LOOPS: usize = 2;
+
+// `verify_tipp_mipp`: the final TIPP checks Z_ab = e(A, B), T_ab = e(A, v1) e(w1, B) and
+// U_ab = e(A, v2) e(w2, B), the final MIPP checks T_c = e(C, v1) and U_c = e(C, v2), and
+// Z_c = C^r.
+const TIPP_MIPP_MILLER_LOOPS: usize = 1 + 2 + 2 + 1 + 1;
+const TIPP_MIPP_G1_SCALAR_MULTIPLICATIONS: usize = 1;
+
+// `verify_aggregate_proof`: the aggregated groth16 equation
+// e(alpha * r_sum, beta) e(g_ic, gamma) e(agg_c, delta) = Z_ab, where r_sum is the sum of the
+// powers of the aggregation challenge r.
+const GROTH16_MILLER_LOOPS: usize = 3;
+const GROTH16_G1_SCALAR_MULTIPLICATIONS: usize = 1;
+
+/// Estimates the group operations `verify_aggregate_seal_commit_proofs` performs to verify an
+/// aggregate of `num_proofs` groth16 proofs, e.g. for fee estimation.
+///
+/// The SnarkPack verifier runs one GIPA round per halving of the padded number of proofs, so the
+/// cost grows logarithmically with `num_proofs`, followed by a constant number of batched pairing
+/// checks. The multiexponentiation over the public inputs, which is linear in their number, is
+/// not included. `AggregateVersion::V2` additionally hashes all public inputs into the
+/// transcript the aggregation challenge is derived from, which `AggregateVersion::V1` does not.
+///
+/// # Arguments
+///
+/// * `num_proofs` - the number of groth16 proofs to be aggregated, before padding.
+/// * `num_public_inputs` - the number of public inputs of each groth16 proof.
+/// * `aggregate_version` - the version of the aggregate proof.
+pub fn estimate_aggregate_verify_cost(
+    num_proofs: usize,
+    num_public_inputs: usize,
+    aggregate_version: groth16::aggregate::AggregateVersion,
+) -> VerifyCost {
+    let padded_proofs = aggregation_padded_count(num_proofs);
+    let gipa_rounds = padded_proofs.trailing_zeros() as usize;
+    let transcript_public_inputs = match aggregate_version {
+        groth16::aggregate::AggregateVersion::V1 => 0,
+        groth16::aggregate::AggregateVersion::V2 => padded_proofs * num_public_inputs,
+    };
+
+    VerifyCost {
+        gipa_rounds,
+        miller_loops: KZG_OPENINGS * KZG_OPENING_MILLER_LOOPS
+            + TIPP_MIPP_MILLER_LOOPS
+            + GROTH16_MILLER_LOOPS,
+        final_exponentiations: 1,
+        gt_exponentiations: gipa_rounds * GIPA_ROUND_GT_EXPONENTIATIONS,
+        g1_scalar_multiplications: gipa_rounds * GIPA_ROUND_G1_SCALAR_MULTIPLICATIONS
+            + KZG_OPENINGS
+            + TIPP_MIPP_G1_SCALAR_MULTIPLICATIONS
+            + GROTH16_G1_SCALAR_MULTIPLICATIONS,
+        g2_scalar_multiplications: KZG_OPENINGS,
+        transcript_public_inputs,
+    }
+}
+
 /// Given a porep_config, an aggregate proof, a list of seeds and a combined and flattened list
 /// of public inputs, this method verifies the aggregate seal proof.
 ///
@@ -830,7 +1512,7 @@ pub fn verify_aggregate_seal_commit_proofs<Tree: 'static + MerkleTreeTrait>(
     let aggregate_proof =
         groth16::aggregate::AggregateProof::read(std::io::Cursor::new(&aggregate_proof_bytes))?;
 
-    let aggregated_proofs_len = aggregate_proof.tmipp.gipa.nproofs as usize;
+    let aggregated_proofs_len = aggregate_proof_count(&aggregate_proof);
 
     ensure!(aggregated_proofs_len != 0, "cannot verify zero proofs");
     ensure!(!commit_inputs.is_empty(), "cannot verify with empty inputs");
@@ -867,9 +1549,9 @@ pub fn verify_aggregate_seal_commit_proofs<Tree: 'static + MerkleTreeTrait>(
         target_inputs_len / aggregated_proofs_len,
     );
 
-    // Pad public inputs if needed.
-    let commit_inputs =
-        pad_inputs_to_target(&commit_inputs, num_inputs_per_proof, target_inputs_len)?;
+    // Pad public inputs if needed, without copying them, as there can be a lot of them.
+    let mut commit_inputs = commit_inputs;
+    pad_inputs_to_target_in_place(&mut commit_inputs, num_inputs_per_proof, target_inputs_len)?;
 
     let verifying_key = get_stacked_verifying_key::<Tree>(porep_config)?;
     let srs_verifier_key =
@@ -902,46 +1584,321 @@ pub fn verify_aggregate_seal_commit_proofs<Tree: 'static + MerkleTreeTrait>(
     Ok(result)
 }
 
-/// Computes a sectors's `comm_d` given its pieces.
+/// Returns the public inputs of the `index`-th seal proof of the flattened `commit_inputs` of an
+/// aggregate, i.e. the inputs of all of its partitions, as returned by `get_seal_inputs`.
 ///
 /// # Arguments
 ///
-/// * `porep_config` - this sector's porep config that contains the number of bytes in the sector.
-/// * `piece_infos` - the piece info (commitment and byte length) for each piece in this sector.
-pub fn compute_comm_d(sector_size: SectorSize, piece_infos: &[PieceInfo]) -> Result<Commitment> {
-    trace!("compute_comm_d:start");
-
-    let result = pieces::compute_comm_d(sector_size, piece_infos);
+/// * `porep_config` - this sector's porep config that contains the number of partitions.
+/// * `commit_inputs` - the ordered list of all public inputs, as passed to
+///    `verify_aggregate_seal_commit_proofs`.
+/// * `index` - the index of the seal proof within the aggregate.
+pub fn aggregate_constituent_inputs<'a>(
+    porep_config: &PoRepConfig,
+    commit_inputs: &'a [Vec<Fr>],
+    index: usize,
+) -> Result<&'a [Vec<Fr>]> {
+    let partitions = usize::from(porep_config.partitions);
+    let start = index * partitions;
+    let end = start + partitions;
+    ensure!(
+        end <= commit_inputs.len(),
+        "seal proof {} is out of bounds for {} inputs of {} partitions each",
+        index,
+        commit_inputs.len(),
+        partitions,
+    );
 
-    trace!("compute_comm_d:finish");
-    result
+    Ok(&commit_inputs[start..end])
 }
 
-/// Verifies the output of some previously-run seal operation.
+/// Verifies an aggregate seal proof like `verify_aggregate_seal_commit_proofs` and, if it does
+/// not verify, verifies each of the aggregated seal proofs against its own public inputs to find
+/// the one causing the failure. This is a debugging aid, it is much more expensive than verifying
+/// the aggregate alone.
+///
+/// Returns `None` if the aggregate verifies, or the index of the first seal proof that does not
+/// verify. Fails if the aggregate does not verify, although all seal proofs do, e.g. because
+/// `comm_rs` or `seeds` do not match those used for aggregation.
 ///
 /// # Arguments
 ///
-/// * `porep_config` - this sector's porep config that contains the number of bytes in this sector.
-/// * `comm_r_in` - commitment to the sector's replica (`comm_r`).
-/// * `comm_d_in` - commitment to the sector's data (`comm_d`).
-/// * `prover_id` - the prover-id that sealed this sector.
-/// * `sector_id` - this sector's sector-id.
-/// * `ticket` - the ticket that was used to generate this sector's replica-id.
-/// * `seed` - the seed used to derive the porep challenges.
-/// * `proof_vec` - the porep circuit proof serialized into a vector of bytes.
-#[allow(clippy::too_many_arguments)]
-pub fn verify_seal<Tree: 'static + MerkleTreeTrait>(
+/// * `porep_config` - this sector's porep config that contains the number of bytes in the sector.
+/// * `aggregate_proof_bytes` - the returned aggregate proof from 'aggreate_seal_commit_proofs'.
+/// * `comm_rs` - an ordered list of the replica commitments of the aggregated seal proofs.
+/// * `seeds` - an ordered list of seeds used to derive the PoRep challenges.
+/// * `commit_inputs` - the ordered list of all public inputs, as passed to
+///    `verify_aggregate_seal_commit_proofs`.
+/// * `commit_outputs` - the ordered list of seal proof outputs that were aggregated.
+pub fn verify_aggregate_locate_failure<Tree: 'static + MerkleTreeTrait>(
     porep_config: &PoRepConfig,
-    comm_r_in: Commitment,
-    comm_d_in: Commitment,
-    prover_id: ProverId,
-    sector_id: SectorId,
-    ticket: Ticket,
-    seed: Ticket,
-    proof_vec: &[u8],
-) -> Result<bool> {
-    info!("verify_seal:start: {:?}", sector_id);
-
+    aggregate_proof_bytes: AggregateSnarkProof,
+    comm_rs: &[[u8; 32]],
+    seeds: &[[u8; 32]],
+    commit_inputs: &[Vec<Fr>],
+    commit_outputs: &[SealCommitOutput],
+    aggregate_version: groth16::aggregate::AggregateVersion,
+) -> Result<Option<usize>> {
+    info!("verify_aggregate_locate_failure:start");
+
+    let partitions = usize::from(porep_config.partitions);
+    ensure!(
+        commit_inputs.len() == commit_outputs.len() * partitions,
+        "expected {} inputs for {} seal proofs, got {}",
+        commit_outputs.len() * partitions,
+        commit_outputs.len(),
+        commit_inputs.len(),
+    );
+
+    if verify_aggregate_seal_commit_proofs::<Tree>(
+        porep_config,
+        aggregate_proof_bytes,
+        comm_rs,
+        seeds,
+        commit_inputs.to_vec(),
+        aggregate_version,
+    )? {
+        info!("verify_aggregate_locate_failure:finish");
+        return Ok(None);
+    }
+
+    let verifying_key = get_stacked_verifying_key::<Tree>(porep_config)?;
+    for (index, commit_output) in commit_outputs.iter().enumerate() {
+        let inputs = aggregate_constituent_inputs(porep_config, commit_inputs, index)?;
+        // A seal proof which cannot even be read is as bad as one which does not verify.
+        let valid = match MultiProof::new_from_reader(
+            Some(partitions),
+            &commit_output.proof[..],
+            &verifying_key,
+        ) {
+            Ok(multi_proof) => {
+                let proofs: Vec<_> = multi_proof.circuit_proofs.iter().collect();
+                groth16::verify_proofs_batch(&verifying_key, &mut OsRng, &proofs, inputs)?
+            }
+            Err(_) => false,
+        };
+
+        if !valid {
+            trace!("seal proof {} of the aggregate does not verify", index);
+            info!("verify_aggregate_locate_failure:finish");
+            return Ok(Some(index));
+        }
+    }
+
+    Err(anyhow!(
+        "aggregate proof does not verify, although all {} seal proofs do",
+        commit_outputs.len()
+    ))
+}
+
+/// Computes a stable digest over the inputs an aggregate seal proof is verified against, e.g.
+/// to record in a transparency log which batch was verified with
+/// `verify_aggregate_seal_commit_proofs`.
+///
+/// The digest is the SHA-256 of the number and the bytes of all `comm_rs`, then of all `seeds`,
+/// followed by the number of `commit_inputs` and, for each of them, its length and the
+/// canonical (little-endian) bytes of its field elements. All lengths are encoded as
+/// little-endian `u64`s, so differently split inputs cannot produce the same digest.
+///
+/// # Arguments
+///
+/// * `comm_rs` - an ordered list of the replica commitments of the aggregated seal proofs.
+/// * `seeds` - an ordered list of seeds used to derive the PoRep challenges.
+/// * `commit_inputs` - the ordered list of all public inputs, as passed to
+///    `verify_aggregate_seal_commit_proofs`.
+pub fn aggregate_public_inputs_digest(
+    comm_rs: &[[u8; 32]],
+    seeds: &[[u8; 32]],
+    commit_inputs: &[Vec<Fr>],
+) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+
+    hasher.update((comm_rs.len() as u64).to_le_bytes());
+    for comm_r in comm_rs {
+        hasher.update(comm_r);
+    }
+    hasher.update((seeds.len() as u64).to_le_bytes());
+    for seed in seeds {
+        hasher.update(seed);
+    }
+    hasher.update((commit_inputs.len() as u64).to_le_bytes());
+    for inputs in commit_inputs {
+        hasher.update((inputs.len() as u64).to_le_bytes());
+        for input in inputs {
+            hasher.update(fr_to_commitment(input));
+        }
+    }
+
+    hasher.finalize().into()
+}
+
+/// Checks whether two aggregate proofs are equal, by comparing both their native and their
+/// bincode serializations. The two encodings are produced by different codepaths, so this also
+/// catches a field missing from only one of them.
+///
+/// Returns `false` if either proof cannot be serialized.
+pub fn aggregate_proofs_equal(
+    a: &groth16::aggregate::AggregateProof<Bls12>,
+    b: &groth16::aggregate::AggregateProof<Bls12>,
+) -> bool {
+    let native = |proof: &groth16::aggregate::AggregateProof<Bls12>| {
+        let mut bytes = Vec::new();
+        proof.write(&mut bytes).map(|_| bytes)
+    };
+
+    match (native(a), native(b), serialize(a), serialize(b)) {
+        (Ok(native_a), Ok(native_b), Ok(bincode_a), Ok(bincode_b)) => {
+            native_a == native_b && bincode_a == bincode_b
+        }
+        _ => false,
+    }
+}
+
+/// Collects the comm_rs, seeds and public inputs of the seal proofs in an aggregate one seal
+/// at a time, e.g. as they are received, and verifies the aggregate proof over all of them
+/// with `verify_aggregate_seal_commit_proofs`.
+#[derive(Debug)]
+pub struct AggregateVerifierBuilder<Tree: 'static + MerkleTreeTrait> {
+    porep_config: PoRepConfig,
+    comm_rs: Vec<Commitment>,
+    seeds: Vec<Ticket>,
+    commit_inputs: Vec<Vec<Fr>>,
+    _tree: PhantomData<Tree>,
+}
+
+impl<Tree: 'static + MerkleTreeTrait> AggregateVerifierBuilder<Tree> {
+    pub fn new(porep_config: &PoRepConfig) -> Self {
+        AggregateVerifierBuilder {
+            porep_config: porep_config.clone(),
+            comm_rs: Vec::new(),
+            seeds: Vec::new(),
+            commit_inputs: Vec::new(),
+            _tree: PhantomData,
+        }
+    }
+
+    /// Adds the next seal, in aggregation order.
+    ///
+    /// # Arguments
+    ///
+    /// * `comm_r` - the replica commitment of the sealed sector.
+    /// * `seed` - the seed used to derive the porep challenges.
+    /// * `inputs` - the public inputs of the seal proof, as returned by `get_seal_inputs`.
+    pub fn add(&mut self, comm_r: Commitment, seed: Ticket, inputs: Vec<Vec<Fr>>) -> Result<()> {
+        ensure!(
+            inputs.len() == usize::from(self.porep_config.partitions),
+            "expected public inputs for {} partitions, got {}",
+            usize::from(self.porep_config.partitions),
+            inputs.len()
+        );
+
+        self.comm_rs.push(comm_r);
+        self.seeds.push(seed);
+        self.commit_inputs.extend(inputs);
+
+        Ok(())
+    }
+
+    /// Returns the number of seals added so far.
+    pub fn len(&self) -> usize {
+        self.comm_rs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.comm_rs.is_empty()
+    }
+
+    /// Verifies the aggregate proof over all added seals.
+    pub fn finish(
+        self,
+        aggregate_proof_bytes: AggregateSnarkProof,
+        aggregate_version: groth16::aggregate::AggregateVersion,
+    ) -> Result<bool> {
+        ensure!(!self.is_empty(), "no seals were added");
+
+        verify_aggregate_seal_commit_proofs::<Tree>(
+            &self.porep_config,
+            aggregate_proof_bytes,
+            &self.comm_rs,
+            &self.seeds,
+            self.commit_inputs,
+            aggregate_version,
+        )
+    }
+}
+
+/// Finds sectors which share randomness, given the `(sector_id, ticket, seed)` records of a
+/// sealing history. Tickets and seeds are compared across both sets, so a ticket reused as the
+/// seed of another sector is found as well. Every pair of sectors sharing randomness is reported
+/// once, ordered by the positions of its sectors in `records`, with the earlier sector first.
+///
+/// # Arguments
+///
+/// * `records` - the sector id, ticket and seed of each sealed sector.
+pub fn detect_randomness_reuse(
+    records: &[(SectorId, Ticket, Ticket)],
+) -> Vec<(SectorId, SectorId)> {
+    let mut seen: HashMap<&Ticket, Vec<usize>> = HashMap::new();
+    let mut pairs = BTreeSet::new();
+
+    for (i, (_, ticket, seed)) in records.iter().enumerate() {
+        for randomness in [ticket, seed] {
+            let earlier = seen.entry(randomness).or_default();
+            // A sector whose seed equals its own ticket does not pair with itself.
+            if earlier.last() == Some(&i) {
+                continue;
+            }
+            pairs.extend(earlier.iter().map(|j| (*j, i)));
+            earlier.push(i);
+        }
+    }
+
+    pairs
+        .into_iter()
+        .map(|(i, j)| (records[i].0, records[j].0))
+        .collect()
+}
+
+/// Computes a sectors's `comm_d` given its pieces.
+///
+/// # Arguments
+///
+/// * `porep_config` - this sector's porep config that contains the number of bytes in the sector.
+/// * `piece_infos` - the piece info (commitment and byte length) for each piece in this sector.
+pub fn compute_comm_d(sector_size: SectorSize, piece_infos: &[PieceInfo]) -> Result<Commitment> {
+    trace!("compute_comm_d:start");
+
+    let result = pieces::compute_comm_d(sector_size, piece_infos);
+
+    trace!("compute_comm_d:finish");
+    result
+}
+
+/// Verifies the output of some previously-run seal operation.
+///
+/// # Arguments
+///
+/// * `porep_config` - this sector's porep config that contains the number of bytes in this sector.
+/// * `comm_r_in` - commitment to the sector's replica (`comm_r`).
+/// * `comm_d_in` - commitment to the sector's data (`comm_d`).
+/// * `prover_id` - the prover-id that sealed this sector.
+/// * `sector_id` - this sector's sector-id.
+/// * `ticket` - the ticket that was used to generate this sector's replica-id.
+/// * `seed` - the seed used to derive the porep challenges.
+/// * `proof_vec` - the porep circuit proof serialized into a vector of bytes.
+#[allow(clippy::too_many_arguments)]
+pub fn verify_seal<Tree: 'static + MerkleTreeTrait>(
+    porep_config: &PoRepConfig,
+    comm_r_in: Commitment,
+    comm_d_in: Commitment,
+    prover_id: ProverId,
+    sector_id: SectorId,
+    ticket: Ticket,
+    seed: Ticket,
+    proof_vec: &[u8],
+) -> Result<bool> {
+    info!("verify_seal:start: {:?}", sector_id);
+
     ensure!(comm_d_in != [0; 32], "Invalid all zero commitment (comm_d)");
     ensure!(comm_r_in != [0; 32], "Invalid all zero commitment (comm_r)");
     ensure!(!proof_vec.is_empty(), "Invalid proof bytes (empty vector)");
@@ -1011,6 +1968,453 @@ pub fn verify_seal<Tree: 'static + MerkleTreeTrait>(
     result
 }
 
+/// Like `verify_seal`, but first recomputes comm_d from `piece_infos` and only verifies the proof
+/// if it matches `comm_d_in`. This rejects a proof which is valid, but for other data than the
+/// caller's piece manifest, without paying for the SNARK verification.
+///
+/// Returns `false` if the pieces do not match `comm_d_in`.
+///
+/// # Arguments
+///
+/// * `porep_config` - this sector's porep config that contains the number of bytes in this sector.
+/// * `comm_r_in` - commitment to the sector's replica (`comm_r`).
+/// * `comm_d_in` - commitment to the sector's data (`comm_d`).
+/// * `piece_infos` - the piece info (commitment and byte length) for each piece in this sector.
+/// * `prover_id` - the prover-id that sealed this sector.
+/// * `sector_id` - this sector's sector-id.
+/// * `ticket` - the ticket that was used to generate this sector's replica-id.
+/// * `seed` - the seed used to derive the porep challenges.
+/// * `proof_vec` - the porep circuit proof serialized into a vector of bytes.
+#[allow(clippy::too_many_arguments)]
+pub fn verify_seal_with_pieces<Tree: 'static + MerkleTreeTrait>(
+    porep_config: &PoRepConfig,
+    comm_r_in: Commitment,
+    comm_d_in: Commitment,
+    piece_infos: &[PieceInfo],
+    prover_id: ProverId,
+    sector_id: SectorId,
+    ticket: Ticket,
+    seed: Ticket,
+    proof_vec: &[u8],
+) -> Result<bool> {
+    info!("verify_seal_with_pieces:start: {:?}", sector_id);
+
+    if !verify_pieces(&comm_d_in, piece_infos, porep_config.sector_size)? {
+        trace!("pieces do not match comm_d of sector {:?}", sector_id);
+        info!("verify_seal_with_pieces:finish: {:?}", sector_id);
+        return Ok(false);
+    }
+
+    let result = verify_seal::<Tree>(
+        porep_config,
+        comm_r_in,
+        comm_d_in,
+        prover_id,
+        sector_id,
+        ticket,
+        seed,
+        proof_vec,
+    );
+
+    info!("verify_seal_with_pieces:finish: {:?}", sector_id);
+    result
+}
+
+/// Generates the seal proof from `phase1_output` with `seal_commit_phase2` and verifies it with
+/// `verify_seal` against the commitments, ticket and seed of `phase1_output`, returning the
+/// proof together with whether it verified. Meant for tests and simulations that want to prove
+/// and verify in one call.
+///
+/// # Arguments
+///
+/// * `porep_config` - this sector's porep config that contains the number of bytes in this sector.
+/// * `phase1_output` - the output of `seal_commit_phase1` for this sector.
+/// * `prover_id` - the prover-id that sealed this sector.
+/// * `sector_id` - this sector's sector-id.
+pub fn prove_and_verify_seal<Tree: 'static + MerkleTreeTrait>(
+    porep_config: &PoRepConfig,
+    phase1_output: SealCommitPhase1Output<Tree>,
+    prover_id: ProverId,
+    sector_id: SectorId,
+) -> Result<(SealCommitOutput, bool)> {
+    info!("prove_and_verify_seal:start: {:?}", sector_id);
+
+    let (comm_r, comm_d) = (phase1_output.comm_r, phase1_output.comm_d);
+    let (ticket, seed) = (phase1_output.ticket, phase1_output.seed);
+    let commit_output = seal_commit_phase2(porep_config, phase1_output, prover_id, sector_id)?;
+    let verified = verify_seal::<Tree>(
+        porep_config,
+        comm_r,
+        comm_d,
+        prover_id,
+        sector_id,
+        ticket,
+        seed,
+        &commit_output.proof,
+    )?;
+
+    info!("prove_and_verify_seal:finish: {:?}", sector_id);
+    Ok((commit_output, verified))
+}
+
+// The files in the cache directory the phase outputs of a `SealJob` are persisted in.
+const SEAL_JOB_PRE_COMMIT_PHASE1_OUTPUT: &str = "seal-job-pre-commit-phase1-output";
+const SEAL_JOB_PRE_COMMIT_OUTPUT: &str = "seal-job-pre-commit-output";
+const SEAL_JOB_COMMIT_PHASE1_OUTPUT: &str = "seal-job-commit-phase1-output";
+const SEAL_JOB_OUTPUTS: [&str; 3] = [
+    SEAL_JOB_PRE_COMMIT_PHASE1_OUTPUT,
+    SEAL_JOB_PRE_COMMIT_OUTPUT,
+    SEAL_JOB_COMMIT_PHASE1_OUTPUT,
+];
+
+// Removes the phase outputs persisted by `execute_seal_job` from `cache_dir`, if any.
+pub(crate) fn remove_seal_job_outputs(cache_dir: &Path) -> Result<()> {
+    for name in SEAL_JOB_OUTPUTS {
+        let path = cache_dir.join(name);
+        if path.exists() {
+            fs::remove_file(&path).with_context(|| format!("could not remove path={:?}", path))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn persist_seal_job_output<T: Serialize>(job: &SealJob, name: &str, output: &T) -> Result<()> {
+    let path = job.cache_path.join(name);
+    let bytes = serialize(output)?;
+    fs::write(&path, bytes).with_context(|| format!("could not write to path={:?}", path))
+}
+
+fn read_seal_job_output<T: DeserializeOwned>(job: &SealJob, name: &str) -> Result<T> {
+    let path = job.cache_path.join(name);
+    let bytes = fs::read(&path).with_context(|| format!("could not read from path={:?}", path))?;
+    deserialize(&bytes).with_context(|| format!("could not deserialize path={:?}", path))
+}
+
+/// Runs the seal phase of `job`, e.g. after a worker received it from a work queue.
+///
+/// The outputs of the `PreCommit1`, `PreCommit2` and `Commit1` phases are persisted in the cache
+/// directory of the job, from where the next phase reads them, the outputs of `PreCommit2` and
+/// `Commit2` are also returned. All persisted outputs are removed once `Commit2` has finished.
+///
+/// # Arguments
+///
+/// * `job` - the seal phase to run.
+pub fn execute_seal_job<Tree: 'static + MerkleTreeTrait>(job: &SealJob) -> Result<SealJobOutput> {
+    info!(
+        "execute_seal_job:start: {:?} {:?}",
+        job.sector_id, job.phase
+    );
+
+    let output = match job.phase {
+        SealJobPhase::PreCommit1 => {
+            let phase1_output = seal_pre_commit_phase1::<_, _, _, Tree>(
+                &job.porep_config,
+                &job.cache_path,
+                &job.staged_path,
+                &job.sealed_path,
+                job.prover_id,
+                job.sector_id,
+                job.ticket,
+                &job.piece_infos,
+            )?;
+            persist_seal_job_output(job, SEAL_JOB_PRE_COMMIT_PHASE1_OUTPUT, &phase1_output)?;
+
+            SealJobOutput::PreCommit1
+        }
+        SealJobPhase::PreCommit2 => {
+            let phase1_output: SealPreCommitPhase1Output<Tree> =
+                read_seal_job_output(job, SEAL_JOB_PRE_COMMIT_PHASE1_OUTPUT)?;
+            let pre_commit_output = seal_pre_commit_phase2(
+                &job.porep_config,
+                phase1_output,
+                &job.cache_path,
+                &job.sealed_path,
+            )?;
+            persist_seal_job_output(job, SEAL_JOB_PRE_COMMIT_OUTPUT, &pre_commit_output)?;
+
+            SealJobOutput::PreCommit2(pre_commit_output)
+        }
+        SealJobPhase::Commit1 => {
+            let seed = job.seed.context("commit phase 1 requires a seed")?;
+            let pre_commit_output: SealPreCommitOutput =
+                read_seal_job_output(job, SEAL_JOB_PRE_COMMIT_OUTPUT)?;
+            validate_cache_for_commit::<_, _, Tree>(&job.cache_path, &job.sealed_path)?;
+            let commit_phase1_output = seal_commit_phase1::<_, Tree>(
+                &job.porep_config,
+                &job.cache_path,
+                &job.sealed_path,
+                job.prover_id,
+                job.sector_id,
+                job.ticket,
+                seed,
+                pre_commit_output,
+                &job.piece_infos,
+            )?;
+            persist_seal_job_output(job, SEAL_JOB_COMMIT_PHASE1_OUTPUT, &commit_phase1_output)?;
+
+            SealJobOutput::Commit1
+        }
+        SealJobPhase::Commit2 => {
+            let commit_phase1_output: SealCommitPhase1Output<Tree> =
+                read_seal_job_output(job, SEAL_JOB_COMMIT_PHASE1_OUTPUT)?;
+            let commit_output = seal_commit_phase2(
+                &job.porep_config,
+                commit_phase1_output,
+                job.prover_id,
+                job.sector_id,
+            )?;
+            remove_seal_job_outputs(&job.cache_path)?;
+
+            SealJobOutput::Commit2(commit_output)
+        }
+    };
+
+    info!(
+        "execute_seal_job:finish: {:?} {:?}",
+        job.sector_id, job.phase
+    );
+
+    Ok(output)
+}
+
+/// Verifies a seal proof given in its stable on-wire encoding (see [`SealProof`]).
+///
+/// Unlike `verify_seal`, this rejects any proof whose length does not exactly match the
+/// number of partitions of `porep_config`.
+///
+/// # Arguments
+///
+/// * `porep_config` - this sector's porep config that contains the number of bytes in this sector.
+/// * `proof_bytes` - the encoded seal proof, one groth16 proof per partition.
+/// * `comm_r_in` - commitment to the sector's replica (`comm_r`).
+/// * `comm_d_in` - commitment to the sector's data (`comm_d`).
+/// * `prover_id` - the prover-id that sealed this sector.
+/// * `sector_id` - this sector's sector-id.
+/// * `ticket` - the ticket that was used to generate this sector's replica-id.
+/// * `seed` - the seed used to derive the porep challenges.
+#[allow(clippy::too_many_arguments)]
+pub fn verify_seal_proof_bytes<Tree: 'static + MerkleTreeTrait>(
+    porep_config: &PoRepConfig,
+    proof_bytes: &[u8],
+    comm_r_in: Commitment,
+    comm_d_in: Commitment,
+    prover_id: ProverId,
+    sector_id: SectorId,
+    ticket: Ticket,
+    seed: Ticket,
+) -> Result<bool> {
+    SealProof::from_bytes(porep_config, proof_bytes)?;
+
+    verify_seal::<Tree>(
+        porep_config,
+        comm_r_in,
+        comm_d_in,
+        prover_id,
+        sector_id,
+        ticket,
+        seed,
+        proof_bytes,
+    )
+}
+
+/// Verifies a seal submission bundled for on-chain submission, after checking that it is
+/// internally consistent (see [`ChainSealSubmission::validate`]).
+///
+/// # Arguments
+///
+/// * `porep_config` - this sector's porep config that contains the number of bytes in this sector.
+/// * `submission` - the seal proof together with its commitments, sector-id, ticket and seed.
+/// * `prover_id` - the prover-id that sealed this sector.
+pub fn verify_chain_seal_submission<Tree: 'static + MerkleTreeTrait>(
+    porep_config: &PoRepConfig,
+    submission: &ChainSealSubmission,
+    prover_id: ProverId,
+) -> Result<bool> {
+    submission
+        .validate(porep_config)
+        .with_context(|| format!("invalid submission for {:?}", submission.sector_id))?;
+
+    verify_seal::<Tree>(
+        porep_config,
+        submission.comm_r,
+        submission.comm_d,
+        prover_id,
+        submission.sector_id,
+        submission.ticket,
+        submission.seed,
+        &submission.proof,
+    )
+}
+
+/// Verifies the output of some previously-run seal operation under several candidate porep
+/// configs, e.g. to accept proofs generated under the prior api version during an upgrade. The
+/// configs must only differ in their api version and porep_id; each is verified with its own
+/// verifying key.
+///
+/// Returns the index of the first config under which the proof is valid, or `None` if it is
+/// invalid under all of them. A config whose verification fails with an error, e.g. because its
+/// verifying key is not available, is treated like one under which the proof is invalid.
+///
+/// # Arguments
+///
+/// * `porep_configs` - the candidate porep configs, in order of preference.
+/// * `comm_r_in` - commitment to the sector's replica (`comm_r`).
+/// * `comm_d_in` - commitment to the sector's data (`comm_d`).
+/// * `prover_id` - the prover-id that sealed this sector.
+/// * `sector_id` - this sector's sector-id.
+/// * `ticket` - the ticket that was used to generate this sector's replica-id.
+/// * `seed` - the seed used to derive the porep challenges.
+/// * `proof_vec` - the porep circuit proof serialized into a vector of bytes.
+#[allow(clippy::too_many_arguments)]
+pub fn verify_seal_multi_version<Tree: 'static + MerkleTreeTrait>(
+    porep_configs: &[PoRepConfig],
+    comm_r_in: Commitment,
+    comm_d_in: Commitment,
+    prover_id: ProverId,
+    sector_id: SectorId,
+    ticket: Ticket,
+    seed: Ticket,
+    proof_vec: &[u8],
+) -> Result<Option<usize>> {
+    info!("verify_seal_multi_version:start: {:?}", sector_id);
+
+    ensure!(!porep_configs.is_empty(), "no porep configs provided");
+    ensure!(
+        porep_configs.iter().all(|config| {
+            config.sector_size == porep_configs[0].sector_size
+                && usize::from(config.partitions) == usize::from(porep_configs[0].partitions)
+        }),
+        "porep configs must only differ in api version and porep_id"
+    );
+
+    let mut verified = None;
+    for (i, porep_config) in porep_configs.iter().enumerate() {
+        match verify_seal::<Tree>(
+            porep_config,
+            comm_r_in,
+            comm_d_in,
+            prover_id,
+            sector_id,
+            ticket,
+            seed,
+            proof_vec,
+        ) {
+            Ok(true) => {
+                verified = Some(i);
+                break;
+            }
+            Ok(false) => {}
+            Err(err) => debug!(
+                "verify_seal_multi_version: config {} ({}) failed: {:#}",
+                i, porep_config.api_version, err
+            ),
+        }
+    }
+
+    info!("verify_seal_multi_version:finish: {:?}", sector_id);
+
+    Ok(verified)
+}
+
+/// Verifies the output of some previously-run seal operation against several candidate seeds,
+/// e.g. when the seed might have changed because of a chain reorg. The public parameters,
+/// verifying key and proof are only loaded once for all seeds.
+///
+/// Returns the first seed for which the proof is valid, or `None` if it is invalid for all of
+/// them.
+///
+/// # Arguments
+///
+/// * `porep_config` - this sector's porep config that contains the number of bytes in this sector.
+/// * `comm_r_in` - commitment to the sector's replica (`comm_r`).
+/// * `comm_d_in` - commitment to the sector's data (`comm_d`).
+/// * `prover_id` - the prover-id that sealed this sector.
+/// * `sector_id` - this sector's sector-id.
+/// * `ticket` - the ticket that was used to generate this sector's replica-id.
+/// * `seeds` - the candidate seeds which may have been used to derive the porep challenges.
+/// * `proof_vec` - the porep circuit proof serialized into a vector of bytes.
+#[allow(clippy::too_many_arguments)]
+pub fn verify_seal_any_seed<Tree: 'static + MerkleTreeTrait>(
+    porep_config: &PoRepConfig,
+    comm_r_in: Commitment,
+    comm_d_in: Commitment,
+    prover_id: ProverId,
+    sector_id: SectorId,
+    ticket: Ticket,
+    seeds: &[Ticket],
+    proof_vec: &[u8],
+) -> Result<Option<Ticket>> {
+    info!("verify_seal_any_seed:start: {:?}", sector_id);
+
+    ensure!(comm_d_in != [0; 32], "Invalid all zero commitment (comm_d)");
+    ensure!(comm_r_in != [0; 32], "Invalid all zero commitment (comm_r)");
+    ensure!(!proof_vec.is_empty(), "Invalid proof bytes (empty vector)");
+
+    let comm_r: <Tree::Hasher as Hasher>::Domain = as_safe_commitment(&comm_r_in, "comm_r")?;
+    let comm_d: DefaultPieceDomain = as_safe_commitment(&comm_d_in, "comm_d")?;
+
+    let replica_id = generate_replica_id::<Tree::Hasher, _>(
+        &prover_id,
+        sector_id.into(),
+        &ticket,
+        comm_d,
+        &porep_config.porep_id,
+    );
+
+    let compound_setup_params = compound_proof::SetupParams {
+        vanilla_params: setup_params(
+            porep_config.padded_bytes_amount(),
+            usize::from(porep_config.partitions),
+            porep_config.porep_id,
+            porep_config.api_version,
+        )?,
+        partitions: Some(usize::from(porep_config.partitions)),
+        priority: false,
+    };
+
+    let compound_public_params: compound_proof::PublicParams<
+        '_,
+        StackedDrg<'_, Tree, DefaultPieceHasher>,
+    > = StackedCompound::setup(&compound_setup_params)?;
+
+    let verifying_key = get_stacked_verifying_key::<Tree>(porep_config)?;
+    let proof = MultiProof::new_from_reader(
+        Some(usize::from(porep_config.partitions)),
+        proof_vec,
+        &verifying_key,
+    )?;
+    let requirements = ChallengeRequirements {
+        minimum_challenges: POREP_MINIMUM_CHALLENGES
+            .from_sector_size(u64::from(porep_config.sector_size)),
+    };
+
+    let mut valid_seed = None;
+    for seed in seeds {
+        let public_inputs =
+            stacked::PublicInputs::<<Tree::Hasher as Hasher>::Domain, DefaultPieceDomain> {
+                replica_id,
+                tau: Some(Tau { comm_r, comm_d }),
+                seed: *seed,
+                k: None,
+            };
+
+        if StackedCompound::verify(
+            &compound_public_params,
+            &public_inputs,
+            &proof,
+            &requirements,
+        )? {
+            valid_seed = Some(*seed);
+            break;
+        }
+    }
+
+    info!("verify_seal_any_seed:finish: {:?}", sector_id);
+
+    Ok(valid_seed)
+}
+
 /// Verifies a batch of outputs of some previously-run seal operations.
 ///
 /// # Arguments
@@ -1,5 +1,5 @@
-use std::fs::{self, File};
-use std::io::Write;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufReader, Read, Write};
 use std::path::Path;
 
 use anyhow::{ensure, Context, Result};
@@ -7,28 +7,32 @@ use bincode::{deserialize, serialize};
 use filecoin_hashers::{Domain, Hasher};
 use generic_array::typenum::Unsigned;
 use log::{info, trace};
+use memmap2::MmapOptions;
 use merkletree::merkle::get_merkle_tree_len;
 use merkletree::store::StoreConfig;
+use rayon::{ThreadPool, ThreadPoolBuilder};
 use storage_proofs_core::{
     cache_key::CacheKey,
     compound_proof::{self, CompoundProof},
-    merkle::{get_base_tree_count, MerkleTreeTrait},
+    merkle::{get_base_tree_count, MerkleProofTrait, MerkleTreeTrait},
     multi_proof::MultiProof,
     proof::ProofScheme,
 };
 use storage_proofs_porep::stacked::{PersistentAux, TemporaryAux};
 use storage_proofs_update::{
-    constants::TreeDArity, constants::TreeRHasher, EmptySectorUpdate, EmptySectorUpdateCompound,
-    PartitionProof, PrivateInputs, PublicInputs, PublicParams, SetupParams,
+    constants::TreeDArity, constants::TreeRHasher, Challenges, EmptySectorUpdate,
+    EmptySectorUpdateCircuit, EmptySectorUpdateCompound, PartitionProof, PrivateInputs,
+    PublicInputs, PublicParams, SetupParams,
 };
 
 use crate::{
+    api::{as_safe_commitment, comm_r_from_parts, fr_to_commitment},
     caches::{get_empty_sector_update_params, get_empty_sector_update_verifying_key},
     constants::{DefaultPieceDomain, DefaultPieceHasher},
-    pieces::verify_pieces,
+    pieces::{compute_comm_d_from_data, verify_pieces},
     types::{
-        Commitment, EmptySectorUpdateEncoded, EmptySectorUpdateProof, PieceInfo, PoRepConfig,
-        SectorUpdateConfig,
+        CacheKind, Commitment, EmptySectorUpdateEncoded, EmptySectorUpdateProof, FileAccessPlan,
+        PaddedBytesAmount, PieceInfo, PoRepConfig, SectorUpdateConfig, UpdateOp,
     },
 };
 
@@ -92,6 +96,15 @@ fn persist_t_aux<Tree: 'static + MerkleTreeTrait<Hasher = TreeRHasher>>(
     Ok(())
 }
 
+// Marks cache_path as the cache of an updated replica, see `classify_cache`.
+fn persist_update_marker(cache_path: &Path) -> Result<()> {
+    let marker_path = cache_path.join(CacheKey::UpdateMarker.to_string());
+    File::create(&marker_path)
+        .with_context(|| format!("could not create file update marker={:?}", marker_path))?;
+
+    Ok(())
+}
+
 // Re-instantiate a t_aux with the new cache path, then use the tree_d
 // and tree_r_last configs from it.  This is done to preserve the
 // original tree configuration info (in particular, the
@@ -134,10 +147,100 @@ fn get_new_configs_from_t_aux_old<Tree: 'static + MerkleTreeTrait<Hasher = TreeR
     Ok((tree_d_new_config, tree_r_last_new_config))
 }
 
+/// Determines whether `cache_dir` belongs to an originally sealed sector or to a replica updated
+/// with `encode_into`, e.g. so that tooling scanning disks does not garbage collect files of one
+/// kind according to the rules of the other.
+///
+/// A cache is `Updated` if it contains the marker written by `encode_into`, and `Sealed` if it
+/// contains a p_aux and a t_aux for `Tree` as well as labels or tree-c, which only sealing
+/// writes. Anything else is `Unknown`, in particular caches sealed and cleared with
+/// `clear_cache`, which cannot be told apart from caches of replicas updated before the marker
+/// existed.
+///
+/// # Arguments
+///
+/// * `cache_dir` - the cache directory to classify.
+pub fn classify_cache<Tree: 'static + MerkleTreeTrait<Hasher = TreeRHasher>>(
+    cache_dir: &Path,
+) -> Result<CacheKind> {
+    ensure!(
+        cache_dir.is_dir(),
+        "cache_dir={:?} is not a directory",
+        cache_dir
+    );
+
+    let has_file = |key: CacheKey| cache_dir.join(key.to_string()).is_file();
+    if !has_file(CacheKey::PAux) || !has_file(CacheKey::TAux) {
+        return Ok(CacheKind::Unknown);
+    }
+    if get_p_aux::<Tree>(cache_dir).is_err() || get_t_aux::<Tree>(cache_dir).is_err() {
+        return Ok(CacheKind::Unknown);
+    }
+
+    if has_file(CacheKey::UpdateMarker) {
+        return Ok(CacheKind::Updated);
+    }
+
+    let has_store = |id: &str| Path::new(&StoreConfig::data_path(cache_dir, id)).is_file();
+    let tree_c_id = CacheKey::CommCTree.to_string();
+    if has_store(&CacheKey::label_layer(1))
+        || has_store(&tree_c_id)
+        || has_store(&format!("{}-0", tree_c_id))
+    {
+        Ok(CacheKind::Sealed)
+    } else {
+        Ok(CacheKind::Unknown)
+    }
+}
+
+/// Computes comm_d_new for the staged data without encoding it, e.g.
+/// so that it can be committed to before `encode_into` is run.  The
+/// result is the same as the `comm_d_new` returned by `encode_into`
+/// for this staged data.
+///
+/// # Arguments
+///
+/// * `porep_config` - porep configuration of the sector being updated.
+/// * `staged_data_path` - path to the staged (fr32 padded) sector data.
+/// * `piece_infos` - the pieces of the staged data, checked against comm_d_new.
+pub fn compute_comm_d_new<Tree: 'static + MerkleTreeTrait<Hasher = TreeRHasher>>(
+    porep_config: &PoRepConfig,
+    staged_data_path: &Path,
+    piece_infos: &[PieceInfo],
+) -> Result<Commitment> {
+    info!("compute_comm_d_new:start");
+    let sector_bytes = u64::from(PaddedBytesAmount::from(porep_config.sector_size));
+
+    let file = File::open(staged_data_path)
+        .with_context(|| format!("could not open staged_data_path={:?}", staged_data_path))?;
+    ensure!(
+        file.metadata()?.len() >= sector_bytes,
+        "staged data is smaller than the sector size"
+    );
+    let comm_d = compute_comm_d_from_data(
+        porep_config.sector_size,
+        BufReader::new(file).take(sector_bytes),
+    )?;
+
+    ensure!(comm_d != [0; 32], "Invalid all zero commitment (comm_d)");
+    ensure!(
+        verify_pieces(&comm_d, piece_infos, porep_config.sector_size)?,
+        "pieces and comm_d do not match"
+    );
+
+    info!("compute_comm_d_new:finish");
+
+    Ok(comm_d)
+}
+
 /// Encodes data into an existing replica.  The original replica is
 /// not modified and the resulting output data is written as
 /// new_replica_path (with required artifacts located in
 /// new_cache_path).
+///
+/// Every node of the new replica depends on comm_d_new (through phi
+/// and the derived rho values), so changing any part of the staged
+/// data requires re-encoding the entire replica.
 #[allow(clippy::too_many_arguments)]
 pub fn encode_into<Tree: 'static + MerkleTreeTrait<Hasher = TreeRHasher>>(
     porep_config: &PoRepConfig,
@@ -196,6 +299,7 @@ pub fn encode_into<Tree: 'static + MerkleTreeTrait<Hasher = TreeRHasher>>(
     p_aux.comm_r_last = comm_r_last_domain;
     persist_p_aux::<Tree>(&p_aux, new_cache_path)?;
     persist_t_aux::<Tree>(&t_aux, new_cache_path)?;
+    persist_update_marker(new_cache_path)?;
 
     info!("encode_into:finish");
 
@@ -237,6 +341,62 @@ pub fn decode_from<Tree: 'static + MerkleTreeTrait<Hasher = TreeRHasher>>(
     Ok(())
 }
 
+/// Like `decode_from`, but decodes the replica on a dedicated rayon pool of `num_threads`
+/// threads. The replica is decoded in chunks of nodes, each of which is written to its own
+/// range of the memory mapped `out_data_path`, so the output is identical to `decode_from`
+/// regardless of the number of threads.
+#[allow(clippy::too_many_arguments)]
+pub fn decode_from_parallel<Tree: 'static + MerkleTreeTrait<Hasher = TreeRHasher>>(
+    config: SectorUpdateConfig,
+    out_data_path: &Path,
+    replica_path: &Path,
+    sector_key_path: &Path,
+    sector_key_cache_path: &Path,
+    comm_d_new: Commitment,
+    num_threads: usize,
+) -> Result<()> {
+    ensure!(num_threads > 0, "num_threads must be greater than zero");
+    let pool = ThreadPoolBuilder::new()
+        .num_threads(num_threads)
+        .build()
+        .context("failed to build decode thread pool")?;
+
+    pool.install(|| {
+        decode_from::<Tree>(
+            config,
+            out_data_path,
+            replica_path,
+            sector_key_path,
+            sector_key_cache_path,
+            comm_d_new,
+        )
+    })
+}
+
+/// Checks that the data decoded by `decode_from` commits to `expected_comm_d_new`, so that a
+/// decode can be confirmed to be faithful without access to the original staged data.
+///
+/// # Arguments
+///
+/// * `config` - sector update configuration of the updated sector.
+/// * `decoded_path` - path to the decoded (fr32 padded) sector data.
+/// * `expected_comm_d_new` - the data commitment of the updated sector.
+pub fn verify_decoded_matches_comm_d<Tree: 'static + MerkleTreeTrait<Hasher = TreeRHasher>>(
+    config: SectorUpdateConfig,
+    decoded_path: &Path,
+    expected_comm_d_new: Commitment,
+) -> Result<bool> {
+    info!("verify_decoded_matches_comm_d:start");
+
+    let file = File::open(decoded_path)
+        .with_context(|| format!("could not open decoded_path={:?}", decoded_path))?;
+    let comm_d = compute_comm_d_from_data(config.sector_size, BufReader::new(file))?;
+
+    info!("verify_decoded_matches_comm_d:finish");
+
+    Ok(comm_d == expected_comm_d_new)
+}
+
 /// Removes encoded data and outputs the sector key.
 #[allow(clippy::too_many_arguments)]
 pub fn remove_encoded_data<Tree: 'static + MerkleTreeTrait<Hasher = TreeRHasher>>(
@@ -281,6 +441,263 @@ pub fn remove_encoded_data<Tree: 'static + MerkleTreeTrait<Hasher = TreeRHasher>
     Ok(())
 }
 
+/// Lists the files `decode_from` or `remove_encoded_data` read and write when run with the
+/// paths of `op`, e.g. so that a sandboxed worker can be granted access to exactly these files.
+///
+/// The outputs of both operations are memory mapped and must exist with their final length
+/// beforehand, and the cache directories of `op` must exist as well.
+///
+/// # Arguments
+///
+/// * `op` - the operation and the paths it is run on.
+pub fn sector_update_file_access_plan<Tree: 'static + MerkleTreeTrait<Hasher = TreeRHasher>>(
+    op: &UpdateOp,
+) -> FileAccessPlan {
+    let p_aux = CacheKey::PAux.to_string();
+    let t_aux = CacheKey::TAux.to_string();
+
+    match op {
+        UpdateOp::Decode {
+            out_data_path,
+            replica_path,
+            sector_key_path,
+            sector_key_cache_path,
+        } => FileAccessPlan {
+            reads: vec![
+                sector_key_cache_path.join(&p_aux),
+                replica_path.clone(),
+                sector_key_path.clone(),
+            ],
+            writes: vec![out_data_path.clone()],
+        },
+        UpdateOp::RemoveEncodedData {
+            sector_key_path,
+            sector_key_cache_path,
+            replica_path,
+            replica_cache_path,
+            data_path,
+        } => {
+            let mut writes = vec![
+                sector_key_path.clone(),
+                sector_key_cache_path.join(&p_aux),
+                sector_key_cache_path.join(&t_aux),
+            ];
+            // The tree_r_last of the recovered sector key is rebuilt into its cache.
+            let base_tree_count = get_base_tree_count::<Tree>();
+            for i in 0..base_tree_count {
+                let id = if base_tree_count == 1 {
+                    CacheKey::CommRLastTree.to_string()
+                } else {
+                    format!("{}-{}", CacheKey::CommRLastTree, i)
+                };
+                writes.push(StoreConfig::data_path(sector_key_cache_path, &id));
+            }
+
+            FileAccessPlan {
+                reads: vec![
+                    replica_cache_path.join(&p_aux),
+                    replica_cache_path.join(&t_aux),
+                    replica_path.clone(),
+                    data_path.clone(),
+                ],
+                writes,
+            }
+        }
+    }
+}
+
+// Creates (or truncates) the file at path with a length of len bytes, so that it can be mmapped
+// as the output of encoding, decoding or data removal.
+fn create_sized_file(path: &Path, len: u64) -> Result<()> {
+    let f = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)
+        .with_context(|| format!("could not open path={:?}", path))?;
+    f.set_len(len)
+        .with_context(|| format!("could not set length of path={:?}", path))?;
+
+    Ok(())
+}
+
+// Compares the first len bytes of both files.
+fn files_match(a: &Path, b: &Path, len: usize) -> Result<bool> {
+    let map = |path: &Path| -> Result<_> {
+        let file = File::open(path).with_context(|| format!("could not open path={:?}", path))?;
+        unsafe { MmapOptions::new().map(&file) }
+            .with_context(|| format!("could not mmap path={:?}", path))
+    };
+    let a = map(a)?;
+    let b = map(b)?;
+    ensure!(
+        a.len() >= len && b.len() >= len,
+        "cannot compare {} bytes of files with lengths {} and {}",
+        len,
+        a.len(),
+        b.len()
+    );
+
+    Ok(a[..len] == b[..len])
+}
+
+/// Runs the full empty sector update cycle on top of an existing sector key and checks that
+/// it is correct: the staged data is encoded into a new replica, the update proof over it is
+/// generated and verified, the replica is decoded again and the encoded data is removed.
+///
+/// Returns `true` only if the proof verifies, the decoded data matches the staged data and the
+/// sector key recovered by removing the encoded data matches `sector_key_path`. This is
+/// intended as a correctness check for tooling and tests, all intermediate artifacts (the new
+/// replica and cache, the decoded data and the recovered sector key) are written to
+/// `work_dir`, which should be a temporary directory.
+///
+/// # Arguments
+///
+/// * `porep_config` - porep configuration of the sector being updated.
+/// * `sector_key_path` - path to the sealed sector key (the original replica).
+/// * `sector_key_cache_path` - path to the cache directory of the sector key.
+/// * `staged_data_path` - path to the staged (fr32 padded) sector data.
+/// * `piece_infos` - the pieces of the staged data.
+/// * `work_dir` - directory to write all intermediate artifacts to.
+pub fn verify_sector_update_roundtrip<Tree: 'static + MerkleTreeTrait<Hasher = TreeRHasher>>(
+    porep_config: &PoRepConfig,
+    sector_key_path: &Path,
+    sector_key_cache_path: &Path,
+    staged_data_path: &Path,
+    piece_infos: &[PieceInfo],
+    work_dir: &Path,
+) -> Result<bool> {
+    info!("verify_sector_update_roundtrip:start");
+    let config = SectorUpdateConfig::from_porep_config(porep_config);
+    let sector_bytes = usize::from(PaddedBytesAmount::from(porep_config.sector_size));
+
+    let comm_r_old = {
+        let p_aux = get_p_aux::<Tree>(sector_key_cache_path)?;
+        fr_to_commitment(&comm_r_from_parts::<Tree>(&p_aux.comm_c, &p_aux.comm_r_last).into())
+    };
+
+    let replica_len = fs::metadata(sector_key_path)
+        .with_context(|| format!("could not read metadata of path={:?}", sector_key_path))?
+        .len();
+
+    let replica_path = work_dir.join("sector-update-replica");
+    let replica_cache_path = work_dir.join("sector-update-cache");
+    fs::create_dir_all(&replica_cache_path)
+        .with_context(|| format!("could not create path={:?}", replica_cache_path))?;
+    create_sized_file(&replica_path, replica_len)?;
+
+    let encoded = encode_into::<Tree>(
+        porep_config,
+        &replica_path,
+        &replica_cache_path,
+        sector_key_path,
+        sector_key_cache_path,
+        staged_data_path,
+        piece_infos,
+    )?;
+
+    let proof = generate_empty_sector_update_proof::<Tree>(
+        porep_config,
+        comm_r_old,
+        encoded.comm_r_new,
+        encoded.comm_d_new,
+        sector_key_path,
+        sector_key_cache_path,
+        &replica_path,
+        &replica_cache_path,
+    )?;
+    let proof_is_valid = verify_empty_sector_update_proof::<Tree>(
+        porep_config,
+        &proof.0,
+        comm_r_old,
+        encoded.comm_r_new,
+        encoded.comm_d_new,
+    )?;
+
+    let decoded_path = work_dir.join("sector-update-decoded");
+    create_sized_file(&decoded_path, replica_len)?;
+    decode_from::<Tree>(
+        config,
+        &decoded_path,
+        &replica_path,
+        sector_key_path,
+        sector_key_cache_path,
+        encoded.comm_d_new,
+    )?;
+    let decoded_matches = files_match(&decoded_path, staged_data_path, sector_bytes)?;
+
+    let removed_path = work_dir.join("sector-update-sector-key");
+    let removed_cache_path = work_dir.join("sector-update-sector-key-cache");
+    fs::create_dir_all(&removed_cache_path)
+        .with_context(|| format!("could not create path={:?}", removed_cache_path))?;
+    create_sized_file(&removed_path, replica_len)?;
+    // The sector key cache is passed as the replica cache, as it holds the p_aux and t_aux of
+    // the original sealing.
+    remove_encoded_data::<Tree>(
+        config,
+        &removed_path,
+        &removed_cache_path,
+        &replica_path,
+        sector_key_cache_path,
+        staged_data_path,
+        encoded.comm_d_new,
+    )?;
+    let removed_matches = files_match(&removed_path, sector_key_path, sector_bytes)?;
+
+    trace!(
+        "sector update roundtrip: proof valid {}, decoded matches {}, removed matches {}",
+        proof_is_valid,
+        decoded_matches,
+        removed_matches,
+    );
+    info!("verify_sector_update_roundtrip:finish");
+
+    Ok(proof_is_valid && decoded_matches && removed_matches)
+}
+
+/// Returns the blank (witness-free) empty sector update circuit for the given sector size.
+///
+/// This is the circuit used for parameter generation, so that tooling can produce the groth
+/// parameters and verifying key without a real witness.
+pub fn empty_sector_update_blank_circuit<Tree: 'static + MerkleTreeTrait<Hasher = TreeRHasher>>(
+    porep_config: &PoRepConfig,
+) -> EmptySectorUpdateCircuit<Tree> {
+    let public_params: PublicParams =
+        PublicParams::from_sector_size(u64::from(porep_config.sector_size));
+
+    <EmptySectorUpdateCompound<Tree> as CompoundProof<
+        EmptySectorUpdate<Tree>,
+        EmptySectorUpdateCircuit<Tree>,
+    >>::blank_circuit(&public_params)
+}
+
+/// Returns the node indices challenged by the vanilla proof of partition `partition_index`, in
+/// the order of its challenge proofs. These are the same challenges `generate_partition_proofs`
+/// and `generate_single_partition_proof` prove, and depend on `comm_r_new` only.
+///
+/// # Arguments
+///
+/// * `config` - sector update configuration of the updated sector.
+/// * `comm_r_new` - the replica commitment of the updated replica.
+/// * `partition_index` - the index of the partition to return the challenges of.
+pub fn sector_update_challenges<Tree: 'static + MerkleTreeTrait<Hasher = TreeRHasher>>(
+    config: SectorUpdateConfig,
+    comm_r_new: Commitment,
+    partition_index: usize,
+) -> Result<Vec<u64>> {
+    let comm_r_new_safe = <Tree::Hasher as Hasher>::Domain::try_from_bytes(&comm_r_new)?;
+
+    let partitions = usize::from(config.update_partitions);
+    ensure!(partition_index < partitions, "invalid partition index");
+
+    let challenges = Challenges::new(config.nodes_count, comm_r_new_safe, partition_index)
+        .map(u64::from)
+        .collect();
+
+    Ok(challenges)
+}
+
 /// Generate a single vanilla partition proof for a specified partition.
 #[allow(clippy::too_many_arguments)]
 pub fn generate_single_partition_proof<Tree: 'static + MerkleTreeTrait<Hasher = TreeRHasher>>(
@@ -435,6 +852,137 @@ pub fn generate_partition_proofs<Tree: 'static + MerkleTreeTrait<Hasher = TreeRH
     Ok(partition_proofs)
 }
 
+/// Like `generate_partition_proofs`, but runs its rayon work on the given rayon `pool` instead
+/// of the global one. The generated partition proofs do not depend on the pool.
+#[allow(clippy::too_many_arguments)]
+pub fn generate_partition_proofs_in_pool<Tree: 'static + MerkleTreeTrait<Hasher = TreeRHasher>>(
+    pool: &ThreadPool,
+    config: SectorUpdateConfig,
+    comm_r_old: Commitment,
+    comm_r_new: Commitment,
+    comm_d_new: Commitment,
+    sector_key_path: &Path,
+    sector_key_cache_path: &Path,
+    replica_path: &Path,
+    replica_cache_path: &Path,
+) -> Result<Vec<PartitionProof<Tree>>> {
+    pool.install(|| {
+        generate_partition_proofs::<Tree>(
+            config,
+            comm_r_old,
+            comm_r_new,
+            comm_d_new,
+            sector_key_path,
+            sector_key_cache_path,
+            replica_path,
+            replica_cache_path,
+        )
+    })
+}
+
+/// Like `generate_partition_proofs`, but lazily generates the partition proofs one at a time,
+/// in partition order, so that a caller can stream them out without holding all of them in
+/// memory. Each partition proof is generated as with `generate_single_partition_proof`.
+#[allow(clippy::too_many_arguments)]
+pub fn partition_proofs_iter<'a, Tree: 'static + MerkleTreeTrait<Hasher = TreeRHasher>>(
+    config: SectorUpdateConfig,
+    comm_r_old: Commitment,
+    comm_r_new: Commitment,
+    comm_d_new: Commitment,
+    sector_key_path: &'a Path,
+    sector_key_cache_path: &'a Path,
+    replica_path: &'a Path,
+    replica_cache_path: &'a Path,
+) -> impl Iterator<Item = Result<PartitionProof<Tree>>> + 'a {
+    (0..usize::from(config.update_partitions)).map(move |partition_index| {
+        generate_single_partition_proof::<Tree>(
+            config,
+            partition_index,
+            comm_r_old,
+            comm_r_new,
+            comm_d_new,
+            sector_key_path,
+            sector_key_cache_path,
+            replica_path,
+            replica_cache_path,
+        )
+    })
+}
+
+/// Checks that `partition_proofs` is a complete set of partition proofs in partition order, as
+/// expected by `verify_partition_proofs`, so that a set assembled from proofs generated in
+/// different places fails with a precise error instead of a failed verification.
+///
+/// The partition a proof belongs to is derived from its challenges, which all lie within the
+/// nodes of that partition. The proofs themselves are not verified.
+///
+/// # Arguments
+///
+/// * `config` - the sector update config of the sector.
+/// * `partition_proofs` - the partition proofs of all partitions, in order.
+pub fn validate_partition_proof_set<Tree: 'static + MerkleTreeTrait<Hasher = TreeRHasher>>(
+    config: SectorUpdateConfig,
+    partition_proofs: &[PartitionProof<Tree>],
+) -> Result<()> {
+    let partitions = config.partition_count();
+    ensure!(
+        partition_proofs.len() == partitions,
+        "expected {} partition proofs, got {}",
+        partitions,
+        partition_proofs.len()
+    );
+
+    let partition_nodes = config.nodes_count / partitions;
+    // The positions of the proofs of each partition.
+    let mut proven_by = vec![Vec::new(); partitions];
+    for (i, partition_proof) in partition_proofs.iter().enumerate() {
+        let mut challenged_partitions = partition_proof
+            .challenge_proofs
+            .iter()
+            .map(|challenge_proof| challenge_proof.proof_r_new.path_index() / partition_nodes);
+        let k = challenged_partitions
+            .next()
+            .with_context(|| format!("partition proof {} has no challenge proofs", i))?;
+        ensure!(
+            challenged_partitions.all(|other| other == k),
+            "partition proof {} has challenges of more than a single partition",
+            i
+        );
+        ensure!(
+            k < partitions,
+            "partition proof {} proves invalid partition {}",
+            i,
+            k
+        );
+        proven_by[k].push(i);
+    }
+
+    let missing: Vec<usize> = (0..partitions)
+        .filter(|&k| proven_by[k].is_empty())
+        .collect();
+    let duplicated: Vec<usize> = (0..partitions)
+        .filter(|&k| proven_by[k].len() > 1)
+        .collect();
+    ensure!(
+        missing.is_empty(),
+        "partitions {:?} are proven more than once, partitions {:?} are not proven",
+        duplicated,
+        missing
+    );
+
+    // All partitions are proven exactly once, so only their order can be wrong.
+    for (k, positions) in proven_by.iter().enumerate() {
+        ensure!(
+            positions[0] == k,
+            "partition proof {} proves partition {}, the proofs must be in partition order",
+            positions[0],
+            k
+        );
+    }
+
+    Ok(())
+}
+
 /// Verify all vanilla partition proofs across all partitions.
 pub fn verify_partition_proofs<Tree: 'static + MerkleTreeTrait<Hasher = TreeRHasher>>(
     config: SectorUpdateConfig,
@@ -469,6 +1017,126 @@ pub fn verify_partition_proofs<Tree: 'static + MerkleTreeTrait<Hasher = TreeRHas
     Ok(valid)
 }
 
+/// Like `verify_partition_proofs`, but takes the partition proofs serialized with `bincode`, as
+/// received e.g. by a verifier over the network.
+///
+/// # Arguments
+///
+/// * `config` - the sector update config of the sector.
+/// * `proofs` - the serialized partition proofs of all partitions, in order.
+/// * `comm_r_old` - the replica commitment of the sector before the update.
+/// * `comm_r_new` - the replica commitment of the updated sector.
+/// * `comm_d_new` - the data commitment of the updated sector.
+pub fn verify_partition_proofs_from_bytes<Tree: 'static + MerkleTreeTrait<Hasher = TreeRHasher>>(
+    config: SectorUpdateConfig,
+    proofs: &[Vec<u8>],
+    comm_r_old: Commitment,
+    comm_r_new: Commitment,
+    comm_d_new: Commitment,
+) -> Result<bool> {
+    let proofs = proofs
+        .iter()
+        .enumerate()
+        .map(|(k, bytes)| {
+            deserialize::<PartitionProof<Tree>>(bytes)
+                .with_context(|| format!("could not deserialize partition proof {}", k))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    verify_partition_proofs::<Tree>(config, &proofs, comm_r_old, comm_r_new, comm_d_new)
+}
+
+// Computes comm_r = H(comm_c || comm_r_last) of a replica from the commitments in its p_aux.
+fn comm_r_old_from_parts<Tree: 'static + MerkleTreeTrait<Hasher = TreeRHasher>>(
+    comm_c: Commitment,
+    comm_r_last: Commitment,
+) -> Result<Commitment> {
+    let comm_c_safe = as_safe_commitment(&comm_c, "comm_c_old")?;
+    let comm_r_last_safe = as_safe_commitment(&comm_r_last, "comm_r_last_old")?;
+
+    Ok(fr_to_commitment(
+        &comm_r_from_parts::<Tree>(&comm_c_safe, &comm_r_last_safe).into(),
+    ))
+}
+
+/// Like `verify_single_partition_proof`, but takes the replica commitment of the sector before
+/// the update as its parts, e.g. as read from the p_aux of the sector key, and computes
+/// `comm_r_old` from them.
+///
+/// # Arguments
+///
+/// * `config` - the sector update config of the sector.
+/// * `partition_index` - the partition proven by `proof`.
+/// * `proof` - the partition proof.
+/// * `comm_c_old` - the column commitment of the sector before the update.
+/// * `comm_r_last_old` - the root of the replica tree of the sector before the update.
+/// * `comm_r_new` - the replica commitment of the updated sector.
+/// * `comm_d_new` - the data commitment of the updated sector.
+#[allow(clippy::too_many_arguments)]
+pub fn verify_single_partition_proof_from_parts<
+    Tree: 'static + MerkleTreeTrait<Hasher = TreeRHasher>,
+>(
+    config: SectorUpdateConfig,
+    partition_index: usize,
+    proof: PartitionProof<Tree>,
+    comm_c_old: Commitment,
+    comm_r_last_old: Commitment,
+    comm_r_new: Commitment,
+    comm_d_new: Commitment,
+) -> Result<bool> {
+    let comm_r_old = comm_r_old_from_parts::<Tree>(comm_c_old, comm_r_last_old)?;
+
+    verify_single_partition_proof::<Tree>(
+        config,
+        partition_index,
+        proof,
+        comm_r_old,
+        comm_r_new,
+        comm_d_new,
+    )
+}
+
+/// Like `verify_partition_proofs`, but takes the replica commitment of the sector before the
+/// update as its parts, e.g. as read from the p_aux of the sector key, and computes `comm_r_old`
+/// from them.
+///
+/// # Arguments
+///
+/// * `config` - the sector update config of the sector.
+/// * `proofs` - the partition proofs of all partitions, in order.
+/// * `comm_c_old` - the column commitment of the sector before the update.
+/// * `comm_r_last_old` - the root of the replica tree of the sector before the update.
+/// * `comm_r_new` - the replica commitment of the updated sector.
+/// * `comm_d_new` - the data commitment of the updated sector.
+pub fn verify_partition_proofs_from_parts<Tree: 'static + MerkleTreeTrait<Hasher = TreeRHasher>>(
+    config: SectorUpdateConfig,
+    proofs: &[PartitionProof<Tree>],
+    comm_c_old: Commitment,
+    comm_r_last_old: Commitment,
+    comm_r_new: Commitment,
+    comm_d_new: Commitment,
+) -> Result<bool> {
+    let comm_r_old = comm_r_old_from_parts::<Tree>(comm_c_old, comm_r_last_old)?;
+
+    verify_partition_proofs::<Tree>(config, proofs, comm_r_old, comm_r_new, comm_d_new)
+}
+
+/// Generates an empty sector update proof from previously generated partition proofs (see
+/// `generate_partition_proofs` or `generate_single_partition_proof`).
+///
+/// This is the entry point for provers which generate the vanilla partition proofs elsewhere,
+/// e.g. on other machines of a cluster: `PartitionProof` implements `Serialize` and
+/// `Deserialize`, so the proofs can be shipped to the machine running the snark. The partition
+/// proofs are verified before proving, so proofs which don't match the commitments are rejected
+/// early.
+///
+/// # Arguments
+///
+/// * `porep_config` - this sector's porep config that contains the number of bytes in the sector.
+/// * `vanilla_proofs` - the partition proofs of all partitions, in order.
+/// * `comm_r_old` - the replica commitment of the sector before the update.
+/// * `comm_r_new` - the replica commitment of the updated sector.
+/// * `comm_d_new` - the data commitment of the updated sector.
 #[allow(clippy::too_many_arguments)]
 pub fn generate_empty_sector_update_proof_with_vanilla<
     Tree: 'static + MerkleTreeTrait<Hasher = TreeRHasher>,
@@ -489,6 +1157,12 @@ pub fn generate_empty_sector_update_proof_with_vanilla<
     let config = SectorUpdateConfig::from_porep_config(porep_config);
 
     let partitions = usize::from(config.update_partitions);
+    ensure!(
+        vanilla_proofs.len() == partitions,
+        "expected {} partition proofs, got {}",
+        partitions,
+        vanilla_proofs.len()
+    );
     let public_inputs: storage_proofs_update::PublicInputs = PublicInputs {
         k: partitions,
         comm_r_old: comm_r_old_safe,
@@ -506,6 +1180,15 @@ pub fn generate_empty_sector_update_proof_with_vanilla<
     };
     let pub_params_compound = EmptySectorUpdateCompound::<Tree>::setup(&setup_params_compound)?;
 
+    ensure!(
+        EmptySectorUpdate::<Tree>::verify_all_partitions(
+            &pub_params_compound.vanilla_params,
+            &public_inputs,
+            &vanilla_proofs,
+        )?,
+        "partition proofs failed to verify"
+    );
+
     let groth_params = get_empty_sector_update_params::<Tree>(porep_config)?;
     let multi_proof = EmptySectorUpdateCompound::prove_with_vanilla(
         &pub_params_compound,
@@ -519,6 +1202,21 @@ pub fn generate_empty_sector_update_proof_with_vanilla<
     Ok(EmptySectorUpdateProof(multi_proof.to_vec()?))
 }
 
+/// Generates an empty sector update proof in a single call: the vanilla partition proofs are
+/// generated and proven without being returned to the caller. The proof is identical in format
+/// to the one of `generate_empty_sector_update_proof_with_vanilla`, i.e. one groth16 proof per
+/// partition.
+///
+/// # Arguments
+///
+/// * `porep_config` - this sector's porep config that contains the number of bytes in the sector.
+/// * `comm_r_old` - the replica commitment of the sector before the update.
+/// * `comm_r_new` - the replica commitment of the updated sector.
+/// * `comm_d_new` - the data commitment of the updated sector.
+/// * `sector_key_path` - path to the sector key (the replica before the update).
+/// * `sector_key_cache_path` - path to the cache directory of the sector key.
+/// * `replica_path` - path to the updated replica.
+/// * `replica_cache_path` - path to the cache directory of the updated replica.
 #[allow(clippy::too_many_arguments)]
 pub fn generate_empty_sector_update_proof<Tree: 'static + MerkleTreeTrait<Hasher = TreeRHasher>>(
     porep_config: &PoRepConfig,
@@ -1,30 +1,150 @@
+use std::env;
+use std::fs::File;
 use std::mem::size_of;
+use std::path::Path;
 
-use anyhow::{Context, Result};
+use anyhow::{ensure, Context, Result};
 use blstrs::Scalar as Fr;
-use filecoin_hashers::{Domain, Hasher};
+use filecoin_hashers::{Domain, HashFunction, Hasher};
 use fr32::{bytes_into_fr, fr_into_bytes};
+use memmap2::MmapOptions;
 use merkletree::merkle::{get_merkle_tree_leafs, get_merkle_tree_len};
-use storage_proofs_core::merkle::{get_base_tree_count, MerkleTreeTrait};
+use rayon::prelude::{IndexedParallelIterator, ParallelIterator, ParallelSlice};
+use storage_proofs_core::{
+    merkle::{get_base_tree_count, MerkleTreeTrait},
+    sector::SectorId,
+};
 use typenum::Unsigned;
 
-use crate::types::{Commitment, SectorSize};
+use crate::types::{Commitment, ProverId, SectorSize};
+
+/// Converts a commitment into the field element it encodes.
+///
+/// This is the canonical byte-order contract for all commitments (`comm_r`, `comm_d`, `comm_c`,
+/// `comm_r_last`), as well as for prover ids and randomness: the 32 bytes are the
+/// **little-endian**, non-Montgomery representation of the field element, i.e. exactly what
+/// `Fr::to_repr` produces. Callers integrating from other languages must not reverse the bytes
+/// (big-endian) before passing them in.
+///
+/// Returns an error if the bytes do not encode a valid field element.
+pub fn commitment_to_fr(c: &Commitment) -> Result<Fr> {
+    bytes_into_fr(c)
+}
+
+/// Converts a field element into its canonical commitment bytes.
+///
+/// This is the inverse of [`commitment_to_fr`] and produces the little-endian, non-Montgomery
+/// representation of `fr`.
+pub fn fr_to_commitment(fr: &Fr) -> Commitment {
+    let mut commitment = [0; 32];
+    commitment.copy_from_slice(&fr_into_bytes(fr));
+    commitment
+}
+
+/// Converts a field element into a prover id, using the canonical byte order of
+/// [`fr_to_commitment`].
+pub fn prover_id_from_fr(fr: &Fr) -> ProverId {
+    fr_to_commitment(fr)
+}
+
+/// Converts a prover id into the field element it encodes, the inverse of
+/// [`prover_id_from_fr`].
+///
+/// Returns an error if the bytes do not encode a valid field element.
+pub fn prover_id_to_fr(prover_id: &ProverId) -> Result<Fr> {
+    commitment_to_fr(prover_id)
+}
+
+/// Converts a field element into randomness (e.g. a PoSt `ChallengeSeed`), using the canonical
+/// byte order of [`fr_to_commitment`].
+pub fn randomness_from_fr(fr: &Fr) -> [u8; 32] {
+    fr_to_commitment(fr)
+}
+
+/// Converts randomness into the field element it encodes, the inverse of
+/// [`randomness_from_fr`].
+///
+/// Returns an error if the bytes do not encode a valid field element.
+pub fn randomness_to_fr(randomness: &[u8; 32]) -> Result<Fr> {
+    commitment_to_fr(randomness)
+}
+
+/// The number of low bits of a `SectorId` holding the per-miner sequence number, see
+/// [`next_sector_id`].
+pub const SECTOR_ID_SEQUENCE_BITS: u32 = 32;
+
+/// The largest sector number the chain accepts, 2^63 - 1.
+pub const MAX_SECTOR_ID: u64 = i64::MAX as u64;
+
+/// Deterministically allocates a `SectorId` for the `sequence`-th sector of `miner_actor`.
+///
+/// The 64 bits of the resulting `SectorId` are laid out as follows:
+///
+/// ```text
+/// | 63 ............ 32 | 31 ............ 0 |
+/// |    miner_actor     |     sequence      |
+/// ```
+///
+/// That is, the id is `(miner_actor << 32) | sequence`, so ids are monotonic in `sequence` for
+/// a fixed miner, and two different `(miner_actor, sequence)` pairs never map to the same id.
+/// `SectorId` is otherwise treated as an opaque `u64` by the rest of the API (e.g. when
+/// computing the replica id), so any id produced here is valid.
+///
+/// Returns an error if either `miner_actor` or `sequence` does not fit into its 32 bits, as
+/// the id could otherwise collide with the one of another miner, or if the id exceeds
+/// [`MAX_SECTOR_ID`], which is the case for all miner actors from 2^31 on.
+///
+/// # Arguments
+///
+/// * `miner_actor` - the actor id of the miner owning the sector.
+/// * `sequence` - the per-miner sector sequence number.
+pub fn next_sector_id(miner_actor: u64, sequence: u64) -> Result<SectorId> {
+    ensure!(
+        miner_actor >> (64 - SECTOR_ID_SEQUENCE_BITS) == 0,
+        "miner actor {} does not fit into {} bits",
+        miner_actor,
+        64 - SECTOR_ID_SEQUENCE_BITS
+    );
+    ensure!(
+        sequence >> SECTOR_ID_SEQUENCE_BITS == 0,
+        "sector sequence {} does not fit into {} bits",
+        sequence,
+        SECTOR_ID_SEQUENCE_BITS
+    );
+
+    let id = (miner_actor << SECTOR_ID_SEQUENCE_BITS) | sequence;
+    ensure!(
+        id <= MAX_SECTOR_ID,
+        "sector id {} exceeds the maximum sector id {}",
+        id,
+        MAX_SECTOR_ID
+    );
+
+    Ok(SectorId::from(id))
+}
+
+/// Returns the replica commitment comm_r = H(comm_c || comm_r_last) of a sector with the given
+/// roots of tree-c and tree-r-last.
+pub(crate) fn comm_r_from_parts<Tree: MerkleTreeTrait>(
+    comm_c: &<Tree::Hasher as Hasher>::Domain,
+    comm_r_last: &<Tree::Hasher as Hasher>::Domain,
+) -> <Tree::Hasher as Hasher>::Domain {
+    <Tree::Hasher as Hasher>::Function::hash2(comm_c, comm_r_last)
+}
 
 pub fn as_safe_commitment<H: Domain, T: AsRef<str>>(
     comm: &[u8; 32],
     commitment_name: T,
 ) -> Result<H> {
-    bytes_into_fr(comm)
+    commitment_to_fr(comm)
         .map(Into::into)
         .with_context(|| format!("Invalid commitment ({})", commitment_name.as_ref(),))
 }
 
+/// Same as [`fr_to_commitment`], which should be used instead.
+#[deprecated(note = "use `fr_to_commitment` instead")]
 pub fn commitment_from_fr(fr: Fr) -> Commitment {
-    let mut commitment = [0; 32];
-    for (i, b) in fr_into_bytes(&fr).iter().enumerate() {
-        commitment[i] = *b;
-    }
-    commitment
+    fr_to_commitment(&fr)
 }
 
 pub fn get_base_tree_size<Tree: MerkleTreeTrait>(sector_size: SectorSize) -> Result<usize> {
@@ -38,3 +158,110 @@ pub fn get_base_tree_size<Tree: MerkleTreeTrait>(sector_size: SectorSize) -> Res
 pub fn get_base_tree_leafs<Tree: MerkleTreeTrait>(base_tree_size: usize) -> Result<usize> {
     get_merkle_tree_leafs(base_tree_size, Tree::Arity::to_usize())
 }
+
+// Number of field elements compared per parallel chunk by `diff_field_element_files`.
+const DIFF_CHUNK_ELEMENTS: usize = 1 << 16;
+
+/// Compares two files of equal length element by element, where each element is a 32 byte
+/// chunk, e.g. to compare replicas or the output of `decode_from` and `remove_encoded_data`
+/// against the expected data.
+///
+/// Returns the byte offset and the bytes of both elements of the first `max_mismatches` elements
+/// which differ, in ascending order of offset. An empty result means the files match. Elements
+/// are compared as raw bytes, so corrupted elements which are not valid field elements are
+/// reported like any other mismatch; `bytes_into_fr` tells whether they are.
+///
+/// # Arguments
+///
+/// * `path1` - path to the first file.
+/// * `path2` - path to the second file.
+/// * `parallel` - whether to compare chunks of the files in parallel.
+/// * `max_mismatches` - the maximum number of mismatches to return, at least 1.
+pub fn diff_field_element_files(
+    path1: &Path,
+    path2: &Path,
+    parallel: bool,
+    max_mismatches: usize,
+) -> Result<Vec<(u64, [u8; 32], [u8; 32])>> {
+    ensure!(max_mismatches > 0, "max_mismatches must be greater than 0");
+
+    let map = |path: &Path| -> Result<_> {
+        let file = File::open(path).with_context(|| format!("could not open path={:?}", path))?;
+        unsafe { MmapOptions::new().map(&file) }
+            .with_context(|| format!("could not mmap path={:?}", path))
+    };
+    let data1 = map(path1)?;
+    let data2 = map(path2)?;
+
+    let fr_size = size_of::<Fr>();
+    ensure!(data1.len() == data2.len(), "File sizes must match");
+    ensure!(
+        data1.len() % fr_size == 0,
+        "File size {} is not a multiple of the field element size",
+        data1.len()
+    );
+
+    // Compares one chunk of both files, starting at byte `start`.
+    let diff_chunk = |start: usize, chunk1: &[u8], chunk2: &[u8]| {
+        let mut mismatches = Vec::new();
+        for (i, (bytes1, bytes2)) in chunk1
+            .chunks_exact(fr_size)
+            .zip(chunk2.chunks_exact(fr_size))
+            .enumerate()
+        {
+            if mismatches.len() == max_mismatches {
+                break;
+            }
+            if bytes1 != bytes2 {
+                let mut element1 = [0u8; 32];
+                element1.copy_from_slice(bytes1);
+                let mut element2 = [0u8; 32];
+                element2.copy_from_slice(bytes2);
+                mismatches.push(((start + i * fr_size) as u64, element1, element2));
+            }
+        }
+        mismatches
+    };
+
+    let mut mismatches: Vec<_> = if parallel {
+        let chunk_size = DIFF_CHUNK_ELEMENTS * fr_size;
+        let chunks: Vec<_> = data1
+            .par_chunks(chunk_size)
+            .zip(data2.par_chunks(chunk_size))
+            .enumerate()
+            .map(|(i, (chunk1, chunk2))| diff_chunk(i * chunk_size, chunk1, chunk2))
+            .collect();
+        chunks.into_iter().flatten().collect()
+    } else {
+        diff_chunk(0, &data1, &data2)
+    };
+    mismatches.truncate(max_mismatches);
+
+    Ok(mismatches)
+}
+
+/// Returns the GPU backend the SNARK proofs are generated with, or `None` if they are generated
+/// on the CPU only.
+///
+/// This reflects the `cuda` and `opencl` features this crate was built with, where CUDA takes
+/// precedence if both are enabled, and the `BELLMAN_NO_GPU` environment variable, which disables
+/// GPU proving at runtime. If no usable device is found when proving, bellperson still falls back
+/// to the CPU.
+pub fn gpu_backend_name() -> Option<&'static str> {
+    if env::var_os("BELLMAN_NO_GPU").is_some() {
+        return None;
+    }
+
+    if cfg!(feature = "cuda") {
+        Some("cuda")
+    } else if cfg!(feature = "opencl") {
+        Some("opencl")
+    } else {
+        None
+    }
+}
+
+/// Returns whether the SNARK proofs are generated on the GPU, see `gpu_backend_name`.
+pub fn gpu_proving_enabled() -> bool {
+    gpu_backend_name().is_some()
+}
@@ -1,14 +1,21 @@
 use std::collections::BTreeMap;
 
 use anyhow::{ensure, Context, Result};
+use blstrs::Scalar as Fr;
 use filecoin_hashers::Hasher;
+use generic_array::typenum::Unsigned;
 use log::info;
-use rayon::prelude::{IntoParallelRefIterator, ParallelIterator};
+use rayon::{
+    prelude::{IntoParallelRefIterator, ParallelIterator},
+    ThreadPool,
+};
 use storage_proofs_core::{
+    api_version::ApiVersion,
     compound_proof::{self, CompoundProof},
-    merkle::MerkleTreeTrait,
+    merkle::{MerkleProofTrait, MerkleTreeTrait},
     multi_proof::MultiProof,
     sector::SectorId,
+    util::default_rows_to_discard,
 };
 use storage_proofs_post::fallback::{
     self, FallbackPoSt, FallbackPoStCompound, PrivateSector, PublicSector,
@@ -16,16 +23,19 @@ use storage_proofs_post::fallback::{
 
 use crate::{
     api::{
-        as_safe_commitment, get_partitions_for_window_post, partition_vanilla_proofs,
-        single_partition_vanilla_proofs,
+        as_safe_commitment, comm_r_from_parts, generate_fallback_sector_challenges,
+        generate_single_vanilla_proof, get_num_partition_for_fallback_post,
+        get_partitions_for_window_post, partition_vanilla_proofs, single_partition_vanilla_proofs,
+        validate_post_config,
     },
     caches::{get_post_params, get_post_verifying_key},
+    constants::has_sector_shape,
     parameters::window_post_setup_params,
     types::{
-        ChallengeSeed, FallbackPoStSectorProof, PoStConfig, PrivateReplicaInfo, ProverId,
-        PublicReplicaInfo, SnarkProof,
+        ChallengeSeed, Commitment, FallbackPoStSectorProof, PartialFaultReport, PoStConfig,
+        PrivateReplicaInfo, ProverId, PublicReplicaInfo, SectorFault, SnarkProof,
     },
-    PartitionSnarkProof, PoStType,
+    with_shape, PartitionSnarkProof, PoStType, SINGLE_PARTITION_PROOF_LEN,
 };
 
 /// Generates a Window proof-of-spacetime with provided vanilla proofs.
@@ -40,6 +50,7 @@ pub fn generate_window_post_with_vanilla<Tree: 'static + MerkleTreeTrait>(
         post_config.typ == PoStType::Window,
         "invalid post config type"
     );
+    validate_post_config(post_config, vanilla_proofs.len())?;
 
     let randomness_safe: <Tree::Hasher as Hasher>::Domain =
         as_safe_commitment(randomness, "randomness")?;
@@ -103,11 +114,19 @@ pub fn generate_window_post<Tree: 'static + MerkleTreeTrait>(
     replicas: &BTreeMap<SectorId, PrivateReplicaInfo<Tree>>,
     prover_id: ProverId,
 ) -> Result<SnarkProof> {
+    #[cfg(feature = "tracing")]
+    let _span = tracing::info_span!(
+        "generate_window_post",
+        sector_size = u64::from(post_config.sector_size),
+        sector_count = replicas.len(),
+    )
+    .entered();
     info!("generate_window_post:start");
     ensure!(
         post_config.typ == PoStType::Window,
         "invalid post config type"
     );
+    validate_post_config(post_config, replicas.len())?;
 
     let randomness_safe = as_safe_commitment(randomness, "randomness")?;
     let prover_id_safe = as_safe_commitment(&prover_id, "prover_id")?;
@@ -176,7 +195,349 @@ pub fn generate_window_post<Tree: 'static + MerkleTreeTrait>(
     proof.to_vec()
 }
 
+/// Generates the Window proof-of-spacetime of a single deadline, whose sector ids are usually
+/// sparse, e.g. after sectors were terminated.
+///
+/// Sectors are assigned to partitions by their position in `replicas`, i.e. in ascending
+/// sector-id order, never by their id, so gaps in the ids do not leave holes in the partitions.
+/// Every partition proves `post_config.sector_count` sectors, except that the last one is padded
+/// with copies of its last sector, as the chain expects, see `sector_partition_location`.
+///
+/// # Arguments
+///
+/// * `post_config` - post config that contains the sector size of each sector that we are
+/// proving.
+/// * `randomness` - randomness of the deadline, used to derive the challenges.
+/// * `replicas` - the replicas of the deadline to prove, keyed by sector-id.
+/// * `prover_id` - the prover-id that sealed the sectors.
+pub fn generate_window_post_for_deadline<Tree: 'static + MerkleTreeTrait>(
+    post_config: &PoStConfig,
+    randomness: &ChallengeSeed,
+    replicas: &BTreeMap<SectorId, PrivateReplicaInfo<Tree>>,
+    prover_id: ProverId,
+) -> Result<SnarkProof> {
+    info!("generate_window_post_for_deadline:start");
+    ensure!(!replicas.is_empty(), "no sectors in the deadline");
+
+    let proof = generate_window_post::<Tree>(post_config, randomness, replicas, prover_id)?;
+    ensure!(
+        proof.len()
+            == get_num_partition_for_fallback_post(post_config, replicas.len())
+                * SINGLE_PARTITION_PROOF_LEN,
+        "invalid number of partition proofs for {} sectors",
+        replicas.len()
+    );
+
+    info!("generate_window_post_for_deadline:finish");
+
+    Ok(proof)
+}
+
+/// Like `generate_window_post`, but generates the per-sector vanilla proofs first and returns
+/// them together with the Window proof-of-spacetime built from them, so that they can be
+/// archived for a cheaper re-verification later on.
+///
+/// The returned vanilla proofs are exactly the ones fed into the SNARK, in sector-id order, and
+/// each of them can be checked with `verify_single_vanilla_proof` against the challenges of
+/// `generate_fallback_sector_challenges`.
+///
+/// # Arguments
+///
+/// * `post_config` - post config that contains the sector size of each sector that we are
+/// proving.
+/// * `randomness` - randomness used to derive the challenges.
+/// * `replicas` - the replicas to prove, keyed by sector-id.
+/// * `prover_id` - the prover-id that sealed the sectors.
+pub fn generate_window_post_with_vanilla_output<Tree: 'static + MerkleTreeTrait>(
+    post_config: &PoStConfig,
+    randomness: &ChallengeSeed,
+    replicas: &BTreeMap<SectorId, PrivateReplicaInfo<Tree>>,
+    prover_id: ProverId,
+) -> Result<(SnarkProof, Vec<FallbackPoStSectorProof<Tree>>)> {
+    info!("generate_window_post_with_vanilla_output:start");
+    ensure!(
+        post_config.typ == PoStType::Window,
+        "invalid post config type"
+    );
+    validate_post_config(post_config, replicas.len())?;
+
+    let sector_ids: Vec<SectorId> = replicas.keys().copied().collect();
+    let challenges = generate_fallback_sector_challenges::<Tree>(
+        post_config,
+        randomness,
+        &sector_ids,
+        prover_id,
+    )?;
+
+    let vanilla_proofs = replicas
+        .par_iter()
+        .map(|(sector_id, replica)| {
+            generate_single_vanilla_proof::<Tree>(
+                post_config,
+                *sector_id,
+                replica,
+                &challenges[sector_id],
+            )
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let proof = generate_window_post_with_vanilla::<Tree>(
+        post_config,
+        randomness,
+        prover_id,
+        vanilla_proofs.clone(),
+    )?;
+
+    info!("generate_window_post_with_vanilla_output:finish");
+
+    Ok((proof, vanilla_proofs))
+}
+
+/// Checks each challenge of a Window proof-of-spacetime independently instead of generating the
+/// proof, so that a partially corrupted replica reports exactly which challenged leaves are bad,
+/// rather than the whole sector being reported as faulty.
+///
+/// # Arguments
+///
+/// * `post_config` - post config that contains the sector size of each sector that we are
+/// checking.
+/// * `randomness` - randomness used to derive the challenges.
+/// * `replicas` - the replicas to check, keyed by sector-id.
+/// * `prover_id` - the prover-id that sealed the sectors.
+pub fn check_window_post_faults<Tree: 'static + MerkleTreeTrait>(
+    post_config: &PoStConfig,
+    randomness: &ChallengeSeed,
+    replicas: &BTreeMap<SectorId, PrivateReplicaInfo<Tree>>,
+    prover_id: ProverId,
+) -> Result<PartialFaultReport> {
+    info!("check_window_post_faults:start");
+    ensure!(
+        post_config.typ == PoStType::Window,
+        "invalid post config type"
+    );
+
+    let sector_ids: Vec<SectorId> = replicas.keys().copied().collect();
+    let challenges = generate_fallback_sector_challenges::<Tree>(
+        post_config,
+        randomness,
+        &sector_ids,
+        prover_id,
+    )?;
+
+    let faults = replicas
+        .par_iter()
+        .filter_map(|(sector_id, replica)| {
+            sector_fault(post_config, replica, &challenges[sector_id])
+                .map(|fault| (*sector_id, fault))
+        })
+        .collect();
+
+    info!("check_window_post_faults:finish");
+
+    Ok(PartialFaultReport { faults })
+}
+
+// Proves each challenge of a single sector on its own, mirroring the checks done by the fallback
+// vanilla prover.
+fn sector_fault<Tree: 'static + MerkleTreeTrait>(
+    post_config: &PoStConfig,
+    replica: &PrivateReplicaInfo<Tree>,
+    challenges: &[u64],
+) -> Option<SectorFault> {
+    let mapped_replica = match replica.mapped_replica(post_config.sector_size) {
+        Ok(mapped_replica) => mapped_replica,
+        Err(err) => return Some(SectorFault::Total(format!("{:?}", err))),
+    };
+
+    let comm_r_last = mapped_replica.safe_comm_r_last();
+    if mapped_replica.safe_comm_r()
+        != comm_r_from_parts::<Tree>(&mapped_replica.safe_comm_c(), &comm_r_last)
+    {
+        return Some(SectorFault::Total(
+            "comm_r does not match comm_c and comm_r_last".to_string(),
+        ));
+    }
+
+    let tree = mapped_replica.tree();
+    let rows_to_discard = default_rows_to_discard(tree.leafs(), Tree::Arity::to_usize());
+    let failed: Vec<u64> = challenges
+        .iter()
+        .copied()
+        .filter(|challenge| {
+            match tree.gen_cached_proof(*challenge as usize, Some(rows_to_discard)) {
+                Ok(proof) => !(proof.validate(*challenge as usize) && proof.root() == comm_r_last),
+                Err(_) => true,
+            }
+        })
+        .collect();
+
+    if failed.is_empty() {
+        None
+    } else {
+        Some(SectorFault::Challenges(failed))
+    }
+}
+
+/// Returns the tree-r-last leaves read for each sector when generating a Window PoSt, sorted
+/// and without duplicates, e.g. so that the corresponding replica pages can be prefetched
+/// before proving. These are exactly the challenges `generate_single_vanilla_proof` proves
+/// for the sector.
+///
+/// # Arguments
+///
+/// * `post_config` - post config that contains the sector size, challenge count and sector count.
+/// * `randomness` - randomness used to derive the challenges.
+/// * `sectors` - the id and replica commitment of every proven sector.
+/// * `prover_id` - the prover id of the sectors.
+pub fn window_post_challenge_plan<Tree: 'static + MerkleTreeTrait>(
+    post_config: &PoStConfig,
+    randomness: &ChallengeSeed,
+    sectors: &[(SectorId, Commitment)],
+    prover_id: ProverId,
+) -> Result<BTreeMap<SectorId, Vec<u64>>> {
+    info!("window_post_challenge_plan:start");
+    ensure!(
+        post_config.typ == PoStType::Window,
+        "invalid post config type"
+    );
+
+    // Reject the sectors which could not be proven in the first place.
+    for (sector_id, comm_r) in sectors {
+        as_safe_commitment::<<Tree::Hasher as Hasher>::Domain, _>(comm_r, "comm_r")
+            .with_context(|| format!("invalid comm_r for sector {:?}", sector_id))?;
+    }
+
+    let sector_ids: Vec<SectorId> = sectors.iter().map(|(sector_id, _)| *sector_id).collect();
+    let mut plan = generate_fallback_sector_challenges::<Tree>(
+        post_config,
+        randomness,
+        &sector_ids,
+        prover_id,
+    )?;
+    for challenges in plan.values_mut() {
+        challenges.sort_unstable();
+        challenges.dedup();
+    }
+
+    info!("window_post_challenge_plan:finish");
+
+    Ok(plan)
+}
+
+/// Like `generate_window_post`, but runs its rayon work, i.e. the vanilla proving, on the given
+/// rayon `pool` instead of the global one. The groth16 prover of bellperson runs on its own
+/// thread pool and does not honour `pool`.
+pub fn generate_window_post_in_pool<Tree: 'static + MerkleTreeTrait>(
+    pool: &ThreadPool,
+    post_config: &PoStConfig,
+    randomness: &ChallengeSeed,
+    replicas: &BTreeMap<SectorId, PrivateReplicaInfo<Tree>>,
+    prover_id: ProverId,
+) -> Result<SnarkProof> {
+    pool.install(|| generate_window_post::<Tree>(post_config, randomness, replicas, prover_id))
+}
+
+/// Generates a Window proof-of-spacetime incrementally: the vanilla proof of each sector is
+/// generated as soon as the sector is added, and `finalize` only has to run the SNARK over all
+/// added sectors. This allows overlapping vanilla proving with the sealing of later sectors.
+///
+/// Before `ApiVersion::V1_2_0` the challenges depend on the position of a sector within the
+/// whole set, so incremental proving requires at least that version.
+pub struct WindowPostAccumulator<Tree: 'static + MerkleTreeTrait> {
+    post_config: PoStConfig,
+    randomness: ChallengeSeed,
+    prover_id: ProverId,
+    vanilla_proofs: BTreeMap<SectorId, FallbackPoStSectorProof<Tree>>,
+}
+
+impl<Tree: 'static + MerkleTreeTrait> WindowPostAccumulator<Tree> {
+    /// Creates an empty accumulator for a window post over `randomness`.
+    pub fn new(
+        post_config: &PoStConfig,
+        randomness: &ChallengeSeed,
+        prover_id: ProverId,
+    ) -> Result<Self> {
+        ensure!(
+            post_config.typ == PoStType::Window,
+            "invalid post config type"
+        );
+        ensure!(
+            post_config.api_version >= ApiVersion::V1_2_0,
+            "incremental window post requires api version {} or later, got {}",
+            ApiVersion::V1_2_0,
+            post_config.api_version
+        );
+
+        Ok(WindowPostAccumulator {
+            post_config: post_config.clone(),
+            randomness: *randomness,
+            prover_id,
+            vanilla_proofs: BTreeMap::new(),
+        })
+    }
+
+    /// Generates the vanilla proof of the given sector and adds it to the proven set.
+    pub fn add_sector(
+        &mut self,
+        sector_id: SectorId,
+        replica: &PrivateReplicaInfo<Tree>,
+    ) -> Result<()> {
+        ensure!(
+            !self.vanilla_proofs.contains_key(&sector_id),
+            "sector {:?} was already added",
+            sector_id
+        );
+
+        let challenges = generate_fallback_sector_challenges::<Tree>(
+            &self.post_config,
+            &self.randomness,
+            &[sector_id],
+            self.prover_id,
+        )?;
+        let sector_challenges = challenges
+            .get(&sector_id)
+            .with_context(|| format!("missing challenges for sector {:?}", sector_id))?;
+        let proof = generate_single_vanilla_proof::<Tree>(
+            &self.post_config,
+            sector_id,
+            replica,
+            sector_challenges,
+        )?;
+        self.vanilla_proofs.insert(sector_id, proof);
+
+        Ok(())
+    }
+
+    /// Returns the number of sectors added so far.
+    pub fn len(&self) -> usize {
+        self.vanilla_proofs.len()
+    }
+
+    /// Returns true if no sector was added yet.
+    pub fn is_empty(&self) -> bool {
+        self.vanilla_proofs.is_empty()
+    }
+
+    /// Generates the window post over all added sectors.
+    pub fn finalize(self) -> Result<SnarkProof> {
+        ensure!(!self.vanilla_proofs.is_empty(), "no sectors were added");
+
+        generate_window_post_with_vanilla::<Tree>(
+            &self.post_config,
+            &self.randomness,
+            self.prover_id,
+            self.vanilla_proofs.into_values().collect(),
+        )
+    }
+}
+
 /// Verifies a window proof-of-spacetime.
+///
+/// Window post challenges are derived from `randomness` and the sector ids only, and `prover_id`
+/// is not constrained by the circuit, so the proof does not commit to the prover. Replicas are
+/// bound to their prover through `comm_r` (the replica id is derived from the prover id), so the
+/// caller has to make sure that `replicas` belong to `prover_id`, e.g. by looking them up from
+/// chain state.
 pub fn verify_window_post<Tree: 'static + MerkleTreeTrait>(
     post_config: &PoStConfig,
     randomness: &ChallengeSeed,
@@ -190,6 +551,7 @@ pub fn verify_window_post<Tree: 'static + MerkleTreeTrait>(
         post_config.typ == PoStType::Window,
         "invalid post config type"
     );
+    validate_post_config(post_config, replicas.len())?;
 
     let randomness_safe = as_safe_commitment(randomness, "randomness")?;
     let prover_id_safe = as_safe_commitment(&prover_id, "prover_id")?;
@@ -247,6 +609,193 @@ pub fn verify_window_post<Tree: 'static + MerkleTreeTrait>(
     Ok(true)
 }
 
+/// A window proof-of-spacetime to verify with `verify_window_posts_concurrent`, consisting of the
+/// post config, randomness, replicas, prover id and proof as passed to `verify_window_post`.
+pub type WindowPoStVerifyJob = (
+    PoStConfig,
+    ChallengeSeed,
+    BTreeMap<SectorId, PublicReplicaInfo>,
+    ProverId,
+    SnarkProof,
+);
+
+/// Verifies many window proofs-of-spacetime, e.g. of multiple deadlines or epochs, concurrently
+/// on the global rayon pool. Each result, in the order of `jobs`, is the same as that of calling
+/// `verify_window_post` for the job.
+pub fn verify_window_posts_concurrent<Tree: 'static + MerkleTreeTrait>(
+    jobs: &[WindowPoStVerifyJob],
+) -> Vec<Result<bool>> {
+    info!("verify_window_posts_concurrent:start");
+
+    let results = jobs
+        .par_iter()
+        .map(|(post_config, randomness, replicas, prover_id, proof)| {
+            verify_window_post::<Tree>(post_config, randomness, replicas, *prover_id, proof)
+        })
+        .collect();
+
+    info!("verify_window_posts_concurrent:finish");
+
+    results
+}
+
+/// Returns the groth16 public inputs, one vector per partition, that `verify_window_post`
+/// checks a window proof-of-spacetime against. Diffing these against the inputs used by the
+/// prover helps to localize a verification failure.
+///
+/// # Arguments
+///
+/// * `post_config` - post config that contains the sector size of all sectors.
+/// * `randomness` - the challenge seed the proof was generated with.
+/// * `replicas` - the public replica infos of the proven sectors.
+/// * `prover_id` - the prover id.
+pub fn window_post_expected_public_inputs<Tree: 'static + MerkleTreeTrait>(
+    post_config: &PoStConfig,
+    randomness: &ChallengeSeed,
+    replicas: &BTreeMap<SectorId, PublicReplicaInfo>,
+    prover_id: ProverId,
+) -> Result<Vec<Vec<Fr>>> {
+    info!("window_post_expected_public_inputs:start");
+
+    ensure!(
+        post_config.typ == PoStType::Window,
+        "invalid post config type"
+    );
+
+    let randomness_safe = as_safe_commitment(randomness, "randomness")?;
+    let prover_id_safe = as_safe_commitment(&prover_id, "prover_id")?;
+
+    let vanilla_params = window_post_setup_params(post_config);
+    let partitions = get_partitions_for_window_post(replicas.len(), post_config);
+
+    let setup_params = compound_proof::SetupParams {
+        vanilla_params,
+        partitions,
+        priority: false,
+    };
+    let pub_params: compound_proof::PublicParams<'_, FallbackPoSt<'_, Tree>> =
+        FallbackPoStCompound::setup(&setup_params)?;
+
+    let pub_sectors: Vec<_> = replicas
+        .iter()
+        .map(|(sector_id, replica)| {
+            let comm_r = replica.safe_comm_r().with_context(|| {
+                format!(
+                    "window_post_expected_public_inputs: safe_comm_r failed: {:?}",
+                    sector_id
+                )
+            })?;
+            Ok(PublicSector {
+                id: *sector_id,
+                comm_r,
+            })
+        })
+        .collect::<Result<_>>()?;
+
+    let pub_inputs = fallback::PublicInputs {
+        randomness: randomness_safe,
+        prover_id: prover_id_safe,
+        sectors: pub_sectors,
+        k: None,
+    };
+
+    let inputs = (0..FallbackPoStCompound::partition_count(&pub_params))
+        .map(|k| {
+            FallbackPoStCompound::<Tree>::generate_public_inputs(
+                &pub_inputs,
+                &pub_params.vanilla_params,
+                Some(k),
+            )
+        })
+        .collect::<Result<_>>()?;
+
+    info!("window_post_expected_public_inputs:finish");
+
+    Ok(inputs)
+}
+
+/// Verifies window proofs-of-spacetime over sectors of different sizes.
+///
+/// Replicas are grouped by the sector size recorded in their `PublicReplicaInfo`, and each
+/// group is verified against the proof whose config has the same sector size.
+///
+/// # Arguments
+///
+/// * `randomness` - the challenge seed shared by all proofs.
+/// * `replicas` - the public replica infos, each of which must carry its sector size.
+/// * `prover_id` - the prover id.
+/// * `proofs` - exactly one window post config and proof per sector size of `replicas`.
+///
+/// Returns `true` only if the proofs for all sector sizes are valid.
+pub fn verify_window_post_mixed(
+    randomness: &ChallengeSeed,
+    replicas: &BTreeMap<SectorId, PublicReplicaInfo>,
+    prover_id: ProverId,
+    proofs: &[(PoStConfig, Vec<u8>)],
+) -> Result<bool> {
+    info!("verify_window_post_mixed:start");
+
+    let mut groups: BTreeMap<u64, BTreeMap<SectorId, PublicReplicaInfo>> = BTreeMap::new();
+    for (sector_id, replica) in replicas.iter() {
+        let sector_size = replica.sector_size().with_context(|| {
+            format!(
+                "verify_window_post_mixed: missing sector size: {:?}",
+                sector_id
+            )
+        })?;
+        groups
+            .entry(u64::from(sector_size))
+            .or_default()
+            .insert(*sector_id, replica.clone());
+    }
+
+    let mut proofs_by_size: BTreeMap<u64, (&PoStConfig, &[u8])> = BTreeMap::new();
+    for (post_config, proof) in proofs.iter() {
+        let sector_size = u64::from(post_config.sector_size);
+        ensure!(
+            has_sector_shape(sector_size),
+            "unsupported sector size: {}",
+            sector_size
+        );
+        ensure!(
+            proofs_by_size
+                .insert(sector_size, (post_config, &proof[..]))
+                .is_none(),
+            "more than one proof for sector size {}",
+            sector_size
+        );
+    }
+    ensure!(
+        proofs_by_size.len() == groups.len(),
+        "expected {} proofs, got {}",
+        groups.len(),
+        proofs_by_size.len()
+    );
+
+    for (sector_size, group) in groups.iter() {
+        let (post_config, proof) = *proofs_by_size
+            .get(sector_size)
+            .with_context(|| format!("no proof for sector size {}", sector_size))?;
+
+        let is_valid = with_shape!(
+            *sector_size,
+            verify_window_post,
+            post_config,
+            randomness,
+            group,
+            prover_id,
+            proof,
+        )?;
+        if !is_valid {
+            return Ok(false);
+        }
+    }
+
+    info!("verify_window_post_mixed:finish");
+
+    Ok(true)
+}
+
 /// Generates a Window proof-of-spacetime with provided vanilla proofs of a single partition.
 pub fn generate_single_window_post_with_vanilla<Tree: 'static + MerkleTreeTrait>(
     post_config: &PoStConfig,
@@ -260,6 +809,7 @@ pub fn generate_single_window_post_with_vanilla<Tree: 'static + MerkleTreeTrait>
         post_config.typ == PoStType::Window,
         "invalid post config type"
     );
+    validate_post_config(post_config, vanilla_proofs.len())?;
 
     let randomness_safe: <Tree::Hasher as Hasher>::Domain =
         as_safe_commitment(randomness, "randomness")?;
@@ -3,6 +3,7 @@ use filecoin_hashers::Hasher;
 use log::info;
 use storage_proofs_core::{
     compound_proof::{self, CompoundProof},
+    error::Error,
     merkle::MerkleTreeTrait,
     multi_proof::MultiProof,
     sector::SectorId,
@@ -13,7 +14,7 @@ use storage_proofs_post::fallback::{
 };
 
 use crate::{
-    api::{as_safe_commitment, partition_vanilla_proofs},
+    api::{as_safe_commitment, partition_vanilla_proofs, validate_post_config},
     caches::{get_post_params, get_post_verifying_key},
     parameters::winning_post_setup_params,
     types::{
@@ -23,6 +24,26 @@ use crate::{
     PoStType,
 };
 
+#[cfg(feature = "testing")]
+use crate::constants::winning_post_challenge_count_override;
+
+// Under the `testing` feature, the challenge count set with `set_winning_post_challenge_count`
+// replaces the one of `post_config`.
+#[cfg(feature = "testing")]
+fn winning_post_config(post_config: &PoStConfig) -> PoStConfig {
+    let mut post_config = post_config.clone();
+    if let Some(challenge_count) = winning_post_challenge_count_override() {
+        post_config.challenge_count = challenge_count;
+    }
+
+    post_config
+}
+
+#[cfg(not(feature = "testing"))]
+fn winning_post_config(post_config: &PoStConfig) -> PoStConfig {
+    post_config.clone()
+}
+
 /// Generates a Winning proof-of-spacetime with provided vanilla proofs.
 pub fn generate_winning_post_with_vanilla<Tree: 'static + MerkleTreeTrait>(
     post_config: &PoStConfig,
@@ -31,15 +52,13 @@ pub fn generate_winning_post_with_vanilla<Tree: 'static + MerkleTreeTrait>(
     vanilla_proofs: Vec<FallbackPoStSectorProof<Tree>>,
 ) -> Result<SnarkProof> {
     info!("generate_winning_post_with_vanilla:start");
+    let post_config = &winning_post_config(post_config);
     ensure!(
         post_config.typ == PoStType::Winning,
         "invalid post config type"
     );
 
-    ensure!(
-        vanilla_proofs.len() == post_config.sector_count,
-        "invalid amount of vanilla proofs"
-    );
+    validate_post_config(post_config, vanilla_proofs.len())?;
 
     let randomness_safe: <Tree::Hasher as Hasher>::Domain =
         as_safe_commitment(randomness, "randomness")?;
@@ -102,15 +121,13 @@ pub fn generate_winning_post<Tree: 'static + MerkleTreeTrait>(
     prover_id: ProverId,
 ) -> Result<SnarkProof> {
     info!("generate_winning_post:start");
+    let post_config = &winning_post_config(post_config);
     ensure!(
         post_config.typ == PoStType::Winning,
         "invalid post config type"
     );
 
-    ensure!(
-        replicas.len() == post_config.sector_count,
-        "invalid amount of replicas"
-    );
+    validate_post_config(post_config, replicas.len())?;
 
     let randomness_safe: <Tree::Hasher as Hasher>::Domain =
         as_safe_commitment(randomness, "randomness")?;
@@ -194,7 +211,10 @@ pub fn generate_winning_post_sector_challenge<Tree: MerkleTreeTrait>(
     prover_id: Commitment,
 ) -> Result<Vec<u64>> {
     info!("generate_winning_post_sector_challenge:start");
-    ensure!(sector_set_size != 0, "empty sector set is invalid");
+    let post_config = &winning_post_config(post_config);
+    if sector_set_size == 0 {
+        return Err(Error::NoSectorsToChallenge.into());
+    }
     ensure!(
         post_config.typ == PoStType::Winning,
         "invalid post config type"
@@ -230,15 +250,13 @@ pub fn verify_winning_post<Tree: 'static + MerkleTreeTrait>(
     proof: &[u8],
 ) -> Result<bool> {
     info!("verify_winning_post:start");
+    let post_config = &winning_post_config(post_config);
 
     ensure!(
         post_config.typ == PoStType::Winning,
         "invalid post config type"
     );
-    ensure!(
-        post_config.sector_count == replicas.len(),
-        "invalid amount of replicas provided"
-    );
+    validate_post_config(post_config, replicas.len())?;
 
     let randomness_safe: <Tree::Hasher as Hasher>::Domain =
         as_safe_commitment(randomness, "randomness")?;
@@ -1,3 +1,5 @@
+#[cfg(feature = "testing")]
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::RwLock;
 use std::{collections::HashMap, sync::RwLockWriteGuard};
 
@@ -7,13 +9,14 @@ pub use storage_proofs_porep::stacked::EXP_DEGREE;
 use filecoin_hashers::{poseidon::PoseidonHasher, sha256::Sha256Hasher, Hasher};
 use lazy_static::lazy_static;
 use storage_proofs_core::{
+    api_version::ApiVersion,
     merkle::{BinaryMerkleTree, DiskTree, LCTree},
     util::NODE_SIZE,
     MAX_LEGACY_POREP_REGISTERED_PROOF_ID,
 };
 use typenum::{U0, U2, U8};
 
-use crate::types::UnpaddedBytesAmount;
+use crate::types::{SectorSize, UnpaddedBytesAmount};
 
 pub const SECTOR_SIZE_2_KIB: u64 = 1 << 11;
 pub const SECTOR_SIZE_4_KIB: u64 = 1 << 12;
@@ -29,10 +32,66 @@ pub const SECTOR_SIZE_64_GIB: u64 = 1 << 36;
 pub const WINNING_POST_CHALLENGE_COUNT: usize = 66;
 pub const WINNING_POST_SECTOR_COUNT: usize = 1;
 
+// The overridden number of winning post challenges, zero if not overridden.
+#[cfg(feature = "testing")]
+static WINNING_POST_CHALLENGE_COUNT_OVERRIDE: AtomicUsize = AtomicUsize::new(0);
+
+/// Overrides the number of challenges winning posts are generated and verified with: the
+/// winning post functions use it instead of the `challenge_count` of the `PoStConfig` they are
+/// given. Passing zero removes the override.
+///
+/// This is meant for test networks which want faster proofs only. Proofs with any other number
+/// of challenges than `WINNING_POST_CHALLENGE_COUNT` are not consensus compatible, on mainnet the
+/// constant must be used. As the override is process wide, it must be set before any winning post
+/// is proven.
+#[cfg(feature = "testing")]
+pub fn set_winning_post_challenge_count(challenge_count: usize) {
+    WINNING_POST_CHALLENGE_COUNT_OVERRIDE.store(challenge_count, Ordering::SeqCst);
+}
+
+/// The challenge count set with `set_winning_post_challenge_count`, if any.
+#[cfg(feature = "testing")]
+pub(crate) fn winning_post_challenge_count_override() -> Option<usize> {
+    match WINNING_POST_CHALLENGE_COUNT_OVERRIDE.load(Ordering::SeqCst) {
+        0 => None,
+        challenge_count => Some(challenge_count),
+    }
+}
+
+/// The number of challenges winning posts are generated and verified with. This is
+/// `WINNING_POST_CHALLENGE_COUNT`, unless overridden with `set_winning_post_challenge_count`
+/// (only available with the `testing` feature).
+pub fn winning_post_challenge_count() -> usize {
+    #[cfg(feature = "testing")]
+    {
+        if let Some(challenge_count) = winning_post_challenge_count_override() {
+            return challenge_count;
+        }
+    }
+
+    WINNING_POST_CHALLENGE_COUNT
+}
+
 pub const WINDOW_POST_CHALLENGE_COUNT: usize = 10;
 
 pub const MAX_LEGACY_REGISTERED_SEAL_PROOF_ID: u64 = MAX_LEGACY_POREP_REGISTERED_PROOF_ID;
 
+/// Returns true if the registered seal proof `id` predates porep ids (and api versions), i.e.
+/// it has to be sealed and verified with `ApiVersion::V1_0_0`.
+pub fn is_legacy_registered_proof(id: u64) -> bool {
+    id <= MAX_LEGACY_REGISTERED_SEAL_PROOF_ID
+}
+
+/// Returns the api version to use for the registered seal proof `id`. Legacy proofs map to
+/// `ApiVersion::V1_0_0`, all others to the latest api version, `ApiVersion::V1_2_0`.
+pub fn api_version_for_registered_proof(id: u64) -> ApiVersion {
+    if is_legacy_registered_proof(id) {
+        ApiVersion::V1_0_0
+    } else {
+        ApiVersion::V1_2_0
+    }
+}
+
 /// Sector sizes for which parameters have been published.
 pub const PUBLISHED_SECTOR_SIZES: [u64; 10] = [
     SECTOR_SIZE_2_KIB,
@@ -217,6 +276,33 @@ pub fn is_sector_shape_top2(sector_size: u64) -> bool {
     matches!(sector_size, SECTOR_SIZE_32_KIB | SECTOR_SIZE_64_GIB)
 }
 
+/// Returns whether the provided sector size has a sector shape, i.e. whether `with_shape!` can
+/// dispatch on it.
+pub fn has_sector_shape(sector_size: u64) -> bool {
+    is_sector_shape_base(sector_size)
+        || is_sector_shape_sub2(sector_size)
+        || is_sector_shape_sub8(sector_size)
+        || is_sector_shape_top2(sector_size)
+}
+
+/// Returns the sector sizes this build can seal and prove, i.e. the published sector sizes which
+/// have a sector shape and a configured number of PoRep partitions.
+///
+/// Note that all sector shapes are always compiled into the library; the `big-tests` feature
+/// only gates which sizes the test suite exercises.
+pub fn supported_sector_sizes() -> Vec<SectorSize> {
+    let partitions = POREP_PARTITIONS.read().expect("POREP_PARTITIONS poisoned");
+
+    PUBLISHED_SECTOR_SIZES
+        .iter()
+        .copied()
+        .filter(|sector_size| {
+            has_sector_shape(*sector_size) && partitions.contains_key(sector_size)
+        })
+        .map(SectorSize)
+        .collect()
+}
+
 /// Calls a function with the type hint of the sector shape matching the provided sector.
 /// Panics if provided with an unknown sector size.
 #[macro_export]
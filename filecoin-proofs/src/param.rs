@@ -1,14 +1,43 @@
 use std::collections::BTreeMap;
 use std::ffi::OsStr;
-use std::fs::File;
-use std::io;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
-use anyhow::{Context, Result};
+use anyhow::{ensure, Context, Result};
 use blake2b_simd::State as Blake2b;
-use storage_proofs_core::parameter_cache::{
-    parameter_cache_dir, CacheEntryMetadata, PARAMETER_METADATA_EXT,
+use log::info;
+use storage_proofs_core::{
+    api_version::ApiVersion,
+    merkle::MerkleTreeTrait,
+    parameter_cache::{
+        get_parameter_data_from_id, parameter_cache_dir, parameter_id, verifying_key_id,
+        CacheEntryMetadata, CacheableParameters, PARAMETER_METADATA_EXT,
+    },
 };
+use storage_proofs_update::{
+    circuit::EmptySectorUpdateCircuit, compound::EmptySectorUpdateCompound, constants::TreeRHasher,
+    PublicParams,
+};
+
+use crate::{
+    caches::{
+        get_empty_sector_update_verifying_key, get_post_verifying_key, get_stacked_verifying_key,
+        Bls12PreparedVerifyingKey,
+    },
+    constants::{
+        has_sector_shape, WINDOW_POST_CHALLENGE_COUNT, WINDOW_POST_SECTOR_COUNT,
+        WINNING_POST_CHALLENGE_COUNT, WINNING_POST_SECTOR_COUNT,
+    },
+    types::{PoRepConfig, PoStConfig, PoStType, SectorSize},
+    with_shape,
+};
+
+/// The size of an uncompressed BLS12-381 G1 point, in memory and in parameter files.
+const G1_AFFINE_BYTES: u64 = 96;
+/// The size of an uncompressed BLS12-381 G2 point, in memory and in parameter files.
+const G2_AFFINE_BYTES: u64 = 192;
 
 // Produces an absolute path to a file within the cache
 pub fn get_full_path_for_file_within_cache(filename: &str) -> PathBuf {
@@ -72,3 +101,298 @@ pub fn filename_to_parameter_id<'a, P: AsRef<Path> + 'a>(filename: P) -> Option<
         .and_then(OsStr::to_str)
         .map(ToString::to_string)
 }
+
+/// The Winning PoSt config the published parameters of `sector_size` are made for.
+fn winning_post_config(sector_size: u64, api_version: ApiVersion) -> PoStConfig {
+    PoStConfig {
+        sector_size: SectorSize(sector_size),
+        challenge_count: WINNING_POST_CHALLENGE_COUNT,
+        sector_count: WINNING_POST_SECTOR_COUNT,
+        typ: PoStType::Winning,
+        priority: false,
+        api_version,
+    }
+}
+
+/// The Window PoSt config the published parameters of `sector_size` are made for.
+fn window_post_config(sector_size: u64, api_version: ApiVersion) -> Result<PoStConfig> {
+    let sector_count = *WINDOW_POST_SECTOR_COUNT
+        .read()
+        .expect("WINDOW_POST_SECTOR_COUNT poisoned")
+        .get(&sector_size)
+        .with_context(|| format!("unsupported sector size {}", sector_size))?;
+
+    Ok(PoStConfig {
+        sector_size: SectorSize(sector_size),
+        challenge_count: WINDOW_POST_CHALLENGE_COUNT,
+        sector_count,
+        typ: PoStType::Window,
+        priority: false,
+        api_version,
+    })
+}
+
+/// The proof a groth16 parameter file is used for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParamProofKind {
+    Seal,
+    WinningPost,
+    WindowPost,
+    EmptySectorUpdate,
+}
+
+/// Whether a parameter file holds the groth16 proving parameters or the verifying key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParamFileKind {
+    Params,
+    VerifyingKey,
+}
+
+/// A parameter file required for proving or verifying, see `parameter_manifest`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParamEntry {
+    pub proof: ParamProofKind,
+    pub file: ParamFileKind,
+    /// The name of the file within the parameter cache directory.
+    pub filename: String,
+    /// The published digest, `None` if the file is not part of the published parameters.
+    pub digest: Option<String>,
+    /// The size of the file in the local parameter cache, `None` if it is not present there.
+    /// The published parameters do not record file sizes.
+    pub size: Option<u64>,
+}
+
+/// Lists the groth16 parameters and verifying keys needed to seal, prove PoSt over and update
+/// sectors of the given size, so that they can be fetched and checked ahead of time.
+///
+/// # Arguments
+///
+/// * `sector_size` - the size of the sectors in bytes.
+/// * `api_version` - the api version the parameters are used with.
+pub fn parameter_manifest(sector_size: u64, api_version: ApiVersion) -> Result<Vec<ParamEntry>> {
+    ensure!(
+        has_sector_shape(sector_size),
+        "unsupported sector size {}",
+        sector_size
+    );
+
+    let cache_ids = with_shape!(sector_size, param_cache_ids, sector_size, api_version)?;
+
+    let mut entries = Vec::with_capacity(2 * cache_ids.len());
+    for (proof, cache_id) in cache_ids {
+        for (file, filename) in vec![
+            (ParamFileKind::Params, parameter_id(&cache_id)),
+            (ParamFileKind::VerifyingKey, verifying_key_id(&cache_id)),
+        ] {
+            let digest = get_parameter_data_from_id(&filename).map(|data| data.digest.clone());
+            let size = fs::metadata(get_full_path_for_file_within_cache(&filename))
+                .ok()
+                .map(|metadata| metadata.len());
+
+            entries.push(ParamEntry {
+                proof,
+                file,
+                filename,
+                digest,
+                size,
+            });
+        }
+    }
+
+    Ok(entries)
+}
+
+// The parameter cache identifiers of every proof for sectors of the given size.
+fn param_cache_ids<Tree: 'static + MerkleTreeTrait<Hasher = TreeRHasher>>(
+    sector_size: u64,
+    api_version: ApiVersion,
+) -> Result<Vec<(ParamProofKind, String)>> {
+    let porep_config = PoRepConfig::new_groth16(sector_size, [0; 32], api_version);
+    let winning_post_config = winning_post_config(sector_size, api_version);
+    let window_post_config = window_post_config(sector_size, api_version)?;
+    let update_id = <EmptySectorUpdateCompound<Tree> as CacheableParameters<
+        EmptySectorUpdateCircuit<Tree>,
+        _,
+    >>::cache_identifier(&PublicParams::from_sector_size(sector_size));
+
+    let cache_ids = vec![
+        (
+            ParamProofKind::Seal,
+            porep_config.get_cache_identifier::<Tree>()?,
+        ),
+        (
+            ParamProofKind::WinningPost,
+            winning_post_config.get_cache_identifier::<Tree>()?,
+        ),
+        (
+            ParamProofKind::WindowPost,
+            window_post_config.get_cache_identifier::<Tree>()?,
+        ),
+        (ParamProofKind::EmptySectorUpdate, update_id),
+    ];
+
+    Ok(cache_ids)
+}
+
+/// Returns the memory in bytes needed to hold the groth16 proving parameters of a proof for
+/// sectors of the given size, so that it can be compared against the memory of a machine
+/// before it starts proving.
+///
+/// The size is computed from the number of points in each section of the parameter file in the
+/// local parameter cache, so the file must have been fetched already. Only the section lengths
+/// are read, not the points themselves, which are all held uncompressed in memory.
+///
+/// # Arguments
+///
+/// * `sector_size` - the size of the sectors in bytes.
+/// * `api_version` - the api version the parameters are used with.
+/// * `proof_kind` - the proof the parameters are used for.
+pub fn proving_key_memory_bytes(
+    sector_size: u64,
+    api_version: ApiVersion,
+    proof_kind: ParamProofKind,
+) -> Result<u64> {
+    ensure!(
+        has_sector_shape(sector_size),
+        "unsupported sector size {}",
+        sector_size
+    );
+
+    let cache_ids = with_shape!(sector_size, param_cache_ids, sector_size, api_version)?;
+    let (_, cache_id) = cache_ids
+        .into_iter()
+        .find(|(kind, _)| *kind == proof_kind)
+        .expect("missing cache id");
+    let path = get_full_path_for_file_within_cache(&parameter_id(&cache_id));
+
+    params_file_memory_bytes(&path)
+        .with_context(|| format!("could not read parameter file {:?}", path))
+}
+
+// Reads the lengths of the sections of the groth16 parameter file at `path`, skipping the
+// points in between, and returns the memory the points take.
+fn params_file_memory_bytes(path: &Path) -> Result<u64> {
+    let mut file = File::open(path)?;
+    let file_len = file.metadata()?.len();
+
+    // The file starts with the verifying key: alpha_g1, beta_g1, beta_g2, gamma_g2, delta_g1
+    // and delta_g2, followed by the sections ic, h, l, a, b_g1 and b_g2, each prefixed by its
+    // number of points as big endian u32.
+    let mut memory_bytes = 3 * G1_AFFINE_BYTES + 3 * G2_AFFINE_BYTES;
+    file.seek(SeekFrom::Start(memory_bytes))?;
+    for point_bytes in [
+        G1_AFFINE_BYTES,
+        G1_AFFINE_BYTES,
+        G1_AFFINE_BYTES,
+        G1_AFFINE_BYTES,
+        G1_AFFINE_BYTES,
+        G2_AFFINE_BYTES,
+    ] {
+        let mut len = [0u8; 4];
+        file.read_exact(&mut len)?;
+        let section_bytes = u64::from(u32::from_be_bytes(len)) * point_bytes;
+        file.seek(SeekFrom::Current(section_bytes as i64))?;
+        memory_bytes += section_bytes;
+    }
+    ensure!(
+        file.stream_position()? == file_len,
+        "section lengths do not match the file length {}",
+        file_len
+    );
+
+    Ok(memory_bytes)
+}
+
+/// The prepared verifying keys loaded by `preload_verify_params`.
+#[derive(Clone, Default)]
+pub struct LoadedParams {
+    verifying_keys: Vec<(ParamProofKind, Arc<Bls12PreparedVerifyingKey>)>,
+}
+
+impl LoadedParams {
+    /// The prepared verifying key of `proof_kind`, if it was loaded.
+    pub fn verifying_key(
+        &self,
+        proof_kind: ParamProofKind,
+    ) -> Option<&Arc<Bls12PreparedVerifyingKey>> {
+        self.verifying_keys
+            .iter()
+            .find(|(kind, _)| *kind == proof_kind)
+            .map(|(_, verifying_key)| verifying_key)
+    }
+
+    /// The proofs the verifying keys were loaded for.
+    pub fn proof_kinds(&self) -> Vec<ParamProofKind> {
+        self.verifying_keys.iter().map(|(kind, _)| *kind).collect()
+    }
+}
+
+/// Loads and prepares the verifying keys of the given proofs for sectors of `sector_size`, e.g.
+/// when a verifier node starts, so that missing or broken parameter files are reported right
+/// away instead of on the first verification.
+///
+/// The keys are kept in the in-memory verifying key cache, which every verify function looks
+/// the keys up in, so no verification after this needs to read them from disk again.
+///
+/// # Arguments
+///
+/// * `sector_size` - the size of the sectors in bytes.
+/// * `api_version` - the api version the parameters are used with.
+/// * `proof_kinds` - the proofs to load the verifying keys for.
+pub fn preload_verify_params(
+    sector_size: u64,
+    api_version: ApiVersion,
+    proof_kinds: &[ParamProofKind],
+) -> Result<LoadedParams> {
+    info!("preload_verify_params:start");
+    ensure!(
+        has_sector_shape(sector_size),
+        "unsupported sector size {}",
+        sector_size
+    );
+
+    let loaded = with_shape!(
+        sector_size,
+        preload_verify_params_inner,
+        sector_size,
+        api_version,
+        proof_kinds
+    )?;
+
+    info!("preload_verify_params:finish");
+
+    Ok(loaded)
+}
+
+fn preload_verify_params_inner<Tree: 'static + MerkleTreeTrait<Hasher = TreeRHasher>>(
+    sector_size: u64,
+    api_version: ApiVersion,
+    proof_kinds: &[ParamProofKind],
+) -> Result<LoadedParams> {
+    let porep_config = PoRepConfig::new_groth16(sector_size, [0; 32], api_version);
+
+    let mut loaded = LoadedParams::default();
+    for proof_kind in proof_kinds {
+        if loaded.verifying_key(*proof_kind).is_some() {
+            continue;
+        }
+
+        let verifying_key = match proof_kind {
+            ParamProofKind::Seal => get_stacked_verifying_key::<Tree>(&porep_config),
+            ParamProofKind::WinningPost => {
+                get_post_verifying_key::<Tree>(&winning_post_config(sector_size, api_version))
+            }
+            ParamProofKind::WindowPost => {
+                get_post_verifying_key::<Tree>(&window_post_config(sector_size, api_version)?)
+            }
+            ParamProofKind::EmptySectorUpdate => {
+                get_empty_sector_update_verifying_key::<Tree>(&porep_config)
+            }
+        }
+        .with_context(|| format!("failed to load the {:?} verifying key", proof_kind))?;
+
+        loaded.verifying_keys.push((*proof_kind, verifying_key));
+    }
+
+    Ok(loaded)
+}
@@ -34,6 +34,101 @@ pub fn verify_pieces(
     Ok(&comm_d_calculated == comm_d)
 }
 
+/// Validates `piece_infos` against the capacity of a sector of `sector_size`, before any sealing
+/// work is done.
+///
+/// Each piece must have a padded size which is a power of two, no smaller than the minimum piece
+/// size, and all pieces (including the alignment required between them) must fit in the sector.
+pub fn validate_piece_infos(sector_size: SectorSize, piece_infos: &[PieceInfo]) -> Result<()> {
+    for (i, piece_info) in piece_infos.iter().enumerate() {
+        let padded_size = PaddedBytesAmount::from(piece_info.size);
+        ensure!(
+            u64::from(padded_size).is_power_of_two(),
+            "Piece {} size ({:?}) must be a power of 2.",
+            i,
+            padded_size
+        );
+        ensure!(
+            u64::from(piece_info.size) >= MINIMUM_PIECE_SIZE,
+            "Piece {} size ({:?}) is smaller than the minimum piece size ({}).",
+            i,
+            piece_info.size,
+            MINIMUM_PIECE_SIZE
+        );
+    }
+
+    let piece_sizes: Vec<UnpaddedBytesAmount> = piece_infos.iter().map(|info| info.size).collect();
+    let total_size = PaddedBytesAmount::from(sum_piece_bytes_with_alignment(&piece_sizes));
+    ensure!(
+        total_size <= PaddedBytesAmount::from(sector_size),
+        "Pieces ({:?} including alignment) do not fit in a sector of {:?}.",
+        total_size,
+        sector_size
+    );
+
+    Ok(())
+}
+
+/// Validates the placement of pieces within a sector of `sector_size`. Each placement is a piece
+/// and the (unpadded) byte at which its data starts, as returned by `get_piece_start_byte`.
+///
+/// Placements must be in ascending order. Each piece must have a valid size, start at a multiple
+/// of its own size, not overlap the previous piece and end within the sector.
+pub fn validate_sector_layout(
+    sector_size: SectorSize,
+    placements: &[(PieceInfo, UnpaddedByteIndex)],
+) -> Result<()> {
+    let sector_bytes = u64::from(UnpaddedBytesAmount::from(PaddedBytesAmount::from(
+        sector_size,
+    )));
+    let mut previous_end = 0;
+
+    for (i, (piece_info, start)) in placements.iter().enumerate() {
+        let padded_size = PaddedBytesAmount::from(piece_info.size);
+        ensure!(
+            u64::from(padded_size).is_power_of_two(),
+            "Piece {} size ({:?}) must be a power of 2.",
+            i,
+            padded_size
+        );
+        ensure!(
+            u64::from(piece_info.size) >= MINIMUM_PIECE_SIZE,
+            "Piece {} size ({:?}) is smaller than the minimum piece size ({}).",
+            i,
+            piece_info.size,
+            MINIMUM_PIECE_SIZE
+        );
+
+        let size = u64::from(piece_info.size);
+        let start = u64::from(*start);
+        ensure!(
+            start % size == 0,
+            "Piece {} at byte {} is not aligned to its size ({:?}).",
+            i,
+            start,
+            piece_info.size
+        );
+        ensure!(
+            start >= previous_end,
+            "Piece {} at byte {} overlaps the previous piece, which ends at byte {}.",
+            i,
+            start,
+            previous_end
+        );
+        ensure!(
+            start + size <= sector_bytes,
+            "Piece {} at byte {} does not fit in a sector of {:?}.",
+            i,
+            start,
+            sector_size
+        );
+
+        previous_end = start + size;
+    }
+
+    Ok(())
+}
+
 lazy_static! {
     static ref COMMITMENTS: Mutex<HashMap<SectorSize, Commitment>> = Mutex::new(HashMap::new());
 }
@@ -61,6 +156,26 @@ impl Read for EmptySource {
     }
 }
 
+/// A source of piece bytes for `add_piece_from_source`, which may already know the commitment
+/// of the piece, e.g. a CAR file with a precomputed CommP.
+///
+/// Trust model: a known commitment is trusted as is. `add_piece_from_source` then only checks the
+/// length of the piece and does not hash its bytes, so a wrong commitment is not detected while
+/// adding the piece. It is detected by `seal_pre_commit_phase1`, which checks the piece infos
+/// against the comm_d of the staged data, so a wrong commitment cannot result in a valid seal,
+/// but it wastes the work done until then. Only return a commitment from a source you trust.
+pub trait PieceSource {
+    type Reader: Read;
+
+    /// Returns the reader of the unpadded piece bytes.
+    fn into_reader(self) -> Self::Reader;
+
+    /// The trusted commitment of the piece, if known.
+    fn known_comm_p(&self) -> Option<Commitment> {
+        None
+    }
+}
+
 fn empty_comm_d(sector_size: SectorSize) -> Commitment {
     let map = &mut *COMMITMENTS.lock().expect("COMMITMENTS poisoned");
 
@@ -82,12 +197,99 @@ fn empty_comm_d(sector_size: SectorSize) -> Commitment {
     })
 }
 
+/// Returns the `comm_d` of a sector which holds no pieces, e.g. a committed-capacity sector.
+pub fn comm_d_for_empty_sector(sector_size: SectorSize) -> Commitment {
+    empty_comm_d(sector_size)
+}
+
 pub fn compute_comm_d(sector_size: SectorSize, piece_infos: &[PieceInfo]) -> Result<Commitment> {
     trace!("verifying {} pieces", piece_infos.len());
     if piece_infos.is_empty() {
         return Ok(empty_comm_d(sector_size));
     }
 
+    compute_comm_d_padded(sector_size, piece_infos, 0)
+}
+
+/// Computes `comm_d` by streaming the (fr32 padded) staged sector data from `data` in
+/// node-sized chunks, e.g. when the piece infos describing the data are not available.
+/// Data shorter than the sector is padded with zeros, like `seal_pre_commit_phase1` does.
+pub fn compute_comm_d_from_data<R: Read>(
+    sector_size: SectorSize,
+    mut data: R,
+) -> Result<Commitment> {
+    let sector_bytes = u64::from(sector_size);
+
+    let source = (&mut data)
+        .take(sector_bytes)
+        .chain(EmptySource::new(sector_bytes as usize));
+    let mut commitment_reader = CommitmentReader::new(source);
+    let copied = io::copy(
+        &mut (&mut commitment_reader).take(sector_bytes),
+        &mut io::sink(),
+    )?;
+    ensure!(
+        copied == sector_bytes,
+        "failed to read {} bytes of sector data",
+        sector_bytes
+    );
+    let root = commitment_reader.finish()?;
+
+    ensure!(
+        data.read(&mut [0u8; 1])? == 0,
+        "data is larger than the sector size"
+    );
+
+    let mut comm_d = [0u8; 32];
+    comm_d.copy_from_slice(root.as_ref());
+
+    Ok(comm_d)
+}
+
+/// Returns true if two sectors hold the same source data, e.g. to find deduplication
+/// opportunities between replicas sealed by different provers or for different sectors.
+///
+/// The sealed bytes of such replicas differ, as sealing is keyed by the replica id, but
+/// `comm_d` is the root of the binary sha256 tree over the (fr32 padded) plaintext of the
+/// sector. Equal `comm_d`s therefore imply identical plaintext, including the sector size and
+/// the position of every piece, unless a sha256 collision is found. Note that all empty
+/// (committed-capacity) sectors of a size share the same `comm_d`.
+pub fn same_source_data(comm_d_a: Commitment, comm_d_b: Commitment) -> bool {
+    comm_d_a == comm_d_b
+}
+
+/// Computes `comm_d` like `compute_comm_d`, but with every padding node between and after
+/// the pieces filled with `fill` instead of zeros. The two most significant bits of each
+/// padding node are cleared, so that the nodes remain valid field elements.
+///
+/// Zero padding is consensus critical, so a non-zero `fill` is only accepted when the
+/// `testing` feature is enabled. It is meant for building test vectors only.
+pub fn compute_comm_d_with_fill(
+    sector_size: SectorSize,
+    piece_infos: &[PieceInfo],
+    fill: u8,
+) -> Result<Commitment> {
+    if fill == 0 {
+        return compute_comm_d(sector_size, piece_infos);
+    }
+    ensure!(
+        cfg!(feature = "testing"),
+        "non-zero fill requires the testing feature"
+    );
+
+    if piece_infos.is_empty() {
+        let padding = padding_piece(sector_size.into(), fill)?;
+        return Ok(padding.commitment);
+    }
+
+    compute_comm_d_padded(sector_size, piece_infos, fill)
+}
+
+fn compute_comm_d_padded(
+    sector_size: SectorSize,
+    piece_infos: &[PieceInfo],
+    fill: u8,
+) -> Result<Commitment> {
     let unpadded_sector: UnpaddedBytesAmount = sector_size.into();
 
     ensure!(
@@ -127,14 +329,14 @@ pub fn compute_comm_d(sector_size: SectorSize, piece_infos: &[PieceInfo]) -> Res
         );
 
         while stack.peek().size < piece_info.size {
-            stack.shift_reduce(zero_padding(stack.peek().size)?)?
+            stack.shift_reduce(padding_piece(stack.peek().size, fill)?)?
         }
 
         stack.shift_reduce(piece_info.clone())?;
     }
 
     while stack.len() > 1 {
-        stack.shift_reduce(zero_padding(stack.peek().size)?)?;
+        stack.shift_reduce(padding_piece(stack.peek().size, fill)?)?;
     }
 
     ensure!(stack.len() == 1, "Stack size ({}) must be 1.", stack.len());
@@ -206,8 +408,14 @@ impl Stack {
 
 /// Create a padding `PieceInfo` of size `size`.
 pub fn zero_padding(size: UnpaddedBytesAmount) -> Result<PieceInfo> {
+    padding_piece(size, 0)
+}
+
+/// Create a padding `PieceInfo` of size `size`, where every node is filled with `fill`.
+fn padding_piece(size: UnpaddedBytesAmount, fill: u8) -> Result<PieceInfo> {
     let padded_size: PaddedBytesAmount = size.into();
-    let mut commitment = [0u8; 32];
+    let mut commitment = [fill; 32];
+    commitment[31] &= 0b0011_1111;
 
     // TODO: cache common piece hashes
     let mut hashed_size = 64;
@@ -120,6 +120,20 @@ impl From<PoRepProofBytesAmount> for usize {
     }
 }
 
+/// Returns the size of `unpadded` bytes once they are Fr32 padded, as `add_piece` does: every
+/// 254 bits of data are stored in a 256 bit field element, the bits of a final incomplete
+/// element are stored as is and the result is rounded up to whole bytes. A piece of
+/// `127 * 2^k` bytes therefore takes exactly `128 * 2^k` padded bytes.
+pub fn padded_size(unpadded: UnpaddedBytesAmount) -> PaddedBytesAmount {
+    PaddedBytesAmount::from(unpadded)
+}
+
+/// The inverse of `padded_size`: returns the number of data bytes `padded` bytes hold, rounded
+/// down to whole bytes, e.g. the 2048 padded bytes of a 2KiB sector hold 2032 bytes of data.
+pub fn unpadded_size(padded: PaddedBytesAmount) -> UnpaddedBytesAmount {
+    UnpaddedBytesAmount::from(padded)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -161,4 +175,28 @@ mod tests {
         // assert_eq!(1u64 + u64::from(e), 3u64);
         // assert_eq!(1usize + usize::from(e), 3usize);
     }
+    #[test]
+    fn padded_and_unpadded_size() {
+        assert_eq!(
+            padded_size(UnpaddedBytesAmount(127)),
+            PaddedBytesAmount(128)
+        );
+        assert_eq!(
+            padded_size(UnpaddedBytesAmount(127 << 20)),
+            PaddedBytesAmount(128 << 20)
+        );
+        assert_eq!(
+            unpadded_size(PaddedBytesAmount(2048)),
+            UnpaddedBytesAmount(2032)
+        );
+
+        // Incomplete elements are not padded, and partial bytes round up when padding and
+        // down when unpadding.
+        assert_eq!(padded_size(UnpaddedBytesAmount(1)), PaddedBytesAmount(1));
+        assert_eq!(padded_size(UnpaddedBytesAmount(32)), PaddedBytesAmount(33));
+        assert_eq!(
+            unpadded_size(PaddedBytesAmount(33)),
+            UnpaddedBytesAmount(32)
+        );
+    }
 }
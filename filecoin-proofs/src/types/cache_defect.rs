@@ -0,0 +1,13 @@
+use std::path::PathBuf;
+
+/// A defect found in the cache directory (or replica) of a sector, see
+/// `validate_cache_for_commit_verbose`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CacheDefect {
+    /// The file which contains the defect.
+    pub path: PathBuf,
+    /// The byte offset of the defect within the file, if it could be located.
+    pub offset: Option<u64>,
+    /// A description of the defect.
+    pub description: String,
+}
@@ -0,0 +1,13 @@
+use std::path::PathBuf;
+
+/// Describes a single file expected in the cache directory of a sealed sector.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CacheFileSpec {
+    /// The cache key or store id the file belongs to, e.g. `p_aux` or `tree-r-last-0`.
+    pub name: String,
+    /// The path of the file, relative to the cache directory.
+    pub relative_path: PathBuf,
+    /// The size of the file in bytes, or `None` if it depends on the contents (e.g. `t_aux`,
+    /// which embeds the cache path).
+    pub size: Option<u64>,
+}
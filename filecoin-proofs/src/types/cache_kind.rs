@@ -0,0 +1,10 @@
+/// The kind of sector a cache directory belongs to, see `classify_cache`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CacheKind {
+    /// The cache of an originally sealed sector, i.e. of a sector key.
+    Sealed,
+    /// The cache of a replica updated with `encode_into`.
+    Updated,
+    /// Not a (complete) sector cache, or one of a different tree shape.
+    Unknown,
+}
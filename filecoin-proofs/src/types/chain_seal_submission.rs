@@ -0,0 +1,64 @@
+use anyhow::{ensure, Context, Result};
+use bincode::{deserialize, serialize};
+use serde::{Deserialize, Serialize};
+use storage_proofs_core::sector::SectorId;
+
+use crate::types::{Commitment, PoRepConfig, SealCommitOutput, Ticket};
+
+/// A seal proof together with the metadata needed to verify it, as submitted on chain.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainSealSubmission {
+    pub comm_r: Commitment,
+    pub comm_d: Commitment,
+    pub sector_id: SectorId,
+    pub ticket: Ticket,
+    pub seed: Ticket,
+    /// The proof bytes of the `SealCommitOutput`.
+    pub proof: Vec<u8>,
+}
+
+impl ChainSealSubmission {
+    pub fn new(
+        commit_output: SealCommitOutput,
+        comm_r: Commitment,
+        comm_d: Commitment,
+        sector_id: SectorId,
+        ticket: Ticket,
+        seed: Ticket,
+    ) -> Self {
+        ChainSealSubmission {
+            comm_r,
+            comm_d,
+            sector_id,
+            ticket,
+            seed,
+            proof: commit_output.proof,
+        }
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serialize(self).context("failed to serialize chain seal submission")
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        deserialize(bytes).context("failed to deserialize chain seal submission")
+    }
+
+    /// Checks that the submission is internally consistent for `porep_config`: both commitments
+    /// are non-zero and the proof has the expected shape (see
+    /// `SealCommitOutput::validate_shape`). This does not verify the proof.
+    pub fn validate(&self, porep_config: &PoRepConfig) -> Result<()> {
+        ensure!(
+            self.comm_r != [0; 32],
+            "Invalid all zero commitment (comm_r)"
+        );
+        ensure!(
+            self.comm_d != [0; 32],
+            "Invalid all zero commitment (comm_d)"
+        );
+        SealCommitOutput {
+            proof: self.proof.clone(),
+        }
+        .validate_shape(porep_config)
+    }
+}
@@ -0,0 +1,10 @@
+use std::path::PathBuf;
+
+/// What `clear_cache_reporting` removed from a cache directory.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ClearCacheReport {
+    /// The total size in bytes of all deleted files.
+    pub freed_bytes: u64,
+    /// The deleted files, ordered by path.
+    pub deleted_files: Vec<PathBuf>,
+}
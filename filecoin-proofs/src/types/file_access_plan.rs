@@ -0,0 +1,32 @@
+use std::path::PathBuf;
+
+/// A sector update operation, together with the paths it is run on, see
+/// `sector_update_file_access_plan`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum UpdateOp {
+    /// The paths passed to `decode_from`.
+    Decode {
+        out_data_path: PathBuf,
+        replica_path: PathBuf,
+        sector_key_path: PathBuf,
+        sector_key_cache_path: PathBuf,
+    },
+    /// The paths passed to `remove_encoded_data`.
+    RemoveEncodedData {
+        sector_key_path: PathBuf,
+        sector_key_cache_path: PathBuf,
+        replica_path: PathBuf,
+        replica_cache_path: PathBuf,
+        data_path: PathBuf,
+    },
+}
+
+/// The files a sector update operation accesses.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FileAccessPlan {
+    /// The files which are only read.
+    pub reads: Vec<PathBuf>,
+    /// The files which are written. Files which already exist, e.g. the memory mapped outputs,
+    /// are opened for reading as well.
+    pub writes: Vec<PathBuf>,
+}
@@ -2,6 +2,10 @@ pub use merkletree::store::StoreConfig;
 pub use storage_proofs_core::merkle::{MerkleProof, MerkleTreeTrait};
 pub use storage_proofs_porep::stacked::{Labels, PersistentAux, TemporaryAux};
 
+use std::ops::Range;
+use std::path::PathBuf;
+
+use anyhow::Result;
 use filecoin_hashers::Hasher;
 use serde::{Deserialize, Serialize};
 use storage_proofs_core::{merkle::BinaryMerkleTree, sector::SectorId};
@@ -11,7 +15,16 @@ use storage_proofs_post::fallback;
 use crate::constants::DefaultPieceHasher;
 
 mod bytes_amount;
+mod cache_defect;
+mod cache_file_spec;
+mod cache_kind;
+mod chain_seal_submission;
+mod clear_cache_report;
+mod file_access_plan;
 mod hselect;
+mod partial_fault_report;
+mod partition_inputs;
+mod piece_commitment_cache;
 mod piece_info;
 mod porep_config;
 mod porep_proof_partitions;
@@ -19,13 +32,26 @@ mod post_config;
 mod post_proof_partitions;
 mod private_replica_info;
 mod public_replica_info;
+mod seal_job;
+mod seal_proof;
+mod seal_verify_bundle;
 mod sector_class;
 mod sector_size;
 mod sector_update_config;
 mod update_proof_partitions;
+mod verify_cost;
 
 pub use bytes_amount::*;
+pub use cache_defect::*;
+pub use cache_file_spec::*;
+pub use cache_kind::*;
+pub use chain_seal_submission::*;
+pub use clear_cache_report::*;
+pub use file_access_plan::*;
 pub use hselect::*;
+pub use partial_fault_report::*;
+pub use partition_inputs::*;
+pub use piece_commitment_cache::*;
 pub use piece_info::*;
 pub use porep_config::*;
 pub use porep_proof_partitions::*;
@@ -33,10 +59,14 @@ pub use post_config::*;
 pub use post_proof_partitions::*;
 pub use private_replica_info::*;
 pub use public_replica_info::*;
+pub use seal_job::*;
+pub use seal_proof::*;
+pub use seal_verify_bundle::*;
 pub use sector_class::*;
 pub use sector_size::*;
 pub use sector_update_config::*;
 pub use update_proof_partitions::*;
+pub use verify_cost::*;
 
 pub type Commitment = [u8; 32];
 pub type ChallengeSeed = [u8; 32];
@@ -77,6 +107,58 @@ pub struct SealCommitOutput {
     pub proof: Vec<u8>,
 }
 
+impl SealCommitOutput {
+    /// Checks that the proof consists of one valid groth16 proof per partition of
+    /// `porep_config`, so that truncated or garbage proofs can be rejected before aggregation.
+    /// This does not verify the proof.
+    pub fn validate_shape(&self, porep_config: &PoRepConfig) -> Result<()> {
+        SealProof::from_bytes(porep_config, &self.proof).map(|_| ())
+    }
+}
+
+/// The groth16 proofs for a range of partitions of a seal commit, as generated by
+/// `seal_commit_phase2_range`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SealCommitPartial {
+    pub partitions: Range<usize>,
+    pub proof: Vec<u8>,
+}
+
+/// A merkle path in tree-d from the root of a range of the sector data to comm_d, as generated by
+/// `generate_unsealed_range_proof`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnsealedRangeProof {
+    /// The sibling nodes along the path, starting at the root of the range.
+    pub siblings: Vec<Commitment>,
+}
+
+/// Options for the Fr32 padding done by `add_piece_with_options`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fr32Options {
+    /// The size in bytes of the chunks the piece is read and written in.
+    pub chunk_bytes: usize,
+}
+
+impl Default for Fr32Options {
+    fn default() -> Self {
+        Fr32Options {
+            chunk_bytes: 8 * 1024,
+        }
+    }
+}
+
+/// The per-sector inputs of `seal_pre_commit_phase1`, used for batch sealing.
+#[derive(Debug, Clone)]
+pub struct PreCommitJob {
+    pub cache_path: PathBuf,
+    pub in_path: PathBuf,
+    pub out_path: PathBuf,
+    pub prover_id: ProverId,
+    pub sector_id: SectorId,
+    pub ticket: Ticket,
+    pub piece_infos: Vec<PieceInfo>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct SealPreCommitPhase1Output<Tree: MerkleTreeTrait> {
     #[serde(bound(
@@ -96,6 +178,8 @@ pub type SnarkProof = Vec<u8>;
 pub type AggregateSnarkProof = Vec<u8>;
 pub type VanillaProof<Tree> = fallback::Proof<<Tree as MerkleTreeTrait>::Proof>;
 pub type PartitionProof<Tree> = storage_proofs_update::vanilla::PartitionProof<Tree>;
+/// The merkle inclusion proof of a single tree-r-last leaf, see `prove_leaf_inclusion`.
+pub type LeafInclusionProof<Tree> = <Tree as MerkleTreeTrait>::Proof;
 
 #[derive(Debug, Clone, PartialEq)]
 #[repr(transparent)]
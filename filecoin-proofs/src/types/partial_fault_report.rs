@@ -0,0 +1,31 @@
+use std::collections::BTreeMap;
+
+use storage_proofs_core::sector::SectorId;
+
+/// Why a sector could not be (fully) proven.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SectorFault {
+    /// The replica could not be opened, or its commitments are inconsistent, so none of its
+    /// challenges can be proven.
+    Total(String),
+    /// Only these challenged leaves failed to prove, all other challenges are fine.
+    Challenges(Vec<u64>),
+}
+
+/// The faults found by checking every challenge of a proof-of-spacetime independently, see
+/// `check_window_post_faults`. Sectors without any fault are not part of the report.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PartialFaultReport {
+    pub faults: BTreeMap<SectorId, SectorFault>,
+}
+
+impl PartialFaultReport {
+    pub fn is_empty(&self) -> bool {
+        self.faults.is_empty()
+    }
+
+    /// The ids of all sectors with at least one fault.
+    pub fn faulty_sectors(&self) -> Vec<SectorId> {
+        self.faults.keys().copied().collect()
+    }
+}
@@ -0,0 +1,89 @@
+use anyhow::{ensure, Result};
+use blstrs::Scalar as Fr;
+
+use crate::constants::{DRG_DEGREE, EXP_DEGREE};
+
+/// The public inputs of a seal proof contributed by a single challenge.
+///
+/// All values are node indices encoded as field elements. The inclusion proofs are private, so
+/// each of them only contributes the index of the node it opens.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChallengeInputs {
+    /// Index of the challenged data node in tree-d (comm_d).
+    pub comm_d_leaf: Fr,
+    /// Indices of the DRG parents of the challenged node in tree-c (comm_c).
+    pub drg_parents: Vec<Fr>,
+    /// Indices of the expander parents of the challenged node in tree-c (comm_c).
+    pub exp_parents: Vec<Fr>,
+    /// The challenged node itself.
+    pub challenge: Fr,
+    /// Index of the challenged replica node in tree-r-last (comm_r_last).
+    pub comm_r_last_leaf: Fr,
+    /// Index of the column of the challenged node in tree-c (comm_c).
+    pub comm_c_leaf: Fr,
+}
+
+/// The public inputs of one seal partition, as returned by `get_seal_inputs`, split into the
+/// values they encode.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PartitionInputs {
+    pub replica_id: Fr,
+    pub comm_d: Fr,
+    pub comm_r: Fr,
+    /// The inputs of each challenge of the partition, in order.
+    pub challenges: Vec<ChallengeInputs>,
+}
+
+impl PartitionInputs {
+    /// The number of public inputs contributed by each challenge.
+    pub const CHALLENGE_INPUTS_LEN: usize = 4 + DRG_DEGREE + EXP_DEGREE;
+
+    /// Splits the public inputs of a single partition into their labeled values.
+    pub fn from_inputs(inputs: &[Fr]) -> Result<Self> {
+        ensure!(inputs.len() >= 3, "missing partition commitments");
+        let (commitments, challenge_inputs) = inputs.split_at(3);
+        ensure!(
+            challenge_inputs.len() % Self::CHALLENGE_INPUTS_LEN == 0,
+            "invalid number of partition inputs {}",
+            inputs.len()
+        );
+
+        let challenges = challenge_inputs
+            .chunks(Self::CHALLENGE_INPUTS_LEN)
+            .map(|values| {
+                let (drg_parents, rest) = values[1..].split_at(DRG_DEGREE);
+                let (exp_parents, rest) = rest.split_at(EXP_DEGREE);
+                ChallengeInputs {
+                    comm_d_leaf: values[0],
+                    drg_parents: drg_parents.to_vec(),
+                    exp_parents: exp_parents.to_vec(),
+                    challenge: rest[0],
+                    comm_r_last_leaf: rest[1],
+                    comm_c_leaf: rest[2],
+                }
+            })
+            .collect();
+
+        Ok(PartitionInputs {
+            replica_id: commitments[0],
+            comm_d: commitments[1],
+            comm_r: commitments[2],
+            challenges,
+        })
+    }
+
+    /// Returns the public inputs in the order expected by the verifier, the inverse of
+    /// `from_inputs`.
+    pub fn to_inputs(&self) -> Vec<Fr> {
+        let mut inputs = vec![self.replica_id, self.comm_d, self.comm_r];
+        for challenge in &self.challenges {
+            inputs.push(challenge.comm_d_leaf);
+            inputs.extend_from_slice(&challenge.drg_parents);
+            inputs.extend_from_slice(&challenge.exp_parents);
+            inputs.push(challenge.challenge);
+            inputs.push(challenge.comm_r_last_leaf);
+            inputs.push(challenge.comm_c_leaf);
+        }
+        inputs
+    }
+}
@@ -0,0 +1,52 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::types::{PieceInfo, UnpaddedBytesAmount};
+
+/// A cache of piece commitments keyed by a (blake2b) hash of the raw piece bytes, used by
+/// `generate_piece_commitment_cached` to avoid recomputing the commitment of identical pieces.
+#[derive(Debug, Default)]
+pub struct PieceCommitmentCache {
+    entries: Mutex<HashMap<[u8; 32], PieceInfo>>,
+}
+
+impl PieceCommitmentCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of cached piece commitments.
+    pub fn len(&self) -> usize {
+        self.entries.lock().expect("entries poisoned").len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Removes all cached piece commitments.
+    pub fn clear(&self) {
+        self.entries.lock().expect("entries poisoned").clear();
+    }
+
+    /// Returns the cached piece info for the content hash, if it was computed for `size`.
+    pub(crate) fn get(
+        &self,
+        content_hash: &[u8; 32],
+        size: UnpaddedBytesAmount,
+    ) -> Option<PieceInfo> {
+        self.entries
+            .lock()
+            .expect("entries poisoned")
+            .get(content_hash)
+            .filter(|piece_info| piece_info.size == size)
+            .cloned()
+    }
+
+    pub(crate) fn insert(&self, content_hash: [u8; 32], piece_info: PieceInfo) {
+        self.entries
+            .lock()
+            .expect("entries poisoned")
+            .insert(content_hash, piece_info);
+    }
+}
@@ -1,6 +1,8 @@
+use std::convert::TryFrom;
 use std::path::PathBuf;
 
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
 use storage_proofs_core::{
     api_version::{ApiFeature, ApiVersion},
     merkle::MerkleTreeTrait,
@@ -11,6 +13,8 @@ use storage_proofs_core::{
 };
 use storage_proofs_porep::stacked::{StackedCircuit, StackedCompound};
 
+#[cfg(feature = "testing")]
+use crate::SECTOR_SIZE_2_KIB;
 use crate::{
     constants::DefaultPieceHasher,
     parameters::public_params,
@@ -27,6 +31,71 @@ pub struct PoRepConfig {
     pub api_features: Vec<ApiFeature>,
 }
 
+/// The representation a `PoRepConfig` is serialized in, e.g. within a `SealJob`, which only
+/// consists of primitive types so that it does not depend on the encoding of the config types.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct SerializedPoRepConfig {
+    sector_size: u64,
+    partitions: u8,
+    porep_id: [u8; 32],
+    /// The semver string of the api version, e.g. `1.1.0`.
+    api_version: String,
+}
+
+impl From<&PoRepConfig> for SerializedPoRepConfig {
+    fn from(config: &PoRepConfig) -> Self {
+        // There are no api features yet, adding one requires extending the representation.
+        for feature in &config.api_features {
+            match *feature {}
+        }
+
+        SerializedPoRepConfig {
+            sector_size: config.sector_size.0,
+            partitions: config.partitions.0,
+            porep_id: config.porep_id,
+            api_version: config.api_version.to_string(),
+        }
+    }
+}
+
+impl TryFrom<SerializedPoRepConfig> for PoRepConfig {
+    type Error = anyhow::Error;
+
+    fn try_from(config: SerializedPoRepConfig) -> Result<Self> {
+        Ok(PoRepConfig {
+            sector_size: SectorSize(config.sector_size),
+            partitions: PoRepProofPartitions(config.partitions),
+            porep_id: config.porep_id,
+            api_version: config.api_version.parse()?,
+            api_features: vec![],
+        })
+    }
+}
+
+/// Serializes a `PoRepConfig` field as a `SerializedPoRepConfig`, use with
+/// `#[serde(with = "crate::types::porep_config::serialized")]`.
+pub(crate) mod serialized {
+    use std::convert::TryFrom;
+
+    use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::{PoRepConfig, SerializedPoRepConfig};
+
+    pub fn serialize<S: Serializer>(
+        config: &PoRepConfig,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        SerializedPoRepConfig::from(config).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<PoRepConfig, D::Error> {
+        let config = SerializedPoRepConfig::deserialize(deserializer)?;
+        PoRepConfig::try_from(config).map_err(D::Error::custom)
+    }
+}
+
 impl From<PoRepConfig> for PaddedBytesAmount {
     fn from(x: PoRepConfig) -> Self {
         let PoRepConfig { sector_size, .. } = x;
@@ -73,6 +142,15 @@ impl PoRepConfig {
         }
     }
 
+    /// Constructs the PoRepConfig of the 2KiB sector size, the same as `new_groth16` with
+    /// `SECTOR_SIZE_2_KIB`. 2KiB sectors are sealed over 2 layers with 2 challenges in a single
+    /// partition, which makes them fast to seal and prove in end-to-end tests. Their proofs are
+    /// regular proofs, verified with the published 2KiB parameters as usual.
+    #[cfg(feature = "testing")]
+    pub fn new_groth16_testing(porep_id: [u8; 32], api_version: ApiVersion) -> Self {
+        Self::new_groth16(SECTOR_SIZE_2_KIB, porep_id, api_version)
+    }
+
     #[inline]
     pub fn with_feature(mut self, feat: ApiFeature) -> Self {
         self.enable_feature(feat);
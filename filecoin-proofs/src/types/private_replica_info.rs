@@ -6,7 +6,7 @@ use std::path::{Path, PathBuf};
 
 use anyhow::{ensure, Context, Result};
 use bincode::deserialize;
-use filecoin_hashers::Hasher;
+use filecoin_hashers::{Domain, Hasher};
 use generic_array::typenum::Unsigned;
 use log::trace;
 use merkletree::store::StoreConfig;
@@ -20,7 +20,7 @@ use storage_proofs_core::{
 };
 
 use crate::{
-    api::{as_safe_commitment, get_base_tree_leafs, get_base_tree_size},
+    api::{as_safe_commitment, comm_r_from_parts, get_base_tree_leafs, get_base_tree_size},
     types::{Commitment, PersistentAux, SectorSize},
 };
 
@@ -85,17 +85,44 @@ impl<Tree: MerkleTreeTrait> PartialOrd for PrivateReplicaInfo<Tree> {
 }
 
 impl<Tree: 'static + MerkleTreeTrait> PrivateReplicaInfo<Tree> {
+    /// Creates the replica info from the replica, its commitment and its cache directory.
+    ///
+    /// Updated (snap-deal) replicas need no special handling: `encode_into` writes tree-r-last
+    /// with the same layout as sealing, and a p_aux holding the new comm_r_last, into the new
+    /// cache directory. Pass comm_r_new, the updated replica and that cache directory.
     pub fn new(replica: PathBuf, comm_r: Commitment, cache_dir: PathBuf) -> Result<Self> {
         ensure!(comm_r != [0; 32], "Invalid all zero commitment (comm_r)");
 
-        let aux = {
-            let f_aux_path = cache_dir.join(CacheKey::PAux.to_string());
-            let aux_bytes = fs::read(&f_aux_path)
-                .with_context(|| format!("could not read from path={:?}", f_aux_path))?;
+        let aux = Self::read_aux(&cache_dir)?;
 
-            deserialize(&aux_bytes)
-        }?;
+        Self::with_aux(replica, comm_r, aux, cache_dir)
+    }
+
+    /// Like `new`, but derives comm_r from the comm_c and comm_r_last stored in the p_aux of
+    /// `cache_dir`, so that the commitment cannot get out of sync with the cache.
+    pub fn from_cache(replica: PathBuf, cache_dir: PathBuf) -> Result<Self> {
+        let aux = Self::read_aux(&cache_dir)?;
+
+        let mut comm_r = [0; 32];
+        comm_r_from_parts::<Tree>(&aux.comm_c, &aux.comm_r_last).write_bytes(&mut comm_r)?;
+
+        Self::with_aux(replica, comm_r, aux, cache_dir)
+    }
+
+    fn read_aux(cache_dir: &Path) -> Result<PersistentAux<<Tree::Hasher as Hasher>::Domain>> {
+        let f_aux_path = cache_dir.join(CacheKey::PAux.to_string());
+        let aux_bytes = fs::read(&f_aux_path)
+            .with_context(|| format!("could not read from path={:?}", f_aux_path))?;
+
+        Ok(deserialize(&aux_bytes)?)
+    }
 
+    fn with_aux(
+        replica: PathBuf,
+        comm_r: Commitment,
+        aux: PersistentAux<<Tree::Hasher as Hasher>::Domain>,
+        cache_dir: PathBuf,
+    ) -> Result<Self> {
         ensure!(replica.exists(), "Sealed replica does not exist");
 
         Ok(PrivateReplicaInfo {
@@ -127,7 +154,34 @@ impl<Tree: 'static + MerkleTreeTrait> PrivateReplicaInfo<Tree> {
         self.aux.comm_r_last
     }
 
+    /// Opens the replica (read-only) together with its merkle tree, so that the result can be held
+    /// across multiple challenge proofs instead of reopening the replica for each of them.
+    ///
+    /// The sector size is derived from the length of the replica file.
+    pub fn with_mmap(&self) -> Result<MappedReplica<Tree>> {
+        let replica_len = fs::metadata(&self.replica)
+            .with_context(|| format!("could not read metadata of replica={:?}", self.replica))?
+            .len();
+
+        self.mapped_replica(SectorSize(replica_len))
+    }
+
+    pub(crate) fn mapped_replica(&self, sector_size: SectorSize) -> Result<MappedReplica<Tree>> {
+        Ok(MappedReplica {
+            sector_size,
+            tree: self.merkle_tree(sector_size)?,
+            comm_r: self.safe_comm_r()?,
+            comm_c: self.safe_comm_c(),
+            comm_r_last: self.safe_comm_r_last(),
+        })
+    }
+
     /// Generate the merkle tree of this particular replica.
+    ///
+    /// Neither the replica nor the cached tree-r-last are memory-mapped: the tree reads the
+    /// replica leaves through an `ExternalReader`, i.e. with positional (`pread`) reads of the
+    /// challenged nodes only, and the cached tree rows through regular file reads. This makes
+    /// PoSt generation suitable for network-mounted replicas as is.
     pub fn merkle_tree(
         &self,
         sector_size: SectorSize,
@@ -170,3 +224,48 @@ impl<Tree: 'static + MerkleTreeTrait> PrivateReplicaInfo<Tree> {
         create_tree::<Tree>(base_tree_size, &configs, Some(&replica_config))
     }
 }
+
+/// An opened replica and its merkle tree, as returned by [`PrivateReplicaInfo::with_mmap`].
+pub struct MappedReplica<Tree: MerkleTreeTrait> {
+    sector_size: SectorSize,
+    tree: MerkleTreeWrapper<
+        Tree::Hasher,
+        Tree::Store,
+        Tree::Arity,
+        Tree::SubTreeArity,
+        Tree::TopTreeArity,
+    >,
+    comm_r: <Tree::Hasher as Hasher>::Domain,
+    comm_c: <Tree::Hasher as Hasher>::Domain,
+    comm_r_last: <Tree::Hasher as Hasher>::Domain,
+}
+
+impl<Tree: MerkleTreeTrait> MappedReplica<Tree> {
+    pub fn sector_size(&self) -> SectorSize {
+        self.sector_size
+    }
+
+    pub fn tree(
+        &self,
+    ) -> &MerkleTreeWrapper<
+        Tree::Hasher,
+        Tree::Store,
+        Tree::Arity,
+        Tree::SubTreeArity,
+        Tree::TopTreeArity,
+    > {
+        &self.tree
+    }
+
+    pub fn safe_comm_r(&self) -> <Tree::Hasher as Hasher>::Domain {
+        self.comm_r
+    }
+
+    pub fn safe_comm_c(&self) -> <Tree::Hasher as Hasher>::Domain {
+        self.comm_c
+    }
+
+    pub fn safe_comm_r_last(&self) -> <Tree::Hasher as Hasher>::Domain {
+        self.comm_r_last
+    }
+}
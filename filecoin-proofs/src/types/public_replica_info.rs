@@ -4,7 +4,10 @@ use std::hash::Hash;
 use anyhow::{ensure, Result};
 use filecoin_hashers::Domain;
 
-use crate::{api::as_safe_commitment, types::Commitment};
+use crate::{
+    api::as_safe_commitment,
+    types::{Commitment, SectorSize},
+};
 
 /// The minimal information required about a replica, in order to be able to verify
 /// a PoSt over it.
@@ -12,11 +15,22 @@ use crate::{api::as_safe_commitment, types::Commitment};
 pub struct PublicReplicaInfo {
     /// The replica commitment.
     comm_r: Commitment,
+    /// The size of the sector, if known. Required when verifying PoSts over sectors of
+    /// different sizes.
+    sector_size: Option<SectorSize>,
 }
 
 impl Ord for PublicReplicaInfo {
+    // Consistent with the derived `Eq`, replicas with the same comm_r are ordered by sector size.
     fn cmp(&self, other: &Self) -> Ordering {
-        self.comm_r.as_ref().cmp(other.comm_r.as_ref())
+        self.comm_r
+            .as_ref()
+            .cmp(other.comm_r.as_ref())
+            .then_with(|| {
+                self.sector_size
+                    .map(u64::from)
+                    .cmp(&other.sector_size.map(u64::from))
+            })
     }
 }
 
@@ -29,7 +43,22 @@ impl PartialOrd for PublicReplicaInfo {
 impl PublicReplicaInfo {
     pub fn new(comm_r: Commitment) -> Result<Self> {
         ensure!(comm_r != [0; 32], "Invalid all zero commitment (comm_r)");
-        Ok(PublicReplicaInfo { comm_r })
+        Ok(PublicReplicaInfo {
+            comm_r,
+            sector_size: None,
+        })
+    }
+
+    pub fn new_with_sector_size(comm_r: Commitment, sector_size: SectorSize) -> Result<Self> {
+        ensure!(comm_r != [0; 32], "Invalid all zero commitment (comm_r)");
+        Ok(PublicReplicaInfo {
+            comm_r,
+            sector_size: Some(sector_size),
+        })
+    }
+
+    pub fn sector_size(&self) -> Option<SectorSize> {
+        self.sector_size
     }
 
     pub fn safe_comm_r<T: Domain>(&self) -> Result<T> {
@@ -0,0 +1,125 @@
+use std::convert::TryFrom;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use bincode::{deserialize, serialize};
+use serde::{Deserialize, Serialize};
+use storage_proofs_core::sector::SectorId;
+
+use crate::types::{
+    porep_config::SerializedPoRepConfig, PieceInfo, PoRepConfig, ProverId, SealCommitOutput,
+    SealPreCommitOutput, Ticket,
+};
+
+/// The seal phase a `SealJob` runs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SealJobPhase {
+    PreCommit1,
+    PreCommit2,
+    Commit1,
+    Commit2,
+}
+
+/// A single seal phase of a sector together with everything needed to run it, so that it can be
+/// sent to a worker, see `execute_seal_job`.
+///
+/// The outputs of `PreCommit1`, `PreCommit2` and `Commit1` are persisted in `cache_path`, from
+/// where the job of the next phase reads them, so the jobs of a sector only need to share the
+/// storage of its paths. These files are removed once `Commit2` has finished.
+#[derive(Clone, Debug)]
+pub struct SealJob {
+    pub porep_config: PoRepConfig,
+    pub phase: SealJobPhase,
+    pub cache_path: PathBuf,
+    /// The path to the staged (unsealed) sector data.
+    pub staged_path: PathBuf,
+    pub sealed_path: PathBuf,
+    pub prover_id: ProverId,
+    pub sector_id: SectorId,
+    pub ticket: Ticket,
+    /// The seed used to derive the porep challenges, required by `Commit1` only.
+    pub seed: Option<Ticket>,
+    pub piece_infos: Vec<PieceInfo>,
+}
+
+impl SealJob {
+    /// Returns the job running `phase` for the same sector.
+    pub fn with_phase(&self, phase: SealJobPhase) -> Self {
+        SealJob {
+            phase,
+            ..self.clone()
+        }
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        serialize(&SerializedSealJob::from(self)).context("failed to serialize seal job")
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let job: SerializedSealJob =
+            deserialize(bytes).context("failed to deserialize seal job")?;
+        SealJob::try_from(job)
+    }
+}
+
+// The representation a `SealJob` is serialized in by `SealJob::to_bytes`.
+#[derive(Serialize, Deserialize)]
+struct SerializedSealJob {
+    porep_config: SerializedPoRepConfig,
+    phase: SealJobPhase,
+    cache_path: PathBuf,
+    staged_path: PathBuf,
+    sealed_path: PathBuf,
+    prover_id: ProverId,
+    sector_id: u64,
+    ticket: Ticket,
+    seed: Option<Ticket>,
+    piece_infos: Vec<PieceInfo>,
+}
+
+impl From<&SealJob> for SerializedSealJob {
+    fn from(job: &SealJob) -> Self {
+        SerializedSealJob {
+            porep_config: SerializedPoRepConfig::from(&job.porep_config),
+            phase: job.phase,
+            cache_path: job.cache_path.clone(),
+            staged_path: job.staged_path.clone(),
+            sealed_path: job.sealed_path.clone(),
+            prover_id: job.prover_id,
+            sector_id: job.sector_id.into(),
+            ticket: job.ticket,
+            seed: job.seed,
+            piece_infos: job.piece_infos.clone(),
+        }
+    }
+}
+
+impl TryFrom<SerializedSealJob> for SealJob {
+    type Error = anyhow::Error;
+
+    fn try_from(job: SerializedSealJob) -> Result<Self> {
+        Ok(SealJob {
+            porep_config: PoRepConfig::try_from(job.porep_config)?,
+            phase: job.phase,
+            cache_path: job.cache_path,
+            staged_path: job.staged_path,
+            sealed_path: job.sealed_path,
+            prover_id: job.prover_id,
+            sector_id: job.sector_id.into(),
+            ticket: job.ticket,
+            seed: job.seed,
+            piece_infos: job.piece_infos,
+        })
+    }
+}
+
+/// The result of `execute_seal_job`.
+#[derive(Debug)]
+pub enum SealJobOutput {
+    /// The phase output was persisted in the cache directory.
+    PreCommit1,
+    PreCommit2(SealPreCommitOutput),
+    /// The phase output was persisted in the cache directory.
+    Commit1,
+    Commit2(SealCommitOutput),
+}
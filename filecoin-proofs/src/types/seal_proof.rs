@@ -0,0 +1,60 @@
+use std::io::Cursor;
+
+use anyhow::{ensure, Context, Result};
+use bellperson::groth16;
+use blstrs::Bls12;
+
+use crate::{constants::SINGLE_PARTITION_PROOF_LEN, types::PoRepConfig};
+
+/// A seal (PoRep) proof with a stable on-wire encoding.
+///
+/// The encoding is the concatenation of one groth16 proof per partition, in partition order.
+/// Each groth16 proof is exactly `SINGLE_PARTITION_PROOF_LEN` (192) bytes: the compressed `a`
+/// (G1, 48 bytes), `b` (G2, 96 bytes) and `c` (G1, 48 bytes) points. The total length is therefore
+/// fixed per config, `porep_config.partitions * SINGLE_PARTITION_PROOF_LEN`, and is the same as
+/// `SealCommitOutput::proof`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SealProof {
+    partition_proofs: Vec<groth16::Proof<Bls12>>,
+}
+
+impl SealProof {
+    /// Decodes a seal proof, ensuring it has exactly one groth16 proof per partition of the config.
+    pub fn from_bytes(porep_config: &PoRepConfig, proof_bytes: &[u8]) -> Result<Self> {
+        let partitions = usize::from(porep_config.partitions);
+        ensure!(
+            proof_bytes.len() == partitions * SINGLE_PARTITION_PROOF_LEN,
+            "Invalid seal proof length {}, expected {} ({} partitions)",
+            proof_bytes.len(),
+            partitions * SINGLE_PARTITION_PROOF_LEN,
+            partitions,
+        );
+
+        let partition_proofs = proof_bytes
+            .chunks(SINGLE_PARTITION_PROOF_LEN)
+            .enumerate()
+            .map(|(k, bytes)| {
+                groth16::Proof::read(Cursor::new(bytes))
+                    .with_context(|| format!("Invalid groth16 proof for partition {}", k))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(SealProof { partition_proofs })
+    }
+
+    /// Encodes the seal proof, see the type level documentation for the format.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut proof_bytes =
+            Vec::with_capacity(self.partition_proofs.len() * SINGLE_PARTITION_PROOF_LEN);
+        for proof in &self.partition_proofs {
+            proof.write(&mut proof_bytes)?;
+        }
+
+        Ok(proof_bytes)
+    }
+
+    /// The groth16 proofs, one per partition.
+    pub fn partition_proofs(&self) -> &[groth16::Proof<Bls12>] {
+        &self.partition_proofs
+    }
+}
@@ -0,0 +1,88 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use storage_proofs_core::{merkle::MerkleTreeTrait, sector::SectorId};
+
+use crate::{
+    api::verify_seal,
+    types::{Commitment, PoRepConfig, ProverId, Ticket},
+};
+
+/// All inputs of a `verify_seal` call, so that a failing verification can be written to a file
+/// and reproduced elsewhere, see `replay`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SealVerifyBundle {
+    #[serde(with = "crate::types::porep_config::serialized")]
+    pub porep_config: PoRepConfig,
+    pub comm_r: Commitment,
+    pub comm_d: Commitment,
+    pub prover_id: ProverId,
+    pub sector_id: SectorId,
+    pub ticket: Ticket,
+    pub seed: Ticket,
+    pub proof: Vec<u8>,
+}
+
+impl SealVerifyBundle {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        porep_config: &PoRepConfig,
+        comm_r: Commitment,
+        comm_d: Commitment,
+        prover_id: ProverId,
+        sector_id: SectorId,
+        ticket: Ticket,
+        seed: Ticket,
+        proof: &[u8],
+    ) -> Self {
+        SealVerifyBundle {
+            porep_config: porep_config.clone(),
+            comm_r,
+            comm_d,
+            prover_id,
+            sector_id,
+            ticket,
+            seed,
+            proof: proof.to_vec(),
+        }
+    }
+
+    /// Writes the bundle as JSON to `path`, replacing any existing file.
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let file = File::create(path)
+            .with_context(|| format!("could not create seal verify bundle {:?}", path))?;
+        let mut writer = BufWriter::new(file);
+        serde_json::to_writer(&mut writer, self)
+            .with_context(|| format!("failed to serialize seal verify bundle {:?}", path))?;
+        writer
+            .flush()
+            .with_context(|| format!("could not write seal verify bundle {:?}", path))
+    }
+
+    /// Reads a bundle written by `write`.
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path)
+            .with_context(|| format!("could not open seal verify bundle {:?}", path))?;
+        serde_json::from_reader(BufReader::new(file))
+            .with_context(|| format!("failed to deserialize seal verify bundle {:?}", path))
+    }
+
+    /// Runs `verify_seal` on the inputs of the bundle.
+    pub fn replay<Tree: 'static + MerkleTreeTrait>(&self) -> Result<bool> {
+        verify_seal::<Tree>(
+            &self.porep_config,
+            self.comm_r,
+            self.comm_d,
+            self.prover_id,
+            self.sector_id,
+            self.ticket,
+            self.seed,
+            &self.proof,
+        )
+    }
+}
@@ -1,12 +1,33 @@
+use anyhow::{ensure, Result};
 use fr32::to_unpadded_bytes;
+use log::warn;
 
-use crate::types::{PaddedBytesAmount, UnpaddedBytesAmount};
+use crate::{
+    constants::PUBLISHED_SECTOR_SIZES,
+    types::{PaddedBytesAmount, UnpaddedBytesAmount},
+};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct SectorSize(pub u64);
 
+impl SectorSize {
+    /// Constructs a `SectorSize`, ensuring that `bytes` is one of the published sector sizes.
+    pub fn new(bytes: u64) -> Result<Self> {
+        ensure!(
+            PUBLISHED_SECTOR_SIZES.contains(&bytes),
+            "unsupported sector size {}",
+            bytes
+        );
+        Ok(SectorSize(bytes))
+    }
+}
+
 impl From<u64> for SectorSize {
+    /// Converts without validation, use `SectorSize::new` to reject unsupported sizes.
     fn from(size: u64) -> Self {
+        if !PUBLISHED_SECTOR_SIZES.contains(&size) {
+            warn!("unsupported sector size {}", size);
+        }
         SectorSize(size)
     }
 }
@@ -22,4 +22,9 @@ impl SectorUpdateConfig {
             h_select: HSelect::from_nodes(nodes_count),
         }
     }
+
+    /// The number of partition proofs of a sector update proof.
+    pub fn partition_count(&self) -> usize {
+        usize::from(self.update_partitions)
+    }
 }
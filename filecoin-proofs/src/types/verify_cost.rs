@@ -0,0 +1,17 @@
+/// The number of expensive group operations verifying an aggregate proof performs, as estimated
+/// by `estimate_aggregate_verify_cost`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct VerifyCost {
+    /// The number of GIPA rounds, i.e. the log2 of the number of aggregated proofs.
+    pub gipa_rounds: usize,
+    /// The number of Miller loops of the final pairing checks.
+    pub miller_loops: usize,
+    /// The pairing checks are batched, so they share a single final exponentiation.
+    pub final_exponentiations: usize,
+    pub gt_exponentiations: usize,
+    pub g1_scalar_multiplications: usize,
+    pub g2_scalar_multiplications: usize,
+    /// The number of public inputs hashed into the Fiat-Shamir transcript, which only
+    /// `AggregateVersion::V2` does.
+    pub transcript_public_inputs: usize,
+}
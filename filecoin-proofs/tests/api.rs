@@ -1,46 +1,77 @@
 use std::collections::BTreeMap;
-use std::fs::{metadata, read_dir, remove_file, OpenOptions};
+use std::fs::{metadata, read_dir, remove_file, File, OpenOptions};
 use std::io::{Read, Seek, Write};
 use std::path::{Path, PathBuf};
 
 use anyhow::{ensure, Context, Error, Result};
 use bellperson::groth16;
-use bincode::serialize;
-use blstrs::{Bls12, Scalar as Fr};
-use ff::Field;
-use filecoin_hashers::Hasher;
+use bincode::{deserialize, serialize};
+use blstrs::{Bls12, G1Projective, G2Projective, Scalar as Fr};
+use ff::{Field, PrimeField};
 use filecoin_proofs::{
-    add_piece, aggregate_seal_commit_proofs, clear_cache, compute_comm_d, decode_from, encode_into,
-    fauxrep_aux, generate_empty_sector_update_proof,
+    add_piece, add_piece_with_options, aggregate_constituent_inputs,
+    aggregate_proof_matches_expected, aggregate_proofs_equal, aggregate_seal_commit_proofs,
+    aggregation_is_padded, aggregation_padded_count, assert_comm_r_consistency,
+    cache_file_manifest, check_window_post_faults, classify_cache, clear_cache,
+    clear_cache_reporting, combine_seal_commit_partials, commitment_to_fr, compute_comm_d,
+    compute_comm_d_new, compute_tree_r_last_root, decode_from, decode_from_parallel,
+    detect_randomness_reuse, diff_field_element_files, encode_into, estimate_aggregate_verify_cost,
+    execute_seal_job, fauxrep_aux, fr_to_commitment, generate_empty_sector_update_proof,
     generate_empty_sector_update_proof_with_vanilla, generate_fallback_sector_challenges,
-    generate_partition_proofs, generate_piece_commitment, generate_single_partition_proof,
-    generate_single_vanilla_proof, generate_single_window_post_with_vanilla, generate_window_post,
-    generate_window_post_with_vanilla, generate_winning_post,
+    generate_fallback_sector_challenges_batch, generate_partition_proofs,
+    generate_partition_proofs_in_pool, generate_piece_commitment, generate_piece_commitment_cached,
+    generate_single_partition_proof, generate_single_vanilla_proof,
+    generate_single_vanilla_proof_with_mapped_replica, generate_single_window_post_with_vanilla,
+    generate_unsealed_range_proof, generate_window_post, generate_window_post_for_deadline,
+    generate_window_post_in_pool, generate_window_post_with_vanilla,
+    generate_window_post_with_vanilla_output, generate_winning_post,
     generate_winning_post_sector_challenge, generate_winning_post_with_vanilla,
-    get_num_partition_for_fallback_post, get_seal_inputs, merge_window_post_partition_proofs,
-    remove_encoded_data, seal_commit_phase1, seal_commit_phase2, seal_pre_commit_phase1,
-    seal_pre_commit_phase2, unseal_range, validate_cache_for_commit,
-    validate_cache_for_precommit_phase2, verify_aggregate_seal_commit_proofs,
-    verify_empty_sector_update_proof, verify_partition_proofs, verify_seal,
-    verify_single_partition_proof, verify_window_post, verify_winning_post, Commitment,
-    DefaultTreeDomain, MerkleTreeTrait, PaddedBytesAmount, PieceInfo, PoRepConfig, PoStConfig,
-    PoStType, PrivateReplicaInfo, ProverId, PublicReplicaInfo, SealCommitOutput,
-    SealPreCommitOutput, SealPreCommitPhase1Output, SectorShape16KiB, SectorShape2KiB,
-    SectorShape32KiB, SectorShape4KiB, SectorUpdateConfig, UnpaddedByteIndex, UnpaddedBytesAmount,
+    get_num_partition_for_fallback_post, get_seal_inputs, get_seal_inputs_checked,
+    get_seal_inputs_labeled, merge_window_post_partition_proofs, parents_of, partition_proofs_iter,
+    prove_and_verify_seal, prove_leaf_inclusion, prover_id_from_fr, randomness_from_fr,
+    read_cached_comm_r, remove_encoded_data, sample_verify_replica, seal_commit_challenges,
+    seal_commit_phase1, seal_commit_phase2, seal_commit_phase2_range, seal_pre_commit_phase1,
+    seal_pre_commit_phase1_batch, seal_pre_commit_phase2, seal_pre_commit_phase2_in_pool,
+    sector_partition_location, sector_update_challenges, sector_update_file_access_plan,
+    unseal_range, validate_cache_for_commit, validate_cache_for_commit_verbose,
+    validate_cache_for_precommit_phase2, validate_partition_proof_set, validate_post_config,
+    validate_replica_length, verify_aggregate_locate_failure, verify_aggregate_seal_commit_proofs,
+    verify_cc_sector, verify_chain_seal_submission, verify_decoded_matches_comm_d,
+    verify_empty_sector_update_proof, verify_leaf_inclusion, verify_partition_proofs,
+    verify_partition_proofs_from_bytes, verify_partition_proofs_from_parts, verify_seal,
+    verify_seal_any_seed, verify_seal_multi_version, verify_seal_proof_bytes,
+    verify_seal_with_pieces, verify_sector_update_roundtrip, verify_single_partition_proof,
+    verify_single_partition_proof_from_parts, verify_single_vanilla_proof, verify_unseal_range,
+    verify_window_post, verify_window_post_mixed, verify_window_posts_concurrent,
+    verify_winning_post, window_post_challenge_plan, window_post_expected_public_inputs,
+    AggregateVerifierBuilder, CacheKind, ChainSealSubmission, Commitment, EmptySectorUpdateEncoded,
+    Fr32Options, LeafInclusionProof, MerkleTreeTrait, PaddedBytesAmount, PartitionProof,
+    PersistentAux, PieceCommitmentCache, PieceInfo, PoRepConfig, PoStConfig, PoStType,
+    PreCommitJob, PrivateReplicaInfo, ProverId, PublicReplicaInfo, SealCommitOutput, SealJob,
+    SealJobOutput, SealJobPhase, SealPreCommitOutput, SealPreCommitPhase1Output, SealProof,
+    SealVerifyBundle, SectorFault, SectorShape16KiB, SectorShape2KiB, SectorShape32KiB,
+    SectorShape4KiB, SectorUpdateConfig, StoreConfig, UnpaddedByteIndex, UnpaddedBytesAmount,
+    UpdateOp, VerifyCost, WindowPoStVerifyJob, WindowPostAccumulator, DRG_DEGREE, EXP_DEGREE,
     SECTOR_SIZE_16_KIB, SECTOR_SIZE_2_KIB, SECTOR_SIZE_32_KIB, SECTOR_SIZE_4_KIB,
-    WINDOW_POST_CHALLENGE_COUNT, WINDOW_POST_SECTOR_COUNT, WINNING_POST_CHALLENGE_COUNT,
-    WINNING_POST_SECTOR_COUNT,
+    SINGLE_PARTITION_PROOF_LEN, WINDOW_POST_CHALLENGE_COUNT, WINDOW_POST_SECTOR_COUNT,
+    WINNING_POST_CHALLENGE_COUNT, WINNING_POST_SECTOR_COUNT,
 };
-use fr32::bytes_into_fr;
 use log::info;
-use memmap2::MmapOptions;
 use rand::{random, Rng, SeedableRng};
 use rand_xorshift::XorShiftRng;
-use storage_proofs_core::{api_version::ApiVersion, is_legacy_porep_id, sector::SectorId};
-use storage_proofs_update::constants::TreeRHasher;
+use rayon::ThreadPoolBuilder;
+use storage_proofs_core::{
+    api_version::ApiVersion,
+    cache_key::CacheKey,
+    is_legacy_porep_id,
+    merkle::{get_base_tree_count, MerkleProofTrait},
+    sector::SectorId,
+    util::NODE_SIZE,
+};
+use storage_proofs_update::constants::{TreeRDomain, TreeRHasher};
 use tempfile::{tempdir, NamedTempFile, TempDir};
 
-use filecoin_proofs::constants::MAX_LEGACY_REGISTERED_SEAL_PROOF_ID;
+use filecoin_proofs::MAX_LEGACY_REGISTERED_SEAL_PROOF_ID;
 
 #[cfg(feature = "big-tests")]
 use filecoin_proofs::{
@@ -72,6 +103,200 @@ fn to_porep_id_verified(registered_seal_proof: u64, api_version: ApiVersion) ->
     porep_id
 }
 
+#[test]
+fn test_commitment_fr_byte_order() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let fr = Fr::random(&mut rng);
+
+    // Commitments are the little-endian, canonical representation of the field element.
+    let commitment = fr_to_commitment(&fr);
+    assert_eq!(commitment, fr.to_repr());
+    assert_eq!(commitment_to_fr(&commitment)?, fr);
+
+    // Values above the modulus (0x73eda753...) are not valid field elements.
+    let mut invalid = [0xff; 32];
+    invalid[31] = 0x73;
+    assert!(commitment_to_fr(&invalid).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_diff_field_element_files() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let elements: Vec<Commitment> = (0..8)
+        .map(|_| fr_to_commitment(&Fr::random(&mut rng)))
+        .collect();
+    let mut changed = elements.clone();
+    changed[2] = fr_to_commitment(&Fr::random(&mut rng));
+    // Not a valid field element, e.g. corrupted data.
+    changed[5] = [0xff; 32];
+
+    let write_elements = |elements: &[Commitment]| -> Result<NamedTempFile> {
+        let mut file = NamedTempFile::new()?;
+        for element in elements {
+            file.write_all(element)?;
+        }
+        file.flush()?;
+        Ok(file)
+    };
+    let file1 = write_elements(&elements)?;
+    let file2 = write_elements(&changed)?;
+
+    for parallel in [false, true] {
+        assert!(diff_field_element_files(file1.path(), file1.path(), parallel, 10)?.is_empty());
+
+        let mismatches = diff_field_element_files(file1.path(), file2.path(), parallel, 10)?;
+        assert_eq!(
+            mismatches,
+            vec![
+                (64, elements[2], changed[2]),
+                (160, elements[5], changed[5])
+            ]
+        );
+
+        let mismatches = diff_field_element_files(file1.path(), file2.path(), parallel, 1)?;
+        assert_eq!(mismatches, vec![(64, elements[2], changed[2])]);
+
+        // Asking for no mismatches at all would report equal files.
+        assert!(diff_field_element_files(file1.path(), file2.path(), parallel, 0).is_err());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_porep_parents_cache_key() -> Result<()> {
+    let key = |porep_id, api_version| {
+        porep_parents_cache_key::<SectorShape2KiB>(&porep_config(
+            SECTOR_SIZE_2_KIB,
+            porep_id,
+            api_version,
+        ))
+    };
+
+    let key_v1_0_0 = key(ARBITRARY_POREP_ID_V1_0_0, ApiVersion::V1_0_0)?;
+    let key_v1_1_0 = key(ARBITRARY_POREP_ID_V1_1_0, ApiVersion::V1_1_0)?;
+    assert!(key_v1_0_0.starts_with('v'));
+    assert_ne!(key_v1_0_0, key_v1_1_0);
+
+    // The api version is not part of the key, only the porep_id separates the caches.
+    assert_eq!(
+        key(ARBITRARY_POREP_ID_V1_0_0, ApiVersion::V1_1_0)?,
+        key_v1_0_0
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_parents_of() -> Result<()> {
+    let nodes = SECTOR_SIZE_2_KIB / 32;
+    let parents = |api_version, porep_id| -> Result<Vec<Vec<u64>>> {
+        let config = porep_config(SECTOR_SIZE_2_KIB, porep_id, api_version);
+        (0..nodes)
+            .map(|node| parents_of::<SectorShape2KiB>(&config, node))
+            .collect()
+    };
+
+    let parents_v1_0_0 = parents(ApiVersion::V1_0_0, ARBITRARY_POREP_ID_V1_0_0)?;
+    for node_parents in &parents_v1_0_0 {
+        assert_eq!(node_parents.len(), DRG_DEGREE + EXP_DEGREE);
+        assert!(node_parents.iter().all(|parent| *parent < nodes));
+    }
+    assert_ne!(
+        parents_v1_0_0,
+        parents(ApiVersion::V1_1_0, ARBITRARY_POREP_ID_V1_1_0)?
+    );
+
+    let config = porep_config(
+        SECTOR_SIZE_2_KIB,
+        ARBITRARY_POREP_ID_V1_1_0,
+        ApiVersion::V1_1_0,
+    );
+    assert!(parents_of::<SectorShape2KiB>(&config, nodes).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_detect_randomness_reuse() {
+    let records = [
+        (SectorId::from(1), [1; 32], [1; 32]),
+        (SectorId::from(2), [2; 32], [2; 32]),
+        (SectorId::from(3), [1; 32], [3; 32]),
+        (SectorId::from(4), [4; 32], [2; 32]),
+        (SectorId::from(5), [1; 32], [5; 32]),
+    ];
+
+    assert_eq!(
+        detect_randomness_reuse(&records),
+        vec![
+            (SectorId::from(1), SectorId::from(3)),
+            (SectorId::from(1), SectorId::from(5)),
+            (SectorId::from(2), SectorId::from(4)),
+            (SectorId::from(3), SectorId::from(5)),
+        ]
+    );
+    assert!(detect_randomness_reuse(&records[..2]).is_empty());
+
+    // A ticket reused as the seed of another sector is found too.
+    let records = [
+        (SectorId::from(1), [1; 32], [2; 32]),
+        (SectorId::from(2), [3; 32], [1; 32]),
+        (SectorId::from(3), [4; 32], [5; 32]),
+    ];
+    assert_eq!(
+        detect_randomness_reuse(&records),
+        vec![(SectorId::from(1), SectorId::from(2))]
+    );
+}
+
+#[test]
+fn test_validate_post_config() {
+    let mut config = PoStConfig {
+        sector_size: SECTOR_SIZE_2_KIB.into(),
+        sector_count: WINNING_POST_SECTOR_COUNT,
+        challenge_count: WINNING_POST_CHALLENGE_COUNT,
+        typ: PoStType::Winning,
+        priority: false,
+        api_version: ApiVersion::V1_2_0,
+    };
+    assert!(validate_post_config(&config, 1).is_ok());
+    assert!(validate_post_config(&config, 0).is_err());
+    assert!(validate_post_config(&config, 2).is_err());
+    config.sector_count = 2;
+    assert!(validate_post_config(&config, 2).is_err());
+
+    config.typ = PoStType::Window;
+    config.challenge_count = WINDOW_POST_CHALLENGE_COUNT;
+    assert!(validate_post_config(&config, 3).is_ok());
+    assert!(validate_post_config(&config, 0).is_err());
+    config.sector_count = 0;
+    assert!(validate_post_config(&config, 3).is_err());
+}
+
+#[test]
+fn test_assert_comm_r_consistency() {
+    let winning = [(SectorId::from(1), [1; 32])];
+    let window = [(SectorId::from(1), [1; 32]), (SectorId::from(2), [2; 32])];
+    assert!(assert_comm_r_consistency(&winning, &window).is_ok());
+    assert!(assert_comm_r_consistency(&[], &window).is_ok());
+
+    let window = [(SectorId::from(1), [3; 32])];
+    assert!(assert_comm_r_consistency(&winning, &window).is_err());
+}
+
+#[cfg(feature = "testing")]
+#[test]
+fn test_porep_config_new_groth16_testing() {
+    let porep_id = ARBITRARY_POREP_ID_V1_2_0;
+    let config = PoRepConfig::new_groth16_testing(porep_id, ApiVersion::V1_2_0);
+    assert_eq!(u64::from(config.sector_size), SECTOR_SIZE_2_KIB);
+    assert_eq!(usize::from(config.partitions), 1);
+    assert_eq!(config.porep_id, porep_id);
+}
+
 #[test]
 #[ignore]
 fn test_seal_lifecycle_2kib_base_8() -> Result<()> {
@@ -312,9 +537,7 @@ fn seal_lifecycle<Tree: 'static + MerkleTreeTrait>(
     api_version: ApiVersion,
 ) -> Result<()> {
     let mut rng = XorShiftRng::from_seed(TEST_SEED);
-    let prover_fr: DefaultTreeDomain = Fr::random(&mut rng).into();
-    let mut prover_id = [0u8; 32];
-    prover_id.copy_from_slice(AsRef::<[u8]>::as_ref(&prover_fr));
+    let prover_id = prover_id_from_fr(&Fr::random(&mut rng));
 
     info!(
         "Creating seal proof with ApiVersion {} and PoRep ID {:?}",
@@ -339,9 +562,7 @@ fn seal_lifecycle_upgrade<Tree: 'static + MerkleTreeTrait<Hasher = TreeRHasher>>
     api_version: ApiVersion,
 ) -> Result<()> {
     let mut rng = &mut XorShiftRng::from_seed(TEST_SEED);
-    let prover_fr: DefaultTreeDomain = Fr::random(&mut rng).into();
-    let mut prover_id = [0u8; 32];
-    prover_id.copy_from_slice(AsRef::<[u8]>::as_ref(&prover_fr));
+    let prover_id = prover_id_from_fr(&Fr::random(&mut rng));
 
     info!(
         "Creating seal proof for upgrade with ApiVersion {} and PoRep ID {:?}",
@@ -359,6 +580,133 @@ fn seal_lifecycle_upgrade<Tree: 'static + MerkleTreeTrait<Hasher = TreeRHasher>>
     Ok(())
 }
 
+#[test]
+fn test_generate_piece_commitment_cached() -> Result<()> {
+    let (mut piece_file, _) = generate_piece_file(SECTOR_SIZE_2_KIB)?;
+    let piece_size = UnpaddedBytesAmount::from(PaddedBytesAmount(SECTOR_SIZE_2_KIB));
+    let piece_info = generate_piece_commitment(piece_file.as_file_mut(), piece_size)?;
+
+    let cache = PieceCommitmentCache::new();
+    for _ in 0..2 {
+        piece_file.as_file_mut().rewind()?;
+        let cached =
+            generate_piece_commitment_cached(piece_file.as_file_mut(), piece_size, &cache)?;
+        assert_eq!(cached, piece_info);
+        assert_eq!(cache.len(), 1);
+    }
+
+    // A commitment cached for a different size of the same content is not reused.
+    let smaller_size = UnpaddedBytesAmount::from(PaddedBytesAmount(SECTOR_SIZE_2_KIB / 2));
+    piece_file.as_file_mut().rewind()?;
+    let smaller = generate_piece_commitment(piece_file.as_file_mut(), smaller_size)?;
+    piece_file.as_file_mut().rewind()?;
+    let cached = generate_piece_commitment_cached(piece_file.as_file_mut(), smaller_size, &cache)?;
+    assert_eq!(cached, smaller);
+
+    Ok(())
+}
+
+#[test]
+fn test_add_piece_with_options() -> Result<()> {
+    let (_, piece_bytes) = generate_piece_file(SECTOR_SIZE_32_KIB)?;
+    let piece_size = UnpaddedBytesAmount::from(PaddedBytesAmount(SECTOR_SIZE_32_KIB));
+
+    let mut outputs = Vec::new();
+    for chunk_bytes in [127, 64 * 1024].iter().copied() {
+        let mut target = Vec::new();
+        let (piece_info, _) = add_piece_with_options(
+            &piece_bytes[..],
+            &mut target,
+            piece_size,
+            &[],
+            &Fr32Options { chunk_bytes },
+        )?;
+        outputs.push((piece_info, target));
+    }
+    assert_eq!(outputs[0], outputs[1]);
+
+    let mut target = Vec::new();
+    let (piece_info, _) = add_piece(&piece_bytes[..], &mut target, piece_size, &[])?;
+    assert_eq!(
+        piece_info,
+        generate_piece_commitment(&piece_bytes[..], piece_size)?
+    );
+    assert_eq!((piece_info, target), outputs.remove(0));
+
+    Ok(())
+}
+
+#[test]
+fn test_estimate_aggregate_verify_cost() {
+    let v1 = groth16::aggregate::AggregateVersion::V1;
+    let v2 = groth16::aggregate::AggregateVersion::V2;
+    let inputs = 100;
+
+    // The number of proofs is padded to a power of two.
+    assert_eq!(
+        estimate_aggregate_verify_cost(3, inputs, v2),
+        estimate_aggregate_verify_cost(4, inputs, v2)
+    );
+    assert_eq!(estimate_aggregate_verify_cost(1, inputs, v2).gipa_rounds, 1);
+    assert_eq!(
+        estimate_aggregate_verify_cost(8192, inputs, v2).gipa_rounds,
+        13
+    );
+
+    // The counts of the verifier of 1024 proofs.
+    assert_eq!(
+        estimate_aggregate_verify_cost(1024, inputs, v1),
+        VerifyCost {
+            gipa_rounds: 10,
+            miller_loops: 18,
+            final_exponentiations: 1,
+            gt_exponentiations: 100,
+            g1_scalar_multiplications: 26,
+            g2_scalar_multiplications: 4,
+            transcript_public_inputs: 0,
+        }
+    );
+
+    // The cost grows logarithmically, by the same amount for every doubling.
+    let costs: Vec<_> = [256, 512, 1024]
+        .iter()
+        .map(|&n| estimate_aggregate_verify_cost(n, inputs, v2))
+        .collect();
+    assert_eq!(
+        costs[1].gt_exponentiations - costs[0].gt_exponentiations,
+        costs[2].gt_exponentiations - costs[1].gt_exponentiations
+    );
+    assert_eq!(costs[0].miller_loops, costs[2].miller_loops);
+
+    // Only V2 hashes the (padded) public inputs into the transcript, the group operations are
+    // the same.
+    let v1_cost = estimate_aggregate_verify_cost(3, inputs, v1);
+    let v2_cost = estimate_aggregate_verify_cost(3, inputs, v2);
+    assert_eq!(v1_cost.transcript_public_inputs, 0);
+    assert_eq!(v2_cost.transcript_public_inputs, 4 * inputs);
+    assert_eq!(
+        VerifyCost {
+            transcript_public_inputs: 0,
+            ..v2_cost
+        },
+        v1_cost
+    );
+}
+
+#[test]
+fn test_aggregation_padded_count() {
+    assert_eq!(aggregation_padded_count(1), 2);
+    assert_eq!(aggregation_padded_count(2), 2);
+    assert_eq!(aggregation_padded_count(3), 4);
+    assert_eq!(aggregation_padded_count(5), 8);
+    assert_eq!(aggregation_padded_count(257), 512);
+
+    assert!(aggregation_is_padded(1));
+    assert!(!aggregation_is_padded(2));
+    assert!(aggregation_is_padded(257));
+    assert!(!aggregation_is_padded(1024));
+}
+
 #[test]
 #[ignore]
 fn test_seal_proof_aggregation_1_2kib_porep_id_v1_1_base_8() -> Result<()> {
@@ -482,15 +830,107 @@ fn test_seal_proof_aggregation_818_32kib_porep_id_v1_1_base_8() -> Result<()> {
 //    inner_test_seal_proof_aggregation_2kib_porep_id_v1_1_base_8(proofs_to_aggregate)
 //}
 
+#[test]
+fn test_aggregate_verifier_builder_2kib() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let seals = create_aggregated_seals_2kib(&mut rng)?;
+
+    let mut builder = AggregateVerifierBuilder::<SectorShape2KiB>::new(&seals.config);
+    for (comm_r, seed) in seals.comm_rs.iter().zip(seals.seeds.iter()) {
+        builder.add(*comm_r, *seed, seals.commit_input.clone())?;
+    }
+    assert_eq!(builder.len(), AGGREGATED_SEALS_2KIB_COUNT);
+    assert!(builder.finish(
+        seals.aggregate_proof,
+        groth16::aggregate::AggregateVersion::V2
+    )?);
+
+    Ok(())
+}
+
+#[test]
+fn test_verify_aggregate_from_bincode_2kib() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let seals = create_aggregated_seals_2kib(&mut rng)?;
+
+    // The proof decoded from its bincode encoding is the same, and verifies as well.
+    let native_proof = groth16::aggregate::AggregateProof::<Bls12>::read(std::io::Cursor::new(
+        &seals.aggregate_proof,
+    ))?;
+    let bincode_proof: groth16::aggregate::AggregateProof<Bls12> =
+        deserialize(&serialize(&native_proof)?)?;
+    assert!(aggregate_proofs_equal(&native_proof, &bincode_proof));
+    let mut bincode_proof_bytes = Vec::new();
+    bincode_proof.write(&mut bincode_proof_bytes)?;
+    assert!(verify_aggregate_seal_commit_proofs::<SectorShape2KiB>(
+        &seals.config,
+        bincode_proof_bytes,
+        &seals.comm_rs,
+        &seals.seeds,
+        seals.commit_inputs,
+        groth16::aggregate::AggregateVersion::V2,
+    )?);
+
+    Ok(())
+}
+
+#[test]
+fn test_aggregate_constituent_inputs_2kib() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let seals = create_aggregated_seals_2kib(&mut rng)?;
+
+    for index in 0..AGGREGATED_SEALS_2KIB_COUNT {
+        assert_eq!(
+            aggregate_constituent_inputs(&seals.config, &seals.commit_inputs, index)?,
+            &seals.commit_input[..]
+        );
+    }
+    assert!(aggregate_constituent_inputs(
+        &seals.config,
+        &seals.commit_inputs,
+        AGGREGATED_SEALS_2KIB_COUNT
+    )
+    .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_verify_aggregate_locate_failure_2kib() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let seals = create_aggregated_seals_2kib(&mut rng)?;
+    let locate_failure = |commit_inputs: &[Vec<Fr>]| {
+        verify_aggregate_locate_failure::<SectorShape2KiB>(
+            &seals.config,
+            seals.aggregate_proof.clone(),
+            &seals.comm_rs,
+            &seals.seeds,
+            commit_inputs,
+            &seals.commit_outputs,
+            groth16::aggregate::AggregateVersion::V2,
+        )
+    };
+
+    assert_eq!(locate_failure(&seals.commit_inputs)?, None);
+
+    // Corrupting the inputs of the last seal proof is traced back to it.
+    let mut bad_commit_inputs = seals.commit_inputs.clone();
+    bad_commit_inputs.last_mut().expect("no inputs")[0] = Fr::random(&mut rng);
+    assert_eq!(
+        locate_failure(&bad_commit_inputs)?,
+        Some(AGGREGATED_SEALS_2KIB_COUNT - 1)
+    );
+
+    Ok(())
+}
+
 fn aggregate_proofs<Tree: 'static + MerkleTreeTrait>(
     sector_size: u64,
     porep_id: &[u8; 32],
     num_proofs_to_aggregate: usize,
 ) -> Result<()> {
     let mut rng = XorShiftRng::from_seed(TEST_SEED);
-    let prover_fr: DefaultTreeDomain = Fr::random(&mut rng).into();
-    let mut prover_id = [0u8; 32];
-    prover_id.copy_from_slice(AsRef::<[u8]>::as_ref(&prover_fr));
+    let prover_id = prover_id_from_fr(&Fr::random(&mut rng));
 
     let api_version = ApiVersion::V1_1_0;
     let aggregate_versions = vec![
@@ -648,9 +1088,7 @@ fn run_resumable_seal<Tree: 'static + MerkleTreeTrait>(
 
     let sector_size = SECTOR_SIZE_2_KIB;
     let mut rng = XorShiftRng::from_seed(TEST_SEED);
-    let prover_fr: DefaultTreeDomain = Fr::random(&mut rng).into();
-    let mut prover_id = [0u8; 32];
-    prover_id.copy_from_slice(AsRef::<[u8]>::as_ref(&prover_fr));
+    let prover_id = prover_id_from_fr(&Fr::random(&mut rng));
 
     let (mut piece_file, piece_bytes) =
         generate_piece_file(sector_size).expect("failed to generate piece file");
@@ -744,98 +1182,1162 @@ fn run_resumable_seal<Tree: 'static + MerkleTreeTrait>(
 }
 
 #[test]
-#[ignore]
-fn test_winning_post_2kib_base_8() -> Result<()> {
-    winning_post::<SectorShape2KiB>(SECTOR_SIZE_2_KIB, false, ApiVersion::V1_0_0)?;
-    winning_post::<SectorShape2KiB>(SECTOR_SIZE_2_KIB, true, ApiVersion::V1_0_0)?;
-    winning_post::<SectorShape2KiB>(SECTOR_SIZE_2_KIB, false, ApiVersion::V1_1_0)?;
-    winning_post::<SectorShape2KiB>(SECTOR_SIZE_2_KIB, true, ApiVersion::V1_1_0)
-}
-
-#[test]
-#[ignore]
-fn test_winning_post_4kib_sub_8_2() -> Result<()> {
-    winning_post::<SectorShape4KiB>(SECTOR_SIZE_4_KIB, false, ApiVersion::V1_0_0)?;
-    winning_post::<SectorShape4KiB>(SECTOR_SIZE_4_KIB, true, ApiVersion::V1_0_0)?;
-    winning_post::<SectorShape4KiB>(SECTOR_SIZE_4_KIB, false, ApiVersion::V1_1_0)?;
-    winning_post::<SectorShape4KiB>(SECTOR_SIZE_4_KIB, true, ApiVersion::V1_1_0)
-}
-
-#[test]
-#[ignore]
-fn test_winning_post_16kib_sub_8_8() -> Result<()> {
-    winning_post::<SectorShape16KiB>(SECTOR_SIZE_16_KIB, false, ApiVersion::V1_0_0)?;
-    winning_post::<SectorShape16KiB>(SECTOR_SIZE_16_KIB, true, ApiVersion::V1_0_0)?;
-    winning_post::<SectorShape16KiB>(SECTOR_SIZE_16_KIB, false, ApiVersion::V1_1_0)?;
-    winning_post::<SectorShape16KiB>(SECTOR_SIZE_16_KIB, true, ApiVersion::V1_1_0)
-}
-
-#[test]
-#[ignore]
-fn test_winning_post_32kib_top_8_8_2() -> Result<()> {
-    winning_post::<SectorShape32KiB>(SECTOR_SIZE_32_KIB, false, ApiVersion::V1_0_0)?;
-    winning_post::<SectorShape32KiB>(SECTOR_SIZE_32_KIB, true, ApiVersion::V1_0_0)?;
-    winning_post::<SectorShape32KiB>(SECTOR_SIZE_32_KIB, false, ApiVersion::V1_1_0)?;
-    winning_post::<SectorShape32KiB>(SECTOR_SIZE_32_KIB, true, ApiVersion::V1_1_0)
-}
+fn test_seal_pre_commit_phase2_rerun() -> Result<()> {
+    fil_logger::maybe_init();
 
-#[test]
-fn test_winning_post_empty_sector_challenge() -> Result<()> {
+    let sector_size = SECTOR_SIZE_2_KIB;
     let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let prover_id = prover_id_from_fr(&Fr::random(&mut rng));
 
-    let prover_fr: DefaultTreeDomain = Fr::random(&mut rng).into();
-    let mut prover_id = [0u8; 32];
-    prover_id.copy_from_slice(AsRef::<[u8]>::as_ref(&prover_fr));
+    let config = porep_config(sector_size, ARBITRARY_POREP_ID_V1_1_0, ApiVersion::V1_1_0);
 
-    let sector_count = 0;
-    let sector_size = SECTOR_SIZE_2_KIB;
-    let api_version = ApiVersion::V1_1_0;
-
-    let (_, replica, _, _) = create_seal::<_, SectorShape2KiB>(
-        &mut rng,
-        sector_size,
+    let (mut piece_file, _) = generate_piece_file(sector_size)?;
+    let cache_dir = tempdir()?;
+    let sealed_sector_file = NamedTempFile::new()?;
+    let (_, phase1_output) = run_seal_pre_commit_phase1::<SectorShape2KiB>(
+        &config,
         prover_id,
-        true,
-        &ARBITRARY_POREP_ID_V1_1_0,
-        api_version,
+        rng.gen::<u64>().into(),
+        rng.gen(),
+        &cache_dir,
+        &mut piece_file,
+        &sealed_sector_file,
     )?;
-
-    let random_fr: DefaultTreeDomain = Fr::random(rng).into();
-    let mut randomness = [0u8; 32];
-    randomness.copy_from_slice(AsRef::<[u8]>::as_ref(&random_fr));
-
-    let config = PoStConfig {
-        sector_size: sector_size.into(),
-        sector_count,
-        challenge_count: WINNING_POST_CHALLENGE_COUNT,
-        typ: PoStType::Winning,
-        priority: false,
-        api_version,
+    let phase1_bytes = serialize(&phase1_output)?;
+    let phase1_output_copy = || -> Result<SealPreCommitPhase1Output<SectorShape2KiB>> {
+        Ok(deserialize(&phase1_bytes)?)
     };
 
-    assert!(generate_winning_post_sector_challenge::<SectorShape2KiB>(
+    let pre_commit_output = seal_pre_commit_phase2(
         &config,
-        &randomness,
-        sector_count as u64,
-        prover_id
+        phase1_output,
+        cache_dir.path(),
+        sealed_sector_file.path(),
+    )?;
+    let replica = std::fs::read(sealed_sector_file.path())?;
+
+    // Running phase2 again must neither fail nor encode the replica a second time.
+    let rerun_output = seal_pre_commit_phase2(
+        &config,
+        phase1_output_copy()?,
+        cache_dir.path(),
+        sealed_sector_file.path(),
+    )?;
+    assert_eq!(rerun_output.comm_r, pre_commit_output.comm_r);
+    assert_eq!(rerun_output.comm_d, pre_commit_output.comm_d);
+    assert_eq!(std::fs::read(sealed_sector_file.path())?, replica);
+
+    // The persisted output must not be returned for a different phase1 output.
+    let mut other_phase1_output = phase1_output_copy()?;
+    other_phase1_output.comm_d[0] ^= 1;
+    assert!(seal_pre_commit_phase2(
+        &config,
+        other_phase1_output,
+        cache_dir.path(),
+        sealed_sector_file.path(),
     )
     .is_err());
 
-    replica.close()?;
+    let p_aux_path = cache_dir.path().join(CacheKey::PAux.to_string());
+    let t_aux_path = cache_dir.path().join(CacheKey::TAux.to_string());
+    let tree_r_last_path = cache_dir.path().join("sc-02-data-tree-r-last.dat");
+
+    // Interrupted after building all trees: the trees are reused as they are.
+    std::fs::remove_file(&p_aux_path)?;
+    std::fs::remove_file(&t_aux_path)?;
+    let resumed_output = seal_pre_commit_phase2(
+        &config,
+        phase1_output_copy()?,
+        cache_dir.path(),
+        sealed_sector_file.path(),
+    )?;
+    assert_eq!(resumed_output.comm_r, pre_commit_output.comm_r);
+    assert_eq!(std::fs::read(sealed_sector_file.path())?, replica);
+
+    // Interrupted while building tree_r_last: the replica is restored and encoded again.
+    std::fs::remove_file(&p_aux_path)?;
+    std::fs::remove_file(&t_aux_path)?;
+    let mut tree_r_last = std::fs::read(&tree_r_last_path)?;
+    let tree_r_last_len = tree_r_last.len();
+    tree_r_last[tree_r_last_len - 32..].fill(0);
+    std::fs::write(&tree_r_last_path, &tree_r_last)?;
+    let resumed_output = seal_pre_commit_phase2(
+        &config,
+        phase1_output_copy()?,
+        cache_dir.path(),
+        sealed_sector_file.path(),
+    )?;
+    assert_eq!(resumed_output.comm_r, pre_commit_output.comm_r);
+    assert_eq!(resumed_output.comm_d, pre_commit_output.comm_d);
+    assert_eq!(std::fs::read(sealed_sector_file.path())?, replica);
+
+    // Interrupted after encoding the replica, but before writing any tree_r_last file.
+    std::fs::remove_file(&p_aux_path)?;
+    std::fs::remove_file(&t_aux_path)?;
+    std::fs::remove_file(&tree_r_last_path)?;
+    let resumed_output = seal_pre_commit_phase2(
+        &config,
+        phase1_output_copy()?,
+        cache_dir.path(),
+        sealed_sector_file.path(),
+    )?;
+    assert_eq!(resumed_output.comm_r, pre_commit_output.comm_r);
+    assert_eq!(std::fs::read(sealed_sector_file.path())?, replica);
+
+    // A tree whose root does not match the recorded one is rebuilt.
+    std::fs::remove_file(&p_aux_path)?;
+    std::fs::remove_file(&t_aux_path)?;
+    let tree_c_path = cache_dir.path().join("sc-02-data-tree-c.dat");
+    let mut tree_c = std::fs::read(&tree_c_path)?;
+    let tree_c_len = tree_c.len();
+    tree_c[tree_c_len - 32] ^= 1;
+    std::fs::write(&tree_c_path, &tree_c)?;
+    let resumed_output = seal_pre_commit_phase2(
+        &config,
+        phase1_output_copy()?,
+        cache_dir.path(),
+        sealed_sector_file.path(),
+    )?;
+    assert_eq!(resumed_output.comm_r, pre_commit_output.comm_r);
+    assert_eq!(std::fs::read(sealed_sector_file.path())?, replica);
+
+    validate_cache_for_commit::<_, _, SectorShape2KiB>(
+        cache_dir.path(),
+        sealed_sector_file.path(),
+    )?;
 
     Ok(())
 }
 
-fn winning_post<Tree: 'static + MerkleTreeTrait>(
+#[test]
+fn test_seal_pre_commit_phase2_in_pool() -> Result<()> {
+    fil_logger::maybe_init();
+
+    let sector_size = SECTOR_SIZE_2_KIB;
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let prover_id = prover_id_from_fr(&Fr::random(&mut rng));
+
+    let config = porep_config(sector_size, ARBITRARY_POREP_ID_V1_1_0, ApiVersion::V1_1_0);
+
+    let (mut piece_file, _) = generate_piece_file(sector_size)?;
+    let cache_dir = tempdir()?;
+    let sealed_sector_file = NamedTempFile::new()?;
+    let (_, phase1_output) = run_seal_pre_commit_phase1::<SectorShape2KiB>(
+        &config,
+        prover_id,
+        rng.gen::<u64>().into(),
+        rng.gen(),
+        &cache_dir,
+        &mut piece_file,
+        &sealed_sector_file,
+    )?;
+
+    // Run phase2 a second time on copies of the cache and the staged replica.
+    let pool_cache_dir = tempdir()?;
+    for entry in std::fs::read_dir(cache_dir.path())? {
+        let entry = entry?;
+        std::fs::copy(entry.path(), pool_cache_dir.path().join(entry.file_name()))?;
+    }
+    let pool_sealed_sector_file = NamedTempFile::new()?;
+    std::fs::copy(sealed_sector_file.path(), pool_sealed_sector_file.path())?;
+    let pool_phase1_output: SealPreCommitPhase1Output<SectorShape2KiB> =
+        deserialize(&serialize(&phase1_output)?)?;
+
+    let pre_commit_output = seal_pre_commit_phase2(
+        &config,
+        phase1_output,
+        cache_dir.path(),
+        sealed_sector_file.path(),
+    )?;
+
+    let pool = ThreadPoolBuilder::new().num_threads(2).build()?;
+    let pool_pre_commit_output = seal_pre_commit_phase2_in_pool(
+        &pool,
+        &config,
+        pool_phase1_output,
+        pool_cache_dir.path(),
+        pool_sealed_sector_file.path(),
+    )?;
+
+    assert_eq!(pool_pre_commit_output.comm_r, pre_commit_output.comm_r);
+    assert_eq!(pool_pre_commit_output.comm_d, pre_commit_output.comm_d);
+    assert_eq!(
+        std::fs::read(pool_sealed_sector_file.path())?,
+        std::fs::read(sealed_sector_file.path())?
+    );
+    validate_cache_for_commit::<_, _, SectorShape2KiB>(
+        pool_cache_dir.path(),
+        pool_sealed_sector_file.path(),
+    )?;
+
+    Ok(())
+}
+
+#[test]
+fn test_validate_cache_for_commit_verbose() -> Result<()> {
+    fil_logger::maybe_init();
+
+    let sector_size = SECTOR_SIZE_2_KIB;
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let prover_id = prover_id_from_fr(&Fr::random(&mut rng));
+
+    let config = porep_config(sector_size, ARBITRARY_POREP_ID_V1_1_0, ApiVersion::V1_1_0);
+
+    let (mut piece_file, _) = generate_piece_file(sector_size)?;
+    let cache_dir = tempdir()?;
+    let sealed_sector_file = NamedTempFile::new()?;
+    let (_, phase1_output) = run_seal_pre_commit_phase1::<SectorShape2KiB>(
+        &config,
+        prover_id,
+        rng.gen::<u64>().into(),
+        rng.gen(),
+        &cache_dir,
+        &mut piece_file,
+        &sealed_sector_file,
+    )?;
+    seal_pre_commit_phase2(
+        &config,
+        phase1_output,
+        cache_dir.path(),
+        sealed_sector_file.path(),
+    )?;
+
+    let defects = validate_cache_for_commit_verbose::<_, _, SectorShape2KiB>(
+        cache_dir.path(),
+        sealed_sector_file.path(),
+    )?;
+    assert!(defects.is_empty(), "unexpected defects: {:?}", defects);
+
+    // Corrupt the first leaf of tree-d, its parent follows the 64 leaves.
+    let tree_d_path = StoreConfig::data_path(cache_dir.path(), &CacheKey::CommDTree.to_string());
+    let mut tree_d = std::fs::read(&tree_d_path)?;
+    tree_d[0] ^= 1;
+    std::fs::write(&tree_d_path, &tree_d)?;
+
+    let defects = validate_cache_for_commit_verbose::<_, _, SectorShape2KiB>(
+        cache_dir.path(),
+        sealed_sector_file.path(),
+    )?;
+    assert_eq!(defects.len(), 1);
+    assert_eq!(defects[0].path, tree_d_path);
+    assert_eq!(defects[0].offset, Some(sector_size));
+
+    // Structural checks still pass, only the node check locates the corruption.
+    validate_cache_for_commit::<_, _, SectorShape2KiB>(
+        cache_dir.path(),
+        sealed_sector_file.path(),
+    )?;
+
+    // Every defect is reported, not only the first one.
+    let p_aux_path = cache_dir.path().join(CacheKey::PAux.to_string());
+    remove_file(&p_aux_path)?;
+    OpenOptions::new()
+        .write(true)
+        .open(sealed_sector_file.path())?
+        .set_len(0)?;
+    let defects = validate_cache_for_commit_verbose::<_, _, SectorShape2KiB>(
+        cache_dir.path(),
+        sealed_sector_file.path(),
+    )?;
+    for path in [
+        sealed_sector_file.path(),
+        p_aux_path.as_path(),
+        tree_d_path.as_path(),
+    ] {
+        assert!(
+            defects.iter().any(|defect| defect.path == path),
+            "no defect reported for {:?}: {:?}",
+            path,
+            defects
+        );
+    }
+    assert!(validate_cache_for_commit::<_, _, SectorShape2KiB>(
+        cache_dir.path(),
+        sealed_sector_file.path(),
+    )
+    .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_verify_cc_sector() -> Result<()> {
+    fil_logger::maybe_init();
+
+    let sector_size = SECTOR_SIZE_2_KIB;
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let prover_id = prover_id_from_fr(&Fr::random(&mut rng));
+
+    let config = porep_config(sector_size, ARBITRARY_POREP_ID_V1_1_0, ApiVersion::V1_1_0);
+
+    // A committed-capacity sector is sealed from an all-zero staged file without any pieces.
+    let cache_dir = tempdir()?;
+    let staged_sector_file = NamedTempFile::new()?;
+    staged_sector_file.as_file().set_len(sector_size)?;
+    let sealed_sector_file = NamedTempFile::new()?;
+    let phase1_output = seal_pre_commit_phase1::<_, _, _, SectorShape2KiB>(
+        &config,
+        cache_dir.path(),
+        staged_sector_file.path(),
+        sealed_sector_file.path(),
+        prover_id,
+        rng.gen::<u64>().into(),
+        rng.gen(),
+        &[],
+    )?;
+    let pre_commit_output = seal_pre_commit_phase2(
+        &config,
+        phase1_output,
+        cache_dir.path(),
+        sealed_sector_file.path(),
+    )?;
+    assert_eq!(
+        pre_commit_output.comm_d,
+        compute_comm_d(config.sector_size, &[])?
+    );
+    assert!(verify_cc_sector::<_, SectorShape2KiB>(
+        &config,
+        pre_commit_output.comm_r,
+        cache_dir.path()
+    )?);
+
+    // A sector holding a piece is not a committed-capacity sector.
+    let (mut piece_file, _) = generate_piece_file(sector_size)?;
+    let data_cache_dir = tempdir()?;
+    let data_sealed_sector_file = NamedTempFile::new()?;
+    let (_, phase1_output) = run_seal_pre_commit_phase1::<SectorShape2KiB>(
+        &config,
+        prover_id,
+        rng.gen::<u64>().into(),
+        rng.gen(),
+        &data_cache_dir,
+        &mut piece_file,
+        &data_sealed_sector_file,
+    )?;
+    let data_pre_commit_output = seal_pre_commit_phase2(
+        &config,
+        phase1_output,
+        data_cache_dir.path(),
+        data_sealed_sector_file.path(),
+    )?;
+    assert!(!verify_cc_sector::<_, SectorShape2KiB>(
+        &config,
+        data_pre_commit_output.comm_r,
+        data_cache_dir.path()
+    )?);
+
+    // The replica commitment has to match the cache.
+    assert!(!verify_cc_sector::<_, SectorShape2KiB>(
+        &config,
+        data_pre_commit_output.comm_r,
+        cache_dir.path()
+    )?);
+
+    Ok(())
+}
+
+#[test]
+fn test_seal_pre_commit_phase1_batch() -> Result<()> {
+    fil_logger::maybe_init();
+
+    let sector_size = SECTOR_SIZE_2_KIB;
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let prover_id = prover_id_from_fr(&Fr::random(&mut rng));
+
+    let config = porep_config(sector_size, ARBITRARY_POREP_ID_V1_1_0, ApiVersion::V1_1_0);
+
+    let mut jobs = Vec::new();
+    let mut temp_files = Vec::new();
+    let mut single_outputs = Vec::new();
+    let mut single_cache_dirs = Vec::new();
+    for _ in 0..2 {
+        let (mut piece_file, _) = generate_piece_file(sector_size)?;
+        let ticket = rng.gen();
+        let sector_id = rng.gen::<u64>().into();
+
+        // Seal the sector on its own first.
+        let cache_dir = tempdir()?;
+        let sealed_sector_file = NamedTempFile::new()?;
+        let (piece_infos, phase1_output) = run_seal_pre_commit_phase1::<SectorShape2KiB>(
+            &config,
+            prover_id,
+            sector_id,
+            ticket,
+            &cache_dir,
+            &mut piece_file,
+            &sealed_sector_file,
+        )?;
+        single_outputs.push(phase1_output);
+        single_cache_dirs.push(cache_dir);
+
+        piece_file.as_file_mut().rewind()?;
+        let mut staged_sector_file = NamedTempFile::new()?;
+        add_piece(
+            &mut piece_file,
+            &mut staged_sector_file,
+            config.unpadded_bytes_amount(),
+            &[],
+        )?;
+        let batch_cache_dir = tempdir()?;
+        let batch_sealed_sector_file = NamedTempFile::new()?;
+
+        jobs.push(PreCommitJob {
+            cache_path: batch_cache_dir.path().to_path_buf(),
+            in_path: staged_sector_file.path().to_path_buf(),
+            out_path: batch_sealed_sector_file.path().to_path_buf(),
+            prover_id,
+            sector_id,
+            ticket,
+            piece_infos,
+        });
+        temp_files.push((
+            staged_sector_file,
+            batch_sealed_sector_file,
+            batch_cache_dir,
+        ));
+    }
+
+    let batch_outputs = seal_pre_commit_phase1_batch::<SectorShape2KiB>(&config, &jobs)?;
+    assert_eq!(batch_outputs.len(), jobs.len());
+
+    for (i, batch_output) in batch_outputs.iter().enumerate() {
+        assert_eq!(batch_output.comm_d, single_outputs[i].comm_d);
+
+        let single_layers = get_layer_file_paths(&single_cache_dirs[i]);
+        let batch_layers = get_layer_file_paths(&temp_files[i].2);
+        assert_eq!(single_layers.len(), batch_layers.len());
+        for (single_layer, batch_layer) in single_layers.iter().zip(batch_layers.iter()) {
+            assert_eq!(
+                std::fs::read(single_layer)?,
+                std::fs::read(batch_layer)?,
+                "batch labels differ from independently sealed labels"
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_seal_commit_challenges_2kib() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let sector = create_committed_sector_2kib(&mut rng)?;
+
+    let challenges = seal_commit_challenges::<SectorShape2KiB>(
+        &sector.config,
+        sector.pre_commit_output.comm_d,
+        sector.prover_id,
+        sector.sector_id,
+        sector.ticket,
+        sector.seed,
+    )?;
+    assert_eq!(challenges.len(), sector.phase1_output.vanilla_proofs.len());
+    for (partition_challenges, partition_proofs) in challenges
+        .iter()
+        .zip(sector.phase1_output.vanilla_proofs.iter())
+    {
+        let proven: Vec<u64> = partition_proofs
+            .iter()
+            .map(|proof| proof.comm_d_proofs.path_index() as u64)
+            .collect();
+        assert_eq!(partition_challenges, &proven);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_verify_unseal_range_2kib() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let sector = create_committed_sector_2kib(&mut rng)?;
+    let config = &sector.config;
+    let comm_d = sector.pre_commit_output.comm_d;
+
+    // An unsealed range can be authenticated against comm_d alone.
+    let (offset, num_bytes) = (UnpaddedByteIndex(508), UnpaddedBytesAmount(508));
+    let range_proof = generate_unsealed_range_proof(
+        sector.cache_dir.path(),
+        config.sector_size,
+        offset,
+        num_bytes,
+    )?;
+    let mut unsealed = Vec::new();
+    unseal_range::<_, _, _, SectorShape2KiB>(
+        config,
+        sector.cache_dir.path(),
+        File::open(sector.replica.path())?,
+        &mut unsealed,
+        sector.prover_id,
+        sector.sector_id,
+        comm_d,
+        sector.ticket,
+        offset,
+        num_bytes,
+    )?;
+    assert!(verify_unseal_range(
+        &unsealed,
+        config.sector_size,
+        offset,
+        num_bytes,
+        comm_d,
+        &range_proof
+    )?);
+
+    unsealed[0] ^= 1;
+    assert!(!verify_unseal_range(
+        &unsealed,
+        config.sector_size,
+        offset,
+        num_bytes,
+        comm_d,
+        &range_proof
+    )?);
+    unsealed[0] ^= 1;
+
+    let mut truncated_proof = range_proof;
+    truncated_proof.siblings.pop();
+    assert!(verify_unseal_range(
+        &unsealed,
+        config.sector_size,
+        offset,
+        num_bytes,
+        comm_d,
+        &truncated_proof
+    )
+    .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_clear_cache_reporting_2kib() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let sector = create_committed_sector_2kib(&mut rng)?;
+    let cache_dir_path = sector.cache_dir.path();
+
+    let report = clear_cache_reporting::<SectorShape2KiB>(cache_dir_path)?;
+    let tree_d_path = StoreConfig::data_path(cache_dir_path, &CacheKey::CommDTree.to_string());
+    assert!(report.deleted_files.contains(&tree_d_path));
+    assert!(!tree_d_path.exists());
+    assert!(report.freed_bytes > 0);
+
+    Ok(())
+}
+
+#[test]
+fn test_cache_file_manifest_2kib() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let sector = create_committed_sector_2kib(&mut rng)?;
+    let cache_dir_path = sector.cache_dir.path();
+
+    clear_cache::<SectorShape2KiB>(cache_dir_path)?;
+
+    for spec in cache_file_manifest::<SectorShape2KiB>(sector.config.sector_size)? {
+        let path = cache_dir_path.join(&spec.relative_path);
+        assert!(path.exists(), "missing cache file {:?}", path);
+        if let Some(size) = spec.size {
+            assert_eq!(
+                metadata(&path)?.len(),
+                size,
+                "unexpected size of cache file {:?}",
+                path
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_get_seal_inputs_checked_2kib() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let sector = create_committed_sector_2kib(&mut rng)?;
+    let phase1_output = &sector.phase1_output;
+
+    let inputs = get_seal_inputs::<SectorShape2KiB>(
+        &sector.config,
+        phase1_output.comm_r,
+        phase1_output.comm_d,
+        sector.prover_id,
+        sector.sector_id,
+        phase1_output.ticket,
+        phase1_output.seed,
+    )?;
+    let checked_inputs = get_seal_inputs_checked::<SectorShape2KiB>(
+        &sector.config,
+        phase1_output.comm_r,
+        phase1_output.comm_d,
+        &sector.piece_infos,
+        sector.prover_id,
+        sector.sector_id,
+        phase1_output.ticket,
+        phase1_output.seed,
+    )?;
+    assert_eq!(inputs, checked_inputs);
+
+    assert!(get_seal_inputs_checked::<SectorShape2KiB>(
+        &sector.config,
+        phase1_output.comm_r,
+        phase1_output.comm_r,
+        &sector.piece_infos,
+        sector.prover_id,
+        sector.sector_id,
+        phase1_output.ticket,
+        phase1_output.seed,
+    )
+    .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_get_seal_inputs_labeled_2kib() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let sector = create_committed_sector_2kib(&mut rng)?;
+    let phase1_output = &sector.phase1_output;
+
+    let inputs = get_seal_inputs::<SectorShape2KiB>(
+        &sector.config,
+        phase1_output.comm_r,
+        phase1_output.comm_d,
+        sector.prover_id,
+        sector.sector_id,
+        phase1_output.ticket,
+        phase1_output.seed,
+    )?;
+    let labeled_inputs = get_seal_inputs_labeled::<SectorShape2KiB>(
+        &sector.config,
+        phase1_output.comm_r,
+        phase1_output.comm_d,
+        sector.prover_id,
+        sector.sector_id,
+        phase1_output.ticket,
+        phase1_output.seed,
+    )?;
+    assert_eq!(labeled_inputs.len(), inputs.len());
+    for (labeled, partition_inputs) in labeled_inputs.iter().zip(inputs.iter()) {
+        assert_eq!(&labeled.to_inputs(), partition_inputs);
+        assert_eq!(labeled.comm_d, commitment_to_fr(&phase1_output.comm_d)?);
+        assert!(labeled
+            .challenges
+            .iter()
+            .all(|challenge| challenge.comm_d_leaf == challenge.challenge));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_combine_seal_commit_partials_2kib() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let sector = create_committed_sector_2kib(&mut rng)?;
+    let config = &sector.config;
+    let phase1_output = &sector.phase1_output;
+
+    // Proving the partitions separately yields a proof which verifies like a single one.
+    let partials = (0..usize::from(config.partitions))
+        .rev()
+        .map(|k| {
+            seal_commit_phase2_range(
+                config,
+                phase1_output.clone(),
+                sector.prover_id,
+                sector.sector_id,
+                k..k + 1,
+            )
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let combined = combine_seal_commit_partials(config, partials)?;
+    assert!(verify_seal::<SectorShape2KiB>(
+        config,
+        phase1_output.comm_r,
+        phase1_output.comm_d,
+        sector.prover_id,
+        sector.sector_id,
+        phase1_output.ticket,
+        phase1_output.seed,
+        &combined.proof,
+    )?);
+
+    assert!(combine_seal_commit_partials(config, Vec::new()).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_prove_and_verify_seal_2kib() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let sector = create_committed_sector_2kib(&mut rng)?;
+
+    let (commit_output, verified) = prove_and_verify_seal::<SectorShape2KiB>(
+        &sector.config,
+        sector.phase1_output.clone(),
+        sector.prover_id,
+        sector.sector_id,
+    )?;
+    assert!(verified);
+
+    // The returned proof is the one which verified.
+    assert!(verify_seal::<SectorShape2KiB>(
+        &sector.config,
+        sector.pre_commit_output.comm_r,
+        sector.pre_commit_output.comm_d,
+        sector.prover_id,
+        sector.sector_id,
+        sector.ticket,
+        sector.seed,
+        &commit_output.proof,
+    )?);
+
+    Ok(())
+}
+
+#[test]
+fn test_seal_commit_output_validate_shape_2kib() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let sector = create_committed_sector_2kib(&mut rng)?;
+    let commit_output = sector.prove()?;
+
+    commit_output.validate_shape(&sector.config)?;
+    let truncated = SealCommitOutput {
+        proof: commit_output.proof[..commit_output.proof.len() - 1].to_vec(),
+    };
+    assert!(truncated.validate_shape(&sector.config).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_read_cached_comm_r_2kib() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let sector = create_committed_sector_2kib(&mut rng)?;
+
+    // comm_r can be read from the roots cached in tree-c and tree-r-last until the cache is cleared.
+    assert_eq!(
+        read_cached_comm_r::<SectorShape2KiB>(
+            &sector.config,
+            sector.replica.path(),
+            sector.cache_dir.path()
+        )?,
+        sector.pre_commit_output.comm_r
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_verify_seal_with_pieces_2kib() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let sector = create_committed_sector_2kib(&mut rng)?;
+    let commit_output = sector.prove()?;
+    let verify_with_pieces = |piece_infos: &[PieceInfo]| {
+        verify_seal_with_pieces::<SectorShape2KiB>(
+            &sector.config,
+            sector.pre_commit_output.comm_r,
+            sector.pre_commit_output.comm_d,
+            piece_infos,
+            sector.prover_id,
+            sector.sector_id,
+            sector.ticket,
+            sector.seed,
+            &commit_output.proof,
+        )
+    };
+
+    assert!(verify_with_pieces(&sector.piece_infos)?);
+
+    // A valid proof is rejected for pieces other than the sealed ones.
+    let mut other_piece_infos = sector.piece_infos.clone();
+    other_piece_infos[0].commitment[0] ^= 1;
+    assert!(!verify_with_pieces(&other_piece_infos)?);
+
+    Ok(())
+}
+
+#[test]
+fn test_seal_verify_bundle_2kib() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let sector = create_committed_sector_2kib(&mut rng)?;
+    let commit_output = sector.prove()?;
+
+    let mut bundle = SealVerifyBundle::new(
+        &sector.config,
+        sector.pre_commit_output.comm_r,
+        sector.pre_commit_output.comm_d,
+        sector.prover_id,
+        sector.sector_id,
+        sector.ticket,
+        sector.seed,
+        &commit_output.proof,
+    );
+    assert!(bundle.replay::<SectorShape2KiB>()?);
+
+    // A failing verification is reproduced from its bundle file.
+    let bundle_dir = tempdir()?;
+    let bundle_path = bundle_dir.path().join("seal-verify-bundle.json");
+    bundle.seed[0] ^= 1;
+    bundle.write(&bundle_path)?;
+    let bundle = SealVerifyBundle::read(&bundle_path)?;
+    assert_eq!(bundle.comm_r, sector.pre_commit_output.comm_r);
+    assert_eq!(bundle.proof, commit_output.proof);
+    assert!(!bundle.replay::<SectorShape2KiB>()?);
+
+    Ok(())
+}
+
+#[test]
+fn test_verify_chain_seal_submission_2kib() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let sector = create_committed_sector_2kib(&mut rng)?;
+    let commit_output = sector.prove()?;
+
+    let submission = ChainSealSubmission::new(
+        commit_output,
+        sector.pre_commit_output.comm_r,
+        sector.pre_commit_output.comm_d,
+        sector.sector_id,
+        sector.ticket,
+        sector.seed,
+    );
+    let submission = ChainSealSubmission::from_bytes(&submission.to_bytes()?)?;
+    assert!(verify_chain_seal_submission::<SectorShape2KiB>(
+        &sector.config,
+        &submission,
+        sector.prover_id
+    )?);
+
+    let mut truncated = submission;
+    truncated.proof.pop();
+    assert!(verify_chain_seal_submission::<SectorShape2KiB>(
+        &sector.config,
+        &truncated,
+        sector.prover_id
+    )
+    .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_verify_seal_proof_bytes_2kib() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let sector = create_committed_sector_2kib(&mut rng)?;
+    let commit_output = sector.prove()?;
+
+    // The proof must round-trip through its stable encoding and verify from raw bytes.
+    let seal_proof = SealProof::from_bytes(&sector.config, &commit_output.proof)?;
+    assert_eq!(seal_proof.to_bytes()?, commit_output.proof);
+
+    assert!(verify_seal_proof_bytes::<SectorShape2KiB>(
+        &sector.config,
+        &seal_proof.to_bytes()?,
+        sector.pre_commit_output.comm_r,
+        sector.pre_commit_output.comm_d,
+        sector.prover_id,
+        sector.sector_id,
+        sector.ticket,
+        sector.seed,
+    )?);
+
+    Ok(())
+}
+
+#[test]
+fn test_verify_seal_any_seed_2kib() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let sector = create_committed_sector_2kib(&mut rng)?;
+    let commit_output = sector.prove()?;
+    let verify_any_seed = |seeds: &[[u8; 32]]| {
+        verify_seal_any_seed::<SectorShape2KiB>(
+            &sector.config,
+            sector.pre_commit_output.comm_r,
+            sector.pre_commit_output.comm_d,
+            sector.prover_id,
+            sector.sector_id,
+            sector.ticket,
+            seeds,
+            &commit_output.proof,
+        )
+    };
+
+    // Only the seed which was actually used is accepted.
+    let mut other_seed = sector.seed;
+    other_seed[0] ^= 1;
+    assert_eq!(
+        verify_any_seed(&[other_seed, sector.seed])?,
+        Some(sector.seed)
+    );
+    assert_eq!(verify_any_seed(&[other_seed])?, None);
+
+    Ok(())
+}
+
+#[test]
+fn test_verify_seal_multi_version_2kib() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let sector = create_committed_sector_2kib(&mut rng)?;
+    let commit_output = sector.prove()?;
+    let verify_multi_version = |configs: &[PoRepConfig]| {
+        verify_seal_multi_version::<SectorShape2KiB>(
+            configs,
+            sector.pre_commit_output.comm_r,
+            sector.pre_commit_output.comm_d,
+            sector.prover_id,
+            sector.sector_id,
+            sector.ticket,
+            sector.seed,
+            &commit_output.proof,
+        )
+    };
+
+    // Only the config the proof was generated under is accepted.
+    let mut other_config = sector.config.clone();
+    other_config.porep_id[0] ^= 1;
+    assert_eq!(
+        verify_multi_version(&[other_config.clone(), sector.config.clone()])?,
+        Some(1)
+    );
+    assert_eq!(verify_multi_version(&[other_config])?, None);
+
+    // As is only the api version it was generated under.
+    let mut other_version = sector.config.clone();
+    other_version.api_version = ApiVersion::V1_0_0;
+    assert_eq!(
+        verify_multi_version(&[other_version.clone(), sector.config.clone()])?,
+        Some(1)
+    );
+    assert_eq!(verify_multi_version(&[other_version])?, None);
+
+    Ok(())
+}
+
+#[test]
+#[ignore]
+fn test_winning_post_2kib_base_8() -> Result<()> {
+    winning_post::<SectorShape2KiB>(SECTOR_SIZE_2_KIB, false, ApiVersion::V1_0_0)?;
+    winning_post::<SectorShape2KiB>(SECTOR_SIZE_2_KIB, true, ApiVersion::V1_0_0)?;
+    winning_post::<SectorShape2KiB>(SECTOR_SIZE_2_KIB, false, ApiVersion::V1_1_0)?;
+    winning_post::<SectorShape2KiB>(SECTOR_SIZE_2_KIB, true, ApiVersion::V1_1_0)
+}
+
+#[test]
+#[ignore]
+fn test_winning_post_4kib_sub_8_2() -> Result<()> {
+    winning_post::<SectorShape4KiB>(SECTOR_SIZE_4_KIB, false, ApiVersion::V1_0_0)?;
+    winning_post::<SectorShape4KiB>(SECTOR_SIZE_4_KIB, true, ApiVersion::V1_0_0)?;
+    winning_post::<SectorShape4KiB>(SECTOR_SIZE_4_KIB, false, ApiVersion::V1_1_0)?;
+    winning_post::<SectorShape4KiB>(SECTOR_SIZE_4_KIB, true, ApiVersion::V1_1_0)
+}
+
+#[test]
+#[ignore]
+fn test_winning_post_16kib_sub_8_8() -> Result<()> {
+    winning_post::<SectorShape16KiB>(SECTOR_SIZE_16_KIB, false, ApiVersion::V1_0_0)?;
+    winning_post::<SectorShape16KiB>(SECTOR_SIZE_16_KIB, true, ApiVersion::V1_0_0)?;
+    winning_post::<SectorShape16KiB>(SECTOR_SIZE_16_KIB, false, ApiVersion::V1_1_0)?;
+    winning_post::<SectorShape16KiB>(SECTOR_SIZE_16_KIB, true, ApiVersion::V1_1_0)
+}
+
+#[test]
+#[ignore]
+fn test_winning_post_32kib_top_8_8_2() -> Result<()> {
+    winning_post::<SectorShape32KiB>(SECTOR_SIZE_32_KIB, false, ApiVersion::V1_0_0)?;
+    winning_post::<SectorShape32KiB>(SECTOR_SIZE_32_KIB, true, ApiVersion::V1_0_0)?;
+    winning_post::<SectorShape32KiB>(SECTOR_SIZE_32_KIB, false, ApiVersion::V1_1_0)?;
+    winning_post::<SectorShape32KiB>(SECTOR_SIZE_32_KIB, true, ApiVersion::V1_1_0)
+}
+
+#[test]
+fn test_winning_post_empty_sector_challenge() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+
+    let prover_id = prover_id_from_fr(&Fr::random(&mut rng));
+
+    let sector_count = 0;
+    let sector_size = SECTOR_SIZE_2_KIB;
+    let api_version = ApiVersion::V1_1_0;
+
+    let (_, replica, _, _) = create_seal::<_, SectorShape2KiB>(
+        &mut rng,
+        sector_size,
+        prover_id,
+        true,
+        &ARBITRARY_POREP_ID_V1_1_0,
+        api_version,
+    )?;
+
+    let randomness = randomness_from_fr(&Fr::random(rng));
+
+    let config = PoStConfig {
+        sector_size: sector_size.into(),
+        sector_count,
+        challenge_count: WINNING_POST_CHALLENGE_COUNT,
+        typ: PoStType::Winning,
+        priority: false,
+        api_version,
+    };
+
+    assert!(generate_winning_post_sector_challenge::<SectorShape2KiB>(
+        &config,
+        &randomness,
+        sector_count as u64,
+        prover_id
+    )
+    .is_err());
+
+    use storage_proofs_core::error::Error as PoStError;
+    match generate_winning_post_sector_challenge::<SectorShape2KiB>(
+        &config,
+        &randomness,
+        sector_count as u64,
+        prover_id,
+    ) {
+        Ok(_) => panic!("challenges generated for an empty sector set"),
+        Err(e) => match e.downcast::<PoStError>() {
+            Ok(PoStError::NoSectorsToChallenge) => {}
+            Ok(e) => panic!("unexpected error: {}", e),
+            Err(_) => panic!("failed to downcast to Error"),
+        },
+    }
+
+    replica.close()?;
+
+    Ok(())
+}
+
+#[test]
+fn test_private_replica_info_from_cache_2kib() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let sectors = create_fake_sectors_2kib(&mut rng, 1, ApiVersion::V1_1_0)?;
+    let (sector_id, replica, _, cache_dir) = &sectors.sectors[0];
+
+    // Deriving comm_r from the cache must yield the commitment returned by sealing.
+    let from_cache = PrivateReplicaInfo::<SectorShape2KiB>::from_cache(
+        replica.path().into(),
+        cache_dir.path().into(),
+    )?;
+    assert_eq!(from_cache, sectors.priv_replicas[sector_id]);
+
+    Ok(())
+}
+
+#[test]
+fn test_compute_tree_r_last_root_2kib() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let sectors = create_fake_sectors_2kib(&mut rng, 1, ApiVersion::V1_1_0)?;
+    let (sector_id, _, _, cache_dir) = &sectors.sectors[0];
+
+    // comm_r_last can be read from the roots cached in the tree-r-last files.
+    let comm_r_last = compute_tree_r_last_root::<SectorShape2KiB>(cache_dir.path())?;
+    assert_eq!(
+        &comm_r_last[..],
+        AsRef::<[u8]>::as_ref(&sectors.priv_replicas[sector_id].safe_comm_r_last())
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_sample_verify_replica_2kib() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let sectors = create_fake_sectors_2kib(&mut rng, 1, ApiVersion::V1_1_0)?;
+    let (_, replica, comm_r, cache_dir) = &sectors.sectors[0];
+    let config = winning_post_config_2kib(ApiVersion::V1_1_0);
+
+    assert!(sample_verify_replica::<SectorShape2KiB, _>(
+        &config,
+        replica.path(),
+        cache_dir.path(),
+        *comm_r,
+        16,
+        &mut rng
+    )?);
+
+    let mut wrong_comm_r = *comm_r;
+    wrong_comm_r[0] ^= 1;
+    assert!(!sample_verify_replica::<SectorShape2KiB, _>(
+        &config,
+        replica.path(),
+        cache_dir.path(),
+        wrong_comm_r,
+        16,
+        &mut rng
+    )?);
+    assert!(sample_verify_replica::<SectorShape2KiB, _>(
+        &config,
+        replica.path(),
+        cache_dir.path(),
+        *comm_r,
+        0,
+        &mut rng
+    )
+    .is_err());
+
+    // The base trees are rebuilt from the replica when proving, so a single corrupted node fails
+    // all samples within its base tree, which is the whole 2KiB tree-r-last.
+    let mut replica_data = std::fs::read(replica.path())?;
+    let corrupted_node = rng.gen_range(0..replica_data.len() / NODE_SIZE);
+    replica_data[corrupted_node * NODE_SIZE] ^= 1;
+    std::fs::write(replica.path(), &replica_data)?;
+    assert!(!sample_verify_replica::<SectorShape2KiB, _>(
+        &config,
+        replica.path(),
+        cache_dir.path(),
+        *comm_r,
+        16,
+        &mut rng
+    )?);
+
+    Ok(())
+}
+
+#[test]
+fn test_prove_leaf_inclusion_2kib() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let sectors = create_fake_sectors_2kib(&mut rng, 1, ApiVersion::V1_1_0)?;
+    let (_, replica, _, cache_dir) = &sectors.sectors[0];
+    let config = winning_post_config_2kib(ApiVersion::V1_1_0);
+    let comm_r_last = compute_tree_r_last_root::<SectorShape2KiB>(cache_dir.path())?;
+
+    // Single leaves can be spot challenged outside of a PoSt.
+    let leaves = SECTOR_SIZE_2_KIB / NODE_SIZE as u64;
+    let leaf_index = rng.gen_range(0..leaves);
+    let leaf_proof: LeafInclusionProof<SectorShape2KiB> = prove_leaf_inclusion::<SectorShape2KiB>(
+        &config,
+        replica.path(),
+        cache_dir.path(),
+        leaf_index,
+    )?;
+    assert!(verify_leaf_inclusion::<SectorShape2KiB>(
+        comm_r_last,
+        leaf_index,
+        &leaf_proof
+    )?);
+    assert!(!verify_leaf_inclusion::<SectorShape2KiB>(
+        comm_r_last,
+        (leaf_index + 1) % leaves,
+        &leaf_proof
+    )?);
+
+    assert!(prove_leaf_inclusion::<SectorShape2KiB>(
+        &config,
+        replica.path(),
+        cache_dir.path(),
+        leaves
+    )
+    .is_err());
+
+    Ok(())
+}
+
+fn winning_post<Tree: 'static + MerkleTreeTrait>(
     sector_size: u64,
     fake: bool,
     api_version: ApiVersion,
 ) -> Result<()> {
     let mut rng = XorShiftRng::from_seed(TEST_SEED);
 
-    let prover_fr: DefaultTreeDomain = Fr::random(&mut rng).into();
-    let mut prover_id = [0u8; 32];
-    prover_id.copy_from_slice(AsRef::<[u8]>::as_ref(&prover_fr));
+    let prover_id = prover_id_from_fr(&Fr::random(&mut rng));
 
     let porep_id = match api_version {
         ApiVersion::V1_0_0 => ARBITRARY_POREP_ID_V1_0_0,
@@ -857,9 +2359,7 @@ fn winning_post<Tree: 'static + MerkleTreeTrait>(
     };
     let sector_count = WINNING_POST_SECTOR_COUNT;
 
-    let random_fr: DefaultTreeDomain = Fr::random(&mut rng).into();
-    let mut randomness = [0u8; 32];
-    randomness.copy_from_slice(AsRef::<[u8]>::as_ref(&random_fr));
+    let randomness = randomness_from_fr(&Fr::random(&mut rng));
 
     let config = PoStConfig {
         sector_size: sector_size.into(),
@@ -870,117 +2370,703 @@ fn winning_post<Tree: 'static + MerkleTreeTrait>(
         api_version,
     };
 
-    let challenged_sectors = generate_winning_post_sector_challenge::<Tree>(
+    let challenged_sectors = generate_winning_post_sector_challenge::<Tree>(
+        &config,
+        &randomness,
+        sector_count as u64,
+        prover_id,
+    )?;
+    assert_eq!(challenged_sectors.len(), sector_count);
+    assert_eq!(challenged_sectors[0], 0); // with a sector_count of 1, the only valid index is 0
+
+    let pub_replicas = vec![(sector_id, PublicReplicaInfo::new(comm_r)?)];
+    let private_replica_info =
+        PrivateReplicaInfo::new(replica.path().into(), comm_r, cache_dir.path().into())?;
+
+    /////////////////////////////////////////////
+    // The following methods of proof generation are functionally equivalent:
+    // 1)
+    //
+    let priv_replicas = vec![(sector_id, private_replica_info.clone())];
+    let proof = generate_winning_post::<Tree>(&config, &randomness, &priv_replicas[..], prover_id)?;
+
+    let valid =
+        verify_winning_post::<Tree>(&config, &randomness, &pub_replicas[..], prover_id, &proof)?;
+    assert!(valid, "proof did not verify");
+
+    //
+    // 2)
+    let mut vanilla_proofs = Vec::with_capacity(sector_count);
+    let challenges =
+        generate_fallback_sector_challenges::<Tree>(&config, &randomness, &[sector_id], prover_id)?;
+
+    // Make sure that files can be read-only for a window post.
+    set_readonly_flag(replica.path(), true);
+    set_readonly_flag(cache_dir.path(), true);
+
+    let single_proof = generate_single_vanilla_proof::<Tree>(
+        &config,
+        sector_id,
+        &private_replica_info,
+        &challenges[&sector_id],
+    )?;
+
+    vanilla_proofs.push(single_proof);
+
+    let proof = generate_winning_post_with_vanilla::<Tree>(
+        &config,
+        &randomness,
+        prover_id,
+        vanilla_proofs,
+    )?;
+    /////////////////////////////////////////////
+
+    let valid =
+        verify_winning_post::<Tree>(&config, &randomness, &pub_replicas[..], prover_id, &proof)?;
+    assert!(valid, "proof did not verify");
+
+    // Make files writeable again, so that the temporary directory can be removed.
+    set_readonly_flag(replica.path(), false);
+    set_readonly_flag(cache_dir.path(), false);
+
+    replica.close()?;
+
+    Ok(())
+}
+
+#[test]
+#[ignore]
+fn test_window_post_single_partition_smaller_2kib_base_8() -> Result<()> {
+    let sector_size = SECTOR_SIZE_2_KIB;
+    let sector_count = *WINDOW_POST_SECTOR_COUNT
+        .read()
+        .expect("WINDOW_POST_SECTOR_COUNT poisoned")
+        .get(&sector_size)
+        .expect("unknown sector size");
+
+    let versions = vec![ApiVersion::V1_0_0, ApiVersion::V1_1_0, ApiVersion::V1_2_0];
+    for version in versions {
+        window_post::<SectorShape2KiB>(
+            sector_size,
+            sector_count / 2,
+            sector_count,
+            false,
+            version,
+        )?;
+        window_post::<SectorShape2KiB>(sector_size, sector_count / 2, sector_count, true, version)?;
+    }
+
+    Ok(())
+}
+
+#[test]
+#[ignore]
+fn test_window_post_two_partitions_matching_2kib_base_8() -> Result<()> {
+    let sector_size = SECTOR_SIZE_2_KIB;
+    let sector_count = *WINDOW_POST_SECTOR_COUNT
+        .read()
+        .expect("WINDOW_POST_SECTOR_COUNT poisoned")
+        .get(&sector_size)
+        .expect("unknown sector size");
+
+    let versions = vec![ApiVersion::V1_0_0, ApiVersion::V1_1_0, ApiVersion::V1_2_0];
+    for version in versions {
+        window_post::<SectorShape2KiB>(
+            sector_size,
+            2 * sector_count,
+            sector_count,
+            false,
+            version,
+        )?;
+        window_post::<SectorShape2KiB>(sector_size, 2 * sector_count, sector_count, true, version)?;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_window_post_for_deadline_with_gaps_2kib() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let sector_size = SECTOR_SIZE_2_KIB;
+    let sector_count = *WINDOW_POST_SECTOR_COUNT
+        .read()
+        .expect("WINDOW_POST_SECTOR_COUNT poisoned")
+        .get(&sector_size)
+        .expect("unknown sector size");
+    let api_version = ApiVersion::V1_2_0;
+    let prover_id = prover_id_from_fr(&Fr::random(&mut rng));
+
+    // Sparse sector ids, e.g. after terminations, spanning two partitions, the second of which
+    // has to be padded.
+    let sector_ids: Vec<SectorId> = vec![3, 17, 1000].into_iter().map(SectorId::from).collect();
+
+    let mut sectors = Vec::with_capacity(sector_ids.len());
+    let mut priv_replicas = BTreeMap::new();
+    let mut pub_replicas = BTreeMap::new();
+    for sector_id in &sector_ids {
+        // The fake replicas do not depend on the sector id.
+        let (_, replica, comm_r, cache_dir) = create_fake_seal::<_, SectorShape2KiB>(
+            &mut rng,
+            sector_size,
+            &ARBITRARY_POREP_ID_V1_2_0,
+            api_version,
+        )?;
+        priv_replicas.insert(
+            *sector_id,
+            PrivateReplicaInfo::new(replica.path().into(), comm_r, cache_dir.path().into())?,
+        );
+        pub_replicas.insert(*sector_id, PublicReplicaInfo::new(comm_r)?);
+        sectors.push((replica, cache_dir));
+    }
+
+    let config = PoStConfig {
+        sector_size: sector_size.into(),
+        sector_count,
+        challenge_count: WINDOW_POST_CHALLENGE_COUNT,
+        typ: PoStType::Window,
+        priority: false,
+        api_version,
+    };
+    assert_eq!(
+        get_num_partition_for_fallback_post(&config, sector_ids.len()),
+        2
+    );
+    let randomness = randomness_from_fr(&Fr::random(&mut rng));
+
+    let proof = generate_window_post_for_deadline::<SectorShape2KiB>(
+        &config,
+        &randomness,
+        &priv_replicas,
+        prover_id,
+    )?;
+    assert_eq!(proof.len(), 2 * SINGLE_PARTITION_PROOF_LEN);
+    assert!(verify_window_post::<SectorShape2KiB>(
+        &config,
+        &randomness,
+        &pub_replicas,
+        prover_id,
+        &proof
+    )?);
+
+    assert!(generate_window_post_for_deadline::<SectorShape2KiB>(
+        &config,
+        &randomness,
+        &BTreeMap::new(),
+        prover_id
+    )
+    .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_validate_replica_length_2kib() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let sectors = create_fake_sectors_2kib(&mut rng, 1, ApiVersion::V1_2_0)?;
+    let (_, replica, _, _) = &sectors.sectors[0];
+    let config = window_post_config_2kib(ApiVersion::V1_2_0);
+
+    validate_replica_length::<SectorShape2KiB>(&config, replica.path())?;
+
+    let bad_replica = NamedTempFile::new()?;
+    bad_replica.as_file().set_len(1)?;
+    assert!(validate_replica_length::<SectorShape2KiB>(&config, bad_replica.path()).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_window_post_expected_public_inputs_2kib() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let config = window_post_config_2kib(ApiVersion::V1_2_0);
+    let sectors = create_fake_sectors_2kib(&mut rng, config.sector_count + 1, config.api_version)?;
+    let randomness = randomness_from_fr(&Fr::random(&mut rng));
+
+    let expected_inputs = window_post_expected_public_inputs::<SectorShape2KiB>(
+        &config,
+        &randomness,
+        &sectors.pub_replicas,
+        sectors.prover_id,
+    )?;
+    assert_eq!(
+        expected_inputs.len(),
+        get_num_partition_for_fallback_post(&config, sectors.pub_replicas.len())
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_window_post_accumulator_2kib() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let config = window_post_config_2kib(ApiVersion::V1_2_0);
+    let sectors = create_fake_sectors_2kib(&mut rng, config.sector_count, config.api_version)?;
+    let randomness = randomness_from_fr(&Fr::random(&mut rng));
+
+    let mut accumulator =
+        WindowPostAccumulator::<SectorShape2KiB>::new(&config, &randomness, sectors.prover_id)?;
+    // Sectors can be added in any order.
+    for (sector_id, replica) in sectors.priv_replicas.iter().rev() {
+        accumulator.add_sector(*sector_id, replica)?;
+    }
+    assert_eq!(accumulator.len(), sectors.priv_replicas.len());
+    let proof = accumulator.finalize()?;
+    assert!(verify_window_post::<SectorShape2KiB>(
+        &config,
+        &randomness,
+        &sectors.pub_replicas,
+        sectors.prover_id,
+        &proof,
+    )?);
+
+    let old_config = window_post_config_2kib(ApiVersion::V1_1_0);
+    assert!(WindowPostAccumulator::<SectorShape2KiB>::new(
+        &old_config,
+        &randomness,
+        sectors.prover_id
+    )
+    .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_generate_window_post_in_pool_2kib() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let config = window_post_config_2kib(ApiVersion::V1_2_0);
+    let sectors = create_fake_sectors_2kib(&mut rng, config.sector_count, config.api_version)?;
+    let randomness = randomness_from_fr(&Fr::random(&mut rng));
+
+    let pool = ThreadPoolBuilder::new().num_threads(2).build()?;
+    let proof = generate_window_post_in_pool::<SectorShape2KiB>(
+        &pool,
+        &config,
+        &randomness,
+        &sectors.priv_replicas,
+        sectors.prover_id,
+    )?;
+    assert!(verify_window_post::<SectorShape2KiB>(
+        &config,
+        &randomness,
+        &sectors.pub_replicas,
+        sectors.prover_id,
+        &proof
+    )?);
+
+    Ok(())
+}
+
+#[test]
+fn test_verify_window_posts_concurrent_2kib() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let config = window_post_config_2kib(ApiVersion::V1_2_0);
+    let sectors = create_fake_sectors_2kib(&mut rng, config.sector_count, config.api_version)?;
+    let randomness = randomness_from_fr(&Fr::random(&mut rng));
+    let proof = generate_window_post::<SectorShape2KiB>(
+        &config,
+        &randomness,
+        &sectors.priv_replicas,
+        sectors.prover_id,
+    )?;
+
+    // Batches of proofs verify like standalone proofs.
+    let mut other_randomness = randomness;
+    other_randomness[0] ^= 1;
+    let jobs: Vec<WindowPoStVerifyJob> = vec![
+        (
+            config.clone(),
+            randomness,
+            sectors.pub_replicas.clone(),
+            sectors.prover_id,
+            proof.clone(),
+        ),
+        (
+            config.clone(),
+            other_randomness,
+            sectors.pub_replicas.clone(),
+            sectors.prover_id,
+            proof,
+        ),
+    ];
+    let results = verify_window_posts_concurrent::<SectorShape2KiB>(&jobs);
+    assert_eq!(results.len(), jobs.len());
+    for (result, (config, randomness, replicas, prover_id, proof)) in results.iter().zip(&jobs) {
+        let expected =
+            verify_window_post::<SectorShape2KiB>(config, randomness, replicas, *prover_id, proof);
+        assert_eq!(result.as_ref().ok(), expected.as_ref().ok());
+    }
+    assert!(matches!(results[0], Ok(true)));
+
+    Ok(())
+}
+
+#[test]
+fn test_verify_window_post_other_prover_id_2kib() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let config = window_post_config_2kib(ApiVersion::V1_2_0);
+    let sectors = create_fake_sectors_2kib(&mut rng, config.sector_count, config.api_version)?;
+    let randomness = randomness_from_fr(&Fr::random(&mut rng));
+    let proof = generate_window_post::<SectorShape2KiB>(
+        &config,
+        &randomness,
+        &sectors.priv_replicas,
+        sectors.prover_id,
+    )?;
+
+    // The proof does not commit to the prover id, the replicas are bound to their prover through
+    // comm_r only. Callers have to check the sector ownership themselves.
+    let mut other_prover_id = sectors.prover_id;
+    other_prover_id[0] ^= 1;
+    assert!(verify_window_post::<SectorShape2KiB>(
+        &config,
+        &randomness,
+        &sectors.pub_replicas,
+        other_prover_id,
+        &proof
+    )?);
+
+    Ok(())
+}
+
+#[test]
+fn test_verify_single_vanilla_proof_2kib() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let config = window_post_config_2kib(ApiVersion::V1_2_0);
+    let sectors = create_fake_sectors_2kib(&mut rng, 1, config.api_version)?;
+    let (sector_id, _, comm_r, _) = &sectors.sectors[0];
+    let randomness = randomness_from_fr(&Fr::random(&mut rng));
+
+    let challenges = generate_fallback_sector_challenges::<SectorShape2KiB>(
+        &config,
+        &randomness,
+        &[*sector_id],
+        sectors.prover_id,
+    )?;
+    let sector_challenges = &challenges[sector_id];
+    let single_proof = generate_single_vanilla_proof::<SectorShape2KiB>(
+        &config,
+        *sector_id,
+        &sectors.priv_replicas[sector_id],
+        sector_challenges,
+    )?;
+    assert!(verify_single_vanilla_proof::<SectorShape2KiB>(
+        &config,
+        *sector_id,
+        comm_r,
+        sector_challenges,
+        &single_proof,
+    )?);
+
+    let mut wrong_challenges = sector_challenges.clone();
+    wrong_challenges[0] ^= 1;
+    assert!(!verify_single_vanilla_proof::<SectorShape2KiB>(
+        &config,
+        *sector_id,
+        comm_r,
+        &wrong_challenges,
+        &single_proof,
+    )?);
+
+    Ok(())
+}
+
+#[test]
+fn test_generate_window_post_with_vanilla_output_2kib() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let config = window_post_config_2kib(ApiVersion::V1_2_0);
+    let sectors = create_fake_sectors_2kib(&mut rng, config.sector_count, config.api_version)?;
+    let randomness = randomness_from_fr(&Fr::random(&mut rng));
+
+    // The vanilla proofs used for a proof can be returned along with it.
+    let (proof, vanilla_proofs) = generate_window_post_with_vanilla_output::<SectorShape2KiB>(
+        &config,
+        &randomness,
+        &sectors.priv_replicas,
+        sectors.prover_id,
+    )?;
+    assert!(verify_window_post::<SectorShape2KiB>(
+        &config,
+        &randomness,
+        &sectors.pub_replicas,
+        sectors.prover_id,
+        &proof
+    )?);
+
+    let sector_ids: Vec<SectorId> = sectors.priv_replicas.keys().copied().collect();
+    let challenges = generate_fallback_sector_challenges::<SectorShape2KiB>(
         &config,
         &randomness,
-        sector_count as u64,
-        prover_id,
+        &sector_ids,
+        sectors.prover_id,
     )?;
-    assert_eq!(challenged_sectors.len(), sector_count);
-    assert_eq!(challenged_sectors[0], 0); // with a sector_count of 1, the only valid index is 0
+    assert_eq!(vanilla_proofs.len(), sector_ids.len());
+    for (vanilla_proof, (sector_id, _, comm_r, _)) in vanilla_proofs.iter().zip(&sectors.sectors) {
+        assert_eq!(vanilla_proof.sector_id, *sector_id);
+        assert!(verify_single_vanilla_proof::<SectorShape2KiB>(
+            &config,
+            *sector_id,
+            comm_r,
+            &challenges[sector_id],
+            vanilla_proof,
+        )?);
+    }
 
-    let pub_replicas = vec![(sector_id, PublicReplicaInfo::new(comm_r)?)];
-    let private_replica_info =
-        PrivateReplicaInfo::new(replica.path().into(), comm_r, cache_dir.path().into())?;
+    Ok(())
+}
 
-    /////////////////////////////////////////////
-    // The following methods of proof generation are functionally equivalent:
-    // 1)
-    //
-    let priv_replicas = vec![(sector_id, private_replica_info.clone())];
-    let proof = generate_winning_post::<Tree>(&config, &randomness, &priv_replicas[..], prover_id)?;
+#[test]
+fn test_generate_single_vanilla_proof_with_mapped_replica_2kib() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let config = window_post_config_2kib(ApiVersion::V1_2_0);
+    let sectors = create_fake_sectors_2kib(&mut rng, config.sector_count, config.api_version)?;
+    let randomness = randomness_from_fr(&Fr::random(&mut rng));
 
-    let valid =
-        verify_winning_post::<Tree>(&config, &randomness, &pub_replicas[..], prover_id, &proof)?;
-    assert!(valid, "proof did not verify");
+    let sector_ids: Vec<SectorId> = sectors.priv_replicas.keys().copied().collect();
+    let challenges = generate_fallback_sector_challenges::<SectorShape2KiB>(
+        &config,
+        &randomness,
+        &sector_ids,
+        sectors.prover_id,
+    )?;
 
-    //
-    // 2)
-    let mut vanilla_proofs = Vec::with_capacity(sector_count);
-    let challenges =
-        generate_fallback_sector_challenges::<Tree>(&config, &randomness, &[sector_id], prover_id)?;
+    let mut vanilla_proofs = Vec::with_capacity(sector_ids.len());
+    for (sector_id, replica) in sectors.priv_replicas.iter() {
+        // The opened replica can be reused across any number of challenge proofs.
+        let mapped_replica = replica.with_mmap()?;
+        let single_proof = generate_single_vanilla_proof_with_mapped_replica::<SectorShape2KiB>(
+            &config,
+            *sector_id,
+            &mapped_replica,
+            &challenges[sector_id],
+        )?;
+        assert_eq!(
+            serialize(&single_proof)?,
+            serialize(&generate_single_vanilla_proof::<SectorShape2KiB>(
+                &config,
+                *sector_id,
+                replica,
+                &challenges[sector_id],
+            )?)?
+        );
 
-    // Make sure that files can be read-only for a window post.
-    set_readonly_flag(replica.path(), true);
-    set_readonly_flag(cache_dir.path(), true);
+        vanilla_proofs.push(single_proof);
+    }
 
-    let single_proof = generate_single_vanilla_proof::<Tree>(
+    let proof = generate_window_post_with_vanilla::<SectorShape2KiB>(
         &config,
-        sector_id,
-        &private_replica_info,
-        &challenges[&sector_id],
+        &randomness,
+        sectors.prover_id,
+        vanilla_proofs,
     )?;
+    assert!(verify_window_post::<SectorShape2KiB>(
+        &config,
+        &randomness,
+        &sectors.pub_replicas,
+        sectors.prover_id,
+        &proof
+    )?);
 
-    vanilla_proofs.push(single_proof);
+    Ok(())
+}
 
-    let proof = generate_winning_post_with_vanilla::<Tree>(
+#[test]
+fn test_verify_window_post_mixed_2kib() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let config = window_post_config_2kib(ApiVersion::V1_2_0);
+    let sectors = create_fake_sectors_2kib(&mut rng, config.sector_count, config.api_version)?;
+    let randomness = randomness_from_fr(&Fr::random(&mut rng));
+    let proof = generate_window_post::<SectorShape2KiB>(
         &config,
         &randomness,
-        prover_id,
-        vanilla_proofs,
+        &sectors.priv_replicas,
+        sectors.prover_id,
     )?;
-    /////////////////////////////////////////////
 
-    let valid =
-        verify_winning_post::<Tree>(&config, &randomness, &pub_replicas[..], prover_id, &proof)?;
-    assert!(valid, "proof did not verify");
+    // The proof verifies when the sector size is taken from the replica infos.
+    let sized_replicas = sectors
+        .sectors
+        .iter()
+        .map(|(sector_id, _, comm_r, _)| {
+            Ok((
+                *sector_id,
+                PublicReplicaInfo::new_with_sector_size(*comm_r, config.sector_size)?,
+            ))
+        })
+        .collect::<Result<BTreeMap<_, _>>>()?;
+    assert!(verify_window_post_mixed(
+        &randomness,
+        &sized_replicas,
+        sectors.prover_id,
+        &[(config.clone(), proof.clone())],
+    )?);
 
-    // Make files writeable again, so that the temporary directory can be removed.
-    set_readonly_flag(replica.path(), false);
-    set_readonly_flag(cache_dir.path(), false);
+    // Two proofs for the same sector size are rejected.
+    assert!(verify_window_post_mixed(
+        &randomness,
+        &sized_replicas,
+        sectors.prover_id,
+        &[
+            (config.clone(), proof.clone()),
+            (config.clone(), proof.clone())
+        ]
+    )
+    .is_err());
+    // So are replica infos without a sector size.
+    assert!(verify_window_post_mixed(
+        &randomness,
+        &sectors.pub_replicas,
+        sectors.prover_id,
+        &[(config, proof)]
+    )
+    .is_err());
 
-    replica.close()?;
+    Ok(())
+}
+
+#[test]
+fn test_check_window_post_faults_2kib() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let config = window_post_config_2kib(ApiVersion::V1_2_0);
+    let sectors = create_fake_sectors_2kib(&mut rng, config.sector_count, config.api_version)?;
+    let randomness = randomness_from_fr(&Fr::random(&mut rng));
+
+    assert!(check_window_post_faults::<SectorShape2KiB>(
+        &config,
+        &randomness,
+        &sectors.priv_replicas,
+        sectors.prover_id
+    )?
+    .is_empty());
+
+    // Replicas too short to open any challenged node fail completely.
+    let mut bad_replicas = Vec::with_capacity(sectors.sectors.len());
+    let mut priv_faulty_replicas = BTreeMap::new();
+    for (sector_id, _, comm_r, cache_dir) in &sectors.sectors {
+        let bad_replica = NamedTempFile::new()?;
+        bad_replica.as_file().set_len(1)?;
+        priv_faulty_replicas.insert(
+            *sector_id,
+            PrivateReplicaInfo::<SectorShape2KiB>::new(
+                bad_replica.path().into(),
+                *comm_r,
+                cache_dir.path().into(),
+            )?,
+        );
+        bad_replicas.push(bad_replica);
+    }
+    let report = check_window_post_faults::<SectorShape2KiB>(
+        &config,
+        &randomness,
+        &priv_faulty_replicas,
+        sectors.prover_id,
+    )?;
+    assert_eq!(
+        report.faulty_sectors(),
+        priv_faulty_replicas.keys().copied().collect::<Vec<_>>()
+    );
 
     Ok(())
 }
 
 #[test]
-#[ignore]
-fn test_window_post_single_partition_smaller_2kib_base_8() -> Result<()> {
-    let sector_size = SECTOR_SIZE_2_KIB;
-    let sector_count = *WINDOW_POST_SECTOR_COUNT
-        .read()
-        .expect("WINDOW_POST_SECTOR_COUNT poisoned")
-        .get(&sector_size)
-        .expect("unknown sector size");
+fn test_generate_fallback_sector_challenges_batch_2kib() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let config = window_post_config_2kib(ApiVersion::V1_2_0);
+    let prover_id = prover_id_from_fr(&Fr::random(&mut rng));
+    let randomness = randomness_from_fr(&Fr::random(&mut rng));
+    let other_randomness = randomness_from_fr(&Fr::random(&mut rng));
+    let sector_ids: Vec<SectorId> = vec![3, 17, 1000].into_iter().map(SectorId::from).collect();
 
-    let versions = vec![ApiVersion::V1_0_0, ApiVersion::V1_1_0, ApiVersion::V1_2_0];
-    for version in versions {
-        window_post::<SectorShape2KiB>(
-            sector_size,
-            sector_count / 2,
-            sector_count,
-            false,
-            version,
-        )?;
-        window_post::<SectorShape2KiB>(sector_size, sector_count / 2, sector_count, true, version)?;
+    let batch_challenges = generate_fallback_sector_challenges_batch::<SectorShape2KiB>(
+        &config,
+        &[
+            (randomness, prover_id, sector_ids.clone()),
+            (other_randomness, prover_id, sector_ids.clone()),
+        ],
+    )?;
+    assert_eq!(batch_challenges.len(), 2);
+    for (challenges, randomness) in batch_challenges.iter().zip(&[randomness, other_randomness]) {
+        assert_eq!(
+            challenges,
+            &generate_fallback_sector_challenges::<SectorShape2KiB>(
+                &config,
+                randomness,
+                &sector_ids,
+                prover_id,
+            )?
+        );
     }
 
     Ok(())
 }
 
 #[test]
-#[ignore]
-fn test_window_post_two_partitions_matching_2kib_base_8() -> Result<()> {
-    let sector_size = SECTOR_SIZE_2_KIB;
-    let sector_count = *WINDOW_POST_SECTOR_COUNT
-        .read()
-        .expect("WINDOW_POST_SECTOR_COUNT poisoned")
-        .get(&sector_size)
-        .expect("unknown sector size");
+fn test_window_post_challenge_plan_2kib() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let config = window_post_config_2kib(ApiVersion::V1_2_0);
+    let sectors = create_fake_sectors_2kib(&mut rng, config.sector_count, config.api_version)?;
+    let randomness = randomness_from_fr(&Fr::random(&mut rng));
 
-    let versions = vec![ApiVersion::V1_0_0, ApiVersion::V1_1_0, ApiVersion::V1_2_0];
-    for version in versions {
-        window_post::<SectorShape2KiB>(
-            sector_size,
-            2 * sector_count,
-            sector_count,
-            false,
-            version,
+    let plan_sectors = sectors
+        .sectors
+        .iter()
+        .map(|(sector_id, _, comm_r, _)| (*sector_id, *comm_r))
+        .collect::<Vec<_>>();
+    let plan = window_post_challenge_plan::<SectorShape2KiB>(
+        &config,
+        &randomness,
+        &plan_sectors,
+        sectors.prover_id,
+    )?;
+    assert_eq!(plan.len(), plan_sectors.len());
+
+    let sector_ids: Vec<SectorId> = sectors.priv_replicas.keys().copied().collect();
+    let challenges = generate_fallback_sector_challenges::<SectorShape2KiB>(
+        &config,
+        &randomness,
+        &sector_ids,
+        sectors.prover_id,
+    )?;
+    for (sector_id, replica) in sectors.priv_replicas.iter() {
+        let single_proof = generate_single_vanilla_proof::<SectorShape2KiB>(
+            &config,
+            *sector_id,
+            replica,
+            &challenges[sector_id],
         )?;
-        window_post::<SectorShape2KiB>(sector_size, 2 * sector_count, sector_count, true, version)?;
+        // The plan covers every leaf the vanilla proof reads.
+        for inclusion_proof in single_proof.vanilla_proof.sectors[0].inclusion_proofs() {
+            assert!(plan[sector_id]
+                .binary_search(&(inclusion_proof.path_index() as u64))
+                .is_ok());
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_sector_partition_location_2kib() -> Result<()> {
+    let mut config = window_post_config_2kib(ApiVersion::V1_2_0);
+    let sector_count = 2 * config.sector_count + 1;
+
+    let partitions = get_num_partition_for_fallback_post(&config, sector_count);
+    let sector_indices: Vec<usize> = (0..sector_count).collect();
+    for (partition_index, partition_sectors) in
+        sector_indices.chunks(config.sector_count).enumerate()
+    {
+        assert!(partition_index < partitions);
+        for (i, sector_index) in partition_sectors.iter().enumerate() {
+            assert_eq!(
+                sector_partition_location(&config, *sector_index)?,
+                (partition_index, i)
+            );
+        }
     }
 
+    config.sector_count = 0;
+    assert!(sector_partition_location(&config, 0).is_err());
+
     Ok(())
 }
 
@@ -1161,9 +3247,7 @@ fn partition_window_post<Tree: 'static + MerkleTreeTrait>(
     let mut pub_replicas = BTreeMap::new();
     let mut priv_replicas = BTreeMap::new();
 
-    let prover_fr: <Tree::Hasher as Hasher>::Domain = Fr::random(&mut rng).into();
-    let mut prover_id = [0u8; 32];
-    prover_id.copy_from_slice(AsRef::<[u8]>::as_ref(&prover_fr));
+    let prover_id = prover_id_from_fr(&Fr::random(&mut rng));
 
     let porep_id = match api_version {
         ApiVersion::V1_0_0 => ARBITRARY_POREP_ID_V1_0_0,
@@ -1195,9 +3279,7 @@ fn partition_window_post<Tree: 'static + MerkleTreeTrait>(
     assert_eq!(pub_replicas.len(), total_sector_count);
     assert_eq!(sectors.len(), total_sector_count);
 
-    let random_fr: <Tree::Hasher as Hasher>::Domain = Fr::random(&mut rng).into();
-    let mut randomness = [0u8; 32];
-    randomness.copy_from_slice(AsRef::<[u8]>::as_ref(&random_fr));
+    let randomness = randomness_from_fr(&Fr::random(&mut rng));
 
     let config = PoStConfig {
         sector_size: sector_size.into(),
@@ -1275,6 +3357,84 @@ fn partition_window_post<Tree: 'static + MerkleTreeTrait>(
 }
 
 /// Make all files recursively read-only/writeable, starting at the given directory/file.
+#[test]
+fn test_check_window_post_faults_partial_4kib_sub_8_2() -> Result<()> {
+    let sector_size = SECTOR_SIZE_4_KIB;
+    let api_version = ApiVersion::V1_1_0;
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let prover_id = prover_id_from_fr(&Fr::random(&mut rng));
+
+    let (sector_id, replica, comm_r, cache_dir) = create_seal::<_, SectorShape4KiB>(
+        &mut rng,
+        sector_size,
+        prover_id,
+        true,
+        &ARBITRARY_POREP_ID_V1_1_0,
+        api_version,
+    )?;
+
+    let config = PoStConfig {
+        sector_size: sector_size.into(),
+        sector_count: 1,
+        challenge_count: WINDOW_POST_CHALLENGE_COUNT,
+        typ: PoStType::Window,
+        priority: false,
+        api_version,
+    };
+    let randomness = randomness_from_fr(&Fr::random(&mut rng));
+    let challenges = generate_fallback_sector_challenges::<SectorShape4KiB>(
+        &config,
+        &randomness,
+        &[sector_id],
+        prover_id,
+    )?
+    .remove(&sector_id)
+    .expect("missing challenges");
+
+    // Corrupt the node of the first challenge in an otherwise valid copy of the replica.
+    let corrupted_node = challenges[0] as usize;
+    let mut replica_data = std::fs::read(replica.path())?;
+    replica_data[corrupted_node * NODE_SIZE] ^= 1;
+    let faulty_replica = NamedTempFile::new()?;
+    std::fs::write(faulty_replica.path(), &replica_data)?;
+
+    let mut priv_replicas = BTreeMap::new();
+    priv_replicas.insert(
+        sector_id,
+        PrivateReplicaInfo::<SectorShape4KiB>::new(
+            faulty_replica.path().into(),
+            comm_r,
+            cache_dir.path().into(),
+        )?,
+    );
+
+    // The base trees are rebuilt from the replica when proving, while their roots are read from
+    // the cache. So exactly the challenges within the base tree of the corrupted node fail.
+    let base_tree_leafs =
+        sector_size as usize / NODE_SIZE / get_base_tree_count::<SectorShape4KiB>();
+    let failed_challenges: Vec<u64> = challenges
+        .iter()
+        .copied()
+        .filter(|challenge| {
+            *challenge as usize / base_tree_leafs == corrupted_node / base_tree_leafs
+        })
+        .collect();
+
+    let report = check_window_post_faults::<SectorShape4KiB>(
+        &config,
+        &randomness,
+        &priv_replicas,
+        prover_id,
+    )?;
+    assert_eq!(report.faulty_sectors(), vec![sector_id]);
+    assert_eq!(
+        report.faults[&sector_id],
+        SectorFault::Challenges(failed_challenges)
+    );
+
+    Ok(())
+}
+
 fn set_readonly_flag(path: &Path, readonly: bool) {
     for entry in walkdir::WalkDir::new(path) {
         let entry = entry.expect("couldn't get file");
@@ -1301,9 +3461,7 @@ fn window_post<Tree: 'static + MerkleTreeTrait>(
     let mut priv_replicas = BTreeMap::new();
     let mut priv_faulty_replicas = BTreeMap::new();
 
-    let prover_fr: <Tree::Hasher as Hasher>::Domain = Fr::random(&mut rng).into();
-    let mut prover_id = [0u8; 32];
-    prover_id.copy_from_slice(AsRef::<[u8]>::as_ref(&prover_fr));
+    let prover_id = prover_id_from_fr(&Fr::random(&mut rng));
 
     let porep_id = match api_version {
         ApiVersion::V1_0_0 => ARBITRARY_POREP_ID_V1_0_0,
@@ -1355,9 +3513,7 @@ fn window_post<Tree: 'static + MerkleTreeTrait>(
     assert_eq!(pub_replicas.len(), total_sector_count);
     assert_eq!(sectors.len(), total_sector_count);
 
-    let random_fr: <Tree::Hasher as Hasher>::Domain = Fr::random(&mut rng).into();
-    let mut randomness = [0u8; 32];
-    randomness.copy_from_slice(AsRef::<[u8]>::as_ref(&random_fr));
+    let randomness = randomness_from_fr(&Fr::random(&mut rng));
 
     let config = PoStConfig {
         sector_size: sector_size.into(),
@@ -1771,61 +3927,169 @@ fn create_seal_for_aggregation<R: Rng, Tree: 'static + MerkleTreeTrait>(
         ticket,
         &cache_dir,
         &mut piece_file,
-        &sealed_sector_file,
+        &sealed_sector_file,
+    )?;
+
+    let pre_commit_output = seal_pre_commit_phase2(
+        &config,
+        phase1_output,
+        cache_dir.path(),
+        sealed_sector_file.path(),
+    )?;
+
+    validate_cache_for_commit::<_, _, Tree>(cache_dir.path(), sealed_sector_file.path())?;
+
+    generate_proof::<Tree>(
+        &config,
+        cache_dir.path(),
+        &sealed_sector_file,
+        prover_id,
+        sector_id,
+        ticket,
+        seed,
+        &pre_commit_output,
+        &piece_infos,
+    )
+}
+
+// A 2KiB sector sealed up to commit phase1, for the tests of single seal proof features. The
+// files are removed when it is dropped.
+struct CommittedSector2KiB {
+    config: PoRepConfig,
+    prover_id: ProverId,
+    sector_id: SectorId,
+    ticket: [u8; 32],
+    seed: [u8; 32],
+    replica: NamedTempFile,
+    cache_dir: TempDir,
+    piece_infos: Vec<PieceInfo>,
+    pre_commit_output: SealPreCommitOutput,
+    phase1_output: SealCommitPhase1Output<SectorShape2KiB>,
+}
+
+impl CommittedSector2KiB {
+    fn prove(&self) -> Result<SealCommitOutput> {
+        seal_commit_phase2(
+            &self.config,
+            self.phase1_output.clone(),
+            self.prover_id,
+            self.sector_id,
+        )
+    }
+}
+
+fn create_committed_sector_2kib<R: Rng>(rng: &mut R) -> Result<CommittedSector2KiB> {
+    fil_logger::maybe_init();
+
+    let sector_size = SECTOR_SIZE_2_KIB;
+    let config = porep_config(sector_size, ARBITRARY_POREP_ID_V1_1_0, ApiVersion::V1_1_0);
+    let prover_id = prover_id_from_fr(&Fr::random(&mut *rng));
+    let sector_id = rng.gen::<u64>().into();
+    let ticket = rng.gen();
+    let seed = rng.gen();
+
+    let (mut piece_file, _) = generate_piece_file(sector_size)?;
+    let replica = NamedTempFile::new()?;
+    let cache_dir = tempdir()?;
+    let (piece_infos, phase1_output) = run_seal_pre_commit_phase1::<SectorShape2KiB>(
+        &config,
+        prover_id,
+        sector_id,
+        ticket,
+        &cache_dir,
+        &mut piece_file,
+        &replica,
     )?;
+    let pre_commit_output =
+        seal_pre_commit_phase2(&config, phase1_output, cache_dir.path(), replica.path())?;
 
-    let pre_commit_output = seal_pre_commit_phase2(
+    let phase1_output = seal_commit_phase1::<_, SectorShape2KiB>(
         &config,
-        phase1_output,
         cache_dir.path(),
-        sealed_sector_file.path(),
+        replica.path(),
+        prover_id,
+        sector_id,
+        ticket,
+        seed,
+        pre_commit_output.clone(),
+        &piece_infos,
     )?;
 
-    validate_cache_for_commit::<_, _, Tree>(cache_dir.path(), sealed_sector_file.path())?;
-
-    generate_proof::<Tree>(
-        &config,
-        cache_dir.path(),
-        &sealed_sector_file,
+    Ok(CommittedSector2KiB {
+        config,
         prover_id,
         sector_id,
         ticket,
         seed,
-        &pre_commit_output,
-        &piece_infos,
-    )
+        replica,
+        cache_dir,
+        piece_infos,
+        pre_commit_output,
+        phase1_output,
+    })
+}
+
+// Two copies of a 2KiB seal proof aggregated with SnarkPack v2, for the tests of single
+// aggregation features.
+struct AggregatedSeals2KiB {
+    config: PoRepConfig,
+    comm_rs: Vec<[u8; 32]>,
+    seeds: Vec<[u8; 32]>,
+    commit_outputs: Vec<SealCommitOutput>,
+    commit_input: Vec<Vec<Fr>>,
+    commit_inputs: Vec<Vec<Fr>>,
+    aggregate_proof: Vec<u8>,
+}
+
+const AGGREGATED_SEALS_2KIB_COUNT: usize = 2;
+
+fn create_aggregated_seals_2kib<R: Rng>(rng: &mut R) -> Result<AggregatedSeals2KiB> {
+    let prover_id = prover_id_from_fr(&Fr::random(&mut *rng));
+    let api_version = ApiVersion::V1_1_0;
+    let config = porep_config(SECTOR_SIZE_2_KIB, ARBITRARY_POREP_ID_V1_1_0, api_version);
+
+    let (commit_output, commit_input, seed, comm_r) =
+        create_seal_for_aggregation::<_, SectorShape2KiB>(
+            rng,
+            SECTOR_SIZE_2_KIB,
+            prover_id,
+            &ARBITRARY_POREP_ID_V1_1_0,
+            api_version,
+        )?;
+
+    let commit_outputs = vec![commit_output; AGGREGATED_SEALS_2KIB_COUNT];
+    let commit_inputs = (0..AGGREGATED_SEALS_2KIB_COUNT)
+        .flat_map(|_| commit_input.clone())
+        .collect();
+    let seeds = vec![seed; AGGREGATED_SEALS_2KIB_COUNT];
+    let comm_rs = vec![comm_r; AGGREGATED_SEALS_2KIB_COUNT];
+
+    let aggregate_proof = aggregate_seal_commit_proofs::<SectorShape2KiB>(
+        &config,
+        &comm_rs,
+        &seeds,
+        &commit_outputs,
+        groth16::aggregate::AggregateVersion::V2,
+    )?;
+
+    Ok(AggregatedSeals2KiB {
+        config,
+        comm_rs,
+        seeds,
+        commit_outputs,
+        commit_input,
+        commit_inputs,
+        aggregate_proof,
+    })
 }
 
 fn compare_elements(path1: &Path, path2: &Path) -> Result<(), Error> {
     info!("Comparing elements between {:?} and {:?}", path1, path2);
-    let f_data1 = OpenOptions::new()
-        .read(true)
-        .open(path1)
-        .with_context(|| format!("could not open path={:?}", path1))?;
-    let data1 = unsafe {
-        MmapOptions::new()
-            .map(&f_data1)
-            .with_context(|| format!("could not mmap path={:?}", path1))
-    }?;
-    let f_data2 = OpenOptions::new()
-        .read(true)
-        .open(path2)
-        .with_context(|| format!("could not open path={:?}", path2))?;
-    let data2 = unsafe {
-        MmapOptions::new()
-            .map(&f_data2)
-            .with_context(|| format!("could not mmap path={:?}", path2))
-    }?;
-    let fr_size = std::mem::size_of::<Fr>();
-    let end = metadata(path1)?.len();
-    ensure!(metadata(path2)?.len() == end, "File sizes must match");
-
-    for i in (0..end).step_by(fr_size) {
-        let index = i as usize;
-        let fr1 = bytes_into_fr(&data1[index..index + fr_size])?;
-        let fr2 = bytes_into_fr(&data2[index..index + fr_size])?;
-        ensure!(fr1 == fr2, "Data mismatch when comparing elements");
-    }
+    let mismatches = diff_field_element_files(path1, path2, true, 1)?;
+    ensure!(
+        mismatches.is_empty(),
+        "Data mismatch when comparing elements"
+    );
     info!("Match found for {:?} and {:?}", path1, path2);
 
     Ok(())
@@ -1950,114 +4214,644 @@ fn create_seal_for_upgrade<R: Rng, Tree: 'static + MerkleTreeTrait<Hasher = Tree
         new_cache_dir.path(),
     )?;
 
-    // Verify all partition proofs
-    let proofs_are_valid = verify_partition_proofs::<Tree>(
-        config,
+    // Verify all partition proofs
+    let proofs_are_valid = verify_partition_proofs::<Tree>(
+        config,
+        &partition_proofs,
+        comm_r,
+        encoded.comm_r_new,
+        encoded.comm_d_new,
+    )?;
+    ensure!(proofs_are_valid, "Partition proofs failed to verify");
+
+    let proof = generate_empty_sector_update_proof_with_vanilla::<Tree>(
+        &porep_config,
+        partition_proofs,
+        comm_r,
+        encoded.comm_r_new,
+        encoded.comm_d_new,
+    )?;
+    let valid = verify_empty_sector_update_proof::<Tree>(
+        &porep_config,
+        &proof.0,
+        comm_r,
+        encoded.comm_r_new,
+        encoded.comm_d_new,
+    )?;
+    ensure!(valid, "Compound proof failed to verify");
+
+    let proof = generate_empty_sector_update_proof::<Tree>(
+        &porep_config,
+        comm_r,
+        encoded.comm_r_new,
+        encoded.comm_d_new,
+        sealed_sector_file.path(), /* sector key file */
+        cache_dir.path(),          /* sector key path needed for p_aux and t_aux */
+        new_sealed_sector_file.path(),
+        new_cache_dir.path(),
+    )?;
+    let valid = verify_empty_sector_update_proof::<Tree>(
+        &porep_config,
+        &proof.0,
+        comm_r,
+        encoded.comm_r_new,
+        encoded.comm_d_new,
+    )?;
+    ensure!(valid, "Compound proof failed to verify");
+
+    let decoded_sector_file = NamedTempFile::new()?;
+    // New replica (new_sealed_sector_file) is currently 0 bytes --
+    // set a length here to ensure proper mmap later.  Lotus will
+    // already be passing in a destination path of the proper size in
+    // the future, so this is a test specific work-around.
+    let decoded_sector_target_len = metadata(&sealed_sector_file)?.len();
+    let f_decoded_sector = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .open(decoded_sector_file.path())
+        .with_context(|| format!("could not open path={:?}", decoded_sector_file.path()))?;
+    f_decoded_sector.set_len(decoded_sector_target_len)?;
+
+    decode_from::<Tree>(
+        config,
+        decoded_sector_file.path(),
+        new_sealed_sector_file.path(),
+        sealed_sector_file.path(),
+        cache_dir.path(), /* sector key path needed for p_aux (for comm_c/comm_r_last) */
+        encoded.comm_d_new,
+    )?;
+    // When the data is decoded, it MUST match the original new staged data.
+    compare_elements(decoded_sector_file.path(), new_staged_sector_file.path())?;
+
+    decoded_sector_file.close()?;
+
+    // Remove Data here
+    let remove_encoded_file = NamedTempFile::new()?;
+    let remove_encoded_cache_dir = tempdir().expect("failed to create temp dir");
+    // New replica (new_sealed_sector_file) is currently 0 bytes --
+    // set a length here to ensure proper mmap later.  Lotus will
+    // already be passing in a destination path of the proper size in
+    // the future, so this is a test specific work-around.
+    let remove_encoded_target_len = metadata(&sealed_sector_file)?.len();
+    let f_remove_encoded = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .open(remove_encoded_file.path())
+        .with_context(|| format!("could not open path={:?}", remove_encoded_file.path()))?;
+    f_remove_encoded.set_len(remove_encoded_target_len)?;
+
+    // Note: we pass cache_dir to the remove, which is the original
+    // dir where the data was sealed (for p_aux/t_aux).
+    remove_encoded_data::<Tree>(
+        config,
+        remove_encoded_file.path(),
+        remove_encoded_cache_dir.path(),
+        new_sealed_sector_file.path(),
+        cache_dir.path(),
+        new_staged_sector_file.path(),
+        encoded.comm_d_new,
+    )?;
+    // When the data is removed, it MUST match the original sealed data.
+    compare_elements(remove_encoded_file.path(), sealed_sector_file.path())?;
+
+    remove_encoded_file.close()?;
+
+    clear_cache::<Tree>(cache_dir.path())?;
+    clear_cache::<Tree>(new_cache_dir.path())?;
+
+    Ok((sector_id, sealed_sector_file, comm_r, cache_dir))
+}
+
+// A 2KiB sector key updated with new staged data, for the tests of single sector update
+// features. The files are removed when it is dropped.
+struct UpdatedSector2KiB {
+    porep_config: PoRepConfig,
+    config: SectorUpdateConfig,
+    prover_id: ProverId,
+    sector_id: SectorId,
+    sector_key: NamedTempFile,
+    sector_key_cache_dir: TempDir,
+    comm_r: Commitment,
+    staged_data: NamedTempFile,
+    piece_infos: Vec<PieceInfo>,
+    replica: NamedTempFile,
+    replica_cache_dir: TempDir,
+    encoded: EmptySectorUpdateEncoded,
+}
+
+impl UpdatedSector2KiB {
+    fn partition_proofs(&self) -> Result<Vec<PartitionProof<SectorShape2KiB>>> {
+        generate_partition_proofs::<SectorShape2KiB>(
+            self.config,
+            self.comm_r,
+            self.encoded.comm_r_new,
+            self.encoded.comm_d_new,
+            self.sector_key.path(),
+            self.sector_key_cache_dir.path(),
+            self.replica.path(),
+            self.replica_cache_dir.path(),
+        )
+    }
+}
+
+fn create_updated_sector_2kib<R: Rng>(rng: &mut R) -> Result<UpdatedSector2KiB> {
+    fil_logger::maybe_init();
+
+    let sector_size = SECTOR_SIZE_2_KIB;
+    let porep_config = porep_config(sector_size, ARBITRARY_POREP_ID_V1_2_0, ApiVersion::V1_2_0);
+    let config = SectorUpdateConfig::from_porep_config(&porep_config);
+    let prover_id = prover_id_from_fr(&Fr::random(&mut *rng));
+    let sector_id = rng.gen::<u64>().into();
+
+    let (mut piece_file, _) = generate_piece_file(sector_size)?;
+    let sector_key = NamedTempFile::new()?;
+    let sector_key_cache_dir = tempdir()?;
+    let (_, phase1_output) = run_seal_pre_commit_phase1::<SectorShape2KiB>(
+        &porep_config,
+        prover_id,
+        sector_id,
+        rng.gen(),
+        &sector_key_cache_dir,
+        &mut piece_file,
+        &sector_key,
+    )?;
+    let comm_r = seal_pre_commit_phase2(
+        &porep_config,
+        phase1_output,
+        sector_key_cache_dir.path(),
+        sector_key.path(),
+    )?
+    .comm_r;
+
+    let (mut new_piece_file, _) = generate_piece_file(sector_size)?;
+    let number_of_bytes_in_piece = porep_config.unpadded_bytes_amount();
+    let piece_info =
+        generate_piece_commitment(new_piece_file.as_file_mut(), number_of_bytes_in_piece)?;
+    new_piece_file.as_file_mut().rewind()?;
+    let mut staged_data = NamedTempFile::new()?;
+    add_piece(
+        &mut new_piece_file,
+        &mut staged_data,
+        number_of_bytes_in_piece,
+        &[],
+    )?;
+    let piece_infos = vec![piece_info];
+
+    let replica = NamedTempFile::new()?;
+    replica
+        .as_file()
+        .set_len(metadata(sector_key.path())?.len())?;
+    let replica_cache_dir = tempdir()?;
+    let encoded = encode_into::<SectorShape2KiB>(
+        &porep_config,
+        replica.path(),
+        replica_cache_dir.path(),
+        sector_key.path(),
+        sector_key_cache_dir.path(),
+        staged_data.path(),
+        &piece_infos,
+    )?;
+
+    Ok(UpdatedSector2KiB {
+        porep_config,
+        config,
+        prover_id,
+        sector_id,
+        sector_key,
+        sector_key_cache_dir,
+        comm_r,
+        staged_data,
+        piece_infos,
+        replica,
+        replica_cache_dir,
+        encoded,
+    })
+}
+
+#[test]
+fn test_verify_sector_update_roundtrip_2kib() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let sector = create_updated_sector_2kib(&mut rng)?;
+
+    let roundtrip_dir = tempdir()?;
+    assert!(verify_sector_update_roundtrip::<SectorShape2KiB>(
+        &sector.porep_config,
+        sector.sector_key.path(),
+        sector.sector_key_cache_dir.path(),
+        sector.staged_data.path(),
+        &sector.piece_infos,
+        roundtrip_dir.path(),
+    )?);
+
+    Ok(())
+}
+
+#[test]
+fn test_compute_comm_d_new_2kib() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let sector = create_updated_sector_2kib(&mut rng)?;
+
+    let comm_d_new = compute_comm_d_new::<SectorShape2KiB>(
+        &sector.porep_config,
+        sector.staged_data.path(),
+        &sector.piece_infos,
+    )?;
+    assert_eq!(comm_d_new, sector.encoded.comm_d_new);
+
+    Ok(())
+}
+
+#[test]
+fn test_classify_cache_2kib() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let sector = create_updated_sector_2kib(&mut rng)?;
+
+    assert_eq!(
+        classify_cache::<SectorShape2KiB>(sector.sector_key_cache_dir.path())?,
+        CacheKind::Sealed
+    );
+    assert_eq!(
+        classify_cache::<SectorShape2KiB>(sector.replica_cache_dir.path())?,
+        CacheKind::Updated
+    );
+    let empty_cache_dir = tempdir()?;
+    assert_eq!(
+        classify_cache::<SectorShape2KiB>(empty_cache_dir.path())?,
+        CacheKind::Unknown
+    );
+
+    // Without the marker, an updated cache cannot be told apart from a cleared sealed one.
+    remove_file(sector.replica_cache_dir.path().join("update-marker"))?;
+    assert_eq!(
+        classify_cache::<SectorShape2KiB>(sector.replica_cache_dir.path())?,
+        CacheKind::Unknown
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_sector_update_challenges_2kib() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let sector = create_updated_sector_2kib(&mut rng)?;
+    let partition_proof = generate_single_partition_proof::<SectorShape2KiB>(
+        sector.config,
+        0,
+        sector.comm_r,
+        sector.encoded.comm_r_new,
+        sector.encoded.comm_d_new,
+        sector.sector_key.path(),
+        sector.sector_key_cache_dir.path(),
+        sector.replica.path(),
+        sector.replica_cache_dir.path(),
+    )?;
+
+    // The exposed challenges are exactly the ones proven by the partition proof.
+    let challenges =
+        sector_update_challenges::<SectorShape2KiB>(sector.config, sector.encoded.comm_r_new, 0)?;
+    let proven: Vec<u64> = partition_proof
+        .challenge_proofs
+        .iter()
+        .map(|challenge_proof| challenge_proof.proof_r_new.path_index() as u64)
+        .collect();
+    assert_eq!(challenges, proven);
+
+    assert!(sector_update_challenges::<SectorShape2KiB>(
+        sector.config,
+        sector.encoded.comm_r_new,
+        usize::from(sector.config.update_partitions)
+    )
+    .is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_verify_partition_proofs_from_parts_2kib() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let sector = create_updated_sector_2kib(&mut rng)?;
+    let partition_proofs = sector.partition_proofs()?;
+
+    // Verifying with the parts of comm_r from the p_aux of the sector key agrees.
+    let p_aux: PersistentAux<TreeRDomain> = deserialize(&std::fs::read(
+        sector
+            .sector_key_cache_dir
+            .path()
+            .join(CacheKey::PAux.to_string()),
+    )?)?;
+    let comm_c = fr_to_commitment(&p_aux.comm_c.into());
+    let comm_r_last = fr_to_commitment(&p_aux.comm_r_last.into());
+    assert!(verify_partition_proofs_from_parts::<SectorShape2KiB>(
+        sector.config,
+        &partition_proofs,
+        comm_c,
+        comm_r_last,
+        sector.encoded.comm_r_new,
+        sector.encoded.comm_d_new,
+    )?);
+    assert!(verify_single_partition_proof_from_parts::<SectorShape2KiB>(
+        sector.config,
+        0,
+        partition_proofs[0].clone(),
+        comm_c,
+        comm_r_last,
+        sector.encoded.comm_r_new,
+        sector.encoded.comm_d_new,
+    )?);
+
+    assert!(!verify_partition_proofs_from_parts::<SectorShape2KiB>(
+        sector.config,
+        &partition_proofs,
+        comm_r_last,
+        comm_c,
+        sector.encoded.comm_r_new,
+        sector.encoded.comm_d_new,
+    )?);
+
+    Ok(())
+}
+
+#[test]
+fn test_validate_partition_proof_set_2kib() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let sector = create_updated_sector_2kib(&mut rng)?;
+    let partition_proofs = sector.partition_proofs()?;
+
+    validate_partition_proof_set::<SectorShape2KiB>(sector.config, &partition_proofs)?;
+
+    // Assemblies with a missing or duplicated partition proof are rejected.
+    assert!(
+        validate_partition_proof_set::<SectorShape2KiB>(sector.config, &partition_proofs[1..])
+            .is_err()
+    );
+    let mut duplicated = partition_proofs.clone();
+    duplicated.push(partition_proofs[0].clone());
+    assert!(validate_partition_proof_set::<SectorShape2KiB>(sector.config, &duplicated).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_partition_proofs_iter_2kib() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let sector = create_updated_sector_2kib(&mut rng)?;
+
+    // Lazily generated partition proofs verify like the eager ones.
+    let partition_proofs = partition_proofs_iter::<SectorShape2KiB>(
+        sector.config,
+        sector.comm_r,
+        sector.encoded.comm_r_new,
+        sector.encoded.comm_d_new,
+        sector.sector_key.path(),
+        sector.sector_key_cache_dir.path(),
+        sector.replica.path(),
+        sector.replica_cache_dir.path(),
+    )
+    .collect::<Result<Vec<_>>>()?;
+    assert_eq!(
+        partition_proofs.len(),
+        usize::from(sector.config.update_partitions)
+    );
+    assert!(verify_partition_proofs::<SectorShape2KiB>(
+        sector.config,
         &partition_proofs,
-        comm_r,
-        encoded.comm_r_new,
-        encoded.comm_d_new,
-    )?;
-    ensure!(proofs_are_valid, "Partition proofs failed to verify");
+        sector.comm_r,
+        sector.encoded.comm_r_new,
+        sector.encoded.comm_d_new,
+    )?);
 
-    let proof = generate_empty_sector_update_proof_with_vanilla::<Tree>(
-        &porep_config,
+    Ok(())
+}
+
+#[test]
+fn test_verify_partition_proofs_from_bytes_2kib() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let sector = create_updated_sector_2kib(&mut rng)?;
+    let partition_proofs = sector.partition_proofs()?;
+
+    // Serialized partition proofs verify identically.
+    let serialized_partition_proofs = partition_proofs
+        .iter()
+        .map(serialize)
+        .collect::<Result<Vec<_>, _>>()?;
+    assert!(verify_partition_proofs_from_bytes::<SectorShape2KiB>(
+        sector.config,
+        &serialized_partition_proofs,
+        sector.comm_r,
+        sector.encoded.comm_r_new,
+        sector.encoded.comm_d_new,
+    )?);
+    assert!(verify_partition_proofs_from_bytes::<SectorShape2KiB>(
+        sector.config,
+        &[vec![0u8; 3]],
+        sector.comm_r,
+        sector.encoded.comm_r_new,
+        sector.encoded.comm_d_new,
+    )
+    .is_err());
+
+    // Partition proofs can be generated elsewhere and shipped to the snark prover.
+    let partition_proofs: Vec<PartitionProof<SectorShape2KiB>> =
+        deserialize(&serialize(&partition_proofs)?)?;
+    let proof = generate_empty_sector_update_proof_with_vanilla::<SectorShape2KiB>(
+        &sector.porep_config,
         partition_proofs,
-        comm_r,
-        encoded.comm_r_new,
-        encoded.comm_d_new,
+        sector.comm_r,
+        sector.encoded.comm_r_new,
+        sector.encoded.comm_d_new,
     )?;
-    let valid = verify_empty_sector_update_proof::<Tree>(
-        &porep_config,
+    assert!(verify_empty_sector_update_proof::<SectorShape2KiB>(
+        &sector.porep_config,
         &proof.0,
-        comm_r,
-        encoded.comm_r_new,
-        encoded.comm_d_new,
+        sector.comm_r,
+        sector.encoded.comm_r_new,
+        sector.encoded.comm_d_new,
+    )?);
+
+    Ok(())
+}
+
+#[test]
+fn test_generate_partition_proofs_in_pool_2kib() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let sector = create_updated_sector_2kib(&mut rng)?;
+
+    // The partition proofs do not depend on the thread pool they are generated in.
+    let pool = ThreadPoolBuilder::new().num_threads(2).build()?;
+    let pool_partition_proofs = generate_partition_proofs_in_pool::<SectorShape2KiB>(
+        &pool,
+        sector.config,
+        sector.comm_r,
+        sector.encoded.comm_r_new,
+        sector.encoded.comm_d_new,
+        sector.sector_key.path(),
+        sector.sector_key_cache_dir.path(),
+        sector.replica.path(),
+        sector.replica_cache_dir.path(),
     )?;
-    ensure!(valid, "Compound proof failed to verify");
+    assert_eq!(
+        serialize(&pool_partition_proofs)?,
+        serialize(&sector.partition_proofs()?)?
+    );
 
-    let proof = generate_empty_sector_update_proof::<Tree>(
-        &porep_config,
-        comm_r,
-        encoded.comm_r_new,
-        encoded.comm_d_new,
-        sealed_sector_file.path(), /* sector key file */
-        cache_dir.path(),          /* sector key path needed for p_aux and t_aux */
-        new_sealed_sector_file.path(),
-        new_cache_dir.path(),
+    Ok(())
+}
+
+#[test]
+fn test_verify_decoded_matches_comm_d_2kib() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let sector = create_updated_sector_2kib(&mut rng)?;
+
+    let decoded = NamedTempFile::new()?;
+    decoded
+        .as_file()
+        .set_len(metadata(sector.sector_key.path())?.len())?;
+    decode_from::<SectorShape2KiB>(
+        sector.config,
+        decoded.path(),
+        sector.replica.path(),
+        sector.sector_key.path(),
+        sector.sector_key_cache_dir.path(),
+        sector.encoded.comm_d_new,
     )?;
-    let valid = verify_empty_sector_update_proof::<Tree>(
-        &porep_config,
-        &proof.0,
-        comm_r,
-        encoded.comm_r_new,
-        encoded.comm_d_new,
+
+    // The decoded data can be checked with comm_d_new only.
+    assert!(verify_decoded_matches_comm_d::<SectorShape2KiB>(
+        sector.config,
+        decoded.path(),
+        sector.encoded.comm_d_new
+    )?);
+    let mut wrong_comm_d_new = sector.encoded.comm_d_new;
+    wrong_comm_d_new[0] ^= 1;
+    assert!(!verify_decoded_matches_comm_d::<SectorShape2KiB>(
+        sector.config,
+        decoded.path(),
+        wrong_comm_d_new
+    )?);
+
+    Ok(())
+}
+
+#[test]
+fn test_decode_from_parallel_2kib() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let sector = create_updated_sector_2kib(&mut rng)?;
+
+    // Decoding on a dedicated pool must yield the staged data.
+    let decoded = NamedTempFile::new()?;
+    decoded
+        .as_file()
+        .set_len(metadata(sector.sector_key.path())?.len())?;
+    decode_from_parallel::<SectorShape2KiB>(
+        sector.config,
+        decoded.path(),
+        sector.replica.path(),
+        sector.sector_key.path(),
+        sector.sector_key_cache_dir.path(),
+        sector.encoded.comm_d_new,
+        2,
     )?;
-    ensure!(valid, "Compound proof failed to verify");
+    compare_elements(decoded.path(), sector.staged_data.path())?;
 
-    let decoded_sector_file = NamedTempFile::new()?;
-    // New replica (new_sealed_sector_file) is currently 0 bytes --
-    // set a length here to ensure proper mmap later.  Lotus will
-    // already be passing in a destination path of the proper size in
-    // the future, so this is a test specific work-around.
-    let decoded_sector_target_len = metadata(&sealed_sector_file)?.len();
-    let f_decoded_sector = OpenOptions::new()
-        .read(true)
-        .write(true)
-        .create(true)
-        .open(decoded_sector_file.path())
-        .with_context(|| format!("could not open path={:?}", decoded_sector_file.path()))?;
-    f_decoded_sector.set_len(decoded_sector_target_len)?;
+    Ok(())
+}
 
-    decode_from::<Tree>(
-        config,
-        decoded_sector_file.path(),
-        new_sealed_sector_file.path(),
-        sealed_sector_file.path(),
-        cache_dir.path(), /* sector key path needed for p_aux (for comm_c/comm_r_last) */
-        encoded.comm_d_new,
+#[test]
+fn test_sector_update_file_access_plan_2kib() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let sector = create_updated_sector_2kib(&mut rng)?;
+
+    let sector_key = NamedTempFile::new()?;
+    sector_key
+        .as_file()
+        .set_len(metadata(sector.sector_key.path())?.len())?;
+    let sector_key_cache_dir = tempdir()?;
+    remove_encoded_data::<SectorShape2KiB>(
+        sector.config,
+        sector_key.path(),
+        sector_key_cache_dir.path(),
+        sector.replica.path(),
+        sector.sector_key_cache_dir.path(),
+        sector.staged_data.path(),
+        sector.encoded.comm_d_new,
     )?;
-    // When the data is decoded, it MUST match the original new staged data.
-    compare_elements(decoded_sector_file.path(), new_staged_sector_file.path())?;
 
-    decoded_sector_file.close()?;
+    // The access plan covers every file the removal touched, and nothing else was written.
+    let plan = sector_update_file_access_plan::<SectorShape2KiB>(&UpdateOp::RemoveEncodedData {
+        sector_key_path: sector_key.path().to_path_buf(),
+        sector_key_cache_path: sector_key_cache_dir.path().to_path_buf(),
+        replica_path: sector.replica.path().to_path_buf(),
+        replica_cache_path: sector.sector_key_cache_dir.path().to_path_buf(),
+        data_path: sector.staged_data.path().to_path_buf(),
+    });
+    for path in plan.reads.iter().chain(plan.writes.iter()) {
+        assert!(path.is_file(), "planned file {:?} does not exist", path);
+    }
+    for entry in read_dir(sector_key_cache_dir.path())? {
+        let path = entry?.path();
+        assert!(plan.writes.contains(&path), "unplanned write to {:?}", path);
+    }
 
-    // Remove Data here
-    let remove_encoded_file = NamedTempFile::new()?;
-    let remove_encoded_cache_dir = tempdir().expect("failed to create temp dir");
-    // New replica (new_sealed_sector_file) is currently 0 bytes --
-    // set a length here to ensure proper mmap later.  Lotus will
-    // already be passing in a destination path of the proper size in
-    // the future, so this is a test specific work-around.
-    let remove_encoded_target_len = metadata(&sealed_sector_file)?.len();
-    let f_remove_encoded = OpenOptions::new()
-        .read(true)
-        .write(true)
-        .create(true)
-        .open(remove_encoded_file.path())
-        .with_context(|| format!("could not open path={:?}", remove_encoded_file.path()))?;
-    f_remove_encoded.set_len(remove_encoded_target_len)?;
+    Ok(())
+}
 
-    // Note: we pass cache_dir to the remove, which is the original
-    // dir where the data was sealed (for p_aux/t_aux).
-    remove_encoded_data::<Tree>(
-        config,
-        remove_encoded_file.path(),
-        remove_encoded_cache_dir.path(),
-        new_sealed_sector_file.path(),
-        cache_dir.path(),
-        new_staged_sector_file.path(),
-        encoded.comm_d_new,
-    )?;
-    // When the data is removed, it MUST match the original sealed data.
-    compare_elements(remove_encoded_file.path(), sealed_sector_file.path())?;
+#[test]
+fn test_window_post_with_updated_replica_2kib() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let sector = create_updated_sector_2kib(&mut rng)?;
 
-    remove_encoded_file.close()?;
+    // A window post can cover both the sector key and the updated replica at once.
+    let updated_sector_id = SectorId::from(u64::from(sector.sector_id).wrapping_add(1));
+    let mut priv_replicas = BTreeMap::new();
+    priv_replicas.insert(
+        sector.sector_id,
+        PrivateReplicaInfo::<SectorShape2KiB>::new(
+            sector.sector_key.path().into(),
+            sector.comm_r,
+            sector.sector_key_cache_dir.path().into(),
+        )?,
+    );
+    priv_replicas.insert(
+        updated_sector_id,
+        PrivateReplicaInfo::<SectorShape2KiB>::new(
+            sector.replica.path().into(),
+            sector.encoded.comm_r_new,
+            sector.replica_cache_dir.path().into(),
+        )?,
+    );
+    let mut pub_replicas = BTreeMap::new();
+    pub_replicas.insert(sector.sector_id, PublicReplicaInfo::new(sector.comm_r)?);
+    pub_replicas.insert(
+        updated_sector_id,
+        PublicReplicaInfo::new(sector.encoded.comm_r_new)?,
+    );
 
-    clear_cache::<Tree>(cache_dir.path())?;
-    clear_cache::<Tree>(new_cache_dir.path())?;
+    let randomness = randomness_from_fr(&Fr::random(&mut rng));
+    let config = PoStConfig {
+        sector_size: SECTOR_SIZE_2_KIB.into(),
+        sector_count: priv_replicas.len(),
+        challenge_count: WINDOW_POST_CHALLENGE_COUNT,
+        typ: PoStType::Window,
+        priority: false,
+        api_version: sector.porep_config.api_version,
+    };
+    let proof = generate_window_post::<SectorShape2KiB>(
+        &config,
+        &randomness,
+        &priv_replicas,
+        sector.prover_id,
+    )?;
+    assert!(verify_window_post::<SectorShape2KiB>(
+        &config,
+        &randomness,
+        &pub_replicas,
+        sector.prover_id,
+        &proof,
+    )?);
 
-    Ok((sector_id, sealed_sector_file, comm_r, cache_dir))
+    Ok(())
 }
 
 fn create_fake_seal<R: rand::Rng, Tree: 'static + MerkleTreeTrait>(
@@ -2086,6 +4880,207 @@ fn create_fake_seal<R: rand::Rng, Tree: 'static + MerkleTreeTrait>(
     Ok((sector_id, sealed_sector_file, comm_r, cache_dir))
 }
 
+// Fake 2KiB replicas along with their replica infos, for the tests of single PoSt features. The
+// files are removed when it is dropped.
+struct FakeSectors2KiB {
+    prover_id: ProverId,
+    sectors: Vec<(SectorId, NamedTempFile, Commitment, TempDir)>,
+    priv_replicas: BTreeMap<SectorId, PrivateReplicaInfo<SectorShape2KiB>>,
+    pub_replicas: BTreeMap<SectorId, PublicReplicaInfo>,
+}
+
+fn create_fake_sectors_2kib<R: Rng>(
+    rng: &mut R,
+    sector_count: usize,
+    api_version: ApiVersion,
+) -> Result<FakeSectors2KiB> {
+    let porep_id = match api_version {
+        ApiVersion::V1_0_0 => ARBITRARY_POREP_ID_V1_0_0,
+        ApiVersion::V1_1_0 => ARBITRARY_POREP_ID_V1_1_0,
+        ApiVersion::V1_2_0 => ARBITRARY_POREP_ID_V1_2_0,
+    };
+    let prover_id = prover_id_from_fr(&Fr::random(&mut *rng));
+
+    let mut sectors = Vec::with_capacity(sector_count);
+    let mut priv_replicas = BTreeMap::new();
+    let mut pub_replicas = BTreeMap::new();
+    for _ in 0..sector_count {
+        let (sector_id, replica, comm_r, cache_dir) = create_fake_seal::<_, SectorShape2KiB>(
+            &mut *rng,
+            SECTOR_SIZE_2_KIB,
+            &porep_id,
+            api_version,
+        )?;
+        priv_replicas.insert(
+            sector_id,
+            PrivateReplicaInfo::new(replica.path().into(), comm_r, cache_dir.path().into())?,
+        );
+        pub_replicas.insert(sector_id, PublicReplicaInfo::new(comm_r)?);
+        sectors.push((sector_id, replica, comm_r, cache_dir));
+    }
+    // In the order of the replica infos.
+    sectors.sort_by_key(|(sector_id, _, _, _)| *sector_id);
+
+    Ok(FakeSectors2KiB {
+        prover_id,
+        sectors,
+        priv_replicas,
+        pub_replicas,
+    })
+}
+
+fn window_post_config_2kib(api_version: ApiVersion) -> PoStConfig {
+    let sector_count = *WINDOW_POST_SECTOR_COUNT
+        .read()
+        .expect("WINDOW_POST_SECTOR_COUNT poisoned")
+        .get(&SECTOR_SIZE_2_KIB)
+        .expect("unknown sector size");
+
+    PoStConfig {
+        sector_size: SECTOR_SIZE_2_KIB.into(),
+        sector_count,
+        challenge_count: WINDOW_POST_CHALLENGE_COUNT,
+        typ: PoStType::Window,
+        priority: false,
+        api_version,
+    }
+}
+
+fn winning_post_config_2kib(api_version: ApiVersion) -> PoStConfig {
+    PoStConfig {
+        sector_size: SECTOR_SIZE_2_KIB.into(),
+        sector_count: WINNING_POST_SECTOR_COUNT,
+        challenge_count: WINNING_POST_CHALLENGE_COUNT,
+        typ: PoStType::Winning,
+        priority: false,
+        api_version,
+    }
+}
+
+#[test]
+fn test_seal_job_2kib() -> Result<()> {
+    fil_logger::maybe_init();
+
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let sector_size = SECTOR_SIZE_2_KIB;
+    let porep_config = porep_config(sector_size, ARBITRARY_POREP_ID_V1_1_0, ApiVersion::V1_1_0);
+
+    let (mut piece_file, _piece_bytes) = generate_piece_file(sector_size)?;
+    let number_of_bytes_in_piece = porep_config.unpadded_bytes_amount();
+    let piece_info = generate_piece_commitment(piece_file.as_file_mut(), number_of_bytes_in_piece)?;
+    piece_file.as_file_mut().rewind()?;
+    let mut staged_sector_file = NamedTempFile::new()?;
+    add_piece(
+        &mut piece_file,
+        &mut staged_sector_file,
+        number_of_bytes_in_piece,
+        &[],
+    )?;
+
+    let sealed_sector_file = NamedTempFile::new()?;
+    let cache_dir = tempdir().expect("failed to create temp dir");
+
+    let job = SealJob {
+        porep_config: porep_config.clone(),
+        phase: SealJobPhase::PreCommit1,
+        cache_path: cache_dir.path().to_path_buf(),
+        staged_path: staged_sector_file.path().to_path_buf(),
+        sealed_path: sealed_sector_file.path().to_path_buf(),
+        prover_id: prover_id_from_fr(&Fr::random(&mut rng)),
+        sector_id: rng.gen::<u64>().into(),
+        ticket: rng.gen(),
+        seed: Some(rng.gen()),
+        piece_infos: vec![piece_info],
+    };
+
+    // Every phase is run from a job which went through its serialized form.
+    let run = |phase| -> Result<SealJobOutput> {
+        let job = SealJob::from_bytes(&job.with_phase(phase).to_bytes()?)?;
+        execute_seal_job::<SectorShape2KiB>(&job)
+    };
+
+    run(SealJobPhase::PreCommit1)?;
+    let pre_commit_output = match run(SealJobPhase::PreCommit2)? {
+        SealJobOutput::PreCommit2(output) => output,
+        output => panic!("unexpected seal job output {:?}", output),
+    };
+
+    // Commit phase 1 cannot run without a seed.
+    let mut seedless_job = job.with_phase(SealJobPhase::Commit1);
+    seedless_job.seed = None;
+    assert!(execute_seal_job::<SectorShape2KiB>(&seedless_job).is_err());
+
+    run(SealJobPhase::Commit1)?;
+    // Commit phase 2 does not need the seed.
+    seedless_job.phase = SealJobPhase::Commit2;
+    let seedless_job = SealJob::from_bytes(&seedless_job.to_bytes()?)?;
+    let commit_output = match execute_seal_job::<SectorShape2KiB>(&seedless_job)? {
+        SealJobOutput::Commit2(output) => output,
+        output => panic!("unexpected seal job output {:?}", output),
+    };
+
+    // The persisted phase outputs are removed once the job has finished.
+    for entry in read_dir(cache_dir.path())? {
+        let name = entry?.file_name();
+        assert!(
+            !name.to_string_lossy().starts_with("seal-job-"),
+            "{:?} was not removed",
+            name
+        );
+    }
+
+    assert!(verify_seal::<SectorShape2KiB>(
+        &porep_config,
+        pre_commit_output.comm_r,
+        pre_commit_output.comm_d,
+        job.prover_id,
+        job.sector_id,
+        job.ticket,
+        job.seed.expect("seed is set"),
+        &commit_output.proof,
+    )?);
+
+    Ok(())
+}
+
+#[test]
+fn test_seal_proof_encode_decode() -> Result<()> {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+    let config = porep_config(
+        SECTOR_SIZE_2_KIB,
+        ARBITRARY_POREP_ID_V1_1_0,
+        ApiVersion::V1_1_0,
+    );
+    let partitions = usize::from(config.partitions);
+
+    // Encode arbitrary (valid) curve points as partition proofs.
+    let mut proof_bytes = Vec::new();
+    for _ in 0..partitions {
+        let proof = groth16::Proof::<Bls12> {
+            a: (G1Projective::generator() * Fr::random(&mut rng)).into(),
+            b: (G2Projective::generator() * Fr::random(&mut rng)).into(),
+            c: (G1Projective::generator() * Fr::random(&mut rng)).into(),
+        };
+        proof.write(&mut proof_bytes)?;
+    }
+    assert_eq!(proof_bytes.len(), partitions * SINGLE_PARTITION_PROOF_LEN);
+
+    let seal_proof = SealProof::from_bytes(&config, &proof_bytes)?;
+    assert_eq!(seal_proof.partition_proofs().len(), partitions);
+
+    // Re-serialize the proof to ensure a round-trip match.
+    let proof_bytes2 = seal_proof.to_bytes()?;
+    assert_eq!(proof_bytes, proof_bytes2);
+    assert_eq!(SealProof::from_bytes(&config, &proof_bytes2)?, seal_proof);
+
+    // The length is fixed per config.
+    assert!(SealProof::from_bytes(&config, &proof_bytes[SINGLE_PARTITION_PROOF_LEN..]).is_err());
+    proof_bytes.extend_from_slice(&proof_bytes2[..SINGLE_PARTITION_PROOF_LEN]);
+    assert!(SealProof::from_bytes(&config, &proof_bytes).is_err());
+
+    Ok(())
+}
+
 #[test]
 fn test_aggregate_proof_encode_decode() -> Result<()> {
     // This byte vector is a natively serialized aggregate proof generated from the
@@ -2100,6 +5095,10 @@ fn test_aggregate_proof_encode_decode() -> Result<()> {
     let expected_aggregate_proof_count = 512;
 
     assert_eq!(aggregate_proof_count, expected_aggregate_proof_count);
+    assert!(aggregate_proof_matches_expected(&aggregate_proof, 257));
+    assert!(aggregate_proof_matches_expected(&aggregate_proof, 512));
+    assert!(!aggregate_proof_matches_expected(&aggregate_proof, 256));
+    assert!(!aggregate_proof_matches_expected(&aggregate_proof, 513));
 
     // Re-serialize the proof to ensure a round-trip match.
     let mut aggregate_proof_bytes2 = Vec::new();
@@ -2119,5 +5118,10 @@ fn test_aggregate_proof_encode_decode() -> Result<()> {
         expected_bincode_serialized_proof_len
     );
 
+    // Both encodings decode to the same proof.
+    let bincode_proof: groth16::aggregate::AggregateProof<Bls12> =
+        deserialize(&bincode_serialized_proof)?;
+    assert!(aggregate_proofs_equal(&aggregate_proof, &bincode_proof));
+
     Ok(())
 }
@@ -1,9 +1,11 @@
 use filecoin_proofs::{
-    with_shape, SECTOR_SIZE_16_MIB, SECTOR_SIZE_1_GIB, SECTOR_SIZE_2_KIB, SECTOR_SIZE_32_GIB,
+    api_version_for_registered_proof, is_legacy_registered_proof, supported_sector_sizes,
+    with_shape, SectorSize, MAX_LEGACY_REGISTERED_SEAL_PROOF_ID, PUBLISHED_SECTOR_SIZES,
+    SECTOR_SIZE_16_MIB, SECTOR_SIZE_1_GIB, SECTOR_SIZE_2_KIB, SECTOR_SIZE_32_GIB,
     SECTOR_SIZE_4_KIB, SECTOR_SIZE_512_MIB, SECTOR_SIZE_64_GIB, SECTOR_SIZE_8_MIB,
 };
 use generic_array::typenum::Unsigned;
-use storage_proofs_core::merkle::MerkleTreeTrait;
+use storage_proofs_core::{api_version::ApiVersion, merkle::MerkleTreeTrait};
 
 fn canonical_shape(sector_size: u64) -> (usize, usize, usize) {
     // This could perhaps be cleaned up, but I think it expresses the intended constraints
@@ -83,3 +85,61 @@ fn test_with_shape_macro_aux(sector_size: u64) {
         sector_size, arities, expected
     );
 }
+
+#[test]
+fn test_supported_sector_sizes() {
+    let supported = supported_sector_sizes();
+    assert_eq!(supported.len(), PUBLISHED_SECTOR_SIZES.len());
+
+    for sector_size in supported {
+        // Every supported size must be dispatchable to a shape.
+        let (base, _, _) = with_shape!(u64::from(sector_size), arities_to_usize);
+        assert_eq!(base, 8);
+    }
+}
+
+#[test]
+fn test_sector_size_new() {
+    for sector_size in PUBLISHED_SECTOR_SIZES.iter().copied() {
+        let validated = SectorSize::new(sector_size).expect("published sector size rejected");
+        assert_eq!(validated, SectorSize::from(sector_size));
+    }
+
+    assert!(SectorSize::new(0).is_err());
+    assert!(SectorSize::new(SECTOR_SIZE_2_KIB + 1).is_err());
+    assert!(SectorSize::new(SECTOR_SIZE_64_GIB * 2).is_err());
+}
+
+#[test]
+fn test_api_version_for_registered_proof() {
+    assert!(is_legacy_registered_proof(0));
+    assert!(is_legacy_registered_proof(
+        MAX_LEGACY_REGISTERED_SEAL_PROOF_ID
+    ));
+    assert!(!is_legacy_registered_proof(
+        MAX_LEGACY_REGISTERED_SEAL_PROOF_ID + 1
+    ));
+
+    assert_eq!(api_version_for_registered_proof(0), ApiVersion::V1_0_0);
+    assert_eq!(
+        api_version_for_registered_proof(MAX_LEGACY_REGISTERED_SEAL_PROOF_ID + 1),
+        ApiVersion::V1_2_0
+    );
+}
+
+#[cfg(feature = "testing")]
+#[test]
+fn test_winning_post_challenge_count_override() {
+    use filecoin_proofs::{
+        set_winning_post_challenge_count, winning_post_challenge_count,
+        WINNING_POST_CHALLENGE_COUNT,
+    };
+
+    assert_eq!(winning_post_challenge_count(), WINNING_POST_CHALLENGE_COUNT);
+
+    set_winning_post_challenge_count(2);
+    assert_eq!(winning_post_challenge_count(), 2);
+
+    set_winning_post_challenge_count(0);
+    assert_eq!(winning_post_challenge_count(), WINNING_POST_CHALLENGE_COUNT);
+}
@@ -3,8 +3,13 @@ use std::panic::panic_any;
 use blstrs::Scalar as Fr;
 use ff::Field;
 use filecoin_proofs::{
-    as_safe_commitment, verify_seal, DefaultOctLCTree, DefaultTreeDomain, PoRepConfig,
-    SECTOR_SIZE_2_KIB, TEST_SEED,
+    aggregate_public_inputs_digest, as_safe_commitment, next_sector_id,
+    param::{
+        parameter_manifest, preload_verify_params, proving_key_memory_bytes, ParamFileKind,
+        ParamProofKind,
+    },
+    prover_id_from_fr, prover_id_to_fr, randomness_from_fr, randomness_to_fr, verify_seal,
+    DefaultOctLCTree, DefaultTreeDomain, PoRepConfig, MAX_SECTOR_ID, SECTOR_SIZE_2_KIB, TEST_SEED,
 };
 use fr32::bytes_into_fr;
 use rand::SeedableRng;
@@ -125,3 +130,184 @@ fn test_random_domain_element() {
         assert_eq!(back, random_el);
     }
 }
+
+#[test]
+fn test_prover_id_and_randomness_from_fr() {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+
+    for _ in 0..100 {
+        let fr = Fr::random(&mut rng);
+        let domain: DefaultTreeDomain = fr.into();
+
+        let prover_id = prover_id_from_fr(&fr);
+        assert_eq!(&prover_id[..], AsRef::<[u8]>::as_ref(&domain));
+        assert_eq!(prover_id_to_fr(&prover_id).expect("invalid prover id"), fr);
+
+        let randomness = randomness_from_fr(&fr);
+        assert_eq!(randomness, prover_id);
+        assert_eq!(
+            randomness_to_fr(&randomness).expect("invalid randomness"),
+            fr
+        );
+    }
+    assert!(randomness_to_fr(&[255; 32]).is_err());
+}
+
+#[test]
+fn test_next_sector_id() {
+    let first = next_sector_id(1000, 0).expect("failed to allocate sector id");
+    let second = next_sector_id(1000, 1).expect("failed to allocate sector id");
+    assert_eq!(u64::from(first), 1000 << 32);
+    assert!(u64::from(second) > u64::from(first));
+
+    // The last sequence of a miner must not collide with the first one of the next miner.
+    let last = next_sector_id(1000, u32::MAX as u64).expect("failed to allocate sector id");
+    let next_miner = next_sector_id(1001, 0).expect("failed to allocate sector id");
+    assert!(u64::from(last) < u64::from(next_miner));
+
+    assert!(next_sector_id(1000, 1 << 32).is_err());
+    assert!(next_sector_id(1 << 32, 0).is_err());
+
+    // Ids are bounded by the maximum sector number of the chain.
+    let max = next_sector_id((1 << 31) - 1, u32::MAX as u64).expect("failed to allocate sector id");
+    assert_eq!(u64::from(max), MAX_SECTOR_ID);
+    assert!(next_sector_id(1 << 31, 0).is_err());
+}
+
+#[test]
+fn test_aggregate_public_inputs_digest() {
+    let mut rng = XorShiftRng::from_seed(TEST_SEED);
+
+    let comm_rs = vec![[1; 32], [2; 32]];
+    let seeds = vec![[3; 32], [4; 32]];
+    let commit_inputs = vec![
+        vec![Fr::random(&mut rng), Fr::random(&mut rng)],
+        vec![Fr::random(&mut rng)],
+    ];
+
+    let digest = aggregate_public_inputs_digest(&comm_rs, &seeds, &commit_inputs);
+    assert_eq!(
+        digest,
+        aggregate_public_inputs_digest(&comm_rs, &seeds, &commit_inputs)
+    );
+
+    // The digest depends on the order of the sectors.
+    let reordered_comm_rs = vec![comm_rs[1], comm_rs[0]];
+    assert_ne!(
+        digest,
+        aggregate_public_inputs_digest(&reordered_comm_rs, &seeds, &commit_inputs)
+    );
+
+    // The same field elements, split differently, must not produce the same digest.
+    let resplit_inputs = vec![
+        vec![commit_inputs[0][0]],
+        vec![commit_inputs[0][1], commit_inputs[1][0]],
+    ];
+    assert_ne!(
+        digest,
+        aggregate_public_inputs_digest(&comm_rs, &seeds, &resplit_inputs)
+    );
+}
+
+#[test]
+fn test_aggregate_public_inputs_digest_known_answer() {
+    // The digest is meant to be recorded, so it must never change for the same inputs.
+    let comm_rs = vec![[1; 32], [2; 32]];
+    let seeds = vec![[3; 32], [4; 32]];
+    let commit_inputs = vec![vec![Fr::from(1u64), Fr::from(2u64)], vec![Fr::from(3u64)]];
+
+    assert_eq!(
+        hex::encode(aggregate_public_inputs_digest(
+            &comm_rs,
+            &seeds,
+            &commit_inputs
+        )),
+        "074469ffb2b3670fe96bd05bfe630e061d5f9ba9ea5bc380680a987ad00c47d0"
+    );
+}
+
+#[test]
+fn test_parameter_manifest() {
+    let manifest = parameter_manifest(SECTOR_SIZE_2_KIB, ApiVersion::V1_1_0)
+        .expect("failed to build parameter manifest");
+    assert_eq!(manifest.len(), 8);
+
+    for entry in &manifest {
+        let ext = match entry.file {
+            ParamFileKind::Params => ".params",
+            ParamFileKind::VerifyingKey => ".vk",
+        };
+        assert!(entry.filename.ends_with(ext), "{}", entry.filename);
+        // All 2KiB parameters are published.
+        assert!(entry.digest.is_some(), "{} has no digest", entry.filename);
+    }
+    assert!(manifest
+        .iter()
+        .any(|entry| entry.proof == ParamProofKind::EmptySectorUpdate));
+
+    assert!(parameter_manifest(SECTOR_SIZE_2_KIB + 1, ApiVersion::V1_1_0).is_err());
+}
+
+#[test]
+fn test_preload_verify_params() {
+    let loaded = preload_verify_params(
+        SECTOR_SIZE_2_KIB,
+        ApiVersion::V1_1_0,
+        &[
+            ParamProofKind::Seal,
+            ParamProofKind::WindowPost,
+            ParamProofKind::Seal,
+        ],
+    )
+    .expect("failed to preload verifying keys");
+    assert_eq!(
+        loaded.proof_kinds(),
+        vec![ParamProofKind::Seal, ParamProofKind::WindowPost]
+    );
+    assert!(loaded.verifying_key(ParamProofKind::Seal).is_some());
+    assert!(loaded.verifying_key(ParamProofKind::WinningPost).is_none());
+
+    assert!(preload_verify_params(
+        SECTOR_SIZE_2_KIB + 1,
+        ApiVersion::V1_1_0,
+        &[ParamProofKind::Seal]
+    )
+    .is_err());
+}
+
+#[test]
+fn test_proving_key_memory_bytes() {
+    let memory_bytes = |proof_kind| {
+        proving_key_memory_bytes(SECTOR_SIZE_2_KIB, ApiVersion::V1_1_0, proof_kind)
+            .expect("failed to compute proving key memory")
+    };
+
+    let seal = memory_bytes(ParamProofKind::Seal);
+    let winning_post = memory_bytes(ParamProofKind::WinningPost);
+    let window_post = memory_bytes(ParamProofKind::WindowPost);
+    let update = memory_bytes(ParamProofKind::EmptySectorUpdate);
+    assert!(winning_post > 0);
+    assert!(window_post > 0);
+    assert!(update > 0);
+    // The seal circuit proves labeling, so it is by far the largest one.
+    assert!(seal > winning_post);
+    assert!(seal > window_post);
+
+    // The files hold the same uncompressed points, plus the length of each of their 6 sections.
+    let manifest = parameter_manifest(SECTOR_SIZE_2_KIB, ApiVersion::V1_1_0)
+        .expect("failed to build parameter manifest");
+    for entry in manifest
+        .iter()
+        .filter(|entry| entry.file == ParamFileKind::Params)
+    {
+        let file_len = entry.size.expect("missing parameter file");
+        assert_eq!(memory_bytes(entry.proof), file_len - 6 * 4);
+    }
+
+    assert!(proving_key_memory_bytes(
+        SECTOR_SIZE_2_KIB + 1,
+        ApiVersion::V1_1_0,
+        ParamProofKind::Seal
+    )
+    .is_err());
+}
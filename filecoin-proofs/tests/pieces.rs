@@ -4,10 +4,12 @@ use std::iter::Iterator;
 use anyhow::Result;
 use blstrs::Scalar as Fr;
 use filecoin_proofs::{
-    add_piece, commitment_from_fr,
+    add_piece, add_piece_from_source, fr_to_commitment,
     pieces::{
-        compute_comm_d, get_piece_alignment, get_piece_start_byte, piece_hash, verify_pieces,
-        zero_padding, EmptySource, PieceAlignment,
+        compute_comm_d, compute_comm_d_from_data, compute_comm_d_with_fill, get_piece_alignment,
+        get_piece_start_byte, piece_hash, same_source_data, validate_piece_infos,
+        validate_sector_layout, verify_pieces, zero_padding, EmptySource, PieceAlignment,
+        PieceSource,
     },
     Commitment, DataTree, DefaultPieceHasher, PaddedBytesAmount, PieceInfo, SectorSize,
     UnpaddedByteIndex, UnpaddedBytesAmount, DRG_DEGREE, EXP_DEGREE, TEST_SEED,
@@ -48,6 +50,152 @@ fn test_compute_comm_d_empty() {
     );
 }
 
+#[test]
+fn test_compute_comm_d_from_data() -> Result<()> {
+    let rng = &mut XorShiftRng::from_seed(TEST_SEED);
+    let sector_size = SectorSize(4 * 128);
+    let piece_sizes = [UnpaddedBytesAmount(127), UnpaddedBytesAmount(127)];
+
+    let mut staged_sector = Vec::new();
+    let mut staged_sector_io = Cursor::new(&mut staged_sector);
+    let mut piece_infos = Vec::new();
+    for (i, piece_size) in piece_sizes.iter().enumerate() {
+        let mut piece_bytes = vec![0u8; u64::from(*piece_size) as usize];
+        rng.fill_bytes(&mut piece_bytes);
+
+        let (piece_info, _) = add_piece(
+            Cursor::new(&piece_bytes),
+            &mut staged_sector_io,
+            *piece_size,
+            &piece_sizes[..i],
+        )?;
+        piece_infos.push(piece_info);
+    }
+
+    // The staged data only covers half of the sector.
+    assert_eq!(staged_sector.len(), 256);
+    let comm_d = compute_comm_d_from_data(sector_size, Cursor::new(&staged_sector))?;
+    assert_eq!(comm_d, compute_comm_d(sector_size, &piece_infos)?);
+
+    staged_sector.resize(4 * 128 + 1, 0);
+    assert!(compute_comm_d_from_data(sector_size, Cursor::new(&staged_sector)).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_same_source_data() -> Result<()> {
+    let rng = &mut XorShiftRng::from_seed(TEST_SEED);
+    let sector_size = SectorSize(4 * 128);
+
+    let mut staged_sector = vec![0u8; 4 * 128];
+    rng.fill_bytes(&mut staged_sector[..254]);
+    // Keep every node a valid field element.
+    for node in staged_sector.chunks_mut(NODE_SIZE) {
+        node[31] &= 0b0011_1111;
+    }
+
+    let comm_d_a = compute_comm_d_from_data(sector_size, Cursor::new(&staged_sector))?;
+    let comm_d_b = compute_comm_d_from_data(sector_size, Cursor::new(&staged_sector))?;
+    assert!(same_source_data(comm_d_a, comm_d_b));
+
+    staged_sector[3] ^= 1;
+    let comm_d_c = compute_comm_d_from_data(sector_size, Cursor::new(&staged_sector))?;
+    assert!(!same_source_data(comm_d_a, comm_d_c));
+
+    // The data differs from the zeros of an empty sector.
+    assert!(!same_source_data(
+        comm_d_a,
+        compute_comm_d(sector_size, &[])?
+    ));
+
+    Ok(())
+}
+
+// A piece which may know its commitment, like a CAR file with a precomputed CommP.
+struct TestPieceSource {
+    bytes: Vec<u8>,
+    comm_p: Option<Commitment>,
+}
+
+impl PieceSource for TestPieceSource {
+    type Reader = Cursor<Vec<u8>>;
+
+    fn into_reader(self) -> Self::Reader {
+        Cursor::new(self.bytes)
+    }
+
+    fn known_comm_p(&self) -> Option<Commitment> {
+        self.comm_p
+    }
+}
+
+#[test]
+fn test_add_piece_from_source() -> Result<()> {
+    let rng = &mut XorShiftRng::from_seed(TEST_SEED);
+    let piece_size = UnpaddedBytesAmount(127);
+    let mut piece_bytes = vec![0u8; 127];
+    rng.fill_bytes(&mut piece_bytes);
+
+    let mut expected_staged = Vec::new();
+    let expected = add_piece(
+        Cursor::new(&piece_bytes),
+        &mut expected_staged,
+        piece_size,
+        &[],
+    )?;
+
+    for comm_p in [None, Some(expected.0.commitment)].iter().copied() {
+        let mut staged = Vec::new();
+        let source = TestPieceSource {
+            bytes: piece_bytes.clone(),
+            comm_p,
+        };
+        assert_eq!(
+            add_piece_from_source(source, &mut staged, piece_size, &[])?,
+            expected
+        );
+        assert_eq!(staged, expected_staged);
+    }
+
+    // A known commitment is trusted, only the length of the piece is checked.
+    let source = TestPieceSource {
+        bytes: piece_bytes.clone(),
+        comm_p: Some([1; 32]),
+    };
+    let (piece_info, _) = add_piece_from_source(source, &mut Vec::new(), piece_size, &[])?;
+    assert_eq!(piece_info.commitment, [1; 32]);
+
+    let source = TestPieceSource {
+        bytes: piece_bytes[..100].to_vec(),
+        comm_p: Some(expected.0.commitment),
+    };
+    assert!(add_piece_from_source(source, &mut Vec::new(), piece_size, &[]).is_err());
+
+    Ok(())
+}
+
+#[test]
+fn test_compute_comm_d_with_fill() {
+    let sector_size = SectorSize(512);
+    let piece =
+        PieceInfo::new([1u8; 32], UnpaddedBytesAmount(127)).expect("failed to create piece info");
+
+    for piece_infos in [vec![], vec![piece]].iter() {
+        let comm_d = compute_comm_d(sector_size, piece_infos).expect("failed to compute comm_d");
+        let filled = compute_comm_d_with_fill(sector_size, piece_infos, 0)
+            .expect("failed to compute comm_d with zero fill");
+        assert_eq!(comm_d, filled);
+
+        let filled = compute_comm_d_with_fill(sector_size, piece_infos, 0xff);
+        if cfg!(feature = "testing") {
+            assert_ne!(comm_d, filled.expect("failed to compute comm_d with fill"));
+        } else {
+            assert!(filled.is_err(), "non-zero fill must be rejected");
+        }
+    }
+}
+
 #[test]
 fn test_get_piece_alignment() {
     let table = vec![
@@ -79,6 +227,75 @@ fn test_get_piece_alignment() {
     }
 }
 
+#[test]
+fn test_validate_piece_infos() {
+    let sector_size = SectorSize(2048);
+    let piece = |size| PieceInfo {
+        commitment: [0; 32],
+        size: UnpaddedBytesAmount(size),
+    };
+
+    validate_piece_infos(sector_size, &[]).expect("empty pieces are valid");
+    validate_piece_infos(sector_size, &[piece(2032)]).expect("full sector piece is valid");
+    validate_piece_infos(sector_size, &[piece(127), piece(254), piece(1016)])
+        .expect("aligned pieces fit in the sector");
+
+    // Not a power of two once padded.
+    assert!(validate_piece_infos(sector_size, &[piece(381)]).is_err());
+    // Smaller than the minimum piece size, although a power of two (64 bytes) once padded.
+    let err = validate_piece_infos(sector_size, &[piece(63)])
+        .expect_err("piece below the minimum size is invalid");
+    assert!(err
+        .to_string()
+        .contains("is smaller than the minimum piece size"));
+    // Larger than the sector.
+    assert!(validate_piece_infos(sector_size, &[piece(4064)]).is_err());
+    // Fits when summed naively, but not once aligned.
+    assert!(validate_piece_infos(sector_size, &[piece(127), piece(1016), piece(508)]).is_err());
+}
+
+#[test]
+fn test_validate_sector_layout() {
+    let sector_size = SectorSize(2048);
+    let placement = |size, start| {
+        (
+            PieceInfo {
+                commitment: [0; 32],
+                size: UnpaddedBytesAmount(size),
+            },
+            UnpaddedByteIndex(start),
+        )
+    };
+
+    validate_sector_layout(sector_size, &[]).expect("empty layout is valid");
+    validate_sector_layout(sector_size, &[placement(2032, 0)]).expect("full sector is valid");
+    // The layout produced by adding pieces of 127, 254 and 1016 bytes.
+    validate_sector_layout(
+        sector_size,
+        &[
+            placement(127, 0),
+            placement(254, 254),
+            placement(1016, 1016),
+        ],
+    )
+    .expect("aligned pieces are valid");
+
+    // Not aligned to its size.
+    assert!(validate_sector_layout(sector_size, &[placement(254, 127)]).is_err());
+    // Overlapping the previous piece.
+    assert!(
+        validate_sector_layout(sector_size, &[placement(508, 0), placement(254, 254)]).is_err()
+    );
+    // Out of order.
+    assert!(
+        validate_sector_layout(sector_size, &[placement(127, 127), placement(127, 0)]).is_err()
+    );
+    // Beyond the end of the sector.
+    assert!(validate_sector_layout(sector_size, &[placement(1016, 2032)]).is_err());
+    // Invalid piece size.
+    assert!(validate_sector_layout(sector_size, &[placement(381, 0)]).is_err());
+}
+
 #[test]
 fn test_get_piece_start_byte() {
     let pieces = [
@@ -406,7 +623,7 @@ fn build_sector(
         create_base_merkle_tree::<DataTree>(None, graph.size(), &staged_sector)
             .expect("failed to create data tree");
     let comm_d_root: Fr = data_tree.root().into();
-    let comm_d = commitment_from_fr(comm_d_root);
+    let comm_d = fr_to_commitment(&comm_d_root);
 
     Ok((comm_d, piece_infos))
 }
@@ -1,7 +1,9 @@
 mod convert;
 mod padding;
 mod reader;
+mod unpad_reader;
 
 pub use convert::*;
 pub use padding::*;
 pub use reader::*;
+pub use unpad_reader::*;
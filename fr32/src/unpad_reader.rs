@@ -0,0 +1,181 @@
+use std::io::{self, Read};
+
+use crate::{to_unpadded_bytes, write_unpadded};
+
+/// The size of a padded block, 4 `Fr32`s, which holds exactly 127 unpadded bytes.
+const NUM_BYTES_PADDED_BLOCK: usize = 128;
+/// The number of padded blocks which are unpadded at once.
+const NUM_BLOCKS_PER_CHUNK: usize = 256;
+
+const NUM_BYTES_IN_CHUNK: usize = NUM_BYTES_PADDED_BLOCK * NUM_BLOCKS_PER_CHUNK;
+
+/// An `io::Reader` that converts `Fr32` padded input back into the unpadded data, it is the
+/// inverse of `Fr32Reader`.
+///
+/// The output has a length of `to_unpadded_bytes(padded_len)`, so if the padded source does not
+/// end on a 128 byte boundary, the last bytes are the zero bits of the incomplete element.
+pub struct Fr32UnpadReader<R> {
+    /// The padded source.
+    source: R,
+    /// Currently read chunk of padded bytes.
+    in_buffer: Vec<u8>,
+    /// The unpadded bytes of the current chunk.
+    out_buffer: Vec<u8>,
+    /// The current offset into the `out_buffer` in bytes.
+    out_offset: usize,
+    /// Are we done reading?
+    done: bool,
+}
+
+impl<R: Read> Fr32UnpadReader<R> {
+    pub fn new(source: R) -> Self {
+        Fr32UnpadReader {
+            source,
+            in_buffer: vec![0; NUM_BYTES_IN_CHUNK],
+            out_buffer: Vec::with_capacity(to_unpadded_bytes(NUM_BYTES_IN_CHUNK as u64) as usize),
+            out_offset: 0,
+            done: false,
+        }
+    }
+
+    /// Fills `in_buffer` as far as possible. Only the last chunk of the source can be partial,
+    /// so every chunk but the last starts on an element boundary of the unpadded data.
+    fn fill_in_buffer(&mut self) -> io::Result<usize> {
+        let mut bytes_read = 0;
+        let mut buf = &mut self.in_buffer[..];
+
+        while !buf.is_empty() {
+            match self.source.read(buf) {
+                Ok(0) => {
+                    break;
+                }
+                Ok(n) => {
+                    buf = &mut buf[n..];
+                    bytes_read += n;
+                }
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(bytes_read)
+    }
+
+    /// Unpads the first `padded_len` bytes of `in_buffer` into `out_buffer`.
+    fn process_chunk(&mut self, padded_len: usize) -> io::Result<()> {
+        let unpadded_len = to_unpadded_bytes(padded_len as u64) as usize;
+
+        self.out_buffer.clear();
+        write_unpadded(
+            &self.in_buffer[..padded_len],
+            &mut self.out_buffer,
+            0,
+            unpadded_len,
+        )?;
+        self.out_offset = 0;
+
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for Fr32UnpadReader<R> {
+    fn read(&mut self, target: &mut [u8]) -> io::Result<usize> {
+        if target.is_empty() {
+            return Ok(0);
+        }
+
+        if self.out_offset == self.out_buffer.len() {
+            if self.done {
+                return Ok(0);
+            }
+
+            let bytes_read = self.fill_in_buffer()?;
+            if bytes_read < NUM_BYTES_IN_CHUNK {
+                self.done = true;
+            }
+            if bytes_read == 0 {
+                return Ok(0);
+            }
+
+            self.process_chunk(bytes_read)?;
+        }
+
+        let available = &self.out_buffer[self.out_offset..];
+        let len = available.len().min(target.len());
+        target[..len].copy_from_slice(&available[..len]);
+        self.out_offset += len;
+
+        Ok(len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Cursor;
+
+    use rand::{Rng, SeedableRng};
+    use rand_xorshift::XorShiftRng;
+
+    use crate::Fr32Reader;
+
+    const TEST_SEED: [u8; 16] = [
+        0x59, 0x62, 0xbe, 0x5d, 0x76, 0x3d, 0x31, 0x8d, 0x17, 0xdb, 0x37, 0x32, 0x54, 0x06, 0xbc,
+        0xe5,
+    ];
+
+    #[test]
+    fn test_unpad_reader_roundtrip() {
+        let mut rng = XorShiftRng::from_seed(TEST_SEED);
+
+        // Cover a single element, whole blocks and more than a single chunk, including sizes
+        // that do not end on a block boundary.
+        for &len in &[1, 31, 127, 254, 1000, 127 * NUM_BLOCKS_PER_CHUNK + 5] {
+            let data: Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+
+            let mut padded = Vec::new();
+            Fr32Reader::new(Cursor::new(&data))
+                .read_to_end(&mut padded)
+                .expect("in-memory read failed");
+
+            let mut unpadded = Vec::new();
+            Fr32UnpadReader::new(Cursor::new(&padded))
+                .read_to_end(&mut unpadded)
+                .expect("in-memory read failed");
+
+            assert_eq!(
+                unpadded.len() as u64,
+                to_unpadded_bytes(padded.len() as u64)
+            );
+            assert_eq!(&unpadded[..len], &data[..], "len {}", len);
+            assert!(unpadded[len..].iter().all(|&b| b == 0));
+        }
+    }
+
+    #[test]
+    fn test_unpad_reader_small_reads() {
+        let mut rng = XorShiftRng::from_seed(TEST_SEED);
+        let data: Vec<u8> = (0..127 * 3).map(|_| rng.gen()).collect();
+
+        let mut padded = Vec::new();
+        Fr32Reader::new(Cursor::new(&data))
+            .read_to_end(&mut padded)
+            .expect("in-memory read failed");
+
+        // Read the unpadded data a few bytes at a time, from a source split mid element.
+        let source = Cursor::new(padded[..50].to_vec()).chain(Cursor::new(padded[50..].to_vec()));
+        let mut reader = Fr32UnpadReader::new(source);
+        let mut unpadded = Vec::new();
+        let mut buf = [0u8; 7];
+        loop {
+            let n = reader.read(&mut buf).expect("in-memory read failed");
+            if n == 0 {
+                break;
+            }
+            unpadded.extend_from_slice(&buf[..n]);
+        }
+
+        assert_eq!(unpadded, data);
+    }
+}
@@ -235,6 +235,26 @@ where
         pub_params: &S::PublicParams,
         groth_params: &groth16::MappedParameters<Bls12>,
         priority: bool,
+    ) -> Result<Vec<groth16::Proof<Bls12>>> {
+        Self::circuit_proofs_from_partition(
+            pub_in,
+            0,
+            vanilla_proofs,
+            pub_params,
+            groth_params,
+            priority,
+        )
+    }
+
+    /// Like `circuit_proofs`, but for the vanilla proofs of consecutive partitions starting at
+    /// `first_partition`, so that the partitions of a single proof can be proven separately.
+    fn circuit_proofs_from_partition(
+        pub_in: &S::PublicInputs,
+        first_partition: usize,
+        vanilla_proofs: Vec<S::Proof>,
+        pub_params: &S::PublicParams,
+        groth_params: &groth16::MappedParameters<Bls12>,
+        priority: bool,
     ) -> Result<Vec<groth16::Proof<Bls12>>> {
         let mut rng = OsRng;
         ensure!(
@@ -251,7 +271,7 @@ where
                     C::ComponentPrivateInputs::default(),
                     &vanilla_proof,
                     pub_params,
-                    Some(k),
+                    Some(first_partition + k),
                 )
             })
             .collect::<Result<Vec<_>>>()?;
@@ -41,6 +41,8 @@ pub enum Error {
     FaultySectors(Vec<SectorId>),
     #[error("Invalid parameters file: {}", _0)]
     InvalidParameters(String),
+    #[error("empty sector set is invalid, there are no sectors to challenge")]
+    NoSectorsToChallenge,
 }
 
 impl From<Box<dyn Any + Send>> for Error {
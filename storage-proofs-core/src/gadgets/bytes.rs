@@ -0,0 +1,73 @@
+use bellperson::{
+    gadgets::{boolean::Boolean, num::AllocatedNum},
+    ConstraintSystem, SynthesisError,
+};
+use ff::PrimeFieldBits;
+
+/// Decomposes `num` into its canonical little-endian byte representation (`Scalar::Repr`), each
+/// byte given as 8 little-endian bits. The decomposition is constrained to pack back into `num`
+/// and to be smaller than the field modulus; the unused high bits of the top byte are constant
+/// `false`.
+pub fn num_into_bytes_le<Scalar, CS>(
+    mut cs: CS,
+    num: &AllocatedNum<Scalar>,
+) -> Result<Vec<Vec<Boolean>>, SynthesisError>
+where
+    Scalar: PrimeFieldBits,
+    CS: ConstraintSystem<Scalar>,
+{
+    let byte_len = Scalar::Repr::default().as_ref().len();
+
+    let mut bits = num.to_bits_le_strict(cs.namespace(|| "bits"))?;
+    assert!(bits.len() <= byte_len * 8);
+    bits.resize(byte_len * 8, Boolean::Constant(false));
+
+    Ok(bits.chunks(8).map(|byte| byte.to_vec()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use bellperson::util_cs::test_cs::TestConstraintSystem;
+    use blstrs::Scalar as Fr;
+    use ff::{Field, PrimeField};
+    use rand::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    use crate::TEST_SEED;
+
+    #[test]
+    fn test_num_into_bytes_le() {
+        let mut rng = XorShiftRng::from_seed(TEST_SEED);
+
+        for value in vec![Fr::zero(), -Fr::one(), Fr::random(&mut rng)] {
+            let mut cs = TestConstraintSystem::<Fr>::new();
+
+            let num =
+                AllocatedNum::alloc(cs.namespace(|| "num"), || Ok(value)).expect("alloc failed");
+            let bytes = num_into_bytes_le(cs.namespace(|| "bytes"), &num)
+                .expect("num_into_bytes_le failed");
+
+            assert!(cs.is_satisfied(), "constraints not satisfied");
+            assert_eq!(bytes.len(), 32);
+
+            // The top byte only holds the 7 remaining bits of the 255-bit field.
+            assert!(!bytes[31][7].get_value().expect("get_value failed"));
+
+            let actual: Vec<u8> = bytes
+                .iter()
+                .map(|byte| {
+                    byte.iter().enumerate().fold(0u8, |acc, (i, bit)| {
+                        acc | ((bit.get_value().expect("get_value failed") as u8) << i)
+                    })
+                })
+                .collect();
+            assert_eq!(actual, value.to_repr().as_ref());
+
+            let mut repr = <Fr as PrimeField>::Repr::default();
+            repr.as_mut().copy_from_slice(&actual);
+            assert_eq!(Fr::from_repr_vartime(repr), Some(value));
+        }
+    }
+}
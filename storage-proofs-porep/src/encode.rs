@@ -1,5 +1,10 @@
+use anyhow::{ensure, Context};
 use blstrs::Scalar as Fr;
+use ff::PrimeField;
 use filecoin_hashers::Domain;
+use fr32::bytes_into_fr;
+use rayon::prelude::{IndexedParallelIterator, ParallelIterator, ParallelSlice, ParallelSliceMut};
+use storage_proofs_core::{error::Result, util::NODE_SIZE};
 
 pub fn encode<T: Domain>(key: T, value: T) -> T {
     let value: Fr = value.into();
@@ -13,6 +18,41 @@ pub fn encode_fr(key: &mut Fr, value: Fr) {
     *key += value;
 }
 
+/// Encodes `values` into `region` in place, where both are treated as arrays of 32-byte field
+/// elements: each key node in `region` is replaced by `encode_fr(key, value)`.
+///
+/// This allows encoding directly over an mmap'd replica region, without an intermediate copy.
+pub fn encode_mmap_region(region: &mut [u8], values: &[u8]) -> Result<()> {
+    ensure!(
+        region.len() % NODE_SIZE == 0,
+        "region length {} is not a multiple of {}",
+        region.len(),
+        NODE_SIZE
+    );
+    ensure!(
+        region.len() == values.len(),
+        "region length {} does not match values length {}",
+        region.len(),
+        values.len()
+    );
+
+    region
+        .par_chunks_mut(NODE_SIZE)
+        .zip(values.par_chunks(NODE_SIZE))
+        .enumerate()
+        .try_for_each(|(i, (key_bytes, value_bytes))| -> Result<()> {
+            let mut key =
+                bytes_into_fr(key_bytes).with_context(|| format!("invalid key at node {}", i))?;
+            let value = bytes_into_fr(value_bytes)
+                .with_context(|| format!("invalid value at node {}", i))?;
+
+            encode_fr(&mut key, value);
+            key_bytes.copy_from_slice(&key.to_repr());
+
+            Ok(())
+        })
+}
+
 pub fn decode<T: Domain>(key: T, value: T) -> T {
     let mut result: Fr = value.into();
     let key: Fr = key.into();
@@ -20,3 +60,39 @@ pub fn decode<T: Domain>(key: T, value: T) -> T {
     result -= key;
     result.into()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ff::Field;
+    use fr32::fr_into_bytes;
+    use rand::SeedableRng;
+    use rand_xorshift::XorShiftRng;
+
+    use storage_proofs_core::TEST_SEED;
+
+    #[test]
+    fn test_encode_mmap_region() {
+        let mut rng = XorShiftRng::from_seed(TEST_SEED);
+        let keys: Vec<Fr> = (0..8).map(|_| Fr::random(&mut rng)).collect();
+        let values: Vec<Fr> = (0..8).map(|_| Fr::random(&mut rng)).collect();
+
+        let mut region: Vec<u8> = keys.iter().flat_map(fr_into_bytes).collect();
+        let value_bytes: Vec<u8> = values.iter().flat_map(fr_into_bytes).collect();
+
+        encode_mmap_region(&mut region, &value_bytes).expect("encode_mmap_region failed");
+
+        for (i, (key, value)) in keys.iter().zip(values.iter()).enumerate() {
+            let mut expected = *key;
+            encode_fr(&mut expected, *value);
+            assert_eq!(
+                &region[i * NODE_SIZE..(i + 1) * NODE_SIZE],
+                &fr_into_bytes(&expected)[..]
+            );
+        }
+
+        assert!(encode_mmap_region(&mut region[..31], &value_bytes[..31]).is_err());
+        assert!(encode_mmap_region(&mut region, &value_bytes[..NODE_SIZE]).is_err());
+    }
+}
@@ -1,6 +1,7 @@
 use std::convert::{TryFrom, TryInto};
 use std::fmt::{self, Debug, Formatter};
 use std::marker::PhantomData;
+use std::path::PathBuf;
 
 use anyhow::ensure;
 use filecoin_hashers::Hasher;
@@ -21,7 +22,7 @@ use storage_proofs_core::{
     PoRepID,
 };
 
-use crate::stacked::vanilla::cache::ParentCache;
+use crate::stacked::vanilla::cache::{cache_path, ParentCache};
 
 /// The expansion degree used for Stacked Graphs.
 pub const EXP_DEGREE: usize = 8;
@@ -143,6 +144,13 @@ where
 
         ParentCache::new(cache_size, cache_entries, self)
     }
+
+    /// Returns the path of the parent cache file used by `parent_cache`, without opening or
+    /// generating it.
+    pub fn parent_cache_path(&self) -> PathBuf {
+        cache_path(self.size() as u32, self)
+    }
+
     pub fn copy_parents_data_exp(
         &self,
         node: u32,
@@ -27,6 +27,7 @@ use storage_proofs_core::{
 };
 
 use crate::stacked::vanilla::{
+    proof::{encoding_marker_path, tree_root_path},
     Column, ColumnProof, EncodingProof, LabelingProof, LayerChallenges, StackedBucketGraph,
 };
 
@@ -470,6 +471,17 @@ impl<Tree: MerkleTreeTrait, G: Hasher> TemporaryAux<Tree, G> {
         }
         trace!("tree c deleted");
 
+        // The records phase2 keeps to resume an interrupted run are of no use without the labels.
+        for path in [
+            tree_root_path(&t_aux.tree_c_config),
+            tree_root_path(&t_aux.tree_r_last_config),
+            encoding_marker_path(&t_aux.tree_r_last_config),
+        ] {
+            if path.exists() {
+                remove_file(&path).with_context(|| format!("Failed to delete {:?}", &path))?;
+            }
+        }
+
         for i in 0..t_aux.labels.labels.len() {
             let cur_config = t_aux.labels.labels[i].clone();
             if cached(&cur_config) {
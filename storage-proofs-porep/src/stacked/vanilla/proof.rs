@@ -1,5 +1,6 @@
 use std::any::TypeId;
-use std::fs;
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom, Write};
 use std::marker::PhantomData;
 use std::panic::panic_any;
 use std::path::{Path, PathBuf};
@@ -15,7 +16,7 @@ use generic_array::typenum::{Unsigned, U0, U11, U2, U8};
 use lazy_static::lazy_static;
 use log::{error, info, trace, warn};
 use merkletree::{
-    merkle::{get_merkle_tree_len, is_merkle_tree_size_valid},
+    merkle::{get_merkle_tree_cache_size, get_merkle_tree_len, is_merkle_tree_size_valid},
     store::{DiskStore, Store, StoreConfig},
 };
 use rayon::prelude::{
@@ -55,6 +56,55 @@ use crate::{
     PoRep,
 };
 
+/// Returns the path of the file recording the root of the tree stored with `config`. It is
+/// written by phase2 once the tree is complete, and a tree left behind by an interrupted run is
+/// only reused if its root matches the recorded one.
+pub fn tree_root_path(config: &StoreConfig) -> PathBuf {
+    StoreConfig::data_path(&config.path, &format!("{}-root", config.id))
+}
+
+/// Returns the root recorded for the tree stored with `config`, if phase2 completed that tree.
+pub fn persisted_tree_root<D: Domain>(config: &StoreConfig) -> Result<Option<D>> {
+    let path = tree_root_path(config);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let bytes = fs::read(&path).with_context(|| format!("could not read {:?}", path))?;
+
+    D::try_from_bytes(&bytes).map(Some)
+}
+
+// Returns the path of the marker written right before the replica starts to be encoded in place
+// while building the tree stored with `config`.
+pub(crate) fn encoding_marker_path(config: &StoreConfig) -> PathBuf {
+    StoreConfig::data_path(&config.path, &format!("{}-encoding", config.id))
+}
+
+// Removes the recorded root of the tree stored with `config` before the tree is rebuilt, so that
+// a partially rebuilt tree never matches the root of an earlier build.
+fn discard_tree_root(config: &StoreConfig) -> Result<()> {
+    let path = tree_root_path(config);
+    if path.exists() {
+        fs::remove_file(&path).with_context(|| format!("could not remove {:?}", path))?;
+    }
+
+    Ok(())
+}
+
+fn persist_tree_root<D: Domain>(config: &StoreConfig, root: &D) -> Result<()> {
+    let path = tree_root_path(config);
+    let tmp_path = path.with_extension("tmp");
+    {
+        let mut file =
+            File::create(&tmp_path).with_context(|| format!("could not create {:?}", tmp_path))?;
+        file.write_all(&root.into_bytes())?;
+        file.sync_all()?;
+    }
+    fs::rename(&tmp_path, &path).with_context(|| format!("could not persist {:?}", path))?;
+
+    Ok(())
+}
+
 pub const TOTAL_PARENTS: usize = 37;
 
 lazy_static! {
@@ -1266,6 +1316,74 @@ impl<'a, Tree: 'static + MerkleTreeTrait, G: 'static + Hasher> StackedDrg<'a, Tr
         .context("failed to transform")
     }
 
+    // Checks whether all base trees stored with `configs` have the expected shape, so that they
+    // can be opened: each store must have the length of `store_len` nodes and end with a
+    // non-zero root. This does not show that a tree is complete, see `reusable_tree_root`.
+    fn base_trees_complete(configs: &[StoreConfig], store_len: usize) -> Result<bool> {
+        for config in configs {
+            let path = StoreConfig::data_path(&config.path, &config.id);
+            let len = match fs::metadata(&path) {
+                Ok(metadata) => metadata.len(),
+                Err(_) => return Ok(false),
+            };
+            if len != (store_len * NODE_SIZE) as u64 {
+                return Ok(false);
+            }
+
+            let mut root = [0u8; NODE_SIZE];
+            let mut file =
+                File::open(&path).with_context(|| format!("could not open {:?}", path))?;
+            file.seek(SeekFrom::End(-(NODE_SIZE as i64)))?;
+            file.read_exact(&mut root)
+                .with_context(|| format!("could not read root of {:?}", path))?;
+            if root == [0u8; NODE_SIZE] {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    // Returns the root of the tree stored with `config` (split into `configs`) by an earlier,
+    // interrupted run of phase2, if that tree was completed: its stores must have the expected
+    // shape and the root opened by `open_root` must match the root recorded for the tree.
+    fn reusable_tree_root<F>(
+        config: &StoreConfig,
+        configs: &[StoreConfig],
+        store_len: usize,
+        open_root: F,
+    ) -> Result<Option<<Tree::Hasher as Hasher>::Domain>>
+    where
+        F: FnOnce() -> Result<<Tree::Hasher as Hasher>::Domain>,
+    {
+        let persisted_root = match persisted_tree_root(config)? {
+            Some(root) => root,
+            None => return Ok(None),
+        };
+        if !Self::base_trees_complete(configs, store_len)? {
+            return Ok(None);
+        }
+        let root = open_root()?;
+
+        Ok(if root == persisted_root {
+            Some(root)
+        } else {
+            None
+        })
+    }
+
+    // Overwrites `data` with the original (unencoded) data, which are the leaves of tree_d.
+    fn restore_data_from_tree_d(data: &mut Data<'_>, tree_d_config: &StoreConfig) -> Result<()> {
+        let path = StoreConfig::data_path(&tree_d_config.path, &tree_d_config.id);
+        let mut file = File::open(&path).with_context(|| format!("could not open {:?}", path))?;
+
+        data.ensure_data()?;
+        file.read_exact(data.as_mut())
+            .with_context(|| format!("could not read the leaves of {:?}", path))?;
+
+        Ok(())
+    }
+
     pub(crate) fn transform_and_replicate_layers_inner(
         graph: &StackedBucketGraph<Tree::Hasher>,
         layer_challenges: &LayerChallenges,
@@ -1350,38 +1468,51 @@ impl<'a, Tree: 'static + MerkleTreeTrait, G: 'static + Hasher> StackedDrg<'a, Tr
             None => error!("Failed to raise the fd limit"),
         };
 
-        let tree_c_root = match layers {
-            2 => {
-                let tree_c = Self::generate_tree_c::<U2, Tree::Arity>(
-                    layers,
-                    nodes_count,
-                    tree_count,
-                    configs,
-                    &labels,
-                )?;
-                tree_c.root()
-            }
-            8 => {
-                let tree_c = Self::generate_tree_c::<U8, Tree::Arity>(
-                    layers,
-                    nodes_count,
-                    tree_count,
-                    configs,
-                    &labels,
-                )?;
-                tree_c.root()
-            }
-            11 => {
-                let tree_c = Self::generate_tree_c::<U11, Tree::Arity>(
-                    layers,
-                    nodes_count,
-                    tree_count,
-                    configs,
-                    &labels,
-                )?;
-                tree_c.root()
-            }
-            _ => panic_any("Unsupported column arity"),
+        let tree_c_len = get_merkle_tree_len(nodes_count, Tree::Arity::to_usize())?;
+        let reused_tree_c_root =
+            Self::reusable_tree_root(&tree_c_config, &configs, tree_c_len, || {
+                Ok(create_disk_tree::<Tree>(tree_c_len, &configs)?.root())
+            })?;
+        let tree_c_root = if let Some(root) = reused_tree_c_root {
+            info!("reusing tree_c of an earlier run");
+            root
+        } else {
+            discard_tree_root(&tree_c_config)?;
+            let tree_c_root = match layers {
+                2 => {
+                    let tree_c = Self::generate_tree_c::<U2, Tree::Arity>(
+                        layers,
+                        nodes_count,
+                        tree_count,
+                        configs,
+                        &labels,
+                    )?;
+                    tree_c.root()
+                }
+                8 => {
+                    let tree_c = Self::generate_tree_c::<U8, Tree::Arity>(
+                        layers,
+                        nodes_count,
+                        tree_count,
+                        configs,
+                        &labels,
+                    )?;
+                    tree_c.root()
+                }
+                11 => {
+                    let tree_c = Self::generate_tree_c::<U11, Tree::Arity>(
+                        layers,
+                        nodes_count,
+                        tree_count,
+                        configs,
+                        &labels,
+                    )?;
+                    tree_c.root()
+                }
+                _ => panic_any("Unsupported column arity"),
+            };
+            persist_tree_root(&tree_c_config, &tree_c_root)?;
+            tree_c_root
         };
         info!("tree_c done");
 
@@ -1409,27 +1540,71 @@ impl<'a, Tree: 'static + MerkleTreeTrait, G: 'static + Hasher> StackedDrg<'a, Tr
         let tree_d_root = tree_d.root();
         drop(tree_d);
 
-        // Encode original data into the last layer.
-        let last_layer_labels = labels.labels_for_last_layer()?;
-        data.ensure_data()?;
+        let tree_r_last_len = get_merkle_tree_len(nodes_count, Tree::Arity::to_usize())?;
+        let (tree_r_last_configs, replica_config) = split_config_and_replica(
+            tree_r_last_config.clone(),
+            replica_path.clone(),
+            nodes_count,
+            tree_count,
+        )?;
+        let tree_r_last_cache_len = get_merkle_tree_cache_size(
+            nodes_count,
+            Tree::Arity::to_usize(),
+            tree_r_last_config.rows_to_discard,
+        )?;
 
-        info!("building tree_r_last");
-        let tree_r_last = measure_op(Operation::GenerateTreeRLast, || {
-            Self::generate_tree_r_last::<Tree::Arity>(
-                &mut data,
-                nodes_count,
-                tree_count,
-                tree_r_last_config.clone(),
-                replica_path.clone(),
-                last_layer_labels,
-                None,
-            )
-            .context("failed to generate tree_r_last")
-        })?;
-        info!("tree_r_last done");
+        let reused_tree_r_last_root = Self::reusable_tree_root(
+            &tree_r_last_config,
+            &tree_r_last_configs,
+            tree_r_last_cache_len,
+            || {
+                Ok(
+                    create_lc_tree::<Tree>(tree_r_last_len, &tree_r_last_configs, &replica_config)?
+                        .root(),
+                )
+            },
+        )?;
+        let tree_r_last_root = if let Some(root) = reused_tree_r_last_root {
+            // The replica is encoded while building tree_r_last, so it is encoded already.
+            info!("reusing tree_r_last of an earlier run");
+            root
+        } else {
+            // Each chunk of the replica is encoded in place before its tree is written, so an
+            // interrupted run may have encoded any part of the replica, even without leaving a
+            // tree_r_last file behind. The marker records that encoding started.
+            discard_tree_root(&tree_r_last_config)?;
+            let encoding_marker = encoding_marker_path(&tree_r_last_config);
+            if encoding_marker.exists() {
+                info!("restoring the replica from tree_d before encoding");
+                Self::restore_data_from_tree_d(&mut data, &tree_d_config)?;
+            } else {
+                File::create(&encoding_marker)
+                    .and_then(|file| file.sync_all())
+                    .with_context(|| format!("could not create {:?}", encoding_marker))?;
+            }
 
-        let tree_r_last_root = tree_r_last.root();
-        drop(tree_r_last);
+            // Encode original data into the last layer.
+            let last_layer_labels = labels.labels_for_last_layer()?;
+            data.ensure_data()?;
+
+            info!("building tree_r_last");
+            let tree_r_last = measure_op(Operation::GenerateTreeRLast, || {
+                Self::generate_tree_r_last::<Tree::Arity>(
+                    &mut data,
+                    nodes_count,
+                    tree_count,
+                    tree_r_last_config.clone(),
+                    replica_path.clone(),
+                    last_layer_labels,
+                    None,
+                )
+                .context("failed to generate tree_r_last")
+            })?;
+            let tree_r_last_root = tree_r_last.root();
+            persist_tree_root(&tree_r_last_config, &tree_r_last_root)?;
+            tree_r_last_root
+        };
+        info!("tree_r_last done");
 
         data.drop_data()?;
 
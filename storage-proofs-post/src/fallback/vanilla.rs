@@ -187,6 +187,12 @@ pub fn generate_sector_challenges<T: Domain>(
 }
 
 /// Generate a single sector challenge.
+///
+/// The challenge is the first 8 bytes, read as a little-endian `u64`, of
+/// `sha256(prover_id || randomness || n)` reduced modulo `sector_set_len`. `prover_id` and
+/// `randomness` are hashed as the 32 bytes of their domain representation, which is the
+/// little-endian field element repr, and `n` as a little-endian `u64`, so the challenges are the
+/// same on every target.
 pub fn generate_sector_challenge<T: Domain>(
     randomness: T,
     n: usize,
@@ -196,7 +202,8 @@ pub fn generate_sector_challenge<T: Domain>(
     let mut hasher = Sha256::new();
     hasher.update(AsRef::<[u8]>::as_ref(&prover_id));
     hasher.update(AsRef::<[u8]>::as_ref(&randomness));
-    hasher.update(&n.to_le_bytes()[..]);
+    // Hash `n` as a `u64`, `usize` is only 4 bytes wide on 32-bit targets.
+    hasher.update(&(n as u64).to_le_bytes()[..]);
 
     let hash = hasher.finalize();
 
@@ -207,21 +214,43 @@ pub fn generate_sector_challenge<T: Domain>(
 }
 
 /// Generate all challenged leaf ranges for a single sector, such that the range fits into the sector.
+///
+/// Challenge `i` is the first 8 bytes, read as a little-endian `u64`, of
+/// `sha256(randomness || sector_id || i)` reduced modulo the number of nodes of the sector.
+/// `randomness` is hashed as the 32 bytes of its domain representation, which is the
+/// little-endian field element repr, and `sector_id` and `i` as little-endian `u64`s, so the
+/// challenges are the same on every target.
 pub fn generate_leaf_challenges<T: Domain>(
     pub_params: &PublicParams,
     randomness: T,
     sector_id: u64,
     challenge_count: usize,
+) -> Vec<u64> {
+    generate_leaf_challenges_with_digest::<T, Sha256>(
+        pub_params,
+        randomness,
+        sector_id,
+        challenge_count,
+    )
+}
+
+/// Like `generate_leaf_challenges`, but derives the challenges using the digest `D` instead of
+/// `Sha256`. This is an experimentation hook only: provers and verifiers always use `Sha256`.
+pub fn generate_leaf_challenges_with_digest<T: Domain, D: Digest + Clone>(
+    pub_params: &PublicParams,
+    randomness: T,
+    sector_id: u64,
+    challenge_count: usize,
 ) -> Vec<u64> {
     let mut challenges = Vec::with_capacity(challenge_count);
 
-    let mut hasher = Sha256::new();
+    let mut hasher = D::new();
     hasher.update(AsRef::<[u8]>::as_ref(&randomness));
     hasher.update(&sector_id.to_le_bytes()[..]);
 
     for challenge_index in 0..challenge_count {
         let challenge =
-            generate_leaf_challenge_inner::<T>(hasher.clone(), pub_params, challenge_index as u64);
+            leaf_challenge_from_digest(hasher.clone(), pub_params, challenge_index as u64);
         challenges.push(challenge)
     }
 
@@ -243,7 +272,15 @@ pub fn generate_leaf_challenge<T: Domain>(
 }
 
 pub fn generate_leaf_challenge_inner<T: Domain>(
-    mut hasher: Sha256,
+    hasher: Sha256,
+    pub_params: &PublicParams,
+    leaf_challenge_index: u64,
+) -> u64 {
+    leaf_challenge_from_digest(hasher, pub_params, leaf_challenge_index)
+}
+
+fn leaf_challenge_from_digest<D: Digest>(
+    mut hasher: D,
     pub_params: &PublicParams,
     leaf_challenge_index: u64,
 ) -> u64 {
@@ -2,6 +2,7 @@ use filecoin_hashers::{poseidon::PoseidonHasher, Domain, HashFunction, Hasher};
 use generic_array::typenum::{U0, U2, U4, U8};
 use rand::SeedableRng;
 use rand_xorshift::XorShiftRng;
+use sha2::{Sha256, Sha512};
 use storage_proofs_core::{
     api_version::ApiVersion,
     error::Error,
@@ -14,6 +15,57 @@ use storage_proofs_core::{
 use storage_proofs_post::fallback::{self, FallbackPoSt, PrivateSector, PublicSector};
 use tempfile::tempdir;
 
+#[test]
+fn test_generate_leaf_challenges_with_digest() {
+    let pub_params = fallback::PublicParams {
+        sector_size: 64 * NODE_SIZE as u64,
+        challenge_count: 5,
+        sector_count: 1,
+        api_version: ApiVersion::V1_2_0,
+    };
+    let randomness = <PoseidonHasher as Hasher>::Domain::try_from_bytes(&[1u8; 32])
+        .expect("failed to create randomness");
+
+    let challenges = fallback::generate_leaf_challenges(&pub_params, randomness, 7, 5);
+    assert_eq!(challenges, vec![44, 10, 1, 36, 63]);
+    assert_eq!(
+        fallback::generate_leaf_challenges_with_digest::<_, Sha256>(&pub_params, randomness, 7, 5),
+        challenges
+    );
+
+    let other =
+        fallback::generate_leaf_challenges_with_digest::<_, Sha512>(&pub_params, randomness, 7, 5);
+    assert_ne!(other, challenges);
+    assert!(other.iter().all(|challenge| *challenge < 64));
+}
+
+// The challenges are consensus critical, these values must be the same on every target.
+#[test]
+fn test_generate_challenges_golden() {
+    let randomness = <PoseidonHasher as Hasher>::Domain::try_from_bytes(&[1u8; 32])
+        .expect("failed to create randomness");
+    let prover_id = <PoseidonHasher as Hasher>::Domain::try_from_bytes(&[2u8; 32])
+        .expect("failed to create prover id");
+
+    // The size in bytes of a 32GiB sector.
+    let pub_params = fallback::PublicParams {
+        sector_size: 1 << 35,
+        challenge_count: 5,
+        sector_count: 1,
+        api_version: ApiVersion::V1_2_0,
+    };
+    assert_eq!(
+        fallback::generate_leaf_challenges(&pub_params, randomness, 7, 5),
+        vec![262629804, 965016778, 186707329, 381118628, 819045567]
+    );
+
+    assert_eq!(
+        fallback::generate_sector_challenges(randomness, 5, 1000, prover_id)
+            .expect("failed to generate sector challenges"),
+        vec![598, 970, 18, 657, 7]
+    );
+}
+
 #[test]
 fn test_fallback_post_poseidon_single_partition_base_8() {
     test_fallback_post::<LCTree<PoseidonHasher, U8, U0, U0>>(5, 5, 1, ApiVersion::V1_0_0);